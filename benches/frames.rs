@@ -0,0 +1,33 @@
+//! Measures how many frames per second the emulator runs at.
+//!
+//! Run with `cargo bench`. Each test ROM is run once to warm up and then timed
+//! over a fixed number of frames, on both DMG and CGB.
+
+use std::time::Instant;
+
+use kpasim::{gameboy::GameBoy, model::Model};
+
+const WARMUP_FRAMES: u32 = 60;
+const FRAMES: u32 = 2000;
+
+fn main() {
+  let roms = [("blargg_cpu_instrs", "tests/blargg_cpu_instrs.gb")];
+  for (name, path) in roms {
+    let rom = std::fs::read(path).unwrap();
+    for model in [Model::Dmg, Model::Cgb] {
+      let mut gb = GameBoy::new(model, rom.clone()).unwrap();
+      for _ in 0..WARMUP_FRAMES {
+        gb.run_frame();
+      }
+      let start = Instant::now();
+      for _ in 0..FRAMES {
+        gb.run_frame();
+      }
+      let secs = start.elapsed().as_secs_f64();
+      println!(
+        "{name} ({model:?}): {FRAMES} frames in {secs:.3}s, {fps:.1} fps",
+        fps = f64::from(FRAMES) / secs
+      );
+    }
+  }
+}
@@ -0,0 +1,26 @@
+use kpasim::disasm::Disassembly;
+
+fn main() {
+  let args: Vec<String> = std::env::args().collect();
+  if args.len() < 3 {
+    println!("usage: gb_disasm <rom> <out.asm>");
+    return;
+  }
+  let rom = match std::fs::read(&args[1]) {
+    Ok(rom) => rom,
+    Err(e) => {
+      println!("`{}`: {e}", args[1]);
+      return;
+    }
+  };
+  let dis = Disassembly::new(&rom);
+  let code = (0..rom.len()).filter(|&i| dis.is_code(i)).count();
+  println!(
+    "{} banks, {code} of {} bytes are code.",
+    dis.bank_count(),
+    rom.len()
+  );
+  if let Err(e) = std::fs::write(&args[2], dis.to_asm()) {
+    println!("`{}`: {e}", args[2]);
+  }
+}
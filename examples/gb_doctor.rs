@@ -0,0 +1,93 @@
+//! Makes and compares [gameboy-doctor](https://github.com/robert/gameboy-doctor)
+//! logs.
+//!
+//! * `gb_doctor log <rom> <out> [instructions]` runs a ROM on a DMG and writes
+//!   the log (1,000,000 instructions by default).
+//! * `gb_doctor diff <ours> <reference> [context]` finds the first line where
+//!   two logs differ and prints it, along with the lines before it (3 by
+//!   default).
+
+use std::{
+  fs::File,
+  io::{BufWriter, Write},
+  process::ExitCode,
+};
+
+use kpasim::{
+  doctor::{first_divergence, setup},
+  gameboy::GameBoy,
+  model::Model,
+};
+
+fn main() -> ExitCode {
+  let args: Vec<String> = std::env::args().skip(1).collect();
+  let args: Vec<&str> = args.iter().map(String::as_str).collect();
+  match args[..] {
+    ["log", rom, out] => log(rom, out, 1_000_000),
+    ["log", rom, out, count] => match count.parse() {
+      Ok(count) => log(rom, out, count),
+      Err(e) => fail(&format!("bad instruction count `{count}`: {e}")),
+    },
+    ["diff", ours, reference] => diff(ours, reference, 3),
+    ["diff", ours, reference, context] => match context.parse() {
+      Ok(context) => diff(ours, reference, context),
+      Err(e) => fail(&format!("bad context line count `{context}`: {e}")),
+    },
+    _ => fail(
+      "usage:\n  gb_doctor log <rom> <out> [instructions]\n  gb_doctor diff <ours> <reference> [context]",
+    ),
+  }
+}
+
+fn fail(message: &str) -> ExitCode {
+  eprintln!("{message}");
+  ExitCode::FAILURE
+}
+
+fn log(rom: &str, out: &str, count: u32) -> ExitCode {
+  let mut gb = match GameBoy::from_file(Model::Dmg, rom) {
+    Ok(Some(gb)) => gb,
+    Ok(None) => return fail(&format!("`{rom}` has an unsupported cart type")),
+    Err(e) => return fail(&format!("can't read `{rom}`: {e}")),
+  };
+  let mut out = match File::create(out) {
+    Ok(file) => BufWriter::new(file),
+    Err(e) => return fail(&format!("can't create `{out}`: {e}")),
+  };
+  setup(&mut gb, move |line| {
+    writeln!(out, "{line}").unwrap();
+  });
+  for _ in 0..count {
+    gb.step_instruction();
+  }
+  // dropping the tracer flushes the log
+  gb.set_tracer(None);
+  ExitCode::SUCCESS
+}
+
+fn diff(ours_path: &str, reference_path: &str, context: usize) -> ExitCode {
+  let read = |path: &str| {
+    std::fs::read_to_string(path)
+      .map_err(|e| format!("can't read `{path}`: {e}"))
+  };
+  let (ours, reference) = match (read(ours_path), read(reference_path)) {
+    (Ok(ours), Ok(reference)) => (ours, reference),
+    (Err(e), _) | (_, Err(e)) => return fail(&e),
+  };
+  let Some(divergence) = first_divergence(&ours, &reference) else {
+    println!("The logs match.");
+    return ExitCode::SUCCESS;
+  };
+  let line_number = divergence.index + 1;
+  println!("The logs differ at line {line_number}.");
+  let first = divergence.index.saturating_sub(context);
+  for (i, line) in ours.lines().enumerate().take(divergence.index).skip(first) {
+    println!("  {:>8}: {}", i + 1, line.trim_end());
+  }
+  println!("  ours:      {}", divergence.ours.unwrap_or("(end of log)"));
+  println!("  reference: {}", divergence.reference.unwrap_or("(end of log)"));
+  for (name, ours, reference) in divergence.fields() {
+    println!("  {name} is {ours}, but should be {reference}");
+  }
+  ExitCode::FAILURE
+}
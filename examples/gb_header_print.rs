@@ -2,6 +2,8 @@
 //!
 //! https://gbdev.io/pandocs/The_Cartridge_Header.html
 
+use kpasim::cart_header::{CartHeader, CgbSupport};
+
 fn main() {
   let args: Vec<String> = std::env::args().collect();
   println!("ARGS: {args:?}");
@@ -19,12 +21,15 @@ fn main() {
       }
     };
 
-    let entry_point = &bytes[0x0100..=0x0103];
-    println!("entry_point: {entry_point:?}");
+    let Some(header) = CartHeader::from_rom(&bytes) else {
+      println!("too small to have a header.");
+      continue;
+    };
+
+    println!("entry_point: {:?}", header.entry_point);
 
-    let logo = &bytes[0x0104..=0x0133];
     print!("logo:");
-    for (i, byte) in logo.iter().enumerate() {
+    for (i, byte) in header.logo.iter().enumerate() {
       if i % 16 == 0 {
         println!();
       } else {
@@ -34,13 +39,13 @@ fn main() {
     }
     println!();
 
-    let title_bytes = &bytes[0x0134..=0x0143];
+    let title_bytes = &header.title;
     match core::str::from_utf8(title_bytes) {
       Ok(title) => println!("title: {title:?}"),
       Err(_) => println!("title: {:?}", String::from_utf8_lossy(title_bytes)),
     };
 
-    let manufacture_code = &bytes[0x013F..=0x0142];
+    let manufacture_code = &header.manufacturer_code;
     match core::str::from_utf8(manufacture_code) {
       Ok(title) => println!("manufacture_code: {title:?}"),
       Err(_) => {
@@ -51,15 +56,14 @@ fn main() {
       }
     };
 
-    let cgb_flag = bytes[0x0143];
     print!("cgb_flag: ");
-    match cgb_flag {
-      0x80 => println!("Both GCB and DMG"),
-      0xC0 => println!("CGB Only"),
-      _ => println!("No Color"),
+    match header.cgb_support() {
+      CgbSupport::Compatible => println!("Both GCB and DMG"),
+      CgbSupport::Only => println!("CGB Only"),
+      CgbSupport::None => println!("No Color"),
     };
 
-    let new_licensee_code = &bytes[0x0144..=0x0145];
+    let new_licensee_code = &header.new_licensee_code;
     match core::str::from_utf8(new_licensee_code) {
       Ok(nlc) => println!("new_licensee_code: {nlc:?}"),
       Err(_) => println!(
@@ -68,27 +72,19 @@ fn main() {
       ),
     };
 
-    let sgb_flag = bytes[0x0146];
-    print!("sgb_flag: ");
-    match sgb_flag {
-      0x03 => println!("true"),
-      _ => println!("false"),
-    };
+    println!("sgb_flag: {}", header.supports_sgb());
 
-    let cart_type = bytes[0x0147];
     print!("cart_type: ");
-    match cart_type {
+    match header.cart_type {
       0x00 => println!("RomOnly"),
       0x01 => println!("MBC1"),
       other => println!("Unknown({other:?})"),
     };
 
-    let rom_size = bytes[0x0148];
-    println!("rom_size: {}kb", 32 << rom_size);
+    println!("rom_size: {}kb", 32 << header.rom_size);
 
-    let ram_size = bytes[0x0149];
     print!("ram_size: ");
-    match ram_size {
+    match header.ram_size {
       0x00 => println!("none"),
       0x01 => println!("2kb"),
       0x02 => println!("8kb"),
@@ -98,25 +94,19 @@ fn main() {
       other => println!("Unknown({other:02X})"),
     }
 
-    let destination_code = bytes[0x014A];
     print!("destination_code: ");
-    match destination_code {
+    match header.destination_code {
       0x00 => println!("Japan"),
       0x01 => println!("non-Japan"),
       other => println!("Unknown({other:?})"),
     };
 
-    let old_licensee_code = bytes[0x014B];
-    println!("old_licensee_code: {old_licensee_code:02X}");
+    println!("old_licensee_code: {:02X}", header.old_licensee_code);
 
-    let mask_rom_version = bytes[0x014C];
-    println!("mask_rom_version: {mask_rom_version}");
+    println!("mask_rom_version: {}", header.mask_rom_version);
 
-    let checksum_byte = bytes[0x014D];
-    println!("checksum_byte: {checksum_byte:02X}");
+    println!("checksum_byte: {:02X}", header.header_checksum);
 
-    let global_checksum =
-      u16::from_be_bytes(bytes[0x014E..=0x14F].try_into().unwrap());
-    println!("global_checksum: {global_checksum:04X}");
+    println!("global_checksum: {:04X}", header.global_checksum);
   }
 }
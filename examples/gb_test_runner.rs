@@ -1,12 +1,24 @@
-use kpasim::{cpu::Cpu, data_bus::DataBus, mbc::MBC1};
+use kpasim::{
+  blargg::{run_blargg, BlarggStatus},
+  boot_rom::BootRom,
+  gameboy::GameBoy,
+  model::Model,
+  trace::{set_trace_hook, trace_to_stderr},
+};
+
+/// About a minute, which is longer than any of blargg's tests take.
+const MAX_FRAMES: u32 = 60 * 60;
 
 fn main() {
   let args: Vec<String> = std::env::args().collect();
   println!("ARGS: {args:?}");
   if args.len() < 2 {
-    println!("expected a rom as arg[1]");
+    println!("expected a rom as arg[1] (and optionally a boot rom as arg[2])");
     return;
   }
+  if std::env::var_os("KPASIM_TRACE").is_some() {
+    set_trace_hook(Some(trace_to_stderr));
+  }
   let path = std::path::Path::new(&args[1]);
   print!("Reading `{}`... ", path.display());
   let bytes = match std::fs::read(path) {
@@ -20,21 +32,34 @@ fn main() {
     }
   };
 
-  let mut bus: Box<dyn DataBus> = match bytes[0x0147] {
-    0x01 => MBC1::new_boxed(bytes),
-    unknown => {
-      println!("Cart type 0x{unknown:02X} unsupported... exiting.");
-      return;
-    }
+  let gb = if let Some(boot_path) = args.get(2) {
+    let boot_rom = match std::fs::read(boot_path).map(BootRom::new) {
+      Ok(Some(boot_rom)) => boot_rom,
+      Ok(None) => {
+        println!("`{boot_path}` isn't a 256 or 2304 byte boot rom.");
+        return;
+      }
+      Err(e) => {
+        println!("{e:?}");
+        return;
+      }
+    };
+    let model = if boot_rom.is_cgb() { Model::Cgb } else { Model::Dmg };
+    GameBoy::with_boot_rom(model, bytes, boot_rom)
+  } else {
+    GameBoy::new(Model::Dmg, bytes)
+  };
+  let Some(mut gb) = gb else {
+    println!("Cart type unsupported... exiting.");
+    return;
   };
-
-  let mut cpu = Cpu::new();
   println!("==== First Boot");
-  println!(">> {cpu:?}");
+  println!(">> {:?}", gb.cpu());
 
-  loop {
-    if cpu.t_cycle(&mut *bus) {
-      println!(">> {cpu:?}");
-    }
+  let report = run_blargg(&mut gb, MAX_FRAMES);
+  println!("{}", report.output);
+  println!("==== {:?} after {} frames", report.status, report.frames);
+  if report.status != BlarggStatus::Passed {
+    std::process::exit(1);
   }
 }
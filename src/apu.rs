@@ -0,0 +1,570 @@
+//! The APU: four sound channels, mixed to stereo.
+//!
+//! The registers are `NR10` through `NR52` (`$FF10..=$FF26`) plus wave RAM
+//! (`$FF30..=$FF3F`). Timers run off the system clock, one call to
+//! [Apu::t_cycle] per dot, while lengths, envelopes, and the sweep are stepped
+//! by the frame sequencer, which is clocked by a bit of `DIV` (see
+//! [Apu::div_tick]).
+//!
+//! Samples are produced at whatever rate is set with [Apu::set_sample_rate],
+//! by taking the current output level every so many dots. There's no
+//! filtering, so the result is a little harsh compared to the real thing.
+//!
+//! * See Also: [Pandocs: Audio](https://gbdev.io/pandocs/Audio.html)
+
+use alloc::vec::Vec;
+
+use bitfrob::u8_get_bit;
+
+/// System T-cycles (dots) per second.
+pub const DOTS_PER_SECOND: u32 = 4_194_304;
+
+/// Bits that always read as 1 in each register, `$FF10..=$FF2F`.
+const READ_MASKS: [u8; 0x20] = [
+  0x80, 0x3F, 0x00, 0xFF, 0xBF, // NR10-NR14
+  0xFF, 0x3F, 0x00, 0xFF, 0xBF, // NR20-NR24
+  0x7F, 0xFF, 0x9F, 0xFF, 0xBF, // NR30-NR34
+  0xFF, 0xFF, 0x00, 0x00, 0xBF, // NR40-NR44
+  0x00, 0x00, 0x70, // NR50-NR52
+  0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+];
+
+/// The 8 step duty patterns for the square channels.
+const DUTY_PATTERNS: [u8; 4] =
+  [0b0000_0001, 0b1000_0001, 0b1000_0111, 0b0111_1110];
+
+/// Noise channel divisors, by the low 3 bits of `NR43`.
+const NOISE_DIVISORS: [u16; 8] = [8, 16, 32, 48, 64, 80, 96, 112];
+
+/// State shared by every channel: on/off and the length counter.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+struct Length {
+  enabled: bool,
+  counter: u16,
+}
+impl Length {
+  fn step(&mut self, on: &mut bool) {
+    if self.enabled && self.counter > 0 {
+      self.counter -= 1;
+      if self.counter == 0 {
+        *on = false;
+      }
+    }
+  }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+struct Envelope {
+  volume: u8,
+  timer: u8,
+}
+impl Envelope {
+  fn trigger(&mut self, nrx2: u8) {
+    self.volume = nrx2 >> 4;
+    self.timer = nrx2 & 0b111;
+  }
+  fn step(&mut self, nrx2: u8) {
+    let period = nrx2 & 0b111;
+    if period == 0 {
+      return;
+    }
+    self.timer = self.timer.saturating_sub(1);
+    if self.timer == 0 {
+      self.timer = period;
+      if u8_get_bit(3, nrx2) {
+        if self.volume < 15 {
+          self.volume += 1;
+        }
+      } else if self.volume > 0 {
+        self.volume -= 1;
+      }
+    }
+  }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+struct Square {
+  on: bool,
+  length: Length,
+  envelope: Envelope,
+  timer: u16,
+  duty_step: u8,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+struct Sweep {
+  enabled: bool,
+  shadow: u16,
+  timer: u8,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+struct Wave {
+  on: bool,
+  length: Length,
+  timer: u16,
+  position: u8,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+struct Noise {
+  on: bool,
+  length: Length,
+  envelope: Envelope,
+  timer: u16,
+  lfsr: u16,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Apu {
+  /// Raw register values, `$FF10..=$FF3F`.
+  regs: [u8; 0x30],
+  powered: bool,
+  ch1: Square,
+  sweep: Sweep,
+  ch2: Square,
+  ch3: Wave,
+  ch4: Noise,
+  frame_step: u8,
+  sample_rate: u32,
+  sample_acc: u32,
+  samples: Vec<[i16; 2]>,
+}
+impl Default for Apu {
+  #[inline]
+  fn default() -> Self {
+    Self::new()
+  }
+}
+impl Apu {
+  /// Makes a powered off APU that doesn't produce samples.
+  #[must_use]
+  pub fn new() -> Self {
+    Self {
+      regs: [0; 0x30],
+      powered: false,
+      ch1: Square::default(),
+      sweep: Sweep::default(),
+      ch2: Square::default(),
+      ch3: Wave::default(),
+      ch4: Noise::default(),
+      frame_step: 0,
+      sample_rate: 0,
+      sample_acc: 0,
+      samples: Vec::new(),
+    }
+  }
+
+  /// Sets the output sample rate (in Hz). A rate of 0 turns off sampling.
+  #[inline]
+  pub fn set_sample_rate(&mut self, sample_rate: u32) {
+    self.sample_rate = sample_rate.min(DOTS_PER_SECOND);
+    self.sample_acc = 0;
+  }
+
+  /// The stereo samples (`[left, right]`) made since the last
+  /// [clear_samples](Apu::clear_samples).
+  #[inline]
+  #[must_use]
+  pub fn samples(&self) -> &[[i16; 2]] {
+    &self.samples
+  }
+  #[inline]
+  pub fn clear_samples(&mut self) {
+    self.samples.clear();
+  }
+
+  #[inline]
+  #[must_use]
+  pub const fn is_powered(&self) -> bool {
+    self.powered
+  }
+
+  #[inline]
+  #[must_use]
+  fn reg(&self, addr: u16) -> u8 {
+    self.regs[usize::from(addr - 0xFF10)]
+  }
+
+  /// Reads an APU register or wave RAM.
+  #[must_use]
+  pub fn read_reg(&self, addr: u16) -> u8 {
+    match addr {
+      0xFF26 => {
+        let mut nr52 = 0x70 | if self.powered { 0x80 } else { 0 };
+        for (bit, on) in [self.ch1.on, self.ch2.on, self.ch3.on, self.ch4.on]
+          .iter()
+          .enumerate()
+        {
+          if *on {
+            nr52 |= 1 << bit;
+          }
+        }
+        nr52
+      }
+      0xFF10..=0xFF2F => {
+        self.reg(addr) | READ_MASKS[usize::from(addr - 0xFF10)]
+      }
+      0xFF30..=0xFF3F => self.reg(addr),
+      _ => 0xFF,
+    }
+  }
+
+  /// Writes an APU register or wave RAM.
+  ///
+  /// While the APU is powered off, only `NR52` and wave RAM can be written.
+  pub fn write_reg(&mut self, addr: u16, byte: u8) {
+    match addr {
+      0xFF26 => {
+        let power = u8_get_bit(7, byte);
+        if self.powered && !power {
+          let wave_ram = self.regs[0x20..].to_vec();
+          *self = Self {
+            sample_rate: self.sample_rate,
+            samples: core::mem::take(&mut self.samples),
+            ..Self::new()
+          };
+          self.regs[0x20..].copy_from_slice(&wave_ram);
+        } else if !self.powered && power {
+          self.frame_step = 0;
+        }
+        self.powered = power;
+      }
+      0xFF30..=0xFF3F => self.regs[usize::from(addr - 0xFF10)] = byte,
+      0xFF10..=0xFF25 if self.powered => {
+        self.regs[usize::from(addr - 0xFF10)] = byte;
+        self.reg_written(addr, byte);
+      }
+      _ => (),
+    }
+  }
+
+  fn reg_written(&mut self, addr: u16, byte: u8) {
+    match addr {
+      0xFF11 => self.ch1.length.counter = 64 - u16::from(byte & 0x3F),
+      0xFF16 => self.ch2.length.counter = 64 - u16::from(byte & 0x3F),
+      0xFF1B => self.ch3.length.counter = 256 - u16::from(byte),
+      0xFF20 => self.ch4.length.counter = 64 - u16::from(byte & 0x3F),
+      0xFF12 if byte & 0xF8 == 0 => self.ch1.on = false,
+      0xFF17 if byte & 0xF8 == 0 => self.ch2.on = false,
+      0xFF1A if !u8_get_bit(7, byte) => self.ch3.on = false,
+      0xFF21 if byte & 0xF8 == 0 => self.ch4.on = false,
+      0xFF14 => {
+        self.ch1.length.enabled = u8_get_bit(6, byte);
+        if u8_get_bit(7, byte) {
+          self.trigger_ch1();
+        }
+      }
+      0xFF19 => {
+        self.ch2.length.enabled = u8_get_bit(6, byte);
+        if u8_get_bit(7, byte) {
+          let nr22 = self.reg(0xFF17);
+          let period = self.square_period(0xFF18);
+          Self::trigger_square(&mut self.ch2, nr22, period);
+        }
+      }
+      0xFF1E => {
+        self.ch3.length.enabled = u8_get_bit(6, byte);
+        if u8_get_bit(7, byte) {
+          let dac_on = u8_get_bit(7, self.reg(0xFF1A));
+          let timer = (2048 - self.freq(0xFF1D)) * 2;
+          let ch3 = &mut self.ch3;
+          ch3.on = dac_on;
+          if ch3.length.counter == 0 {
+            ch3.length.counter = 256;
+          }
+          ch3.timer = timer;
+          ch3.position = 0;
+        }
+      }
+      0xFF23 => {
+        self.ch4.length.enabled = u8_get_bit(6, byte);
+        if u8_get_bit(7, byte) {
+          let nr42 = self.reg(0xFF21);
+          let timer = self.noise_period();
+          let ch4 = &mut self.ch4;
+          ch4.on = nr42 & 0xF8 != 0;
+          if ch4.length.counter == 0 {
+            ch4.length.counter = 64;
+          }
+          ch4.envelope.trigger(nr42);
+          ch4.timer = timer;
+          ch4.lfsr = 0x7FFF;
+        }
+      }
+      _ => (),
+    }
+  }
+
+  /// The 11 bit frequency from `NRx3` and the low bits of `NRx4`.
+  #[inline]
+  #[must_use]
+  fn freq(&self, nrx3: u16) -> u16 {
+    u16::from(self.reg(nrx3)) | (u16::from(self.reg(nrx3 + 1) & 0b111) << 8)
+  }
+
+  #[inline]
+  #[must_use]
+  fn square_period(&self, nrx3: u16) -> u16 {
+    (2048 - self.freq(nrx3)) * 4
+  }
+
+  #[inline]
+  #[must_use]
+  fn noise_period(&self) -> u16 {
+    let nr43 = self.reg(0xFF22);
+    NOISE_DIVISORS[usize::from(nr43 & 0b111)] << (nr43 >> 4)
+  }
+
+  fn trigger_square(ch: &mut Square, nrx2: u8, period: u16) {
+    ch.on = nrx2 & 0xF8 != 0;
+    if ch.length.counter == 0 {
+      ch.length.counter = 64;
+    }
+    ch.envelope.trigger(nrx2);
+    ch.timer = period;
+  }
+
+  fn trigger_ch1(&mut self) {
+    let nr12 = self.reg(0xFF12);
+    let period = self.square_period(0xFF13);
+    Self::trigger_square(&mut self.ch1, nr12, period);
+    let nr10 = self.reg(0xFF10);
+    let sweep_period = (nr10 >> 4) & 0b111;
+    let shift = nr10 & 0b111;
+    self.sweep.shadow = self.freq(0xFF13);
+    self.sweep.timer = if sweep_period == 0 { 8 } else { sweep_period };
+    self.sweep.enabled = sweep_period != 0 || shift != 0;
+    if shift != 0 && self.sweep_target() > 2047 {
+      self.ch1.on = false;
+    }
+  }
+
+  #[inline]
+  #[must_use]
+  fn sweep_target(&self) -> u16 {
+    let nr10 = self.reg(0xFF10);
+    let delta = self.sweep.shadow >> (nr10 & 0b111);
+    if u8_get_bit(3, nr10) {
+      self.sweep.shadow - delta
+    } else {
+      self.sweep.shadow + delta
+    }
+  }
+
+  fn step_sweep(&mut self) {
+    self.sweep.timer = self.sweep.timer.saturating_sub(1);
+    if self.sweep.timer != 0 {
+      return;
+    }
+    let nr10 = self.reg(0xFF10);
+    let period = (nr10 >> 4) & 0b111;
+    self.sweep.timer = if period == 0 { 8 } else { period };
+    if !self.sweep.enabled || period == 0 {
+      return;
+    }
+    let target = self.sweep_target();
+    if target > 2047 {
+      self.ch1.on = false;
+    } else if nr10 & 0b111 != 0 {
+      self.sweep.shadow = target;
+      self.regs[0x03] = target as u8;
+      self.regs[0x04] = (self.regs[0x04] & !0b111) | (target >> 8) as u8;
+      if self.sweep_target() > 2047 {
+        self.ch1.on = false;
+      }
+    }
+  }
+
+  /// Steps the frame sequencer.
+  ///
+  /// This should be called each time bit 4 of `DIV` (bit 5 in double speed
+  /// mode) goes from 1 to 0, which is 512 times a second.
+  pub fn div_tick(&mut self) {
+    if !self.powered {
+      return;
+    }
+    let step = self.frame_step;
+    self.frame_step = (step + 1) & 7;
+    if step.is_multiple_of(2) {
+      self.ch1.length.step(&mut self.ch1.on);
+      self.ch2.length.step(&mut self.ch2.on);
+      self.ch3.length.step(&mut self.ch3.on);
+      self.ch4.length.step(&mut self.ch4.on);
+    }
+    if step == 2 || step == 6 {
+      self.step_sweep();
+    }
+    if step == 7 {
+      self.ch1.envelope.step(self.reg(0xFF12));
+      self.ch2.envelope.step(self.reg(0xFF17));
+      self.ch4.envelope.step(self.reg(0xFF21));
+    }
+  }
+
+  /// Grants a T-cycle (one dot) worth of time to the APU.
+  pub fn t_cycle(&mut self) {
+    if self.powered {
+      self.step_timers();
+    }
+    if self.sample_rate != 0 {
+      self.sample_acc += self.sample_rate;
+      if self.sample_acc >= DOTS_PER_SECOND {
+        self.sample_acc -= DOTS_PER_SECOND;
+        let sample = self.mix();
+        self.samples.push(sample);
+      }
+    }
+  }
+
+  /// Grants many dots worth of time to the APU at once.
+  ///
+  /// This gives the same result as calling [t_cycle](Apu::t_cycle) that many
+  /// times, but skips straight over the dots where no channel timer runs out
+  /// and no sample is due.
+  pub fn advance(&mut self, mut dots: u32) {
+    while dots > 0 {
+      let mut step = dots;
+      if self.powered {
+        for timer in
+          [self.ch1.timer, self.ch2.timer, self.ch3.timer, self.ch4.timer]
+        {
+          step = step.min(u32::from(timer).max(1));
+        }
+      }
+      if self.sample_rate != 0 {
+        let until_sample =
+          (DOTS_PER_SECOND - self.sample_acc).div_ceil(self.sample_rate);
+        step = step.min(until_sample);
+      }
+      // Every dot before the last one of the step only counts down.
+      let plain = step - 1;
+      if self.powered {
+        for timer in [
+          &mut self.ch1.timer,
+          &mut self.ch2.timer,
+          &mut self.ch3.timer,
+          &mut self.ch4.timer,
+        ] {
+          *timer = timer.saturating_sub(plain as u16);
+        }
+      }
+      if self.sample_rate != 0 {
+        self.sample_acc += self.sample_rate * plain;
+      }
+      self.t_cycle();
+      dots -= step;
+    }
+  }
+
+  fn step_timers(&mut self) {
+    self.ch1.timer = self.ch1.timer.saturating_sub(1);
+    if self.ch1.timer == 0 {
+      self.ch1.timer = self.square_period(0xFF13);
+      self.ch1.duty_step = (self.ch1.duty_step + 1) & 7;
+    }
+    self.ch2.timer = self.ch2.timer.saturating_sub(1);
+    if self.ch2.timer == 0 {
+      self.ch2.timer = self.square_period(0xFF18);
+      self.ch2.duty_step = (self.ch2.duty_step + 1) & 7;
+    }
+    self.ch3.timer = self.ch3.timer.saturating_sub(1);
+    if self.ch3.timer == 0 {
+      self.ch3.timer = (2048 - self.freq(0xFF1D)) * 2;
+      self.ch3.position = (self.ch3.position + 1) & 31;
+    }
+    self.ch4.timer = self.ch4.timer.saturating_sub(1);
+    if self.ch4.timer == 0 {
+      self.ch4.timer = self.noise_period();
+      let lfsr = self.ch4.lfsr;
+      let bit = (lfsr ^ (lfsr >> 1)) & 1;
+      let mut lfsr = (lfsr >> 1) | (bit << 14);
+      if u8_get_bit(3, self.reg(0xFF22)) {
+        lfsr = (lfsr & !(1 << 6)) | (bit << 6);
+      }
+      self.ch4.lfsr = lfsr;
+    }
+  }
+
+  /// The digital output (0 to 15) of each channel, or `None` for a channel
+  /// with its DAC off.
+  #[must_use]
+  fn channel_outputs(&self) -> [Option<u8>; 4] {
+    let square = |ch: &Square, nrx1: u8, nrx2: u8| {
+      if nrx2 & 0xF8 == 0 {
+        return None;
+      }
+      let duty = DUTY_PATTERNS[usize::from(nrx1 >> 6)];
+      let high = u8_get_bit(u32::from(ch.duty_step), duty);
+      Some(if ch.on && high { ch.envelope.volume } else { 0 })
+    };
+    let ch1 = square(&self.ch1, self.reg(0xFF11), self.reg(0xFF12));
+    let ch2 = square(&self.ch2, self.reg(0xFF16), self.reg(0xFF17));
+    let ch3 = if u8_get_bit(7, self.reg(0xFF1A)) {
+      let byte = self.regs[0x20 + usize::from(self.ch3.position / 2)];
+      let sample = if self.ch3.position.is_multiple_of(2) {
+        byte >> 4
+      } else {
+        byte & 0xF
+      };
+      let shift = match (self.reg(0xFF1C) >> 5) & 0b11 {
+        0 => 4,
+        n => n - 1,
+      };
+      Some(if self.ch3.on { sample >> shift } else { 0 })
+    } else {
+      None
+    };
+    let ch4 = if self.reg(0xFF21) & 0xF8 != 0 {
+      let high = self.ch4.lfsr & 1 == 0;
+      Some(if self.ch4.on && high { self.ch4.envelope.volume } else { 0 })
+    } else {
+      None
+    };
+    [ch1, ch2, ch3, ch4]
+  }
+
+  /// The current `[left, right]` output level.
+  #[must_use]
+  fn mix(&self) -> [i16; 2] {
+    if !self.powered {
+      return [0, 0];
+    }
+    let nr50 = self.reg(0xFF24);
+    let nr51 = self.reg(0xFF25);
+    let mut out = [0_i16; 2];
+    for (i, level) in self.channel_outputs().iter().enumerate() {
+      // Each DAC maps 0..=15 to a level from +1 down to -1.
+      let Some(level) = level else { continue };
+      let analog = 15 - 2 * i16::from(*level);
+      if u8_get_bit(i as u32 + 4, nr51) {
+        out[0] += analog;
+      }
+      if u8_get_bit(i as u32, nr51) {
+        out[1] += analog;
+      }
+    }
+    let left_volume = i16::from((nr50 >> 4) & 0b111) + 1;
+    let right_volume = i16::from(nr50 & 0b111) + 1;
+    [out[0] * left_volume * 64, out[1] * right_volume * 64]
+  }
+}
+
+#[test]
+fn test_Apu_length() {
+  let mut apu = Apu::new();
+  apu.write_reg(0xFF12, 0xF0);
+  assert_eq!(apu.read_reg(0xFF12), 0x00);
+  apu.write_reg(0xFF26, 0x80);
+  apu.write_reg(0xFF12, 0xF0);
+  apu.write_reg(0xFF11, 0x3E);
+  apu.write_reg(0xFF14, 0xC0);
+  assert_eq!(apu.read_reg(0xFF26), 0xF1);
+  apu.div_tick();
+  assert_eq!(apu.read_reg(0xFF26), 0xF1);
+  apu.div_tick();
+  apu.div_tick();
+  assert_eq!(apu.read_reg(0xFF26), 0xF0);
+  apu.write_reg(0xFF26, 0x00);
+  assert_eq!(apu.read_reg(0xFF11), 0x3F);
+}
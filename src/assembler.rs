@@ -0,0 +1,920 @@
+//! An SM83 assembler for RGBDS style source, mostly so that tests can be
+//! written as assembly instead of hand encoded bytes.
+//!
+//! This covers a small part of what `rgbasm` does:
+//!
+//! * Every instruction, written the way [Instruction]'s `Display` writes it.
+//! * Labels (`Name:`, `Name::`), local labels (`.loop:`), and constants
+//!   (`NAME EQU expr` or `DEF NAME EQU expr`), which can be used before
+//!   they're defined.
+//! * `db`, `dw`, and `ds` (strings are allowed in `db`).
+//! * `SECTION "name", KIND[$addr]` with an optional `BANK[n]`. Without an
+//!   address the section follows the previous one of the same kind.
+//! * Expressions with `@`, numbers in any RGBDS base, parentheses, the usual
+//!   operators, and the `HIGH`, `LOW`, and `BANK` functions.
+//!
+//! There are no macros, `INCLUDE`, or `IF` blocks. Like `rgbasm`, `stop`
+//! assembles to `$10 $00`.
+//!
+//! ```
+//! # use kpasim::assembler::assemble;
+//! assert_eq!(assemble("ld a, 5\n add a, b"), Ok(vec![0x3E, 0x05, 0x80]));
+//! ```
+
+use alloc::{
+  collections::BTreeMap,
+  string::{String, ToString},
+  vec::Vec,
+};
+use core::fmt::{Display, Formatter};
+
+use crate::{
+  instruction::{parse_number, Instruction, Operand},
+  mbc::ROM_BANK_SIZE,
+};
+
+/// Why source couldn't be assembled.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum AsmErrorKind {
+  /// The line doesn't make sense.
+  Syntax,
+  /// There's no instruction with that mnemonic and those operands, or an
+  /// operand is out of range.
+  BadInstruction,
+  /// A symbol that's never defined.
+  UnknownSymbol(String),
+  /// A label or constant that's defined more than once.
+  Redefined(String),
+  /// A value that doesn't fit in the space for it.
+  OutOfRange,
+  /// Division or modulo by zero.
+  DivideByZero,
+  /// A section that overlaps another, or runs past the end of its area.
+  Overlap,
+}
+
+/// An error, and the line (starting from 1) where it happened.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct AsmError {
+  pub line: usize,
+  pub kind: AsmErrorKind,
+}
+impl Display for AsmError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+    write!(f, "line {}: ", self.line)?;
+    match &self.kind {
+      AsmErrorKind::Syntax => write!(f, "syntax error"),
+      AsmErrorKind::BadInstruction => write!(f, "no such instruction"),
+      AsmErrorKind::UnknownSymbol(name) => write!(f, "`{name}` isn't defined"),
+      AsmErrorKind::Redefined(name) => {
+        write!(f, "`{name}` is already defined")
+      }
+      AsmErrorKind::OutOfRange => write!(f, "value out of range"),
+      AsmErrorKind::DivideByZero => write!(f, "division by zero"),
+      AsmErrorKind::Overlap => write!(f, "section overlaps another"),
+    }
+  }
+}
+
+/// The memory area that a section goes in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SectionKind {
+  Rom0,
+  RomX,
+  Vram,
+  Sram,
+  Wram0,
+  WramX,
+  Oam,
+  Hram,
+}
+impl SectionKind {
+  fn parse(text: &str) -> Option<Self> {
+    Some(match text.to_ascii_uppercase().as_str() {
+      "ROM0" => Self::Rom0,
+      "ROMX" => Self::RomX,
+      "VRAM" => Self::Vram,
+      "SRAM" => Self::Sram,
+      "WRAM0" => Self::Wram0,
+      "WRAMX" => Self::WramX,
+      "OAM" => Self::Oam,
+      "HRAM" => Self::Hram,
+      _ => return None,
+    })
+  }
+
+  /// The first address of the area, and one past the last.
+  const fn range(self) -> (u32, u32) {
+    match self {
+      Self::Rom0 => (0x0000, 0x4000),
+      Self::RomX => (0x4000, 0x8000),
+      Self::Vram => (0x8000, 0xA000),
+      Self::Sram => (0xA000, 0xC000),
+      Self::Wram0 => (0xC000, 0xD000),
+      Self::WramX => (0xD000, 0xE000),
+      Self::Oam => (0xFE00, 0xFEA0),
+      Self::Hram => (0xFF80, 0xFFFF),
+    }
+  }
+}
+
+/// A block of assembled bytes.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Section {
+  pub name: String,
+  pub kind: SectionKind,
+  /// The address of the first byte.
+  pub addr: u16,
+  /// The bank, which is 0 for areas that don't have banks.
+  pub bank: usize,
+  pub bytes: Vec<u8>,
+}
+impl Section {
+  /// Where the section goes in a ROM image, if it's in ROM.
+  #[must_use]
+  pub fn rom_offset(&self) -> Option<usize> {
+    match self.kind {
+      SectionKind::Rom0 => Some(usize::from(self.addr)),
+      SectionKind::RomX => {
+        Some(self.bank * ROM_BANK_SIZE + usize::from(self.addr - 0x4000))
+      }
+      _ => None,
+    }
+  }
+}
+
+/// The value of a label or constant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+struct Symbol {
+  value: i32,
+  bank: i32,
+}
+
+/// Passes before the last one, at most. Each pass resolves at least one more
+/// level of constants defined in terms of later labels.
+const MAX_PASSES: usize = 16;
+
+/// Assembled source.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Program {
+  pub sections: Vec<Section>,
+  symbols: BTreeMap<String, Symbol>,
+}
+impl Program {
+  /// Assembles some source.
+  pub fn assemble(source: &str) -> Result<Self, AsmError> {
+    let lines = parse(source)?;
+    let mut program = Self::default();
+    // Early passes find every label, going again while constants that depend
+    // on later labels are still being worked out. The last pass fills in
+    // values.
+    for _ in 0..MAX_PASSES {
+      let previous = program.symbols.clone();
+      program.pass(&lines, false)?;
+      if program.symbols == previous {
+        break;
+      }
+    }
+    program.pass(&lines, true)?;
+    program.check_overlap(&lines)?;
+    Ok(program)
+  }
+
+  /// The value of a label or constant. Local labels are written in full,
+  /// like `Main.loop`.
+  #[must_use]
+  pub fn symbol(&self, name: &str) -> Option<i32> {
+    self.symbols.get(name).map(|s| s.value)
+  }
+
+  /// A ROM image of all the `ROM0` and `ROMX` sections.
+  ///
+  /// The image ends after the last section, and gaps are filled with `$00`
+  /// (like `rgblink` does).
+  #[must_use]
+  pub fn to_rom(&self) -> Vec<u8> {
+    let mut rom = Vec::new();
+    for section in &self.sections {
+      if let Some(offset) = section.rom_offset() {
+        let end = offset + section.bytes.len();
+        if rom.len() < end {
+          rom.resize(end, 0);
+        }
+        rom[offset..end].copy_from_slice(&section.bytes);
+      }
+    }
+    rom
+  }
+
+  fn pass(&mut self, lines: &[Line<'_>], last: bool) -> Result<(), AsmError> {
+    self.sections.clear();
+    let previous = core::mem::take(&mut self.symbols);
+    for line in lines {
+      let mut ctx =
+        Ctx { program: self, previous: &previous, scope: line.scope, last };
+      let result = match &line.statement {
+        Statement::Label(name) => ctx.define_label(name),
+        Statement::Equ(name, expr) => ctx.define_equ(name, expr),
+        Statement::Section { name, kind, addr, bank } => {
+          ctx.start_section(name, *kind, *addr, *bank)
+        }
+        Statement::Data { width, items } => ctx.data(*width, items),
+        Statement::Space { count, fill } => ctx.space(count, *fill),
+        Statement::Op { mnemonic, args } => ctx.instruction(mnemonic, args),
+      };
+      result.map_err(|kind| AsmError { line: line.number, kind })?;
+    }
+    Ok(())
+  }
+
+  fn check_overlap(&self, lines: &[Line<'_>]) -> Result<(), AsmError> {
+    let area = |s: &Section| {
+      let start = u32::from(s.addr);
+      (start, start + s.bytes.len() as u32)
+    };
+    for (i, a) in self.sections.iter().enumerate() {
+      let (start, end) = area(a);
+      let overlaps = end > a.kind.range().1
+        || self.sections[..i].iter().any(|b| {
+          let (b_start, b_end) = area(b);
+          a.kind == b.kind
+            && a.bank == b.bank
+            && start < b_end
+            && b_start < end
+            && start != end
+        });
+      if overlaps {
+        let line = lines
+          .iter()
+          .find(|l| matches!(&l.statement, Statement::Section { name, .. } if *name == a.name))
+          .map_or(0, |l| l.number);
+        return Err(AsmError { line, kind: AsmErrorKind::Overlap });
+      }
+    }
+    Ok(())
+  }
+}
+
+/// Assembles some source into a ROM image.
+///
+/// Sections that aren't in ROM are left out, use [Program] to get those.
+pub fn assemble(source: &str) -> Result<Vec<u8>, AsmError> {
+  Program::assemble(source).map(|p| p.to_rom())
+}
+
+/// An instruction operand, before any expression in it has a value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Arg<'s> {
+  Fixed(Operand),
+  Imm(&'s str),
+  Mem(&'s str),
+  SpOffset(&'s str),
+}
+
+/// One item in a `db` or `dw`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Item<'s> {
+  Expr(&'s str),
+  Str(&'s str),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Statement<'s> {
+  Label(String),
+  Equ(String, &'s str),
+  Section {
+    name: &'s str,
+    kind: SectionKind,
+    addr: Option<&'s str>,
+    bank: Option<&'s str>,
+  },
+  Data {
+    width: usize,
+    items: Vec<Item<'s>>,
+  },
+  Space {
+    count: &'s str,
+    fill: Option<&'s str>,
+  },
+  Op {
+    mnemonic: String,
+    args: Vec<Arg<'s>>,
+  },
+}
+
+struct Line<'s> {
+  number: usize,
+  /// The last global label, for local labels.
+  scope: &'s str,
+  statement: Statement<'s>,
+}
+
+/// Splits the source into statements, which don't depend on any values.
+fn parse(source: &str) -> Result<Vec<Line<'_>>, AsmError> {
+  let mut lines = Vec::new();
+  let mut scope = "";
+  for (i, text) in source.lines().enumerate() {
+    let number = i + 1;
+    let error = |kind| AsmError { line: number, kind };
+    let mut text = strip_comment(text).trim();
+    // labels
+    let ident_len = text.find(|c| !is_ident_char(c)).unwrap_or(text.len());
+    if ident_len > 0 && text[ident_len..].starts_with(':') {
+      let name = &text[..ident_len];
+      if !name.starts_with('.') {
+        scope = name.split('.').next().unwrap_or(name);
+      }
+      let statement = Statement::Label(qualify(name, scope));
+      lines.push(Line { number, scope, statement });
+      text = text[ident_len..].trim_start_matches(':').trim();
+    }
+    if text.is_empty() {
+      continue;
+    }
+    let (word, rest) =
+      text.split_once(char::is_whitespace).unwrap_or((text, ""));
+    let rest = rest.trim();
+    let lower = word.to_ascii_lowercase();
+    // constants
+    let equ = if lower == "def" { rest } else { text };
+    let equ = equ.split_once(char::is_whitespace).and_then(|(name, value)| {
+      let value = value.trim();
+      let keyword = value.get(..4)?;
+      keyword.eq_ignore_ascii_case("equ ").then(|| (name, value[4..].trim()))
+    });
+    let args = split_args(rest).ok_or(error(AsmErrorKind::Syntax))?;
+    let statement = match (equ, lower.as_str()) {
+      (Some((name, value)), _) => Statement::Equ(qualify(name, scope), value),
+      (_, "section") => {
+        parse_section(&args).ok_or(error(AsmErrorKind::Syntax))?
+      }
+      (_, "db" | "dw") => Statement::Data {
+        width: if lower == "db" { 1 } else { 2 },
+        items: args
+          .iter()
+          .map(|arg| match arg.strip_prefix('"') {
+            Some(s) => s.strip_suffix('"').map(Item::Str),
+            None => Some(Item::Expr(arg)),
+          })
+          .collect::<Option<_>>()
+          .ok_or(error(AsmErrorKind::Syntax))?,
+      },
+      (_, "ds") => match args[..] {
+        [count] => Statement::Space { count, fill: None },
+        [count, fill] => Statement::Space { count, fill: Some(fill) },
+        _ => return Err(error(AsmErrorKind::Syntax)),
+      },
+      _ => Statement::Op {
+        mnemonic: lower,
+        args: args.iter().map(|arg| parse_arg(arg)).collect(),
+      },
+    };
+    lines.push(Line { number, scope, statement });
+  }
+  Ok(lines)
+}
+
+fn parse_section<'s>(args: &[&'s str]) -> Option<Statement<'s>> {
+  let name = args.first()?.strip_prefix('"')?.strip_suffix('"')?;
+  // `KIND[addr]` or `BANK[n]`
+  let bracketed = |arg: &'s str| match arg.split_once('[') {
+    Some((word, rest)) => Some((word.trim(), Some(rest.strip_suffix(']')?))),
+    None => Some((arg, None)),
+  };
+  let (kind, addr) = bracketed(args.get(1)?)?;
+  let bank = match args.get(2) {
+    Some(arg) => match bracketed(arg)? {
+      (word, Some(bank)) if word.eq_ignore_ascii_case("bank") => Some(bank),
+      _ => return None,
+    },
+    None => None,
+  };
+  if args.len() > 3 {
+    return None;
+  }
+  Some(Statement::Section { name, kind: SectionKind::parse(kind)?, addr, bank })
+}
+
+fn parse_arg(arg: &str) -> Arg<'_> {
+  if let Some(inner) = arg.strip_prefix('[').and_then(|a| a.strip_suffix(']')) {
+    let special: String =
+      inner.chars().filter(|c| !c.is_whitespace()).collect();
+    match special.to_ascii_lowercase().as_str() {
+      "hl" | "bc" | "de" | "hl+" | "hli" | "hl-" | "hld" | "c" | "$ff00+c" => {
+        if let Some(op) = Operand::parse(arg) {
+          return Arg::Fixed(op);
+        }
+      }
+      _ => return Arg::Mem(inner),
+    }
+  }
+  let lower = arg.to_ascii_lowercase();
+  match lower.as_str() {
+    "a" | "b" | "c" | "d" | "e" | "h" | "l" | "bc" | "de" | "hl" | "sp"
+    | "af" | "nz" | "z" | "nc" => Arg::Fixed(Operand::parse(arg).unwrap()),
+    _ => match lower.strip_prefix("sp") {
+      Some(rest) if rest.trim_start().starts_with(['+', '-']) => {
+        Arg::SpOffset(arg[2..].trim())
+      }
+      _ => Arg::Imm(arg),
+    },
+  }
+}
+
+/// Cuts off a `;` comment that isn't in a string.
+fn strip_comment(text: &str) -> &str {
+  let mut in_string = false;
+  for (i, c) in text.char_indices() {
+    match c {
+      '"' => in_string = !in_string,
+      ';' if !in_string => return &text[..i],
+      _ => (),
+    }
+  }
+  text
+}
+
+/// Splits on commas that aren't in a string or parentheses.
+fn split_args(text: &str) -> Option<Vec<&str>> {
+  let mut args = Vec::new();
+  if text.is_empty() {
+    return Some(args);
+  }
+  let (mut depth, mut in_string, mut start) = (0_i32, false, 0);
+  for (i, c) in text.char_indices() {
+    match c {
+      '"' => in_string = !in_string,
+      '(' | '[' if !in_string => depth += 1,
+      ')' | ']' if !in_string => depth -= 1,
+      ',' if !in_string && depth == 0 => {
+        args.push(text[start..i].trim());
+        start = i + 1;
+      }
+      _ => (),
+    }
+  }
+  args.push(text[start..].trim());
+  (!in_string && depth == 0 && args.iter().all(|a| !a.is_empty()))
+    .then_some(args)
+}
+
+fn is_ident_char(c: char) -> bool {
+  c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '#')
+}
+
+/// The full name of a label, which is only different for local labels.
+fn qualify(name: &str, scope: &str) -> String {
+  if name.starts_with('.') {
+    let mut full = String::from(scope);
+    full.push_str(name);
+    full
+  } else {
+    name.to_string()
+  }
+}
+
+/// The state while a line is assembled.
+struct Ctx<'p, 's> {
+  program: &'p mut Program,
+  /// The symbols from the pass before, for anything not defined yet in this
+  /// one.
+  previous: &'p BTreeMap<String, Symbol>,
+  scope: &'s str,
+  /// If this is the last pass, so every symbol has to be known.
+  last: bool,
+}
+impl Ctx<'_, '_> {
+  fn section(&mut self) -> &mut Section {
+    if self.program.sections.is_empty() {
+      self.program.sections.push(Section {
+        name: String::new(),
+        kind: SectionKind::Rom0,
+        addr: 0,
+        bank: 0,
+        bytes: Vec::new(),
+      });
+    }
+    self.program.sections.last_mut().unwrap()
+  }
+
+  fn pc(&mut self) -> i32 {
+    let section = self.section();
+    i32::from(section.addr) + section.bytes.len() as i32
+  }
+
+  fn define(&mut self, name: &str, symbol: Symbol) -> Result<(), AsmErrorKind> {
+    if self.program.symbols.insert(name.to_string(), symbol).is_some() {
+      return Err(AsmErrorKind::Redefined(name.to_string()));
+    }
+    Ok(())
+  }
+
+  fn define_label(&mut self, name: &str) -> Result<(), AsmErrorKind> {
+    let value = self.pc();
+    let bank = self.section().bank as i32;
+    self.define(name, Symbol { value, bank })
+  }
+
+  fn define_equ(&mut self, name: &str, expr: &str) -> Result<(), AsmErrorKind> {
+    // A constant that depends on something unknown stays undefined, so that
+    // it's unknown to everything using it too, until a later pass.
+    match self.eval(expr)? {
+      Some(value) => self.define(name, Symbol { value, bank: 0 }),
+      None => Ok(()),
+    }
+  }
+
+  fn start_section(
+    &mut self, name: &str, kind: SectionKind, addr: Option<&str>,
+    bank: Option<&str>,
+  ) -> Result<(), AsmErrorKind> {
+    let bank = match (kind, bank) {
+      (_, Some(expr)) => self.eval_known(expr)?,
+      (SectionKind::RomX, None) => 1,
+      _ => 0,
+    };
+    let bank = usize::try_from(bank).map_err(|_| AsmErrorKind::OutOfRange)?;
+    let (start, end) = kind.range();
+    let addr = match addr {
+      Some(expr) => self.eval_known(expr)? as u32,
+      // after the last section of the same kind, or at the start of the area.
+      None => self
+        .program
+        .sections
+        .iter()
+        .rev()
+        .find(|s| s.kind == kind && s.bank == bank)
+        .map_or(start, |s| u32::from(s.addr) + s.bytes.len() as u32),
+    };
+    if !(start..end).contains(&addr) {
+      return Err(AsmErrorKind::OutOfRange);
+    }
+    self.program.sections.push(Section {
+      name: name.to_string(),
+      kind,
+      addr: addr as u16,
+      bank,
+      bytes: Vec::new(),
+    });
+    Ok(())
+  }
+
+  fn data(&mut self, width: usize, items: &[Item]) -> Result<(), AsmErrorKind> {
+    let mut bytes = Vec::new();
+    for item in items {
+      match *item {
+        Item::Str(s) => {
+          for byte in s.bytes() {
+            bytes.push(byte);
+            bytes.resize(bytes.len() + width - 1, 0);
+          }
+        }
+        Item::Expr(expr) => {
+          let value = self.eval(expr)?.unwrap_or(0);
+          let range = if width == 1 { -0x80..=0xFF } else { -0x8000..=0xFFFF };
+          if !range.contains(&value) {
+            return Err(AsmErrorKind::OutOfRange);
+          }
+          bytes.extend_from_slice(&value.to_le_bytes()[..width]);
+        }
+      }
+    }
+    self.section().bytes.extend(bytes);
+    Ok(())
+  }
+
+  fn space(
+    &mut self, count: &str, fill: Option<&str>,
+  ) -> Result<(), AsmErrorKind> {
+    let count = usize::try_from(self.eval_known(count)?)
+      .map_err(|_| AsmErrorKind::OutOfRange)?;
+    let fill = match fill {
+      Some(expr) => self.eval(expr)?.unwrap_or(0),
+      None => 0,
+    };
+    if !(-0x80..=0xFF).contains(&fill) {
+      return Err(AsmErrorKind::OutOfRange);
+    }
+    let bytes = &mut self.section().bytes;
+    bytes.resize(bytes.len() + count, fill as u8);
+    Ok(())
+  }
+
+  fn instruction(
+    &mut self, mnemonic: &str, args: &[Arg],
+  ) -> Result<(), AsmErrorKind> {
+    let pc = self.pc();
+    let mut values = Vec::new();
+    for arg in args {
+      values.push(match *arg {
+        Arg::Fixed(_) => None,
+        Arg::Imm(expr) | Arg::Mem(expr) | Arg::SpOffset(expr) => {
+          Some(self.eval(expr)?)
+        }
+      });
+    }
+    // Before the last pass, unknown values only need to give the right size, so
+    // try a couple that would fit.
+    let instruction = [0, pc + 2].iter().find_map(|&guess| {
+      let operands: Vec<Operand> = args
+        .iter()
+        .zip(&values)
+        .map(|(arg, value)| {
+          let n = (*value).flatten().unwrap_or(guess);
+          match *arg {
+            Arg::Fixed(op) => op,
+            Arg::Imm(_) => Operand::Imm(n),
+            Arg::Mem(_) => Operand::Mem(n),
+            Arg::SpOffset(_) => Operand::SpOffset(n),
+          }
+        })
+        .collect();
+      Instruction::from_operands(mnemonic, &operands, u16::try_from(pc).ok())
+    });
+    let instruction = instruction.ok_or(AsmErrorKind::BadInstruction)?;
+    let (bytes, len) = instruction.encode();
+    let bytes = &bytes[..len];
+    let section = self.section();
+    section.bytes.extend_from_slice(bytes);
+    if instruction == Instruction::Stop {
+      section.bytes.push(0x00);
+    }
+    Ok(())
+  }
+
+  /// Evaluates an expression that has to be known on every pass.
+  fn eval_known(&mut self, expr: &str) -> Result<i32, AsmErrorKind> {
+    let last = core::mem::replace(&mut self.last, true);
+    let value = self.eval(expr);
+    self.last = last;
+    Ok(value?.unwrap_or(0))
+  }
+
+  /// Evaluates an expression.
+  ///
+  /// * **Returns:** `None` if it uses a symbol that isn't defined yet, which
+  ///   is only allowed before the last pass.
+  fn eval(&mut self, expr: &str) -> Result<Option<i32>, AsmErrorKind> {
+    let pc = self.pc();
+    let mut parser =
+      ExprParser { text: expr, pos: 0, ctx: self, pc, unknown: false };
+    let value = parser.expr(0)?;
+    parser.skip_space();
+    if parser.pos != parser.text.len() {
+      return Err(AsmErrorKind::Syntax);
+    }
+    Ok((!parser.unknown).then_some(value))
+  }
+}
+
+/// Binary operators, from lowest to highest precedence.
+const BINARY_OPS: &[&[&str]] = &[
+  &["||"],
+  &["&&"],
+  &["==", "!=", "<=", ">=", "<", ">"],
+  &["+", "-"],
+  &["&", "|", "^"],
+  &["<<", ">>"],
+  &["*", "/", "%"],
+];
+
+struct ExprParser<'t, 'c, 'p, 's> {
+  text: &'t str,
+  pos: usize,
+  ctx: &'c Ctx<'p, 's>,
+  pc: i32,
+  /// If a symbol wasn't defined yet.
+  unknown: bool,
+}
+impl ExprParser<'_, '_, '_, '_> {
+  fn skip_space(&mut self) {
+    let rest = &self.text[self.pos..];
+    self.pos += rest.len() - rest.trim_start().len();
+  }
+
+  fn rest(&self) -> &str {
+    &self.text[self.pos..]
+  }
+
+  fn expr(&mut self, level: usize) -> Result<i32, AsmErrorKind> {
+    let Some(ops) = BINARY_OPS.get(level) else {
+      return self.unary();
+    };
+    let mut value = self.expr(level + 1)?;
+    loop {
+      self.skip_space();
+      let Some(&op) = ops.iter().find(|&&op| {
+        self.rest().starts_with(op)
+          // don't take `&&` as `&`, or `<<` as `<`.
+          && !(op.len() == 1 && self.rest()[1..].starts_with(op))
+      }) else {
+        return Ok(value);
+      };
+      self.pos += op.len();
+      let rhs = self.expr(level + 1)?;
+      value = match op {
+        "||" => i32::from(value != 0 || rhs != 0),
+        "&&" => i32::from(value != 0 && rhs != 0),
+        "==" => i32::from(value == rhs),
+        "!=" => i32::from(value != rhs),
+        "<=" => i32::from(value <= rhs),
+        ">=" => i32::from(value >= rhs),
+        "<" => i32::from(value < rhs),
+        ">" => i32::from(value > rhs),
+        "+" => value.wrapping_add(rhs),
+        "-" => value.wrapping_sub(rhs),
+        "&" => value & rhs,
+        "|" => value | rhs,
+        "^" => value ^ rhs,
+        "<<" => value.wrapping_shl(rhs as u32),
+        ">>" => value.wrapping_shr(rhs as u32),
+        "*" => value.wrapping_mul(rhs),
+        "/" | "%" if rhs == 0 => {
+          if self.unknown {
+            0
+          } else {
+            return Err(AsmErrorKind::DivideByZero);
+          }
+        }
+        "/" => value.wrapping_div(rhs),
+        _ => value.wrapping_rem(rhs),
+      };
+    }
+  }
+
+  fn unary(&mut self) -> Result<i32, AsmErrorKind> {
+    self.skip_space();
+    let Some(c) = self.rest().chars().next() else {
+      return Err(AsmErrorKind::Syntax);
+    };
+    match c {
+      '-' | '+' | '~' | '!' => {
+        self.pos += 1;
+        let value = self.unary()?;
+        Ok(match c {
+          '-' => value.wrapping_neg(),
+          '+' => value,
+          '~' => !value,
+          _ => i32::from(value == 0),
+        })
+      }
+      '(' => {
+        self.pos += 1;
+        let value = self.expr(0)?;
+        self.expect(')')?;
+        Ok(value)
+      }
+      '@' => {
+        self.pos += 1;
+        Ok(self.pc)
+      }
+      '$' | '%' | '&' | '0'..='9' => {
+        let len = 1
+          + self.rest()[1..]
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .unwrap_or(self.rest().len() - 1);
+        let value =
+          parse_number(&self.rest()[..len]).ok_or(AsmErrorKind::Syntax)?;
+        self.pos += len;
+        Ok(value)
+      }
+      _ if is_ident_char(c) => {
+        let len =
+          self.rest().find(|c| !is_ident_char(c)).unwrap_or(self.rest().len());
+        let name = &self.text[self.pos..self.pos + len];
+        self.pos += len;
+        self.skip_space();
+        if self.rest().starts_with('(') {
+          self.function(name)
+        } else {
+          Ok(self.symbol(name)?.map_or(0, |s| s.value))
+        }
+      }
+      _ => Err(AsmErrorKind::Syntax),
+    }
+  }
+
+  fn function(&mut self, name: &str) -> Result<i32, AsmErrorKind> {
+    self.expect('(')?;
+    let value = if name.eq_ignore_ascii_case("bank") {
+      self.skip_space();
+      let len = self.rest().find(|c| !is_ident_char(c)).unwrap_or(0);
+      let symbol = &self.text[self.pos..self.pos + len];
+      self.pos += len;
+      self.symbol(symbol)?.map_or(0, |s| s.bank)
+    } else {
+      let value = self.expr(0)?;
+      match name.to_ascii_lowercase().as_str() {
+        "high" => (value >> 8) & 0xFF,
+        "low" => value & 0xFF,
+        _ => return Err(AsmErrorKind::Syntax),
+      }
+    };
+    self.expect(')')?;
+    Ok(value)
+  }
+
+  fn symbol(&mut self, name: &str) -> Result<Option<Symbol>, AsmErrorKind> {
+    let name = qualify(name, self.ctx.scope);
+    let symbols = &self.ctx.program.symbols;
+    match symbols.get(&name).or_else(|| self.ctx.previous.get(&name)) {
+      Some(&symbol) => Ok(Some(symbol)),
+      None if self.ctx.last => Err(AsmErrorKind::UnknownSymbol(name)),
+      None => {
+        self.unknown = true;
+        Ok(None)
+      }
+    }
+  }
+
+  fn expect(&mut self, c: char) -> Result<(), AsmErrorKind> {
+    self.skip_space();
+    if self.rest().starts_with(c) {
+      self.pos += 1;
+      Ok(())
+    } else {
+      Err(AsmErrorKind::Syntax)
+    }
+  }
+}
+
+#[test]
+fn test_assemble() {
+  use crate::{cpu::Cpu, data_bus::FlatRam, disasm::Disassembly};
+  let source = r#"
+DEF COUNT EQU 3
+SECTION "Code", ROM0[$0100]
+Main:
+    ld b, COUNT ; a comment
+    xor a
+.loop:
+    add a, b
+    dec b
+    jr nz, .loop
+    ld [Result], a
+    ld hl, Table + 1
+    jp Main.done
+.done:
+    jr @
+Table:
+    db 1, $02, %11, "hi"
+    dw Main, HIGH(Table) << 8 | LOW(-1)
+    ds 2, $AA
+SECTION "Far", ROMX[$4000], BANK[2]
+Far:
+    ld a, BANK(Far)
+    stop
+SECTION "Vars", WRAM0[$C000]
+Result:
+"#;
+  let program = Program::assemble(source).unwrap();
+  assert_eq!(program.symbol("Main.loop"), Some(0x0103));
+  assert_eq!(program.symbol("Table"), Some(0x0112));
+  assert_eq!(program.symbol("Result"), Some(0xC000));
+  let rom = program.to_rom();
+  assert_eq!(rom.len(), 2 * ROM_BANK_SIZE + 4);
+  assert_eq!(
+    &rom[0x100..0x122],
+    &[
+      0x06, 0x03, 0xAF, 0x80, 0x05, 0x20, 0xFC, 0xEA, 0x00, 0xC0, 0x21, 0x13,
+      0x01, 0xC3, 0x10, 0x01, 0x18, 0xFE, 0x01, 0x02, 0x03, b'h', b'i', 0x00,
+      0x01, 0xFF, 0x01, 0xAA, 0xAA, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ][..]
+  );
+  assert_eq!(&rom[2 * ROM_BANK_SIZE..], &[0x3E, 0x02, 0x10, 0x00]);
+
+  // and it runs
+  let mut ram = FlatRam::new();
+  ram.0[..0x4000].copy_from_slice(&rom[..0x4000]);
+  let mut cpu = Cpu::new();
+  for _ in 0..100 {
+    cpu.m_cycle(&mut ram);
+  }
+  assert_eq!(ram.0[0xC000], 3 + 2 + 1);
+
+  let error = |source| Program::assemble(source).unwrap_err();
+  assert_eq!(
+    error("nop\njp Nowhere"),
+    AsmError { line: 2, kind: AsmErrorKind::UnknownSymbol("Nowhere".into()) }
+  );
+  assert_eq!(error("ld a, 256").kind, AsmErrorKind::BadInstruction);
+  assert_eq!(error("x:\nx:").kind, AsmErrorKind::Redefined("x".into()));
+  assert_eq!(
+    assemble("ld a, X\nX EQU Later + 1\nLater:\nnop"),
+    Ok(alloc::vec![0x3E, 0x03, 0x00])
+  );
+  assert_eq!(
+    assemble("ld a, Y\nY EQU X * 2\nX EQU Later\nLater:"),
+    Ok(alloc::vec![0x3E, 0x04])
+  );
+  assert_eq!(
+    error("X EQU Y\nY EQU X").kind,
+    AsmErrorKind::UnknownSymbol("Y".into())
+  );
+  assert_eq!(error("jr far\nds 200\nfar:").kind, AsmErrorKind::BadInstruction);
+
+  // disassembling a ROM and assembling it again gives the same ROM.
+  let rom = include_bytes!("../tests/blargg_cpu_instrs.gb");
+  let source = Disassembly::new(rom).to_asm();
+  assert_eq!(assemble(&source).as_deref(), Ok(&rom[..]));
+}
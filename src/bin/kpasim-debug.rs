@@ -0,0 +1,517 @@
+//! A command line debugger, which needs nothing but a terminal.
+//!
+//! `kpasim-debug <rom> [model]` boots the ROM (on a DMG by default) and reads
+//! commands from stdin. Type `help` for the list. Numbers use RGBDS syntax,
+//! so addresses are written like `$C000`.
+
+#![cfg_attr(test, allow(nonstandard_style))]
+
+use std::{
+  fmt::Write as _,
+  io::{BufRead, Write},
+  process::ExitCode,
+};
+
+use kpasim::{
+  data_bus::DataBus,
+  gameboy::{GameBoy, RunSummary},
+  instruction::{decode_at, parse_number},
+  model::Model,
+  ppu::DOTS_PER_FRAME,
+  reg_flags::RegFlags,
+};
+
+const MODELS: [Model; 8] = [
+  Model::Dmg0,
+  Model::Dmg,
+  Model::Mgb,
+  Model::Sgb,
+  Model::Sgb2,
+  Model::Cgb0,
+  Model::Cgb,
+  Model::Agb,
+];
+
+/// How long `c` runs without a frame count: a minute, which is plenty to
+/// reach a breakpoint but comes back from a game's idle loop.
+const CONTINUE_FRAMES: u32 = 60 * 60;
+
+const HELP: &str = "\
+commands (an empty line repeats the last one):
+  s [n]              step n instructions
+  m [n]              step n M-cycles
+  t [n]              step n T-cycles (dots)
+  c [frames]         continue until a breakpoint or `ld b, b`, or for at
+                     most that many frames (3600 if not given), and stop
+                     if the CPU locks up, stops, or halts for a frame
+  b [bank:]addr      add a breakpoint, in any bank unless one is given
+  b                  list breakpoints
+  d [n]              delete breakpoint n, or all of them
+  r                  show the registers
+  r <reg> <value>    set a register: a f b c d e h l af bc de hl sp pc,
+                     or the flags zf nf hf cf, or ime
+  x <addr> [len]     hexdump memory
+  w <addr> <byte>..  write bytes to memory, as the CPU would
+  l [addr] [n]       disassemble n instructions, or around PC
+  q                  quit";
+
+/// A PC breakpoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Breakpoint {
+  /// The ROM bank that has to be mapped at `addr`, or `None` for any.
+  bank: Option<usize>,
+  addr: u16,
+}
+impl std::fmt::Display for Breakpoint {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self.bank {
+      Some(bank) => write!(f, "${bank:02X}:${:04X}", self.addr),
+      None => write!(f, "${:04X}", self.addr),
+    }
+  }
+}
+
+struct Debugger {
+  gb: GameBoy,
+  breakpoints: Vec<Breakpoint>,
+}
+
+fn main() -> ExitCode {
+  let args: Vec<String> = std::env::args().skip(1).collect();
+  let (rom, model) = match &args[..] {
+    [rom] => (rom, Model::Dmg),
+    [rom, name] => match MODELS
+      .into_iter()
+      .find(|m| format!("{m:?}").eq_ignore_ascii_case(name))
+    {
+      Some(model) => (rom, model),
+      None => return fail(&format!("unknown model `{name}`")),
+    },
+    _ => {
+      return fail(
+        "usage: kpasim-debug <rom> [dmg0|dmg|mgb|sgb|sgb2|cgb0|cgb|agb]",
+      )
+    }
+  };
+  let mut gb = match GameBoy::from_file(model, rom) {
+    Ok(Some(gb)) => gb,
+    Ok(None) => return fail(&format!("`{rom}` has an unsupported cart type")),
+    Err(e) => return fail(&format!("can't read `{rom}`: {e}")),
+  };
+  gb.cpu_mut().ld_b_b_breakpoint = true;
+  let mut debugger = Debugger { gb, breakpoints: Vec::new() };
+  println!("{}", debugger.status());
+  let mut last = String::new();
+  let mut lines = std::io::stdin().lock().lines();
+  loop {
+    print!("> ");
+    std::io::stdout().flush().unwrap();
+    let Some(Ok(line)) = lines.next() else { break };
+    let line = match line.trim() {
+      "" => last.clone(),
+      line => line.to_string(),
+    };
+    if matches!(line.as_str(), "q" | "quit") {
+      break;
+    }
+    match debugger.command(&line) {
+      Ok(output) => print!("{output}"),
+      Err(why) => println!("error: {why}"),
+    }
+    last = line;
+  }
+  ExitCode::SUCCESS
+}
+
+fn fail(message: &str) -> ExitCode {
+  eprintln!("{message}");
+  ExitCode::FAILURE
+}
+
+fn number(text: &str) -> Result<i32, String> {
+  parse_number(text).ok_or_else(|| format!("bad number `{text}`"))
+}
+
+fn addr(text: &str) -> Result<u16, String> {
+  u16::try_from(number(text)?).map_err(|_| format!("`{text}` isn't an address"))
+}
+
+fn byte(text: &str) -> Result<u8, String> {
+  u8::try_from(number(text)?).map_err(|_| format!("`{text}` isn't a byte"))
+}
+
+/// An optional count, which is 1 by default.
+fn count(text: Option<&&str>) -> Result<u32, String> {
+  text.map_or(Ok(1), |text| {
+    u32::try_from(number(text)?).map_err(|_| format!("bad count `{text}`"))
+  })
+}
+
+impl Debugger {
+  /// Runs one command line.
+  ///
+  /// * **Returns:** What to print.
+  fn command(&mut self, line: &str) -> Result<String, String> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let Some((&name, args)) = words.split_first() else {
+      return Ok(String::new());
+    };
+    match (name, args) {
+      ("h" | "help" | "?", _) => Ok(format!("{HELP}\n")),
+      ("s" | "step", [] | [_]) => {
+        let n = count(args.first())?;
+        Ok(self.step(n, GameBoy::step_instruction))
+      }
+      ("m", [] | [_]) => {
+        Ok(self.step(count(args.first())?, GameBoy::step_m_cycle))
+      }
+      ("t", [] | [_]) => {
+        Ok(self.step(count(args.first())?, GameBoy::step_t_cycle))
+      }
+      ("c" | "continue", []) => Ok(self.continue_for(CONTINUE_FRAMES)),
+      ("c" | "continue", [frames]) => {
+        Ok(self.continue_for(count(Some(frames))?))
+      }
+      ("b" | "break", []) => {
+        let mut out = String::new();
+        for (i, bp) in self.breakpoints.iter().enumerate() {
+          writeln!(out, "{i}: {bp}").unwrap();
+        }
+        Ok(out)
+      }
+      ("b" | "break", [at]) => {
+        let bp = match at.split_once(':') {
+          Some((bank, at)) => {
+            let bank = usize::try_from(number(bank)?)
+              .map_err(|_| format!("bad bank `{bank}`"))?;
+            let addr = addr(at)?;
+            if addr >= 0x8000 {
+              return Err("only ROM addresses have banks".to_string());
+            }
+            Breakpoint { bank: Some(bank), addr }
+          }
+          None => Breakpoint { bank: None, addr: addr(at)? },
+        };
+        self.breakpoints.push(bp);
+        Ok(format!("{}: {bp}\n", self.breakpoints.len() - 1))
+      }
+      ("d" | "delete", []) => {
+        self.breakpoints.clear();
+        Ok(String::new())
+      }
+      ("d" | "delete", [i]) => {
+        let i = count(Some(i))? as usize;
+        if i >= self.breakpoints.len() {
+          return Err(format!("no breakpoint {i}"));
+        }
+        self.breakpoints.remove(i);
+        Ok(String::new())
+      }
+      ("r" | "regs", []) => Ok(format!("{}\n", self.status())),
+      ("r" | "regs", [reg, value]) => {
+        self.set_register(reg, number(value)?)?;
+        Ok(format!("{}\n", self.status()))
+      }
+      ("x", [at] | [at, _]) => {
+        let len = args.get(1).map_or(Ok(64), |len| count(Some(len)))?;
+        Ok(self.hexdump(addr(at)?, len))
+      }
+      ("w", [at, bytes @ ..]) if !bytes.is_empty() => {
+        let at = addr(at)?;
+        let bytes: Vec<u8> =
+          bytes.iter().map(|b| byte(b)).collect::<Result<_, _>>()?;
+        let map = self.gb.map_mut();
+        for (i, &b) in bytes.iter().enumerate() {
+          map.write(at.wrapping_add(i as u16), b);
+        }
+        Ok(String::new())
+      }
+      ("l" | "list", []) => Ok(self.list_around_pc(4, 6)),
+      ("l" | "list", [at] | [at, _]) => {
+        let n = args.get(1).map_or(Ok(10), |n| count(Some(n)))?;
+        Ok(self.list(addr(at)?, n as usize))
+      }
+      _ => Err(format!("can't do `{line}`, try `help`")),
+    }
+  }
+
+  /// Steps `n` times, stopping early at an `ld b, b`.
+  fn step(&mut self, n: u32, step: fn(&mut GameBoy) -> RunSummary) -> String {
+    let mut total = RunSummary::default();
+    for _ in 0..n {
+      total += step(&mut self.gb);
+      if total.breakpoint {
+        break;
+      }
+    }
+    let mut out = String::new();
+    if total.breakpoint {
+      writeln!(out, "ld b, b").unwrap();
+    }
+    writeln!(
+      out,
+      "ran {} dots and {} instructions",
+      total.t_cycles, total.instructions
+    )
+    .unwrap();
+    writeln!(out, "{}", self.status()).unwrap();
+    out
+  }
+
+  /// Runs by instructions until a breakpoint, or for at most `frames`.
+  ///
+  /// This also stops if the CPU can't go on, or is halted for a whole frame
+  /// (which can be forever, after `di` and `halt`).
+  fn continue_for(&mut self, frames: u32) -> String {
+    let max_dots = u64::from(frames) * u64::from(DOTS_PER_FRAME);
+    let mut dots = 0_u64;
+    let why = loop {
+      let summary = self.gb.step_instruction();
+      dots += u64::from(summary.t_cycles);
+      if summary.breakpoint {
+        break "ld b, b".to_string();
+      }
+      if let Some(bp) = self.breakpoint_hit() {
+        break format!("breakpoint {bp}");
+      }
+      // Otherwise `c` would sit there until the frames ran out.
+      let cpu = self.gb.cpu();
+      if cpu.locked {
+        break "the CPU locked up".to_string();
+      }
+      if cpu.stopped {
+        break "the CPU ran `stop`".to_string();
+      }
+      if summary.instructions == 0 {
+        break "a frame went by with no instructions".to_string();
+      }
+      if dots >= max_dots {
+        break "out of frames".to_string();
+      }
+    };
+    format!("{why} after {dots} dots\n{}\n", self.status())
+  }
+
+  /// The breakpoint at PC, if the CPU is about to start an instruction there.
+  fn breakpoint_hit(&self) -> Option<Breakpoint> {
+    let cpu = self.gb.cpu();
+    if !cpu.is_between_instructions() {
+      return None;
+    }
+    let pc = cpu.pc.get();
+    let bank = self.gb.map().rom_bank_at(pc);
+    self
+      .breakpoints
+      .iter()
+      .copied()
+      .find(|bp| bp.addr == pc && bp.bank.is_none_or(|want| bank == Some(want)))
+  }
+
+  /// The registers, and the next instruction.
+  fn status(&mut self) -> String {
+    let cpu = *self.gb.cpu();
+    let mut out = format!(
+      "AF={:04X} BC={:04X} DE={:04X} HL={:04X} SP={:04X} PC={:04X} [{}] ime={}",
+      cpu.af, cpu.bc, cpu.de, cpu.hl, cpu.sp, cpu.pc, cpu.flags, cpu.ime as u8
+    );
+    for (on, name) in
+      [(cpu.halted, "halted"), (cpu.stopped, "stopped"), (cpu.locked, "locked")]
+    {
+      if on {
+        write!(out, " {name}").unwrap();
+      }
+    }
+    write!(out, " t={}", cpu.t_cycles).unwrap();
+    // PC has already moved past some of the instruction that's running.
+    if cpu.is_between_instructions() {
+      write!(out, "\n{}", self.list(cpu.pc.get(), 1).trim_end()).unwrap();
+    } else {
+      write!(out, "\nmid-instruction, still to do: {:?}", cpu.action_queue)
+        .unwrap();
+    }
+    out
+  }
+
+  fn set_register(&mut self, reg: &str, value: i32) -> Result<(), String> {
+    let as_u8 =
+      || u8::try_from(value).map_err(|_| format!("{value} isn't a byte"));
+    let as_u16 =
+      || u16::try_from(value).map_err(|_| format!("{value} isn't 16 bits"));
+    let as_bool = || match value {
+      0 => Ok(false),
+      1 => Ok(true),
+      _ => Err(format!("{value} isn't 0 or 1")),
+    };
+    let cpu = self.gb.cpu_mut();
+    match reg {
+      "a" => cpu.a.set(as_u8()?),
+      "f" => cpu.flags = RegFlags::new(as_u8()?),
+      "b" => cpu.b.set(as_u8()?),
+      "c" => cpu.c.set(as_u8()?),
+      "d" => cpu.d.set(as_u8()?),
+      "e" => cpu.e.set(as_u8()?),
+      "h" => cpu.h.set(as_u8()?),
+      "l" => cpu.l.set(as_u8()?),
+      // the low bits of F are always zero.
+      "af" => cpu.af.set(as_u16()? & 0xFFF0),
+      "bc" => cpu.bc.set(as_u16()?),
+      "de" => cpu.de.set(as_u16()?),
+      "hl" => cpu.hl.set(as_u16()?),
+      "sp" => cpu.sp.set(as_u16()?),
+      "pc" => cpu.pc.set(as_u16()?),
+      "zf" => cpu.flags.set_z(as_bool()?),
+      "nf" => cpu.flags.set_n(as_bool()?),
+      "hf" => cpu.flags.set_h(as_bool()?),
+      "cf" => cpu.flags.set_c(as_bool()?),
+      "ime" => cpu.ime = as_bool()?,
+      _ => return Err(format!("no register `{reg}`")),
+    }
+    Ok(())
+  }
+
+  fn hexdump(&mut self, start: u16, len: u32) -> String {
+    let map = self.gb.map_mut();
+    let bytes: Vec<u8> =
+      (0..len).map(|i| map.peek(start.wrapping_add(i as u16))).collect();
+    let mut out = String::new();
+    for (row, chunk) in bytes.chunks(16).enumerate() {
+      let addr = start.wrapping_add(row as u16 * 16);
+      write!(out, "${addr:04X}:").unwrap();
+      for b in chunk {
+        write!(out, " {b:02X}").unwrap();
+      }
+      let text: String = chunk
+        .iter()
+        .map(
+          |&b| if b.is_ascii_graphic() || b == b' ' { b as char } else { '.' },
+        )
+        .collect();
+      writeln!(out, "{:pad$}  {text}", "", pad = 3 * (16 - chunk.len()))
+        .unwrap();
+    }
+    out
+  }
+
+  /// Disassembles `n` instructions from `start`, marking the one at PC.
+  fn list(&mut self, start: u16, n: usize) -> String {
+    let pc = self.gb.cpu().pc.get();
+    let mut out = String::new();
+    let mut at = start;
+    for _ in 0..n {
+      let bank = self.gb.map().rom_bank_at(at);
+      let map = self.gb.map_mut();
+      let (instruction, len) = decode_at(map, at);
+      let bytes: String = (0..len as u16)
+        .map(|i| format!("{:02X} ", map.peek(at.wrapping_add(i))))
+        .collect();
+      let marker = if at == pc { '>' } else { ' ' };
+      let bank = bank.map_or("   ".to_string(), |bank| format!("{bank:02X}:"));
+      writeln!(
+        out,
+        "{marker} {bank}${at:04X}  {bytes:<9} {}",
+        instruction.at(at)
+      )
+      .unwrap();
+      at = at.wrapping_add(len as u16);
+    }
+    out
+  }
+
+  /// Disassembles a few instructions before PC and some after.
+  ///
+  /// Instructions don't say where the one before them starts, so this looks
+  /// for the furthest back start (up to 3 bytes per instruction) that decodes
+  /// right up to PC.
+  fn list_around_pc(&mut self, before: usize, after: usize) -> String {
+    let pc = self.gb.cpu().pc.get();
+    let map = self.gb.map_mut();
+    let starts = (1..=before as u16 * 3)
+      .rev()
+      .find_map(|back| {
+        let mut starts = Vec::new();
+        let mut left = back;
+        while left > 0 {
+          let at = pc.wrapping_sub(left);
+          let (_, len) = decode_at(map, at);
+          left = left.checked_sub(len as u16)?;
+          starts.push(at);
+        }
+        Some(starts)
+      })
+      .unwrap_or_default();
+    let shown = &starts[starts.len().saturating_sub(before)..];
+    let start = shown.first().copied().unwrap_or(pc);
+    self.list(start, shown.len() + after)
+  }
+}
+
+#[test]
+fn test_Debugger_command() {
+  let mut rom = kpasim::assembler::assemble(
+    r#"
+SECTION "Entry", ROM0[$0100]
+    nop
+    jp Main
+SECTION "Cart", ROM0[$0147]
+    db $19, $01 ; MBC5 with 64 KiB of ROM
+SECTION "Main", ROM0[$0150]
+Main:
+    call $4000
+    ld a, 2
+    ld [$2000], a
+    call $4000
+    di
+    halt
+SECTION "One", ROMX[$4000], BANK[1]
+    ret
+SECTION "Two", ROMX[$4000], BANK[2]
+    ret
+"#,
+  )
+  .unwrap();
+  rom.resize(0x10000, 0);
+  let gb = GameBoy::new(Model::Dmg, rom).unwrap();
+  let mut debugger = Debugger { gb, breakpoints: Vec::new() };
+  let mut run = |line: &str| debugger.command(line);
+
+  assert_eq!(run("b 2:$4000"), Ok("0: $02:$4000\n".to_string()));
+  assert!(run("b 1:$C000").is_err());
+  assert!(run("b $10000").is_err());
+  assert!(run("frobnicate").is_err());
+  // the first call goes to bank 1, so only the second one stops.
+  let out = run("c").unwrap();
+  assert!(out.starts_with("breakpoint $02:$4000"), "{out}");
+  assert_eq!(debugger.gb.cpu().a.get(), 2);
+
+  let mut run = |line: &str| debugger.command(line);
+  assert_eq!(run("d 1"), Err("no breakpoint 1".to_string()));
+  assert_eq!(run("d 0"), Ok(String::new()));
+  // nothing wakes up `di` then `halt`, so `c` stops after a frame of it.
+  let out = run("c").unwrap();
+  assert!(out.starts_with("a frame went by"), "{out}");
+  assert!(debugger.gb.cpu().halted);
+
+  let mut run = |line: &str| debugger.command(line);
+  assert!(run("r a $12").is_ok());
+  assert!(run("r zf 2").is_err());
+  assert!(run("r hf 1").is_ok());
+  assert!(run("r sp $10000").is_err());
+  assert!(run("w $C000 1 2 $D3").is_ok());
+  assert!(run("x $C000 3").unwrap().starts_with("$C000: 01 02 D3"));
+  assert!(run("r pc $C002").is_ok());
+  assert_eq!(debugger.gb.cpu().a.get(), 0x12);
+  assert!(debugger.gb.cpu().flags.h());
+
+  // an illegal op code locks up the CPU, which also stops `c`.
+  debugger.gb.cpu_mut().halted = false;
+  let out = debugger.command("c").unwrap();
+  assert!(out.starts_with("the CPU locked up"), "{out}");
+
+  // a spin loop never stops by itself, so only the frame count ends it.
+  debugger.gb.cpu_mut().locked = false;
+  let mut run = |line: &str| debugger.command(line);
+  assert!(run("w $C100 $18 $FE").is_ok());
+  assert!(run("r pc $C100").is_ok());
+  let out = run("c 2").unwrap();
+  assert!(out.starts_with("out of frames"), "{out}");
+}
@@ -0,0 +1,172 @@
+//! Runs blargg's test ROMs and works out if they passed.
+//!
+//! The ROMs print their results as text over the serial port, ending with
+//! `Passed` or `Failed`. The newer ones also write a report into cart RAM:
+//! `$A001..=$A003` hold the signature `$DE $B0 $61`, `$A000` is `$80` while
+//! the test runs and then the result code (`0` is a pass, and `$81` asks for
+//! the reset button to be pressed), and the text starts at `$A004`.
+//!
+//! * See Also: [blargg's test ROMs](https://github.com/retrio/gb-test-roms)
+
+use alloc::{
+  boxed::Box,
+  string::{String, ToString},
+  vec,
+  vec::Vec,
+};
+
+use crate::{data_bus::DataBus, gameboy::GameBoy, serial::SerialCapture};
+
+/// The names of `cpu_instrs`' sub-tests, which it prints by number.
+pub const CPU_INSTRS_NAMES: [&str; 11] = [
+  "01-special",
+  "02-interrupts",
+  "03-op sp,hl",
+  "04-op r,imm",
+  "05-op rp",
+  "06-ld r,r",
+  "07-jr,jp,call,ret,rst",
+  "08-misc instrs",
+  "09-op r,r",
+  "10-bit ops",
+  "11-op a,(hl)",
+];
+
+/// The signature at `$A001..=$A003` when a test reports to cart RAM.
+const SIGNATURE: [u8; 3] = [0xDE, 0xB0, 0x61];
+
+/// The result code at `$A000` while the test is still running.
+const RUNNING: u8 = 0x80;
+
+/// The result code at `$A000` when the test wants the reset button pressed.
+const RESET_REQUIRED: u8 = 0x81;
+
+/// Frames to keep running after `Passed` or `Failed` shows up, so that the
+/// rest of the text comes through.
+const TRAILING_FRAMES: u32 = 10;
+
+/// How a test ROM finished.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum BlarggStatus {
+  Passed,
+  /// The names of the sub-tests that failed. ROMs without sub-tests give
+  /// their own name.
+  Failed(Vec<String>),
+  /// The test wants the reset button pressed, to check what survives a
+  /// reset. A [GameBoy] has no reset button, so the run stops there.
+  ResetRequired,
+  /// Nothing was reported in time.
+  Timeout,
+}
+
+/// The result of running a test ROM.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BlarggReport {
+  pub status: BlarggStatus,
+  /// Everything the ROM printed, from serial or cart RAM.
+  pub output: String,
+  /// How many frames ran.
+  pub frames: u32,
+}
+
+/// Runs a test ROM until it reports a result, or for `max_frames`.
+///
+/// This plugs its own device into the serial port while it runs, then puts
+/// back whatever was plugged in before.
+pub fn run_blargg(gb: &mut GameBoy, max_frames: u32) -> BlarggReport {
+  let serial = SerialCapture::new();
+  let previous = gb.serial_mut().attach(Box::new(serial.clone()));
+  let mut frames = 0;
+  let mut finish_at = None;
+  let mut ram_report = None;
+  while frames < max_frames && finish_at.is_none_or(|end| frames < end) {
+    gb.run_frame();
+    frames += 1;
+    if finish_at.is_some() {
+      continue;
+    }
+    if let Some(code) = ram_result(gb) {
+      ram_report = Some((code, ram_text(gb)));
+      break;
+    }
+    if serial.contains(b"Passed") || serial.contains(b"Failed") {
+      finish_at = Some(frames + TRAILING_FRAMES);
+    }
+  }
+  match previous {
+    Some(device) => gb.serial_mut().attach(device),
+    None => gb.serial_mut().detach(),
+  };
+  let (status, output) = match ram_report {
+    Some((0, output)) => (BlarggStatus::Passed, output),
+    Some((RESET_REQUIRED, output)) => (BlarggStatus::ResetRequired, output),
+    Some((_, output)) => (BlarggStatus::Failed(failed_tests(&output)), output),
+    None => {
+      let output = String::from_utf8_lossy(&serial.0.borrow()).into_owned();
+      let status = if output.contains("Passed") {
+        BlarggStatus::Passed
+      } else if output.contains("Failed") {
+        BlarggStatus::Failed(failed_tests(&output))
+      } else {
+        BlarggStatus::Timeout
+      };
+      (status, output)
+    }
+  };
+  BlarggReport { status, output, frames }
+}
+
+/// The result code in cart RAM, once the test has finished.
+fn ram_result(gb: &mut GameBoy) -> Option<u8> {
+  let map = gb.map_mut();
+  let signature = [0xA001, 0xA002, 0xA003].map(|addr| map.peek(addr));
+  match map.peek(0xA000) {
+    code if signature == SIGNATURE && code != RUNNING => Some(code),
+    _ => None,
+  }
+}
+
+/// The text in cart RAM, which ends at a `0` byte.
+fn ram_text(gb: &mut GameBoy) -> String {
+  let map = gb.map_mut();
+  let bytes: Vec<u8> = (0xA004..0xC000)
+    .map(|addr| map.peek(addr))
+    .take_while(|&byte| byte != 0)
+    .collect();
+  String::from_utf8_lossy(&bytes).into_owned()
+}
+
+/// Picks out which sub-tests failed.
+///
+/// ROMs with sub-tests print `NN:ok` for each one that passed, and `NN:` with
+/// an error code for each one that didn't.
+fn failed_tests(output: &str) -> Vec<String> {
+  let title = output.lines().map(str::trim).find(|l| !l.is_empty());
+  let failed: Vec<String> = output
+    .split_whitespace()
+    .filter_map(|word| {
+      let (number, result) = word.split_once(':')?;
+      let n: usize = number.parse().ok()?;
+      if number.len() != 2 || result == "ok" || result.is_empty() {
+        return None;
+      }
+      Some(match (title, CPU_INSTRS_NAMES.get(n.wrapping_sub(1))) {
+        (Some("cpu_instrs"), Some(name)) => name.to_string(),
+        _ => number.to_string(),
+      })
+    })
+    .collect();
+  if failed.is_empty() {
+    vec![title.unwrap_or("").to_string()]
+  } else {
+    failed
+  }
+}
+
+#[test]
+fn test_failed_tests() {
+  let output = "cpu_instrs\n\n01:ok  02:04  03:ok  \n\nFailed 1 tests.\n";
+  assert_eq!(failed_tests(output), vec!["02-interrupts"]);
+  let output = "instr_timing\n\n\nFailed #255\n";
+  assert_eq!(failed_tests(output), vec!["instr_timing"]);
+}
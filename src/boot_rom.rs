@@ -0,0 +1,157 @@
+//! Boot ROM images, which cover the start of the cart ROM at power on.
+//!
+//! Until something is written to `$FF50`, reads of `$0000..=$00FF` come from
+//! the boot ROM instead of the cart. A CGB boot ROM also covers
+//! `$0200..=$08FF`, leaving the cart header at `$0100..=$01FF` visible so that
+//! it can be checked. Once unmapped, the boot ROM can't be mapped back in.
+//!
+//! While a CGB boot ROM is mapped it can also write `KEY0` (`$FF4C`) to pick
+//! between CGB mode and DMG compatibility mode.
+//!
+//! * See Also: [Pandocs: Power Up Sequence](https://gbdev.io/pandocs/Power_Up_Sequence.html)
+
+use alloc::{boxed::Box, vec::Vec};
+
+use crate::{compat::Key0, data_bus::DataBus, speed::Speed};
+
+/// Size of a DMG, MGB, or SGB boot ROM.
+pub const DMG_BOOT_ROM_SIZE: usize = 0x100;
+
+/// Size of a CGB or AGB boot ROM (which skips `$0100..=$01FF`, so the image
+/// has 256 unused bytes in the middle).
+pub const CGB_BOOT_ROM_SIZE: usize = 0x900;
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BootRom {
+  bytes: Box<[u8]>,
+  mapped: bool,
+  key0: Key0,
+}
+impl BootRom {
+  /// Wraps a boot ROM image, which starts out mapped.
+  ///
+  /// * **Returns:** `None` if the image isn't one of the two boot ROM sizes.
+  #[must_use]
+  pub fn new(bytes: Vec<u8>) -> Option<Self> {
+    match bytes.len() {
+      DMG_BOOT_ROM_SIZE | CGB_BOOT_ROM_SIZE => Some(Self {
+        bytes: bytes.into_boxed_slice(),
+        mapped: true,
+        key0: Key0::new(),
+      }),
+      _ => None,
+    }
+  }
+
+  /// If this is a CGB (or AGB) boot ROM.
+  #[inline]
+  #[must_use]
+  pub fn is_cgb(&self) -> bool {
+    self.bytes.len() == CGB_BOOT_ROM_SIZE
+  }
+
+  /// If the boot ROM still covers the cart ROM.
+  #[inline]
+  #[must_use]
+  pub const fn is_mapped(&self) -> bool {
+    self.mapped
+  }
+
+  /// The `KEY0` register, which only exists with a CGB boot ROM.
+  #[inline]
+  #[must_use]
+  pub const fn key0(&self) -> Key0 {
+    self.key0
+  }
+
+  /// Reads the boot ROM.
+  ///
+  /// * **Returns:** `None` if the boot ROM doesn't cover that address (any
+  ///   more), so the read should go to the cart.
+  #[inline]
+  #[must_use]
+  pub fn read(&self, addr: u16) -> Option<u8> {
+    let covered =
+      addr < 0x100 || (self.is_cgb() && (0x200..0x900).contains(&addr));
+    if self.mapped && covered {
+      Some(self.bytes[usize::from(addr)])
+    } else {
+      None
+    }
+  }
+
+  /// Reads `KEY0` or `$FF50`.
+  #[inline]
+  #[must_use]
+  pub fn read_reg(&self, addr: u16) -> u8 {
+    match addr {
+      0xFF4C if self.is_cgb() => self.key0.read(),
+      _ => 0xFF,
+    }
+  }
+
+  /// Writes `KEY0` or `$FF50`.
+  ///
+  /// * **Returns:** If this write unmapped the boot ROM. For a CGB boot ROM,
+  ///   that also locks `KEY0`, and the system should switch to whatever
+  ///   [Key0::mode] gives.
+  pub fn write_reg(&mut self, addr: u16, byte: u8) -> bool {
+    match addr {
+      0xFF4C if self.is_cgb() => {
+        self.key0.write(byte);
+        false
+      }
+      0xFF50 if self.mapped && byte != 0 => {
+        self.mapped = false;
+        self.key0.lock();
+        true
+      }
+      _ => false,
+    }
+  }
+}
+
+/// A bus with a boot ROM laid over the front of it.
+///
+/// This is enough to run a boot ROM on a bus that doesn't know about boot
+/// ROMs. Writes to `KEY0` and `$FF50` go to both the boot ROM and the inner
+/// bus, so the inner bus can still see the mode switch.
+pub struct BootRomBus<B> {
+  pub boot_rom: BootRom,
+  pub bus: B,
+}
+impl<B: DataBus> DataBus for BootRomBus<B> {
+  fn read(&mut self, addr: u16) -> u8 {
+    match addr {
+      0xFF4C if self.boot_rom.is_cgb() => self.boot_rom.read_reg(addr),
+      _ => self.boot_rom.read(addr).unwrap_or_else(|| self.bus.read(addr)),
+    }
+  }
+  fn write(&mut self, addr: u16, byte: u8) {
+    if matches!(addr, 0xFF4C | 0xFF50) {
+      self.boot_rom.write_reg(addr, byte);
+    }
+    self.bus.write(addr, byte);
+  }
+  fn stop(&mut self) -> Option<Speed> {
+    self.bus.stop()
+  }
+}
+
+#[test]
+fn test_BootRom_overlay() {
+  assert!(BootRom::new(alloc::vec![0; 0x200]).is_none());
+  let mut boot = BootRom::new(alloc::vec![0xAA; CGB_BOOT_ROM_SIZE]).unwrap();
+  assert_eq!(boot.read(0x0000), Some(0xAA));
+  assert_eq!(boot.read(0x0134), None);
+  assert_eq!(boot.read(0x08FF), Some(0xAA));
+  assert_eq!(boot.read(0x0900), None);
+  assert!(!boot.write_reg(0xFF4C, 0x04));
+  assert!(!boot.write_reg(0xFF50, 0x00));
+  assert!(boot.write_reg(0xFF50, 0x11));
+  assert_eq!(boot.key0().mode(), crate::compat::CgbMode::DmgCompat);
+  assert_eq!(boot.read(0x0000), None);
+  assert!(!boot.write_reg(0xFF50, 0x11));
+  boot.write_reg(0xFF4C, 0x80);
+  assert_eq!(boot.key0().mode(), crate::compat::CgbMode::DmgCompat);
+}
@@ -0,0 +1,140 @@
+//! Parsing of the cartridge header at `$0100..=$014F`.
+//!
+//! * See Also: [Pandocs: The Cartridge Header](https://gbdev.io/pandocs/The_Cartridge_Header.html)
+
+/// How a cart wants to be treated by a CGB, from the byte at `$0143`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum CgbSupport {
+  /// Made for the DMG, a CGB runs it in compatibility mode.
+  #[default]
+  None,
+  /// `$80`: uses CGB features, but also runs on a DMG.
+  Compatible,
+  /// `$C0`: only runs on a CGB.
+  Only,
+}
+
+/// The parsed cartridge header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CartHeader {
+  pub entry_point: [u8; 4],
+  pub logo: [u8; 48],
+  /// The full title area. Newer carts reuse the last 5 bytes for the
+  /// manufacturer code and the CGB flag, so the title is also those bytes.
+  pub title: [u8; 16],
+  pub manufacturer_code: [u8; 4],
+  pub cgb_flag: u8,
+  pub new_licensee_code: [u8; 2],
+  pub sgb_flag: u8,
+  pub cart_type: u8,
+  pub rom_size: u8,
+  pub ram_size: u8,
+  pub destination_code: u8,
+  pub old_licensee_code: u8,
+  pub mask_rom_version: u8,
+  pub header_checksum: u8,
+  pub global_checksum: u16,
+}
+impl CartHeader {
+  /// Parses the header out of a full ROM image.
+  ///
+  /// * **Returns:** `None` if the ROM is too small to have a header.
+  #[must_use]
+  pub fn from_rom(rom: &[u8]) -> Option<Self> {
+    let h = rom.get(0x0100..0x0150)?;
+    let mut logo = [0; 48];
+    logo.copy_from_slice(&h[0x04..0x34]);
+    let mut title = [0; 16];
+    title.copy_from_slice(&h[0x34..0x44]);
+    Some(Self {
+      entry_point: [h[0x00], h[0x01], h[0x02], h[0x03]],
+      logo,
+      title,
+      manufacturer_code: [title[11], title[12], title[13], title[14]],
+      cgb_flag: h[0x43],
+      new_licensee_code: [h[0x44], h[0x45]],
+      sgb_flag: h[0x46],
+      cart_type: h[0x47],
+      rom_size: h[0x48],
+      ram_size: h[0x49],
+      destination_code: h[0x4A],
+      old_licensee_code: h[0x4B],
+      mask_rom_version: h[0x4C],
+      header_checksum: h[0x4D],
+      global_checksum: u16::from_be_bytes([h[0x4E], h[0x4F]]),
+    })
+  }
+
+  #[inline]
+  #[must_use]
+  pub const fn cgb_support(&self) -> CgbSupport {
+    match self.cgb_flag {
+      0x80 => CgbSupport::Compatible,
+      0xC0 => CgbSupport::Only,
+      _ => CgbSupport::None,
+    }
+  }
+
+  /// If a CGB will run this cart in CGB mode.
+  ///
+  /// The boot ROM only checks bit 7 of the CGB flag to decide this.
+  #[inline]
+  #[must_use]
+  pub const fn is_cgb_mode(&self) -> bool {
+    (self.cgb_flag & 0x80) != 0
+  }
+
+  /// If the cart claims to support SGB functions.
+  #[inline]
+  #[must_use]
+  pub const fn supports_sgb(&self) -> bool {
+    self.sgb_flag == 0x03
+  }
+
+  /// ROM size in bytes, if the size code is a known one.
+  #[inline]
+  #[must_use]
+  pub const fn rom_size_bytes(&self) -> Option<usize> {
+    if self.rom_size <= 0x08 {
+      Some((32 * 1024) << self.rom_size)
+    } else {
+      None
+    }
+  }
+
+  /// Cart RAM size in bytes, if the size code is a known one.
+  #[inline]
+  #[must_use]
+  pub const fn ram_size_bytes(&self) -> Option<usize> {
+    match self.ram_size {
+      0x00 => Some(0),
+      0x01 => Some(2 * 1024),
+      0x02 => Some(8 * 1024),
+      0x03 => Some(32 * 1024),
+      0x04 => Some(128 * 1024),
+      0x05 => Some(64 * 1024),
+      _ => None,
+    }
+  }
+
+  /// The checksum of `$0134..=$014C`, as the boot ROM computes it.
+  #[must_use]
+  pub fn computed_header_checksum(&self) -> u8 {
+    let tail = [
+      self.new_licensee_code[0],
+      self.new_licensee_code[1],
+      self.sgb_flag,
+      self.cart_type,
+      self.rom_size,
+      self.ram_size,
+      self.destination_code,
+      self.old_licensee_code,
+      self.mask_rom_version,
+    ];
+    self
+      .title
+      .iter()
+      .chain(tail.iter())
+      .fold(0_u8, |sum, &b| sum.wrapping_sub(b).wrapping_sub(1))
+  }
+}
@@ -0,0 +1,349 @@
+//! Running DMG games on a CGB ("DMG compatibility mode").
+//!
+//! When the CGB boot ROM sees a cart without the CGB flag set, it writes `$04`
+//! to `KEY0` (`$FF4C`) so that the system acts like a DMG, except that the PPU
+//! still takes its colors from palette RAM. Before that, the boot ROM picks
+//! those colors: if the cart is from Nintendo it looks the title up in a
+//! table of known games, and the player can also override the choice by
+//! holding a button combination during the logo.
+//!
+//! The tables here are transcribed from the CGB boot ROM.
+//!
+//! * See Also: [Pandocs: KEY0](https://gbdev.io/pandocs/CGB_Registers.html#ff4c--key0sys-cgb-mode-only-cpu-mode-select)
+//! * See Also: [Pandocs: Compatibility palettes](https://gbdev.io/pandocs/Power_Up_Sequence.html#compatibility-palettes)
+
+use crate::{cart_header::CartHeader, ppu::Ppu};
+
+/// The mode that a CGB system is running in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum CgbMode {
+  /// Full CGB features.
+  #[default]
+  Cgb,
+  /// Acting like a DMG, but with colorized palettes.
+  DmgCompat,
+}
+impl CgbMode {
+  /// The mode that the boot ROM would pick for a cart.
+  #[inline]
+  #[must_use]
+  pub const fn for_header(header: &CartHeader) -> Self {
+    if header.is_cgb_mode() {
+      Self::Cgb
+    } else {
+      Self::DmgCompat
+    }
+  }
+}
+
+/// The `KEY0` register (`$FF4C`), which the boot ROM uses to pick the mode.
+///
+/// Once the boot ROM is unmapped the register locks, and it can't be changed
+/// again until the system resets.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Key0 {
+  value: u8,
+  locked: bool,
+}
+impl Key0 {
+  #[inline]
+  #[must_use]
+  pub const fn new() -> Self {
+    Self { value: 0, locked: false }
+  }
+  #[inline]
+  #[must_use]
+  pub const fn is_locked(self) -> bool {
+    self.locked
+  }
+  #[inline]
+  #[must_use]
+  pub const fn read(self) -> u8 {
+    if self.locked {
+      0xFF
+    } else {
+      self.value
+    }
+  }
+  #[inline]
+  pub fn write(&mut self, byte: u8) {
+    if !self.locked {
+      self.value = byte;
+    }
+  }
+  /// Locks the register, which happens when `$FF50` unmaps the boot ROM.
+  ///
+  /// * **Returns:** The mode that the system is now stuck in.
+  #[inline]
+  pub fn lock(&mut self) -> CgbMode {
+    self.locked = true;
+    self.mode()
+  }
+  /// The mode selected by bits 2 and 3.
+  #[inline]
+  #[must_use]
+  pub const fn mode(self) -> CgbMode {
+    if (self.value & 0b1100) != 0 {
+      CgbMode::DmgCompat
+    } else {
+      CgbMode::Cgb
+    }
+  }
+}
+
+/// The colors used for a DMG game in compatibility mode, as RGB555.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CompatPalette {
+  /// BG palette 0.
+  pub bg: [u16; 4],
+  /// OBJ palette 0 (for objects using `OBP0`).
+  pub obj0: [u16; 4],
+  /// OBJ palette 1 (for objects using `OBP1`).
+  pub obj1: [u16; 4],
+}
+impl CompatPalette {
+  /// Looks up the palette the boot ROM would pick for a cart, without any
+  /// button override.
+  ///
+  /// Only carts that say they're from Nintendo (by either licensee code) get
+  /// checked against the list of titles, everything else gets the default.
+  #[must_use]
+  pub fn for_header(header: &CartHeader) -> Self {
+    Self::from_combination(palette_combination_for(header))
+  }
+
+  /// The palette for a button combination held during the boot logo.
+  #[inline]
+  #[must_use]
+  pub fn for_buttons(buttons: ButtonCombo) -> Self {
+    Self::from_combination(buttons.palette_combination())
+  }
+
+  /// Builds a palette from an entry in the boot ROM's combination table.
+  ///
+  /// ## Panics
+  /// * If the index is past the end of the table.
+  #[must_use]
+  pub fn from_combination(index: u8) -> Self {
+    let [obj0, obj1, bg] = PALETTE_COMBINATIONS[usize::from(index)];
+    Self { bg: palette_at(bg), obj0: palette_at(obj0), obj1: palette_at(obj1) }
+  }
+
+  /// Writes the colors into the PPU's palette RAM, where the PPU uses them in
+  /// DMG compatibility mode.
+  pub fn apply(&self, ppu: &mut Ppu) {
+    for i in 0..4 {
+      ppu.bg_palettes_mut().set_color(0, i, self.bg[usize::from(i)]);
+      ppu.obj_palettes_mut().set_color(0, i, self.obj0[usize::from(i)]);
+      ppu.obj_palettes_mut().set_color(1, i, self.obj1[usize::from(i)]);
+    }
+  }
+}
+
+/// The button combinations that override the compatibility palette.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ButtonCombo {
+  #[default]
+  Right,
+  Left,
+  Up,
+  Down,
+  RightA,
+  LeftA,
+  UpA,
+  DownA,
+  RightB,
+  LeftB,
+  UpB,
+  DownB,
+}
+impl ButtonCombo {
+  /// The entry in the boot ROM's combination table for this combo.
+  #[inline]
+  #[must_use]
+  pub const fn palette_combination(self) -> u8 {
+    BUTTON_COMBINATIONS[self as usize]
+  }
+}
+
+/// The sum of the title bytes, if the cart says it's from Nintendo (by either
+/// licensee code).
+///
+/// The boot ROM only tries to recognize Nintendo's games, and it also leaves
+/// this sum in `B` when it's done.
+#[must_use]
+pub fn nintendo_title_checksum(header: &CartHeader) -> Option<u8> {
+  let nintendo = header.old_licensee_code == 0x01
+    || (header.old_licensee_code == 0x33 && header.new_licensee_code == *b"01");
+  nintendo
+    .then(|| header.title.iter().fold(0_u8, |sum, &b| sum.wrapping_add(b)))
+}
+
+/// Picks the combination table entry for a cart, the way the boot ROM does.
+#[must_use]
+pub fn palette_combination_for(header: &CartHeader) -> u8 {
+  let Some(checksum) = nintendo_title_checksum(header) else {
+    return PALETTE_PER_CHECKSUM[0] & 0x7F;
+  };
+  let fourth_letter = header.title[3];
+  for (i, &c) in TITLE_CHECKSUMS.iter().enumerate() {
+    if c != checksum {
+      continue;
+    }
+    if i < FIRST_DUPLICATE_CHECKSUM {
+      return PALETTE_PER_CHECKSUM[i] & 0x7F;
+    }
+    // Ambiguous checksums also compare the 4th title letter. The letter table
+    // is scanned in rows as long as the duplicate section of the checksum
+    // table, and the row that matches picks the palette.
+    let column = i - FIRST_DUPLICATE_CHECKSUM;
+    for row_start in (0..FOURTH_LETTERS.len()).step_by(DUPLICATE_CHECKSUMS) {
+      let j = row_start + column;
+      if FOURTH_LETTERS.get(j) == Some(&fourth_letter) {
+        return PALETTE_PER_CHECKSUM[FIRST_DUPLICATE_CHECKSUM + j] & 0x7F;
+      }
+    }
+  }
+  PALETTE_PER_CHECKSUM[0] & 0x7F
+}
+
+fn palette_at(byte_offset: u8) -> [u16; 4] {
+  let byte = |i: usize| PALETTES[i / 2].to_le_bytes()[i % 2];
+  let mut out = [0; 4];
+  for (i, c) in out.iter_mut().enumerate() {
+    let at = usize::from(byte_offset) + i * 2;
+    *c = u16::from_le_bytes([byte(at), byte(at + 1)]);
+  }
+  out
+}
+
+const FIRST_DUPLICATE_CHECKSUM: usize = 65;
+const DUPLICATE_CHECKSUMS: usize =
+  TITLE_CHECKSUMS.len() - FIRST_DUPLICATE_CHECKSUM;
+
+#[rustfmt::skip]
+const TITLE_CHECKSUMS: [u8; 79] = [
+  0x00, 0x88, 0x16, 0x36, 0xD1, 0xDB, 0xF2, 0x3C, 0x8C, 0x92, 0x3D, 0x5C, 0x58,
+  0xC9, 0x3E, 0x70, 0x1D, 0x59, 0x69, 0x19, 0x35, 0xA8, 0x14, 0xAA, 0x75, 0x95,
+  0x99, 0x34, 0x6F, 0x15, 0xFF, 0x97, 0x4B, 0x90, 0x17, 0x10, 0x39, 0xF7, 0xF6,
+  0xA2, 0x49, 0x4E, 0xC3, 0x68, 0xE0, 0x8B, 0xF0, 0xCE, 0x0C, 0x29, 0xE8, 0xB7,
+  0x86, 0x9A, 0x52, 0x01, 0x9D, 0x71, 0x9C, 0xBD, 0x5D, 0x6D, 0x67, 0x3F, 0x6B,
+  // these need the 4th letter of the title to tell games apart
+  0xB3, 0x46, 0x28, 0xA5, 0xC6, 0xD3, 0x27, 0x61, 0x18, 0x66, 0x6A, 0xBF, 0x0D,
+  0xF4,
+];
+
+const FOURTH_LETTERS: &[u8; 29] = b"BEFAARBEKEK R-URAR INAILICE R";
+
+/// Combination table entry for each checksum (and 4th letter) match. Bit 7 is
+/// a flag the boot ROM uses for the logo, not part of the index.
+#[rustfmt::skip]
+const PALETTE_PER_CHECKSUM: [u8; 94] = [
+  0, 4, 5, 35, 34, 3, 31, 15, 10, 5, 19, 36, 7 | 0x80, 37, 30, 44, 21, 32, 31,
+  20, 5, 33, 13, 14, 5, 29, 5, 18, 9, 3, 2, 26, 25, 25, 41, 42, 26, 45, 42, 45,
+  36, 38, 26, 42, 30, 41, 34, 34, 5, 42, 6, 5, 33, 25, 42, 42, 40, 2, 16, 25,
+  42, 42, 5, 0, 39,
+  // 4th letter row 0
+  36, 22, 25, 6, 32, 12, 36, 11, 39, 18, 39, 24, 31, 50,
+  // 4th letter row 1
+  17, 46, 6, 27, 0, 47, 41, 41, 0, 0, 19, 34, 23, 18,
+  // 4th letter row 2
+  29,
+];
+
+/// Byte offsets into [PALETTES] for the OBJ0, OBJ1, and BG palettes.
+///
+/// Most entries are whole palettes, but a few deliberately start part way
+/// through one palette and run into the next.
+#[rustfmt::skip]
+const PALETTE_COMBINATIONS: [[u8; 3]; 51] = {
+  const fn p(obj0: u8, obj1: u8, bg: u8) -> [u8; 3] {
+    [obj0 * 8, obj1 * 8, bg * 8]
+  }
+  const fn raw(obj0: u8, obj1: u8, bg: u8) -> [u8; 3] {
+    [obj0 * 2, obj1 * 2, bg * 2]
+  }
+  [
+    p(4, 4, 29), p(18, 18, 18), p(20, 20, 20), p(24, 24, 24), p(9, 9, 9),
+    p(0, 0, 0), p(27, 27, 27), p(5, 5, 5), p(12, 12, 12), p(26, 26, 26),
+    p(16, 8, 8), p(4, 28, 28), p(4, 2, 2), p(3, 4, 4), p(4, 29, 29),
+    p(28, 4, 28), p(2, 17, 2), p(16, 16, 8), p(4, 4, 7), p(4, 4, 18),
+    p(4, 4, 20), p(19, 19, 9), raw(4 * 4 - 1, 4 * 4 - 1, 11 * 4),
+    p(17, 17, 2), p(4, 4, 2), p(4, 4, 3), p(28, 28, 0), p(3, 3, 0),
+    p(0, 0, 1), p(18, 22, 18), p(20, 22, 20), p(24, 22, 24), p(16, 22, 8),
+    p(17, 4, 13), raw(28 * 4 - 1, 0, 14 * 4), raw(28 * 4 - 1, 4 * 4, 15 * 4),
+    p(19, 22, 9), p(16, 28, 10), p(4, 23, 28), p(17, 22, 2), p(4, 0, 2),
+    p(4, 28, 3), p(28, 3, 0), p(3, 28, 4), p(21, 28, 4), p(3, 28, 0),
+    p(25, 3, 28), p(0, 28, 8), p(4, 3, 28), p(28, 3, 6), p(4, 28, 29),
+  ]
+};
+
+/// Combination table entries for each [ButtonCombo], in declaration order.
+const BUTTON_COMBINATIONS: [u8; 12] = [1, 48, 5, 8, 0, 40, 43, 3, 6, 7, 28, 49];
+
+/// The boot ROM's colors, 4 per palette.
+#[rustfmt::skip]
+const PALETTES: [u16; 120] = [
+  0x7FFF, 0x32BF, 0x00D0, 0x0000,
+  0x639F, 0x4279, 0x15B0, 0x04CB,
+  0x7FFF, 0x6E31, 0x454A, 0x0000,
+  0x7FFF, 0x1BEF, 0x0200, 0x0000,
+  0x7FFF, 0x421F, 0x1CF2, 0x0000,
+  0x7FFF, 0x5294, 0x294A, 0x0000,
+  0x7FFF, 0x03FF, 0x012F, 0x0000,
+  0x7FFF, 0x03EF, 0x01D6, 0x0000,
+  0x7FFF, 0x42B5, 0x3DC8, 0x0000,
+  0x7E74, 0x03FF, 0x0180, 0x0000,
+  0x67FF, 0x77AC, 0x1A13, 0x2D6B,
+  0x7ED6, 0x4BFF, 0x2175, 0x0000,
+  0x53FF, 0x4A5F, 0x7E52, 0x0000,
+  0x4FFF, 0x7ED2, 0x3A4C, 0x1CE0,
+  0x03ED, 0x7FFF, 0x255F, 0x0000,
+  0x036A, 0x021F, 0x03FF, 0x7FFF,
+  0x7FFF, 0x01DF, 0x0112, 0x0000,
+  0x231F, 0x035F, 0x00F2, 0x0009,
+  0x7FFF, 0x03EA, 0x011F, 0x0000,
+  0x299F, 0x001A, 0x000C, 0x0000,
+  0x7FFF, 0x027F, 0x001F, 0x0000,
+  0x7FFF, 0x03E0, 0x0206, 0x0120,
+  0x7FFF, 0x7EEB, 0x001F, 0x7C00,
+  0x7FFF, 0x3FFF, 0x7E00, 0x001F,
+  0x7FFF, 0x03FF, 0x001F, 0x0000,
+  0x03FF, 0x001F, 0x000C, 0x0000,
+  0x7FFF, 0x033F, 0x0193, 0x0000,
+  0x0000, 0x4200, 0x037F, 0x7FFF,
+  0x7FFF, 0x7E8C, 0x7C00, 0x0000,
+  0x7FFF, 0x1BEF, 0x6180, 0x0000,
+];
+
+#[test]
+fn test_palette_combination_for() {
+  let mut rom = alloc::vec![0_u8; 0x150];
+  // "TETRIS" from Nintendo
+  rom[0x134..0x13A].copy_from_slice(b"TETRIS");
+  rom[0x14B] = 0x01;
+  let mut header = CartHeader::from_rom(&rom).unwrap();
+  assert_eq!(palette_combination_for(&header), 3);
+  // Not from Nintendo, so it gets the default.
+  header.old_licensee_code = 0x08;
+  assert_eq!(palette_combination_for(&header), 0);
+  // A duplicate checksum, told apart by the 4th letter.
+  header.old_licensee_code = 0x33;
+  header.new_licensee_code = *b"01";
+  header.title = [0; 16];
+  header.title[3] = b'U';
+  header.title[0] = 0xB3 - b'U';
+  assert_eq!(palette_combination_for(&header), 17);
+  header.title[3] = b'B';
+  header.title[0] = 0xB3 - b'B';
+  assert_eq!(palette_combination_for(&header), 36);
+
+  let default = CompatPalette::from_combination(0);
+  assert_eq!(default.bg, [0x7FFF, 0x1BEF, 0x6180, 0x0000]);
+  assert_eq!(default.obj0, [0x7FFF, 0x421F, 0x1CF2, 0x0000]);
+  // starts on the last color of palette 3
+  assert_eq!(
+    CompatPalette::from_combination(22).obj0,
+    [0x0000, 0x7FFF, 0x421F, 0x1CF2]
+  );
+}
@@ -1,4 +1,3 @@
-use alloc::collections::VecDeque;
 use core::{
   fmt::Debug,
   ops::{Deref, DerefMut, Not},
@@ -8,12 +7,19 @@ use core::{
 use bytemuck::{cast_mut, cast_slice, cast_slice_mut};
 
 use crate::{
+  cart_header::CartHeader,
   data_bus::DataBus,
-  op_actions::{ActionRegister, CpuAction, ACTION_TABLE},
-  op_disassembly::DISASSEMBLY_TABLE,
+  instruction::{decode, Instruction},
+  model::Model,
+  op_actions::{
+    ActionAddr, ActionCond, ActionQueue, ActionRegister, AluOp, CbOp,
+    CpuAction, ACTION_TABLE, CB_ACTION_TABLE, INTERRUPT_ACTIONS,
+  },
   reg16::Reg16,
   reg8::Reg8,
   reg_flags::RegFlags,
+  speed::{Speed, SPEED_SWITCH_M_CYCLES},
+  trace::{BusAccess, InstructionRecord, NoTracer, Tracer},
 };
 
 /// Simulates the Game Boy's LR35902 CPU.
@@ -27,7 +33,7 @@ use crate::{
 /// hold a borrow on the CPU longer than a single statement.
 ///
 /// * See Also: [Pandocs: CPU Registers and flags](https://gbdev.io/pandocs/CPU_Registers_and_Flags.html)
-#[derive(Default, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[repr(C)]
 pub struct Cpu {
   pub af: Reg16,
@@ -37,8 +43,31 @@ pub struct Cpu {
   pub sp: Reg16,
   pub pc: Reg16,
   pub t_cycles: u32,
-  pub action_queue: VecDeque<CpuAction>,
+  pub action_queue: ActionQueue,
   pub imm: u16,
+  /// The clock speed, which the bus tells us about when `stop` switches it.
+  pub speed: Speed,
+  /// M-cycles left where the CPU sits idle (eg: after a speed switch).
+  pub stall_m_cycles: u16,
+  /// Set by `stop` when it doesn't switch speeds. Cleared by
+  /// [wake_from_stop](Cpu::wake_from_stop).
+  pub stopped: bool,
+  /// The interrupt master enable flag.
+  pub ime: bool,
+  /// Set by `ei`, so that `ime` turns on after the next instruction.
+  pub ei_pending: bool,
+  /// Set by `halt`, until an interrupt is pending.
+  pub halted: bool,
+  /// Set when `halt` runs with `ime` off and an interrupt already pending,
+  /// which makes the next op code fetch not advance PC.
+  pub halt_bug: bool,
+  /// Set by an illegal op code. Only a reset gets the CPU going again.
+  pub locked: bool,
+  /// If `ld b, b` (`$40`) is a software breakpoint, the way test ROMs like
+  /// mooneye's expect from emulators.
+  pub ld_b_b_breakpoint: bool,
+  /// Set when a breakpoint `ld b, b` runs, until something clears it.
+  pub breakpoint_hit: bool,
 }
 impl Debug for Cpu {
   fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
@@ -68,7 +97,8 @@ impl Cpu {
       // Boot ROM was used to do the start up sequence. Each major model of
       // GB-playing-device has its own Boot ROM with small variations. The only
       // registers that have a consistent value after boot are PC and SP. For
-      // simplicity we just zero all the general registers.
+      // simplicity we just zero all the general registers. Use `post_boot` to
+      // get the registers that a particular model would have.
       //
       // See: https://gbdev.io/pandocs/Power_Up_Sequence.html#cpu-registers
       af: Reg16::new(0),
@@ -78,26 +108,138 @@ impl Cpu {
       sp: Reg16::new(0xFFFE),
       pc: Reg16::new(0x0100),
       t_cycles: 0,
-      action_queue: VecDeque::default(),
+      action_queue: ActionQueue::new(&[]),
       imm: 0,
+      speed: Speed::Normal,
+      stall_m_cycles: 0,
+      stopped: false,
+      ime: false,
+      ei_pending: false,
+      halted: false,
+      halt_bug: false,
+      locked: false,
+      ld_b_b_breakpoint: false,
+      breakpoint_hit: false,
     }
   }
 
-  pub fn fetch_pc(&mut self, bus: &mut dyn DataBus) -> u8 {
-    let b = bus.read(self.pc.get());
-    //println!("FETCH: ${b:02X}");
+  /// Makes a CPU at `$0000`, ready to run a boot ROM.
+  ///
+  /// Everything but `PC` is left to the boot ROM to set up.
+  #[must_use]
+  pub fn power_on() -> Self {
+    let mut cpu = Self::new();
+    cpu.sp.set(0);
+    cpu.pc.set(0);
+    cpu
+  }
+
+  /// Makes a CPU at `$0100`, as `model`'s boot ROM would leave it after
+  /// booting the cart with this header.
+  #[must_use]
+  pub fn post_boot(model: Model, header: &CartHeader) -> Self {
+    let [a, f, b, c, d, e, h, l] = model.post_boot_registers(header);
+    let mut cpu = Self::new();
+    cpu.af.set(u16::from_be_bytes([a, f]));
+    cpu.bc.set(u16::from_be_bytes([b, c]));
+    cpu.de.set(u16::from_be_bytes([d, e]));
+    cpu.hl.set(u16::from_be_bytes([h, l]));
+    cpu
+  }
+
+  /// The current CPU speed.
+  #[inline]
+  #[must_use]
+  pub const fn speed(&self) -> Speed {
+    self.speed
+  }
+
+  /// Leaves the low power mode entered by `stop` (eg: from a joypad press).
+  #[inline]
+  pub fn wake_from_stop(&mut self) {
+    self.stopped = false;
+  }
+
+  /// Reads a byte, telling the tracer about it.
+  fn read<T: Tracer + ?Sized>(
+    &mut self, bus: &mut dyn DataBus, tracer: &mut T, addr: u16,
+  ) -> u8 {
+    let byte = bus.read(addr);
+    tracer.bus_access(BusAccess::Read { addr, byte });
+    byte
+  }
+
+  /// Writes a byte, telling the tracer about it.
+  fn write<T: Tracer + ?Sized>(
+    &mut self, bus: &mut dyn DataBus, tracer: &mut T, addr: u16, byte: u8,
+  ) {
+    tracer.bus_access(BusAccess::Write { addr, byte });
+    bus.write(addr, byte);
+  }
+
+  fn fetch_pc<T: Tracer + ?Sized>(
+    &mut self, bus: &mut dyn DataBus, tracer: &mut T,
+  ) -> u8 {
+    let b = self.read(bus, tracer, self.pc.get());
     self.pc.inc();
     b
   }
 
   /// Grants a T-cycle worth of time to the CPU.
   ///
-  /// The CPU only actually acts once per 4 T-cycles.
+  /// T-cycles here are those of the system clock, the same "dots" that the PPU
+  /// counts. The CPU only actually acts once per 4 T-cycles, or once per 2
+  /// T-cycles in double speed mode.
   ///
   /// * **Returns:** If the CPU took an action.
   pub fn t_cycle(&mut self, bus: &mut dyn DataBus) -> bool {
     self.t_cycles = self.t_cycles.wrapping_add(1);
-    if self.t_cycles % 4 != 0 {
+    if !self.t_cycles.is_multiple_of(self.speed().dots_per_m_cycle()) {
+      return false;
+    }
+    self.m_cycle(bus)
+  }
+
+  /// How many T-cycles until [t_cycle](Cpu::t_cycle) next gets to the point
+  /// where the CPU acts, counting the T-cycle where it acts.
+  #[inline]
+  #[must_use]
+  pub const fn t_cycles_until_m_cycle(&self) -> u32 {
+    let per = self.speed().dots_per_m_cycle();
+    per - self.t_cycles % per
+  }
+
+  /// If the CPU can't do anything until an interrupt (or a joypad press)
+  /// comes in, no matter how much time passes.
+  #[inline]
+  #[must_use]
+  pub fn is_idle(&self, bus: &mut dyn DataBus) -> bool {
+    self.stall_m_cycles == 0
+      && (self.stopped
+        || self.locked
+        || (self.halted
+          && self.action_queue.is_empty()
+          && bus.pending_interrupts() == 0))
+  }
+
+  /// Grants one M-cycle to the CPU, skipping the T-cycle counting that
+  /// [t_cycle](Cpu::t_cycle) does.
+  ///
+  /// * **Returns:** If the CPU took an action.
+  #[inline]
+  pub fn m_cycle(&mut self, bus: &mut dyn DataBus) -> bool {
+    self.m_cycle_traced(bus, &mut NoTracer)
+  }
+
+  /// Like [m_cycle](Cpu::m_cycle), but tells `tracer` what happens.
+  pub fn m_cycle_traced<T: Tracer + ?Sized>(
+    &mut self, bus: &mut dyn DataBus, tracer: &mut T,
+  ) -> bool {
+    if self.stall_m_cycles > 0 {
+      self.stall_m_cycles -= 1;
+      return false;
+    }
+    if self.stopped || self.locked {
       return false;
     }
     // When there's no pending actions we have to get a new op code to queue up
@@ -105,77 +247,553 @@ impl Cpu {
     // actions table must be arranged appropriately. Anything that happens as
     // soon as the op-code comes in (eg: `ld a, b`) will be just 1 action.
     if self.action_queue.is_empty() {
-      let op_code = self.fetch_pc(bus);
-      let disassembly = DISASSEMBLY_TABLE[usize::from(op_code)];
-      let actions = ACTION_TABLE[usize::from(op_code)];
-      println!(
-        "Queue Code (${op_code:02X}): {disassembly: <17} // {actions:?}"
-      );
-      self.action_queue.extend(actions.iter().copied());
+      let pending = bus.pending_interrupts();
+      if self.halted {
+        if pending == 0 {
+          return false;
+        }
+        self.halted = false;
+      }
+      let ime = self.ime;
+      if self.ei_pending {
+        self.ei_pending = false;
+        self.ime = true;
+      }
+      if ime && pending != 0 {
+        if tracer.enabled() {
+          tracer.instruction(&self.record(bus, true));
+        }
+        self.ime = false;
+        self.action_queue = ActionQueue::new(INTERRUPT_ACTIONS);
+      } else {
+        if tracer.enabled() {
+          tracer.instruction(&self.record(bus, false));
+        }
+        let op_code = self.fetch_pc(bus, tracer);
+        if self.halt_bug {
+          self.halt_bug = false;
+          self.pc.set(self.pc.get().wrapping_sub(1));
+        }
+        if op_code == 0x40 && self.ld_b_b_breakpoint {
+          self.breakpoint_hit = true;
+        }
+        self.action_queue =
+          ActionQueue::new(ACTION_TABLE[usize::from(op_code)]);
+      }
     }
     let action = self.action_queue.pop_front().unwrap();
-    self.process_action(bus, action);
+    self.process_action(bus, tracer, action);
     true
   }
 
-  fn process_action(&mut self, bus: &mut dyn DataBus, action: CpuAction) {
+  /// The current state, for a [Tracer].
+  fn record(&self, bus: &mut dyn DataBus, dispatch: bool) -> InstructionRecord {
+    let pc = self.pc.get();
+    let pc_mem = [0, 1, 2, 3].map(|i| bus.peek(pc.wrapping_add(i)));
+    InstructionRecord {
+      pc,
+      pc_mem,
+      // 4 bytes is always enough to decode.
+      instruction: (!dispatch).then(|| decode(&pc_mem).unwrap().0),
+      a: self.a.get(),
+      flags: self.flags,
+      b: self.b.get(),
+      c: self.c.get(),
+      d: self.d.get(),
+      e: self.e.get(),
+      h: self.h.get(),
+      l: self.l.get(),
+      sp: self.sp.get(),
+      t_cycles: self.t_cycles,
+    }
+  }
+
+  /// If the CPU is between instructions, so the next action it takes will
+  /// start a new op code (or an interrupt dispatch).
+  #[inline]
+  #[must_use]
+  pub fn is_between_instructions(&self) -> bool {
+    self.action_queue.is_empty()
+  }
+
+  fn get_r8(&self, reg: ActionRegister) -> u8 {
+    match reg {
+      ActionRegister::A => self.a.get(),
+      ActionRegister::B => self.b.get(),
+      ActionRegister::C => self.c.get(),
+      ActionRegister::D => self.d.get(),
+      ActionRegister::E => self.e.get(),
+      ActionRegister::H => self.h.get(),
+      ActionRegister::L => self.l.get(),
+      _ => unreachable!("not an 8-bit register: {reg:?}"),
+    }
+  }
+
+  fn set_r8(&mut self, reg: ActionRegister, byte: u8) {
+    match reg {
+      ActionRegister::A => self.a.set(byte),
+      ActionRegister::B => self.b.set(byte),
+      ActionRegister::C => self.c.set(byte),
+      ActionRegister::D => self.d.set(byte),
+      ActionRegister::E => self.e.set(byte),
+      ActionRegister::H => self.h.set(byte),
+      ActionRegister::L => self.l.set(byte),
+      _ => unreachable!("not an 8-bit register: {reg:?}"),
+    }
+  }
+
+  fn get_r16(&self, reg: ActionRegister) -> u16 {
+    match reg {
+      ActionRegister::PC => self.pc.get(),
+      ActionRegister::SP => self.sp.get(),
+      ActionRegister::BC => self.bc.get(),
+      ActionRegister::DE => self.de.get(),
+      ActionRegister::HL => self.hl.get(),
+      ActionRegister::AF => self.af.get(),
+      _ => unreachable!("not a 16-bit register: {reg:?}"),
+    }
+  }
+
+  fn set_r16(&mut self, reg: ActionRegister, u: u16) {
+    match reg {
+      ActionRegister::PC => self.pc.set(u),
+      ActionRegister::SP => self.sp.set(u),
+      ActionRegister::BC => self.bc.set(u),
+      ActionRegister::DE => self.de.set(u),
+      ActionRegister::HL => self.hl.set(u),
+      // the low 4 bits of F always stay 0
+      ActionRegister::AF => self.af.set(u & 0xFFF0),
+      _ => unreachable!("not a 16-bit register: {reg:?}"),
+    }
+  }
+
+  fn set_flags(&mut self, z: bool, n: bool, h: bool, c: bool) {
+    self.flags.set_z(z);
+    self.flags.set_n(n);
+    self.flags.set_h(h);
+    self.flags.set_c(c);
+  }
+
+  /// Drops the rest of the instruction, when a condition isn't met.
+  fn skip_rest(&mut self) {
+    self.action_queue.clear();
+    self.imm = 0;
+  }
+
+  fn check(&self, cond: ActionCond) -> bool {
+    match cond {
+      ActionCond::NZ => !self.flags.z(),
+      ActionCond::Z => self.flags.z(),
+      ActionCond::NC => !self.flags.c(),
+      ActionCond::C => self.flags.c(),
+    }
+  }
+
+  /// Resolves an address, applying any `hl` adjustment.
+  fn addr(&mut self, addr: ActionAddr) -> u16 {
+    match addr {
+      ActionAddr::BC => self.bc.get(),
+      ActionAddr::DE => self.de.get(),
+      ActionAddr::HL => self.hl.get(),
+      ActionAddr::HLInc => {
+        let hl = self.hl.get();
+        self.hl.set(hl.wrapping_add(1));
+        hl
+      }
+      ActionAddr::HLDec => {
+        let hl = self.hl.get();
+        self.hl.set(hl.wrapping_sub(1));
+        hl
+      }
+    }
+  }
+
+  fn imm_low(&self) -> u8 {
+    self.imm.to_le_bytes()[0]
+  }
+
+  fn push<T: Tracer + ?Sized>(
+    &mut self, bus: &mut dyn DataBus, tracer: &mut T, byte: u8,
+  ) {
+    self.sp.set(self.sp.get().wrapping_sub(1));
+    self.write(bus, tracer, self.sp.get(), byte);
+  }
+
+  fn pop<T: Tracer + ?Sized>(
+    &mut self, bus: &mut dyn DataBus, tracer: &mut T,
+  ) -> u8 {
+    let byte = self.read(bus, tracer, self.sp.get());
+    self.sp.inc();
+    byte
+  }
+
+  fn alu(&mut self, op: AluOp, val: u8) {
+    let a = self.a.get();
+    let carry = u8::from(self.flags.c());
+    let (out, n, h, c) = match op {
+      AluOp::Add => {
+        let (out, c) = a.overflowing_add(val);
+        (out, false, (a & 0xF) + (val & 0xF) > 0xF, c)
+      }
+      AluOp::Adc => {
+        let sum = u16::from(a) + u16::from(val) + u16::from(carry);
+        let h = (a & 0xF) + (val & 0xF) + carry > 0xF;
+        (sum as u8, false, h, sum > 0xFF)
+      }
+      AluOp::Sub | AluOp::Cp => {
+        let (out, c) = a.overflowing_sub(val);
+        (out, true, (a & 0xF) < (val & 0xF), c)
+      }
+      AluOp::Sbc => {
+        let diff = i16::from(a) - i16::from(val) - i16::from(carry);
+        let h =
+          i16::from(a & 0xF) - i16::from(val & 0xF) - i16::from(carry) < 0;
+        (diff as u8, true, h, diff < 0)
+      }
+      AluOp::And => (a & val, false, true, false),
+      AluOp::Xor => (a ^ val, false, false, false),
+      AluOp::Or => (a | val, false, false, false),
+    };
+    self.set_flags(out == 0, n, h, c);
+    if op != AluOp::Cp {
+      self.a.set(out);
+    }
+  }
+
+  /// Runs a `$CB` op on a byte, setting flags.
+  ///
+  /// * **Returns:** The new byte, or `None` for `bit`, which doesn't write.
+  fn cb(&mut self, op: CbOp, val: u8) -> Option<u8> {
+    let carry = self.flags.c();
+    let (out, c) = match op {
+      CbOp::Rlc => (val.rotate_left(1), val & 0x80 != 0),
+      CbOp::Rrc => (val.rotate_right(1), val & 1 != 0),
+      CbOp::Rl => ((val << 1) | u8::from(carry), val & 0x80 != 0),
+      CbOp::Rr => ((val >> 1) | (u8::from(carry) << 7), val & 1 != 0),
+      CbOp::Sla => (val << 1, val & 0x80 != 0),
+      CbOp::Sra => ((val >> 1) | (val & 0x80), val & 1 != 0),
+      CbOp::Swap => (val.rotate_left(4), false),
+      CbOp::Srl => (val >> 1, val & 1 != 0),
+      CbOp::Bit(n) => {
+        self.flags.set_z(val & (1 << n) == 0);
+        self.flags.set_n(false);
+        self.flags.set_h(true);
+        return None;
+      }
+      CbOp::Res(n) => return Some(val & !(1 << n)),
+      CbOp::Set(n) => return Some(val | (1 << n)),
+    };
+    self.set_flags(out == 0, false, false, c);
+    Some(out)
+  }
+
+  fn inc_dec_flags(&mut self, out: u8, dec: bool) {
+    self.flags.set_z(out == 0);
+    self.flags.set_n(dec);
+    self.flags.set_h(if dec { out & 0xF == 0xF } else { out & 0xF == 0 });
+  }
+
+  /// `sp + i8`, with flags from the low byte addition, as used by `add sp, i8`
+  /// and `ld hl, sp+i8`.
+  fn sp_plus_imm(&mut self) -> u16 {
+    let sp = self.sp.get();
+    let e = self.imm_low();
+    let h = (sp & 0xF) + u16::from(e & 0xF) > 0xF;
+    let c = (sp & 0xFF) + u16::from(e) > 0xFF;
+    self.set_flags(false, false, h, c);
+    sp.wrapping_add(e as i8 as u16)
+  }
+
+  fn process_action<T: Tracer + ?Sized>(
+    &mut self, bus: &mut dyn DataBus, tracer: &mut T, action: CpuAction,
+  ) {
+    tracer.action(action);
     use CpuAction::*;
     match action {
       Internal => (),
-      DisableInterrupts => (/* TODO*/),
+      DisableInterrupts => {
+        self.ime = false;
+        self.ei_pending = false;
+      }
+      EnableInterrupts => self.ei_pending = true,
+      EnableInterruptsNow => self.ime = true,
+      Stop => {
+        if let Some(speed) = bus.stop() {
+          self.speed = speed;
+          self.stall_m_cycles = SPEED_SWITCH_M_CYCLES;
+        } else {
+          self.stopped = true;
+        }
+      }
+      Halt => {
+        if !self.ime && bus.pending_interrupts() != 0 {
+          self.halt_bug = true;
+        } else {
+          self.halted = true;
+        }
+      }
+      Illegal => self.locked = true,
       ImmLow => {
-        let imm8 = self.fetch_pc(bus);
+        let imm8 = self.fetch_pc(bus, tracer);
         let imm_bytes: &mut [u8] =
           cast_slice_mut(slice::from_mut(&mut self.imm));
         let index = usize::from(cfg!(target_endian = "little").not());
         imm_bytes[index] = imm8;
       }
-      ImmLowTo(reg) => match reg {
-        ActionRegister::A => {
-          let imm8 = self.fetch_pc(bus);
-          //println!("ImmLowTo({reg:?}): ${imm8:02X}");
-          self.a.set(imm8);
-          self.imm = 0;
+      ImmLowTo(reg) => {
+        let imm8 = self.fetch_pc(bus, tracer);
+        self.set_r8(reg, imm8);
+        self.imm = 0;
+      }
+      ImmLowIf(cond) => {
+        self.process_action(bus, tracer, ImmLow);
+        if !self.check(cond) {
+          self.skip_rest();
         }
-        ActionRegister::PC => todo!(),
-        ActionRegister::SP => todo!(),
-      },
+      }
       ImmHigh => {
-        let imm8 = self.fetch_pc(bus);
+        let imm8 = self.fetch_pc(bus, tracer);
         let imm_bytes: &mut [u8] =
           cast_slice_mut(slice::from_mut(&mut self.imm));
         let index = usize::from(cfg!(target_endian = "little"));
         imm_bytes[index] = imm8;
       }
       ImmHighTo(reg) => {
-        let imm8 = self.fetch_pc(bus);
-        let imm_bytes: &mut [u8] =
-          cast_slice_mut(slice::from_mut(&mut self.imm));
-        let index = usize::from(cfg!(target_endian = "little"));
-        imm_bytes[index] = imm8;
-        match reg {
-          ActionRegister::PC => self.pc.set(self.imm),
-          ActionRegister::SP => self.sp.set(self.imm),
-          ActionRegister::A => todo!(),
-        }
+        self.process_action(bus, tracer, ImmHigh);
+        self.set_r16(reg, self.imm);
         self.imm = 0;
       }
+      ImmHighIf(cond) => {
+        self.process_action(bus, tracer, ImmHigh);
+        if !self.check(cond) {
+          self.skip_rest();
+        }
+      }
+      InternalIf(cond) => {
+        if !self.check(cond) {
+          self.skip_rest();
+        }
+      }
       WriteRegToImm16(reg) => {
-        match reg {
-          ActionRegister::A => bus.write(self.imm, self.a.get()),
-          ActionRegister::PC => todo!(),
-          ActionRegister::SP => todo!(),
-        };
+        self.write(bus, tracer, self.imm, self.get_r8(reg));
+        self.imm = 0;
+      }
+      ReadImm16To(reg) => {
+        let byte = self.read(bus, tracer, self.imm);
+        self.set_r8(reg, byte);
+        self.imm = 0;
+      }
+      WriteSpLowToImm16 => {
+        self.write(bus, tracer, self.imm, self.sp.get().to_le_bytes()[0]);
+        self.imm = self.imm.wrapping_add(1);
+      }
+      WriteSpHighToImm16 => {
+        self.write(bus, tracer, self.imm, self.sp.get().to_le_bytes()[1]);
         self.imm = 0;
       }
       WriteRegToHalfAddr(reg) => {
         debug_assert!(self.imm <= u16::from(u8::MAX));
         let addr = 0xFF00 + self.imm;
-        match reg {
-          ActionRegister::A => bus.write(addr, self.a.get()),
-          ActionRegister::PC => todo!(),
-          ActionRegister::SP => todo!(),
-        };
+        self.write(bus, tracer, addr, self.get_r8(reg));
+        self.imm = 0;
+      }
+      ReadHalfAddrTo(reg) => {
+        let byte = self.read(bus, tracer, 0xFF00 + self.imm);
+        self.set_r8(reg, byte);
+        self.imm = 0;
+      }
+      WriteRegToHalfC(reg) => {
+        let addr = 0xFF00 + u16::from(self.c.get());
+        self.write(bus, tracer, addr, self.get_r8(reg));
+      }
+      ReadHalfCTo(reg) => {
+        let byte = self.read(bus, tracer, 0xFF00 + u16::from(self.c.get()));
+        self.set_r8(reg, byte);
+      }
+      ReadAddrTo(addr, reg) => {
+        let addr = self.addr(addr);
+        let byte = self.read(bus, tracer, addr);
+        self.set_r8(reg, byte);
+      }
+      WriteRegToAddr(addr, reg) => {
+        // read the register first, since `ld [hl], h` and friends can change
+        // `hl` while resolving the address.
+        let byte = self.get_r8(reg);
+        let addr = self.addr(addr);
+        self.write(bus, tracer, addr, byte);
+      }
+      ReadHlToImm => {
+        self.imm = u16::from(self.read(bus, tracer, self.hl.get()))
+      }
+      WriteImmToHl => {
+        self.write(bus, tracer, self.hl.get(), self.imm_low());
+        self.imm = 0;
+      }
+      WriteIncImmToHl | WriteDecImmToHl => {
+        let dec = action == WriteDecImmToHl;
+        let val = self.imm_low();
+        let out = if dec { val.wrapping_sub(1) } else { val.wrapping_add(1) };
+        self.inc_dec_flags(out, dec);
+        self.write(bus, tracer, self.hl.get(), out);
+        self.imm = 0;
+      }
+      Ld(dst, src) => self.set_r8(dst, self.get_r8(src)),
+      IncR8(reg) => {
+        let out = self.get_r8(reg).wrapping_add(1);
+        self.inc_dec_flags(out, false);
+        self.set_r8(reg, out);
+      }
+      DecR8(reg) => {
+        let out = self.get_r8(reg).wrapping_sub(1);
+        self.inc_dec_flags(out, true);
+        self.set_r8(reg, out);
+      }
+      IncR16(reg) => self.set_r16(reg, self.get_r16(reg).wrapping_add(1)),
+      DecR16(reg) => self.set_r16(reg, self.get_r16(reg).wrapping_sub(1)),
+      AddHl(reg) => {
+        let hl = self.hl.get();
+        let val = self.get_r16(reg);
+        let (out, c) = hl.overflowing_add(val);
+        self.flags.set_n(false);
+        self.flags.set_h((hl & 0xFFF) + (val & 0xFFF) > 0xFFF);
+        self.flags.set_c(c);
+        self.hl.set(out);
+      }
+      Alu(op, reg) => self.alu(op, self.get_r8(reg)),
+      AluReadHl(op) => {
+        let val = self.read(bus, tracer, self.hl.get());
+        self.alu(op, val);
+      }
+      AluImm(op) => {
+        let val = self.fetch_pc(bus, tracer);
+        self.alu(op, val);
+      }
+      RotateA(op) => {
+        let out = self.cb(op, self.a.get()).unwrap();
+        self.a.set(out);
+        self.flags.set_z(false);
+      }
+      Daa => {
+        let mut a = self.a.get();
+        let mut c = self.flags.c();
+        if self.flags.n() {
+          if c {
+            a = a.wrapping_sub(0x60);
+          }
+          if self.flags.h() {
+            a = a.wrapping_sub(0x06);
+          }
+        } else {
+          if c || a > 0x99 {
+            a = a.wrapping_add(0x60);
+            c = true;
+          }
+          if self.flags.h() || (a & 0xF) > 0x9 {
+            a = a.wrapping_add(0x06);
+          }
+        }
+        self.a.set(a);
+        self.flags.set_z(a == 0);
+        self.flags.set_h(false);
+        self.flags.set_c(c);
+      }
+      Cpl => {
+        let a = self.a.get();
+        self.a.set(!a);
+        self.flags.set_n(true);
+        self.flags.set_h(true);
+      }
+      Scf => {
+        self.flags.set_n(false);
+        self.flags.set_h(false);
+        self.flags.set_c(true);
+      }
+      Ccf => {
+        self.flags.set_n(false);
+        self.flags.set_h(false);
+        let c = self.flags.c();
+        self.flags.set_c(!c);
+      }
+      JumpRelative => {
+        let e = self.imm_low() as i8;
+        self.pc.set(self.pc.get().wrapping_add(e as u16));
+        self.imm = 0;
+      }
+      JumpImm => {
+        self.pc.set(self.imm);
+        self.imm = 0;
+      }
+      JumpHl => self.pc.set(self.hl.get()),
+      LdSpHl => self.sp.set(self.hl.get()),
+      LdHlSpImm => {
+        let out = self.sp_plus_imm();
+        self.hl.set(out);
+        self.imm = 0;
+      }
+      AddSpImm => {
+        let out = self.sp_plus_imm();
+        self.sp.set(out);
+        self.imm = 0;
+      }
+      PushHigh(reg) => {
+        let [_, high] = self.get_r16(reg).to_le_bytes();
+        self.push(bus, tracer, high);
+      }
+      PushLow(reg) => {
+        let [low, _] = self.get_r16(reg).to_le_bytes();
+        self.push(bus, tracer, low);
+      }
+      CallPushLow => {
+        let [low, _] = self.pc.get().to_le_bytes();
+        self.push(bus, tracer, low);
+        self.pc.set(self.imm);
+        self.imm = 0;
+      }
+      Rst(addr) => {
+        let [low, _] = self.pc.get().to_le_bytes();
+        self.push(bus, tracer, low);
+        self.pc.set(u16::from(addr));
+      }
+      InterruptPushLow => {
+        let [low, _] = self.pc.get().to_le_bytes();
+        self.push(bus, tracer, low);
+        // The high byte push can overwrite `IE`, so the choice of interrupt is
+        // only made now. If nothing is left, the CPU jumps to $0000.
+        let pending = bus.pending_interrupts();
+        if pending == 0 {
+          self.pc.set(0x0000);
+        } else {
+          let bit = pending & pending.wrapping_neg();
+          bus.acknowledge_interrupt(bit);
+          self.pc.set(0x0040 + 8 * bit.trailing_zeros() as u16);
+        }
+      }
+      PopLow => self.imm = u16::from(self.pop(bus, tracer)),
+      PopHighTo(reg) => {
+        let high = self.pop(bus, tracer);
+        self.imm |= u16::from(high) << 8;
+        self.set_r16(reg, self.imm);
+        self.imm = 0;
+      }
+      CbPrefix => {
+        let op_code = self.fetch_pc(bus, tracer);
+        let actions = CB_ACTION_TABLE[usize::from(op_code)];
+        self.action_queue = ActionQueue::new(actions);
+        let action = self.action_queue.pop_front().unwrap();
+        self.process_action(bus, tracer, action);
+      }
+      Cb(op, reg) => {
+        if let Some(out) = self.cb(op, self.get_r8(reg)) {
+          self.set_r8(reg, out);
+        }
+      }
+      CbBitHl(n) => {
+        let val = self.read(bus, tracer, self.hl.get());
+        self.cb(CbOp::Bit(n), val);
+      }
+      CbWriteHl(op) => {
+        let out = self.cb(op, self.imm_low()).unwrap();
+        self.write(bus, tracer, self.hl.get(), out);
         self.imm = 0;
       }
     }
@@ -183,7 +801,7 @@ impl Cpu {
 }
 
 /// A view of the CPU with the data registers broken into individual bytes.
-#[derive(Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[repr(C)]
 // Note(Lokathor): To support big-endian we'd just have to swap the ordering of
 // each pair. However, until big-endian support is really requested, it's better
@@ -201,6 +819,117 @@ pub struct CpuByteFields {
   pub sp: Reg16,
   pub pc: Reg16,
   pub t_cycles: u32,
-  pub action_queue: VecDeque<CpuAction>,
+  pub action_queue: ActionQueue,
   pub imm: u16,
+  pub speed: Speed,
+  pub stall_m_cycles: u16,
+  pub stopped: bool,
+  pub ime: bool,
+  pub ei_pending: bool,
+  pub halted: bool,
+  pub halt_bug: bool,
+  pub locked: bool,
+  pub ld_b_b_breakpoint: bool,
+  pub breakpoint_hit: bool,
+}
+
+#[test]
+fn test_Cpu_interrupts_and_halt() {
+  use crate::data_bus::FlatRam;
+  fn m_cycle(cpu: &mut Cpu, ram: &mut FlatRam) -> bool {
+    (0..4).fold(false, |acted, _| cpu.t_cycle(ram) | acted)
+  }
+  fn step(cpu: &mut Cpu, ram: &mut FlatRam) {
+    m_cycle(cpu, ram);
+    while !cpu.is_between_instructions() {
+      m_cycle(cpu, ram);
+    }
+  }
+  let mut ram = FlatRam::new();
+  // ei; nop; nop
+  ram.0[0x100..0x103].copy_from_slice(&[0xFB, 0x00, 0x00]);
+  // halt; inc b; illegal
+  ram.0[0x40..0x43].copy_from_slice(&[0x76, 0x04, 0xD3]);
+  ram.0[0xFFFF] = 0x01;
+  ram.0[0xFF0F] = 0x01;
+  let mut cpu = Cpu::new();
+
+  // `ei` only takes effect after the instruction that follows it.
+  step(&mut cpu, &mut ram);
+  assert!(!cpu.ime);
+  step(&mut cpu, &mut ram);
+  assert_eq!(cpu.pc.get(), 0x0102);
+  step(&mut cpu, &mut ram);
+  assert_eq!(cpu.pc.get(), 0x0040);
+  assert_eq!(cpu.sp.get(), 0xFFFC);
+  assert_eq!(ram.0[0xFFFC..0xFFFE], [0x02, 0x01]);
+  assert_eq!(ram.0[0xFF0F], 0);
+  assert!(!cpu.ime);
+
+  // `halt` waits for an interrupt, and with IME off it just carries on.
+  step(&mut cpu, &mut ram);
+  assert!(cpu.halted);
+  for _ in 0..10 {
+    assert!(!m_cycle(&mut cpu, &mut ram));
+  }
+  ram.0[0xFF0F] = 0x01;
+  step(&mut cpu, &mut ram);
+  assert!(!cpu.halted);
+  assert_eq!(cpu.b.get(), 1);
+
+  // an illegal op code locks up the CPU.
+  step(&mut cpu, &mut ram);
+  assert!(cpu.locked);
+  assert!(!m_cycle(&mut cpu, &mut ram));
+}
+
+#[test]
+fn test_Cpu_m_cycle_traced() {
+  use crate::data_bus::FlatRam;
+  use crate::{instruction::R8m, trace::InstructionRecord};
+  use alloc::vec::Vec;
+  #[derive(Default)]
+  struct Log {
+    records: Vec<InstructionRecord>,
+    actions: usize,
+    accesses: Vec<BusAccess>,
+  }
+  impl Tracer for Log {
+    fn instruction(&mut self, record: &InstructionRecord) {
+      self.records.push(*record);
+    }
+    fn action(&mut self, _: CpuAction) {
+      self.actions += 1;
+    }
+    fn bus_access(&mut self, access: BusAccess) {
+      self.accesses.push(access);
+    }
+  }
+  let mut ram = FlatRam::new();
+  // ld a, $12; ld [hl], a
+  ram.0[0x100..0x103].copy_from_slice(&[0x3E, 0x12, 0x77]);
+  let mut cpu = Cpu::new();
+  cpu.hl.set(0xC000);
+  let mut log = Log::default();
+  for _ in 0..4 {
+    cpu.m_cycle_traced(&mut ram, &mut log);
+  }
+  assert_eq!(log.records.len(), 2);
+  assert_eq!(log.records[0].pc, 0x0100);
+  assert_eq!(log.records[0].pc_mem, [0x3E, 0x12, 0x77, 0x00]);
+  assert_eq!(log.records[1].a, 0x12);
+  assert_eq!(
+    log.records[1].instruction,
+    Some(Instruction::LdR8mR8m(R8m::HLm, R8m::A))
+  );
+  assert_eq!(log.actions, 4);
+  assert_eq!(
+    log.accesses,
+    [
+      BusAccess::Read { addr: 0x0100, byte: 0x3E },
+      BusAccess::Read { addr: 0x0101, byte: 0x12 },
+      BusAccess::Read { addr: 0x0102, byte: 0x77 },
+      BusAccess::Write { addr: 0xC000, byte: 0x12 },
+    ]
+  );
 }
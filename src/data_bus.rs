@@ -1,4 +1,98 @@
+use alloc::boxed::Box;
+
+use crate::speed::Speed;
+
 pub trait DataBus {
-  fn read(&self, addr: u16) -> u8;
+  /// Reads a byte.
+  ///
+  /// This takes `&mut self` because a read can have side effects, such as
+  /// catching up the hardware behind the address before looking at it.
+  fn read(&mut self, addr: u16) -> u8;
   fn write(&mut self, addr: u16, byte: u8);
+  /// Reads a byte without any side effects, for debuggers and tracers.
+  ///
+  /// The default just reads.
+  fn peek(&mut self, addr: u16) -> u8 {
+    self.read(addr)
+  }
+  /// Called when the CPU executes `stop`, which resets the timer's `DIV` and
+  /// does any speed switch that `KEY1` has prepared.
+  ///
+  /// The default does nothing, for buses without a timer or `KEY1`.
+  ///
+  /// * **Returns:** The new speed, if it switched.
+  fn stop(&mut self) -> Option<Speed> {
+    None
+  }
+  /// The interrupts that are both requested (`IF`) and enabled (`IE`).
+  ///
+  /// The default reads `$FF0F` and `$FFFF`.
+  fn pending_interrupts(&mut self) -> u8 {
+    self.read(0xFFFF) & self.read(0xFF0F) & 0x1F
+  }
+  /// Clears an `IF` bit, as the CPU starts handling that interrupt.
+  ///
+  /// The default reads and then writes `$FF0F`.
+  fn acknowledge_interrupt(&mut self, bit: u8) {
+    let if_ = self.read(0xFF0F);
+    self.write(0xFF0F, if_ & !bit);
+  }
+  /// Which ROM bank is mapped at `addr`, for debuggers.
+  ///
+  /// The default is `None`, for buses that don't map ROM banks.
+  fn rom_bank_at(&self, addr: u16) -> Option<usize> {
+    let _ = addr;
+    None
+  }
+}
+
+impl<T: DataBus + ?Sized> DataBus for Box<T> {
+  #[inline]
+  fn read(&mut self, addr: u16) -> u8 {
+    T::read(self, addr)
+  }
+  #[inline]
+  fn write(&mut self, addr: u16, byte: u8) {
+    T::write(self, addr, byte)
+  }
+  #[inline]
+  fn peek(&mut self, addr: u16) -> u8 {
+    T::peek(self, addr)
+  }
+  #[inline]
+  fn stop(&mut self) -> Option<Speed> {
+    T::stop(self)
+  }
+  #[inline]
+  fn pending_interrupts(&mut self) -> u8 {
+    T::pending_interrupts(self)
+  }
+  #[inline]
+  fn acknowledge_interrupt(&mut self, bit: u8) {
+    T::acknowledge_interrupt(self, bit)
+  }
+  #[inline]
+  fn rom_bank_at(&self, addr: u16) -> Option<usize> {
+    T::rom_bank_at(self, addr)
+  }
+}
+
+/// A flat 64K of RAM with nothing else mapped, for tests that only need
+/// somewhere to put some code.
+#[cfg(test)]
+pub(crate) struct FlatRam(pub alloc::vec::Vec<u8>);
+#[cfg(test)]
+impl FlatRam {
+  pub fn new() -> Self {
+    Self(alloc::vec![0; 0x10000])
+  }
+}
+#[cfg(test)]
+impl DataBus for FlatRam {
+  fn read(&mut self, addr: u16) -> u8 {
+    self.0[usize::from(addr)]
+  }
+  fn write(&mut self, addr: u16, byte: u8) {
+    self.0[usize::from(addr)] = byte;
+  }
 }
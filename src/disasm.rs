@@ -0,0 +1,404 @@
+//! A recursive descent disassembler, which writes RGBDS source that
+//! reassembles to the same ROM.
+//!
+//! Code is found by walking from the entry point, the `rst` vectors, and the
+//! interrupt vectors, following every jump and call. Anything that's never
+//! reached is written as data.
+//!
+//! The `$4000..=$7FFF` area can hold any bank, so a jump or call there can
+//! only be followed when the bank is known. Code in a switchable bank can
+//! always reach its own bank, and code in bank 0 tracks writes of a known `a`
+//! (or `[hl]` with a known `hl`) to the MBC's bank register at
+//! `$2000..=$3FFF`. Anything else is left as a plain address.
+//!
+//! The output is for RGBDS 0.6 or later:
+//! `rgbasm -o game.o game.asm && rgblink -o game.gb game.o`.
+//!
+//! * See Also: [RGBDS](https://rgbds.gbdev.io/docs/)
+
+use alloc::{collections::BTreeMap, format, string::String, vec, vec::Vec};
+use core::fmt::Write;
+
+use crate::{
+  instruction::{
+    decode, Cond, Instruction, PrefixedOp, R16f, R16id, R16p, R8m,
+  },
+  mbc::ROM_BANK_SIZE,
+};
+
+/// Where the walk starts, and what the label there is called.
+const START_POINTS: &[(u16, &str)] = &[
+  (0x0000, "RST_00"),
+  (0x0008, "RST_08"),
+  (0x0010, "RST_10"),
+  (0x0018, "RST_18"),
+  (0x0020, "RST_20"),
+  (0x0028, "RST_28"),
+  (0x0030, "RST_30"),
+  (0x0038, "RST_38"),
+  (0x0040, "VBlankInterrupt"),
+  (0x0048, "LCDCInterrupt"),
+  (0x0050, "TimerOverflowInterrupt"),
+  (0x0058, "SerialTransferCompleteInterrupt"),
+  (0x0060, "JoypadTransitionInterrupt"),
+  (0x0100, "Boot"),
+];
+
+/// The cart header, which is always data.
+const HEADER: core::ops::Range<usize> = 0x0104..0x0150;
+
+/// How many data bytes go on one `db` line.
+const DATA_PER_LINE: usize = 16;
+
+/// How a ROM byte is used.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+enum Byte {
+  #[default]
+  Data,
+  /// The first byte of an instruction, which is this many bytes long.
+  Start(u8),
+  /// A later byte of an instruction.
+  Operand,
+}
+
+/// Why a label exists, which picks its name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+enum Label {
+  Named(&'static str),
+  Call,
+  Jump,
+}
+
+/// What's known about the CPU at some point in the walk.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+struct State {
+  /// The ROM bank at `$4000..=$7FFF`.
+  bank: Option<usize>,
+  a: Option<u8>,
+  hl: Option<u16>,
+}
+
+/// A disassembled ROM.
+pub struct Disassembly<'r> {
+  rom: &'r [u8],
+  bytes: Vec<Byte>,
+  labels: BTreeMap<usize, Label>,
+  /// Jump and call targets, by the offset of the instruction.
+  targets: BTreeMap<usize, usize>,
+}
+impl<'r> Disassembly<'r> {
+  /// Walks all the code that can be found in a ROM.
+  #[must_use]
+  pub fn new(rom: &'r [u8]) -> Self {
+    let mut this = Self {
+      rom,
+      bytes: vec![Byte::Data; rom.len()],
+      labels: BTreeMap::new(),
+      targets: BTreeMap::new(),
+    };
+    let mut todo: Vec<(usize, State)> = Vec::new();
+    for &(addr, name) in START_POINTS {
+      if usize::from(addr) < rom.len() {
+        this.labels.insert(usize::from(addr), Label::Named(name));
+        todo.push((usize::from(addr), State::default()));
+      }
+    }
+    while let Some((offset, state)) = todo.pop() {
+      this.walk(offset, state, &mut todo);
+    }
+    // Only keep labels that ended up on code.
+    let bytes = &this.bytes;
+    this.labels.retain(|&offset, _| matches!(bytes[offset], Byte::Start(_)));
+    this
+  }
+
+  /// How many 16 KiB banks the ROM has.
+  #[inline]
+  #[must_use]
+  pub fn bank_count(&self) -> usize {
+    self.rom.len().div_ceil(ROM_BANK_SIZE)
+  }
+
+  /// If the byte at a ROM offset is part of an instruction.
+  #[inline]
+  #[must_use]
+  pub fn is_code(&self, offset: usize) -> bool {
+    matches!(self.bytes.get(offset), Some(Byte::Start(_) | Byte::Operand))
+  }
+
+  /// The name of the label at a ROM offset, if there is one.
+  #[must_use]
+  pub fn label(&self, offset: usize) -> Option<String> {
+    let (bank, addr) = bank_addr(offset);
+    Some(match self.labels.get(&offset)? {
+      Label::Named(name) => String::from(*name),
+      Label::Call => format!("Call_{bank:03X}_{addr:04X}"),
+      Label::Jump => format!("Jump_{bank:03X}_{addr:04X}"),
+    })
+  }
+
+  /// Follows code from `offset` until it ends, queueing up anything it
+  /// branches to.
+  fn walk(
+    &mut self, mut offset: usize, mut state: State,
+    todo: &mut Vec<(usize, State)>,
+  ) {
+    loop {
+      if HEADER.contains(&offset) {
+        return;
+      }
+      match self.bytes.get(offset) {
+        Some(Byte::Data) => (),
+        // already walked, or would overlap another instruction.
+        _ => return,
+      }
+      let bank_end = (offset / ROM_BANK_SIZE + 1) * ROM_BANK_SIZE;
+      let end = bank_end.min(self.rom.len()).min(if offset < HEADER.start {
+        HEADER.start
+      } else {
+        usize::MAX
+      });
+      let Ok((instruction, len)) = decode(&self.rom[offset..end]) else {
+        return;
+      };
+      if self.bytes[offset + 1..offset + len].iter().any(|&b| b != Byte::Data) {
+        return;
+      }
+      self.bytes[offset] = Byte::Start(len as u8);
+      self.bytes[offset + 1..offset + len].fill(Byte::Operand);
+
+      let (bank, addr) = bank_addr(offset);
+      if bank > 0 {
+        state.bank = Some(bank);
+      }
+      use Instruction::*;
+      let (target, label, falls_through) = match instruction {
+        JumpRelative(_) | JumpImm16(_) => {
+          (instruction.jump_target(addr), Label::Jump, false)
+        }
+        JumpRelativeCond(..) | JumpCond(..) => {
+          (instruction.jump_target(addr), Label::Jump, true)
+        }
+        Call(_) | CallCond(..) | Restart(_) => {
+          (instruction.jump_target(addr), Label::Call, true)
+        }
+        Return | ReturnIrq | JumpHL | Illegal(_) => (None, Label::Jump, false),
+        _ => (None, Label::Jump, true),
+      };
+      if let Some(target) = target.and_then(|t| self.offset_of(t, state.bank)) {
+        if !matches!(instruction, Restart(_)) {
+          self.targets.insert(offset, target);
+          self.labels.entry(target).or_insert(label);
+        }
+        todo.push((target, State { bank: state.bank, a: None, hl: None }));
+      }
+      if !falls_through || (offset + len) / ROM_BANK_SIZE != bank {
+        return;
+      }
+      state = track(state, instruction, bank);
+      offset += len;
+    }
+  }
+
+  /// The ROM offset of an address, with `bank` at `$4000..=$7FFF`.
+  fn offset_of(&self, addr: u16, bank: Option<usize>) -> Option<usize> {
+    let offset = match addr {
+      0x0000..=0x3FFF => usize::from(addr),
+      0x4000..=0x7FFF => bank? * ROM_BANK_SIZE + usize::from(addr - 0x4000),
+      _ => return None,
+    };
+    (offset < self.rom.len()).then_some(offset)
+  }
+
+  /// Writes one bank as an RGBDS `SECTION`.
+  pub fn write_bank(&self, out: &mut String, bank: usize) {
+    let start = bank * ROM_BANK_SIZE;
+    let end = (start + ROM_BANK_SIZE).min(self.rom.len());
+    if bank == 0 {
+      writeln!(out, "SECTION \"ROM Bank $000\", ROM0[$0000]").unwrap();
+    } else {
+      writeln!(
+        out,
+        "SECTION \"ROM Bank ${bank:03X}\", ROMX[$4000], BANK[${bank:03X}]"
+      )
+      .unwrap();
+    }
+    let mut offset = start;
+    let mut data: Vec<u8> = Vec::new();
+    let flush = |out: &mut String, data: &mut Vec<u8>| {
+      for chunk in data.chunks(DATA_PER_LINE) {
+        out.push_str("    db ");
+        for (i, byte) in chunk.iter().enumerate() {
+          let sep = if i == 0 { "" } else { ", " };
+          write!(out, "{sep}${byte:02X}").unwrap();
+        }
+        out.push('\n');
+      }
+      data.clear();
+    };
+    while offset < end {
+      if let Some(label) = self.label(offset) {
+        flush(out, &mut data);
+        writeln!(out, "\n{label}:").unwrap();
+      }
+      match self.bytes[offset] {
+        Byte::Start(len) => {
+          flush(out, &mut data);
+          let len = usize::from(len);
+          let bytes = &self.rom[offset..offset + len];
+          out.push_str("    ");
+          self.write_instruction(out, offset, bytes);
+          out.push('\n');
+          offset += len;
+        }
+        _ => {
+          data.push(self.rom[offset]);
+          if data.len() == DATA_PER_LINE {
+            flush(out, &mut data);
+          }
+          offset += 1;
+        }
+      }
+    }
+    flush(out, &mut data);
+  }
+
+  /// Writes the whole ROM, one section per bank.
+  #[must_use]
+  pub fn to_asm(&self) -> String {
+    let mut out = String::new();
+    for bank in 0..self.bank_count() {
+      if bank > 0 {
+        out.push('\n');
+      }
+      self.write_bank(&mut out, bank);
+    }
+    out
+  }
+
+  fn write_instruction(&self, out: &mut String, offset: usize, bytes: &[u8]) {
+    let (instruction, _) = decode(bytes).unwrap();
+    let (_, addr) = bank_addr(offset);
+    use Instruction::*;
+    let target = self.targets.get(&offset).and_then(|&t| self.label(t));
+    match (instruction, target) {
+      // `stop` assembles to 2 bytes, and `ld` to the high page might be
+      // turned into `ldh`, so these are written as data to be sure.
+      (Stop, _) | (LdImm16ToA(0xFF00..=0xFFFF, _), _) => {
+        write!(out, "db ").unwrap();
+        for (i, byte) in bytes.iter().enumerate() {
+          let sep = if i == 0 { "" } else { ", " };
+          write!(out, "{sep}${byte:02X}").unwrap();
+        }
+        write!(out, " ; {instruction}").unwrap();
+      }
+      (JumpRelative(_) | JumpImm16(_), Some(label)) => {
+        let op =
+          if matches!(instruction, JumpRelative(_)) { "jr" } else { "jp" };
+        write!(out, "{op} {label}").unwrap();
+      }
+      (JumpRelativeCond(cond, _), Some(label)) => {
+        write!(out, "jr {cond}, {label}").unwrap();
+      }
+      (JumpCond(cond, _), Some(label)) => {
+        write!(out, "jp {cond}, {label}").unwrap();
+      }
+      (Call(_), Some(label)) => write!(out, "call {label}").unwrap(),
+      (CallCond(cond, _), Some(label)) => {
+        write!(out, "call {cond}, {label}").unwrap();
+      }
+      _ => write!(out, "{instruction}").unwrap(),
+    }
+  }
+}
+
+/// The bank and CPU address of a ROM offset.
+#[inline]
+fn bank_addr(offset: usize) -> (usize, u16) {
+  let bank = offset / ROM_BANK_SIZE;
+  let addr =
+    (offset % ROM_BANK_SIZE) as u16 + if bank == 0 { 0 } else { 0x4000 };
+  (bank, addr)
+}
+
+/// Updates what's known after an instruction runs.
+///
+/// Only code in bank 0 can switch banks and keep running, so `bank` is the
+/// bank the instruction is in.
+fn track(mut state: State, instruction: Instruction, bank: usize) -> State {
+  use Instruction::*;
+  let switch = |state: &mut State, addr: u16| {
+    if bank == 0 && (0x2000..=0x3FFF).contains(&addr) {
+      state.bank = state.a.map(|a| usize::from(a.max(1)));
+    }
+  };
+  match instruction {
+    LdR8mImm8(R8m::A, n) => state.a = Some(n),
+    LdImm16ToA(addr, false) => switch(&mut state, addr),
+    LdR8mR8m(R8m::HLm, R8m::A) => {
+      if let Some(hl) = state.hl {
+        switch(&mut state, hl);
+      }
+    }
+    LdR16pImm16(R16p::HL, n) => state.hl = Some(n),
+    // Things that can't change `a` or `hl`.
+    Nop | DI | EI | LdR16pImm16(..) | LdImm16SP(_) | Push(_) => (),
+    LdhImm8ToA(_, false) | LdhCToA(false) | LdR16idToA(R16id::BC, false) => (),
+    LdR16idToA(R16id::DE, false) => (),
+    LdR8mR8m(R8m::B | R8m::C | R8m::D | R8m::E | R8m::HLm, _) => (),
+    LdR8mImm8(R8m::B | R8m::C | R8m::D | R8m::E | R8m::HLm, _) => (),
+    DecIncR8m(R8m::B | R8m::C | R8m::D | R8m::E | R8m::HLm, _) => (),
+    DecIncR16p(R16p::BC | R16p::DE | R16p::SP, _) => (),
+    Pop(R16f::BC | R16f::DE) => (),
+    Cb(
+      PrefixedOp::Bit(..)
+      | PrefixedOp::RotR8m(_, R8m::B | R8m::C | R8m::D | R8m::E)
+      | PrefixedOp::Res(_, R8m::B | R8m::C | R8m::D | R8m::E)
+      | PrefixedOp::Set(_, R8m::B | R8m::C | R8m::D | R8m::E),
+    ) => (),
+    JumpRelativeCond(..) | JumpCond(..) | ReturnCond(_) => (),
+    _ => {
+      state.a = None;
+      state.hl = None;
+    }
+  }
+  state
+}
+
+#[test]
+fn test_Disassembly_new() {
+  let mut rom = vec![0xFF_u8; ROM_BANK_SIZE * 4];
+  // `nop`, `jp $0150`
+  rom[0x100..0x104].copy_from_slice(&[0x00, 0xC3, 0x50, 0x01]);
+  // `ld a, 2`, `ld [$2000], a`, `call $4000`, `jr $0150`, then 2 data bytes
+  rom[0x150..0x15C].copy_from_slice(&[
+    0x3E, 0x02, 0xEA, 0x00, 0x20, 0xCD, 0x00, 0x40, 0x18, 0xF6, 0x12, 0x34,
+  ]);
+  // bank 2: `ret`
+  rom[2 * ROM_BANK_SIZE] = 0xC9;
+  let dis = Disassembly::new(&rom);
+  assert!(dis.is_code(0x100));
+  assert!(!dis.is_code(0x104));
+  assert!(dis.is_code(0x159));
+  assert!(!dis.is_code(0x15A));
+  assert!(dis.is_code(2 * ROM_BANK_SIZE));
+  assert!(!dis.is_code(ROM_BANK_SIZE));
+  assert_eq!(dis.label(0x150).as_deref(), Some("Jump_000_0150"));
+  assert_eq!(dis.label(2 * ROM_BANK_SIZE).as_deref(), Some("Call_002_4000"));
+
+  let asm = dis.to_asm();
+  assert!(asm.contains("\nJump_000_0150:\n    ld a, $02\n    ld [$2000], a\n"));
+  assert!(asm
+    .contains("    call Call_002_4000\n    jr Jump_000_0150\n    db $12, $34"));
+  assert!(asm.contains("SECTION \"ROM Bank $002\", ROMX[$4000], BANK[$002]"));
+
+  // the whole point is that it reassembles to the same ROM.
+  let assemble = crate::assembler::assemble;
+  assert_eq!(assemble(&asm).unwrap(), rom);
+  let blargg = include_bytes!("../tests/blargg_cpu_instrs.gb");
+  let asm = Disassembly::new(blargg).to_asm();
+  assert!(
+    assemble(&asm).unwrap() == blargg,
+    "blargg_cpu_instrs.gb reassembled differently"
+  );
+}
@@ -0,0 +1,158 @@
+//! Logs in the format of [gameboy-doctor], for comparing the CPU against
+//! reference logs.
+//!
+//! Each line is the CPU state just before an instruction runs, with the 4
+//! bytes at `PC`:
+//!
+//! ```txt
+//! A:00 F:11 B:22 C:33 D:44 E:55 H:66 L:77 SP:8888 PC:9999 PCMEM:AA,BB,CC,DD
+//! ```
+//!
+//! The reference logs start from the DMG post-boot state, and are made with
+//! `LY` always reading `$90` so that they don't depend on PPU timing. [setup]
+//! takes care of that. Interrupt dispatches don't get a line, only the
+//! instructions of the handler do.
+//!
+//! [gameboy-doctor]: https://github.com/robert/gameboy-doctor
+
+use alloc::{boxed::Box, vec::Vec};
+use core::fmt::{Display, Formatter};
+
+use crate::{
+  gameboy::GameBoy,
+  trace::{InstructionRecord, Tracer},
+};
+
+/// What `LY` always reads as in gameboy-doctor logs.
+pub const DOCTOR_LY: u8 = 0x90;
+
+/// Formats a record as one gameboy-doctor log line (without the newline).
+#[derive(Debug, Clone, Copy)]
+pub struct DoctorLine<'a>(pub &'a InstructionRecord);
+impl Display for DoctorLine<'_> {
+  fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+    let r = self.0;
+    let [m0, m1, m2, m3] = r.pc_mem;
+    write!(
+      f,
+      "A:{:02X} F:{:02X} B:{:02X} C:{:02X} D:{:02X} E:{:02X} H:{:02X} L:{:02X} SP:{:04X} PC:{:04X} PCMEM:{m0:02X},{m1:02X},{m2:02X},{m3:02X}",
+      r.a,
+      r.flags.get(),
+      r.b,
+      r.c,
+      r.d,
+      r.e,
+      r.h,
+      r.l,
+      r.sp,
+      r.pc
+    )
+  }
+}
+
+/// A [Tracer] that passes a [DoctorLine] for each instruction to a closure.
+pub struct DoctorTracer<F>(pub F);
+impl<F: FnMut(DoctorLine<'_>)> Tracer for DoctorTracer<F> {
+  #[inline]
+  fn instruction(&mut self, record: &InstructionRecord) {
+    if record.instruction.is_some() {
+      (self.0)(DoctorLine(record))
+    }
+  }
+}
+
+/// Sets up a Game Boy to make a gameboy-doctor log, sending each line to
+/// `line`.
+///
+/// The Game Boy should be a [Model::Dmg](crate::model::Model::Dmg) made with
+/// [GameBoy::new], so that it starts from the post-boot state.
+pub fn setup(gb: &mut GameBoy, line: impl FnMut(DoctorLine<'_>) + 'static) {
+  gb.map_mut().stub_ly(Some(DOCTOR_LY));
+  gb.set_tracer(Some(Box::new(DoctorTracer(line))));
+}
+
+/// Where two logs first differ.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Divergence<'a> {
+  /// The index of the first line that differs.
+  pub index: usize,
+  /// Our line, or `None` if our log ended first.
+  pub ours: Option<&'a str>,
+  /// The reference line, or `None` if the reference log ended first.
+  pub reference: Option<&'a str>,
+}
+impl<'a> Divergence<'a> {
+  /// The fields that have different values, as `(name, ours, reference)`.
+  ///
+  /// This is empty if either log ended.
+  #[must_use]
+  pub fn fields(&self) -> Vec<(&'a str, &'a str, &'a str)> {
+    let (Some(ours), Some(reference)) = (self.ours, self.reference) else {
+      return Vec::new();
+    };
+    ours
+      .split_whitespace()
+      .zip(reference.split_whitespace())
+      .filter_map(|(o, r)| {
+        let (key, o_val) = o.split_once(':')?;
+        let (_, r_val) = r.split_once(':')?;
+        (o_val != r_val).then_some((key, o_val, r_val))
+      })
+      .collect()
+  }
+}
+
+/// Finds the first line where our log and a reference log differ.
+///
+/// Lines are compared without any trailing whitespace, so `\r\n` line endings
+/// don't matter.
+///
+/// * **Returns:** `None` if the logs match all the way through.
+#[must_use]
+pub fn first_divergence<'a>(
+  ours: &'a str, reference: &'a str,
+) -> Option<Divergence<'a>> {
+  let mut ours = ours.lines().map(str::trim_end);
+  let mut reference = reference.lines().map(str::trim_end);
+  let mut index = 0;
+  loop {
+    match (ours.next(), reference.next()) {
+      (None, None) => return None,
+      (o, r) if o != r => {
+        return Some(Divergence { index, ours: o, reference: r })
+      }
+      _ => index += 1,
+    }
+  }
+}
+
+#[test]
+fn test_first_divergence() {
+  use alloc::format;
+  let record = InstructionRecord {
+    pc: 0x0100,
+    pc_mem: [0x00, 0xC3, 0x13, 0x02],
+    a: 0x01,
+    flags: crate::reg_flags::RegFlags::new(0xB0),
+    c: 0x13,
+    e: 0xD8,
+    h: 0x01,
+    l: 0x4D,
+    sp: 0xFFFE,
+    ..Default::default()
+  };
+  let line = format!("{}", DoctorLine(&record));
+  assert_eq!(
+    line,
+    "A:01 F:B0 B:00 C:13 D:00 E:D8 H:01 L:4D SP:FFFE PC:0100 PCMEM:00,C3,13,02"
+  );
+
+  let reference = format!("{line}\r\n{line}\r\n");
+  let ours = format!("{line}\n{}\n", line.replace("B:00", "B:01"));
+  let divergence = first_divergence(&ours, &reference).unwrap();
+  assert_eq!(divergence.index, 1);
+  assert_eq!(divergence.fields(), [("B", "01", "00")]);
+  assert_eq!(first_divergence(&reference, &reference), None);
+  let short = first_divergence(&ours[..line.len()], &reference).unwrap();
+  assert_eq!((short.index, short.ours), (1, None));
+}
@@ -0,0 +1,360 @@
+//! A whole Game Boy: the CPU plus the [MemoryMap] and everything behind it.
+//!
+//! All of the parts run off the system clock, counted in dots (4 MiHz
+//! T-cycles). The CPU gets each of its M-cycles in turn, but everything else
+//! is only caught up when the CPU touches it or when a scheduled event (an
+//! interrupt, a PPU mode change, and so on) comes due. While the CPU is halted
+//! the clock jumps straight to the next event. The results are the same as
+//! running every part one dot at a time, just a lot faster.
+
+use alloc::{boxed::Box, vec::Vec};
+use core::ops::AddAssign;
+
+use crate::{
+  apu::Apu,
+  boot_rom::BootRom,
+  cart_header::CartHeader,
+  compat::CompatPalette,
+  cpu::Cpu,
+  data_bus::DataBus,
+  hdma::Hdma,
+  interrupts::INT_JOYPAD,
+  joypad::Buttons,
+  mbc::cart_from_rom,
+  memory_map::MemoryMap,
+  model::Model,
+  ppu::{Ppu, DOTS_PER_FRAME},
+  serial::SerialPort,
+  sgb::Sgb,
+  speed::Key1,
+  trace::Tracer,
+  wram::Wram,
+};
+
+/// What happened during a `step_*` or `run_*` call.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct RunSummary {
+  /// System T-cycles (dots) that passed.
+  pub t_cycles: u32,
+  /// Instructions that finished. Interrupt dispatches count as instructions.
+  pub instructions: u32,
+  /// If VBlank started, so there's a new frame in the PPU.
+  pub frame_ready: bool,
+  /// If the CPU hit a breakpoint, which ends the run early.
+  pub breakpoint: bool,
+}
+impl AddAssign for RunSummary {
+  #[inline]
+  fn add_assign(&mut self, rhs: Self) {
+    self.t_cycles += rhs.t_cycles;
+    self.instructions += rhs.instructions;
+    self.frame_ready |= rhs.frame_ready;
+    self.breakpoint |= rhs.breakpoint;
+  }
+}
+
+pub struct GameBoy {
+  model: Model,
+  cpu: Cpu,
+  map: MemoryMap,
+  tracer: Option<Box<dyn Tracer>>,
+}
+impl GameBoy {
+  /// Makes a Game Boy that's already booted the cart, as if `model`'s boot
+  /// ROM had just finished.
+  ///
+  /// * **Returns:** `None` if the ROM has no header or an unsupported cart
+  ///   type.
+  #[must_use]
+  pub fn new(model: Model, rom: Vec<u8>) -> Option<Self> {
+    let header = CartHeader::from_rom(&rom)?;
+    let cart = cart_from_rom(rom)?;
+    let cgb_mode = model.runs_in_cgb_mode(&header);
+    let mut ppu = Ppu::new(model.is_cgb());
+    if model.is_cgb() && !cgb_mode {
+      CompatPalette::for_header(&header).apply(&mut ppu);
+      ppu.enter_dmg_compat();
+    }
+    let sgb = model.is_sgb().then(Sgb::new);
+    let mut map = MemoryMap::new(
+      cart,
+      None,
+      ppu,
+      Wram::new(cgb_mode),
+      Hdma::new(cgb_mode),
+      Key1::new(cgb_mode),
+      sgb,
+    );
+    // Writing the IO registers with their post-boot values sets up almost
+    // everything. APU power has to go first, and `$FF` means the register
+    // isn't there (or is one where writing `$FF` would start something).
+    let io = model.post_boot_io(&header);
+    map.write(0xFF26, io[0x26]);
+    for (addr, &byte) in (0xFF00..).zip(io.iter()) {
+      if byte != 0xFF && !matches!(addr, 0xFF04 | 0xFF44 | 0xFF46 | 0xFF26) {
+        map.write(addr, byte);
+      }
+    }
+    map.timer.set_counter(model.post_boot_div_counter(cgb_mode));
+    let (ly, dot) = model.post_boot_ppu_position();
+    map.ppu.set_position(ly, dot);
+    Some(Self { model, cpu: Cpu::post_boot(model, &header), map, tracer: None })
+  }
+
+  /// Makes a Game Boy from a ROM file, like [new](GameBoy::new) does.
+  ///
+  /// * **Returns:** `Ok(None)` if the ROM has no header or an unsupported
+  ///   cart type.
+  #[cfg(feature = "std")]
+  pub fn from_file(
+    model: Model, path: impl AsRef<std::path::Path>,
+  ) -> std::io::Result<Option<Self>> {
+    std::fs::read(path).map(|rom| Self::new(model, rom))
+  }
+
+  /// Makes a Game Boy at power on, which will run the boot ROM.
+  ///
+  /// * **Returns:** `None` if the ROM has no header or an unsupported cart
+  ///   type.
+  #[must_use]
+  pub fn with_boot_rom(
+    model: Model, rom: Vec<u8>, boot_rom: BootRom,
+  ) -> Option<Self> {
+    let cart = cart_from_rom(rom)?;
+    let cgb = model.is_cgb();
+    let sgb = model.is_sgb().then(Sgb::new);
+    let map = MemoryMap::new(
+      cart,
+      Some(boot_rom),
+      Ppu::new(cgb),
+      Wram::new(cgb),
+      Hdma::new(cgb),
+      // The boot ROM itself runs in CGB mode.
+      Key1::new(cgb),
+      sgb,
+    );
+    Some(Self { model, cpu: Cpu::power_on(), map, tracer: None })
+  }
+
+  #[inline]
+  #[must_use]
+  pub const fn model(&self) -> Model {
+    self.model
+  }
+  #[inline]
+  #[must_use]
+  pub const fn cpu(&self) -> &Cpu {
+    &self.cpu
+  }
+  #[inline]
+  pub fn cpu_mut(&mut self) -> &mut Cpu {
+    &mut self.cpu
+  }
+  #[inline]
+  #[must_use]
+  pub const fn map(&self) -> &MemoryMap {
+    &self.map
+  }
+  #[inline]
+  pub fn map_mut(&mut self) -> &mut MemoryMap {
+    &mut self.map
+  }
+  #[inline]
+  #[must_use]
+  pub const fn ppu(&self) -> &Ppu {
+    &self.map.ppu
+  }
+  #[inline]
+  #[must_use]
+  pub const fn apu(&self) -> &Apu {
+    &self.map.apu
+  }
+  #[inline]
+  pub fn apu_mut(&mut self) -> &mut Apu {
+    &mut self.map.apu
+  }
+  #[inline]
+  pub fn serial_mut(&mut self) -> &mut SerialPort {
+    &mut self.map.serial
+  }
+  #[inline]
+  #[must_use]
+  pub const fn sgb(&self) -> Option<&Sgb> {
+    self.map.sgb.as_ref()
+  }
+
+  /// Installs a [Tracer] that's told what the CPU does, replacing any
+  /// previous one. `None` removes the tracer.
+  ///
+  /// * **Returns:** The previous tracer.
+  #[inline]
+  pub fn set_tracer(
+    &mut self, tracer: Option<Box<dyn Tracer>>,
+  ) -> Option<Box<dyn Tracer>> {
+    core::mem::replace(&mut self.tracer, tracer)
+  }
+
+  /// Changes which buttons are held.
+  ///
+  /// A new press on a selected line requests the joypad interrupt and wakes
+  /// the CPU from `stop`.
+  pub fn set_buttons(&mut self, held: Buttons) {
+    if self.map.joypad.set_held(held) {
+      self.map.request_interrupts(INT_JOYPAD);
+      self.cpu.wake_from_stop();
+    }
+  }
+
+  /// Advances the whole system by one dot.
+  #[inline]
+  pub fn step_t_cycle(&mut self) -> RunSummary {
+    self.run(1, |_| false)
+  }
+
+  /// Advances until the CPU's next M-cycle has happened, which is 4 dots (or
+  /// 2 in double speed mode) from one M-cycle to the next.
+  #[inline]
+  pub fn step_m_cycle(&mut self) -> RunSummary {
+    self.run_cycles(self.cpu.t_cycles_until_m_cycle())
+  }
+
+  /// Runs until an instruction finishes.
+  ///
+  /// If the CPU is halted, stopped, or locked up, this gives up after a
+  /// frame's worth of dots.
+  #[inline]
+  pub fn step_instruction(&mut self) -> RunSummary {
+    self.run(DOTS_PER_FRAME, |summary| summary.instructions > 0)
+  }
+
+  /// Runs until VBlank starts.
+  ///
+  /// If the LCD is off there's no VBlank, so this gives up after a frame's
+  /// worth of dots.
+  #[inline]
+  pub fn run_until_vblank(&mut self) -> RunSummary {
+    self.run(DOTS_PER_FRAME, |summary| summary.frame_ready)
+  }
+
+  /// Runs for exactly one frame's worth of dots, unless the CPU hits a
+  /// breakpoint.
+  #[inline]
+  pub fn run_frame(&mut self) -> RunSummary {
+    self.run_cycles(DOTS_PER_FRAME)
+  }
+
+  /// Runs for `t_cycles` dots, unless the CPU hits a breakpoint.
+  #[inline]
+  pub fn run_cycles(&mut self, t_cycles: u32) -> RunSummary {
+    self.run(t_cycles, |_| false)
+  }
+
+  /// Runs for up to `max_dots`, stopping early once `done` says so or the CPU
+  /// hits a breakpoint.
+  ///
+  /// Each pass either gives the CPU its next M-cycle, or moves the clock up
+  /// to whichever comes first of the CPU's next M-cycle and the next event.
+  fn run(
+    &mut self, max_dots: u32, done: impl Fn(&RunSummary) -> bool,
+  ) -> RunSummary {
+    let mut summary = RunSummary::default();
+    while summary.t_cycles < max_dots && !summary.breakpoint && !done(&summary)
+    {
+      let now = self.map.now();
+      let next_event = self.map.next_event_time();
+      if now >= next_event {
+        self.map.sync();
+        self.collect(&mut summary);
+        continue;
+      }
+      let until_event = u32::try_from(next_event - now).unwrap_or(u32::MAX);
+      let until_cpu = if self.cpu.is_idle(&mut self.map) {
+        u32::MAX
+      } else {
+        self.cpu.t_cycles_until_m_cycle() - 1
+      };
+      let skip = until_cpu.min(until_event).min(max_dots - summary.t_cycles);
+      if skip > 0 {
+        self.map.add_dots(skip);
+        self.cpu.t_cycles = self.cpu.t_cycles.wrapping_add(skip);
+        summary.t_cycles += skip;
+        continue;
+      }
+      self.cpu.t_cycles = self.cpu.t_cycles.wrapping_add(1);
+      let acted = match self.tracer.as_deref_mut() {
+        Some(tracer) => self.cpu.m_cycle_traced(&mut self.map, tracer),
+        None => self.cpu.m_cycle(&mut self.map),
+      };
+      self.map.add_dots(1);
+      summary.t_cycles += 1;
+      if acted && self.cpu.is_between_instructions() {
+        summary.instructions += 1;
+      }
+      if self.cpu.breakpoint_hit {
+        self.cpu.breakpoint_hit = false;
+        summary.breakpoint = true;
+      }
+      self.map.set_cpu_halted(self.cpu.halted);
+      self.collect(&mut summary);
+    }
+    self.map.sync();
+    self.collect(&mut summary);
+    summary
+  }
+
+  /// Passes along anything the memory map has for the CPU or the caller.
+  fn collect(&mut self, summary: &mut RunSummary) {
+    self.cpu.stall_m_cycles += self.map.take_stall_m_cycles();
+    summary.frame_ready |= self.map.take_frame_ready();
+  }
+}
+
+#[test]
+fn test_GameBoy_vblank_interrupt() {
+  let mut rom = alloc::vec![0_u8; 0x8000];
+  // vblank handler: `inc [hl]`, `reti`
+  rom[0x40..0x42].copy_from_slice(&[0x34, 0xD9]);
+  // `nop`, `jp $0150`
+  rom[0x100..0x104].copy_from_slice(&[0x00, 0xC3, 0x50, 0x01]);
+  // `ld hl, $C000`, `xor a`, `ldh [$0F], a`, `ld a, 1`, `ldh [$FF], a`,
+  // `ei`, `halt`, `jr @-1`
+  rom[0x150..0x15E].copy_from_slice(&[
+    0x21, 0x00, 0xC0, 0xAF, 0xE0, 0x0F, 0x3E, 0x01, 0xE0, 0xFF, 0xFB, 0x76,
+    0x18, 0xFD,
+  ]);
+  let mut gb = GameBoy::new(Model::Dmg, rom).unwrap();
+  assert_eq!(gb.step_instruction().t_cycles, 4);
+  assert_eq!(gb.step_instruction().t_cycles, 16);
+  assert_eq!(gb.cpu().pc.get(), 0x0150);
+
+  let summary = gb.run_until_vblank();
+  assert!(summary.frame_ready);
+  assert!(summary.t_cycles < DOTS_PER_FRAME);
+  let summary = gb.run_frame();
+  assert!(summary.frame_ready);
+  assert_eq!(summary.t_cycles, DOTS_PER_FRAME);
+  gb.run_cycles(100);
+  assert_eq!(gb.map_mut().read(0xC000), 2);
+}
+
+#[test]
+fn test_GameBoy_speed_switch() {
+  use crate::speed::{Speed, SPEED_SWITCH_M_CYCLES};
+  let mut rom = alloc::vec![0_u8; 0x8000];
+  rom[0x143] = 0x80;
+  // `ld a, 1`, `ldh [$4D], a`, `stop`, `nop`
+  rom[0x100..0x106].copy_from_slice(&[0x3E, 0x01, 0xE0, 0x4D, 0x10, 0x00]);
+  let mut gb = GameBoy::new(Model::Cgb, rom).unwrap();
+  assert_eq!(gb.map_mut().peek(0xFF4D), 0x7E);
+  gb.step_instruction();
+  gb.step_instruction();
+  assert_eq!(gb.map_mut().peek(0xFF4D), 0x7F);
+  gb.step_instruction();
+  assert_eq!(gb.cpu().speed(), Speed::Double);
+  assert_eq!(gb.map().speed(), Speed::Double);
+  assert_eq!(gb.map_mut().peek(0xFF4D), 0xFE);
+  // the CPU sits out the switch, then the `nop` only takes 2 dots.
+  let summary = gb.step_instruction();
+  assert_eq!(summary.t_cycles, u32::from(SPEED_SWITCH_M_CYCLES) * 2 + 2);
+  assert_eq!(gb.cpu().pc.get(), 0x0106);
+}
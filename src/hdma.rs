@@ -0,0 +1,188 @@
+//! CGB VRAM DMA, `HDMA1` through `HDMA5` (`$FF51..=$FF55`).
+//!
+//! The DMA doesn't own any memory itself. Instead, whoever owns both the
+//! memory map and VRAM asks [Hdma::next_block] for work, copies each 16 byte
+//! [HdmaBlock] it gets, and stalls the CPU for [block_m_cycles] per block.
+//!
+//! * A general purpose DMA hands out all of its blocks as soon as `HDMA5` is
+//!   written.
+//! * An HBlank DMA hands out one block each time [Hdma::hblank] is called, so
+//!   that should be called as the PPU enters mode 0 on lines 0 through 143.
+//!
+//! * See Also: [Pandocs: VRAM DMA Transfers](https://gbdev.io/pandocs/CGB_Registers.html#lcd-vram-dma-transfers)
+
+use bitfrob::u8_get_bit;
+
+use crate::speed::Speed;
+
+/// A 16 byte copy from `src` (anywhere) to `dst` (in VRAM).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct HdmaBlock {
+  pub src: u16,
+  pub dst: u16,
+}
+
+/// CPU M-cycles that the CPU is stopped for while one block is copied.
+///
+/// A block always takes the same real time (32 dots), so in double speed mode
+/// it costs the CPU twice as many M-cycles.
+#[inline]
+#[must_use]
+pub const fn block_m_cycles(speed: Speed) -> u16 {
+  match speed {
+    Speed::Normal => 8,
+    Speed::Double => 16,
+  }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+enum HdmaState {
+  #[default]
+  Idle,
+  General,
+  /// `armed` is set when an HBlank has come and the next block can go.
+  HBlank {
+    armed: bool,
+  },
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Hdma {
+  cgb: bool,
+  src: u16,
+  dst: u16,
+  /// Blocks left to copy, minus 1, as shown in `HDMA5`.
+  length: u8,
+  state: HdmaState,
+}
+impl Hdma {
+  /// The registers only exist in `cgb` mode.
+  #[inline]
+  #[must_use]
+  pub const fn new(cgb: bool) -> Self {
+    Self { cgb, src: 0, dst: 0, length: 0x7F, state: HdmaState::Idle }
+  }
+
+  /// Changes mode, such as when the boot ROM picks DMG compatibility mode.
+  #[inline]
+  pub fn set_cgb(&mut self, cgb: bool) {
+    self.cgb = cgb;
+  }
+
+  /// If a transfer of either kind is in progress.
+  #[inline]
+  #[must_use]
+  pub const fn is_active(&self) -> bool {
+    !matches!(self.state, HdmaState::Idle)
+  }
+
+  /// Only `HDMA5` can be read. It gives the remaining length, with bit 7
+  /// clear while a transfer is active.
+  #[inline]
+  #[must_use]
+  pub const fn read_reg(&self, addr: u16) -> u8 {
+    match addr {
+      0xFF55 if self.cgb => {
+        let inactive = if self.is_active() { 0 } else { 0x80 };
+        inactive | self.length
+      }
+      _ => 0xFF,
+    }
+  }
+
+  /// Writes one of the `HDMA` registers.
+  ///
+  /// When `HDMA5` starts an HBlank transfer with the LCD off (`lcd_on` is
+  /// false), the first block is ready right away.
+  pub fn write_reg(&mut self, addr: u16, byte: u8, lcd_on: bool) {
+    if !self.cgb {
+      return;
+    }
+    match addr {
+      0xFF51 => self.src = (self.src & 0x00FF) | (u16::from(byte) << 8),
+      0xFF52 => self.src = (self.src & 0xFF00) | u16::from(byte & 0xF0),
+      0xFF53 => self.dst = (self.dst & 0x00FF) | (u16::from(byte & 0x1F) << 8),
+      0xFF54 => self.dst = (self.dst & 0xFF00) | u16::from(byte & 0xF0),
+      0xFF55 => {
+        let hblank = u8_get_bit(7, byte);
+        if matches!(self.state, HdmaState::HBlank { .. }) && !hblank {
+          // Cancels the HBlank transfer, and the length stays readable.
+          self.state = HdmaState::Idle;
+          return;
+        }
+        self.length = byte & 0x7F;
+        self.state = if hblank {
+          HdmaState::HBlank { armed: !lcd_on }
+        } else {
+          HdmaState::General
+        };
+      }
+      _ => (),
+    }
+  }
+
+  /// Signals the start of an HBlank.
+  ///
+  /// HBlank transfers don't run while the CPU is halted, so a block is only
+  /// readied if `cpu_halted` is false.
+  #[inline]
+  pub fn hblank(&mut self, cpu_halted: bool) {
+    if let HdmaState::HBlank { armed } = &mut self.state {
+      if !cpu_halted {
+        *armed = true;
+      }
+    }
+  }
+
+  /// Takes the next block to copy, if one is ready.
+  pub fn next_block(&mut self) -> Option<HdmaBlock> {
+    match &mut self.state {
+      HdmaState::Idle => return None,
+      HdmaState::General => (),
+      HdmaState::HBlank { armed } => {
+        if !*armed {
+          return None;
+        }
+        *armed = false;
+      }
+    }
+    // Sources in `$E000..` actually read from cart RAM at `$A000..`.
+    let src = if self.src >= 0xE000 { self.src - 0x4000 } else { self.src };
+    let block = HdmaBlock { src, dst: 0x8000 | self.dst };
+    self.src = self.src.wrapping_add(16);
+    self.dst = (self.dst + 16) & 0x1FF0;
+    if self.length == 0 {
+      self.length = 0x7F;
+      self.state = HdmaState::Idle;
+    } else {
+      self.length -= 1;
+    }
+    Some(block)
+  }
+}
+
+#[test]
+fn test_Hdma_transfers() {
+  let mut hdma = Hdma::new(true);
+  hdma.write_reg(0xFF51, 0xC1, true);
+  hdma.write_reg(0xFF52, 0x2F, true);
+  hdma.write_reg(0xFF53, 0xFF, true);
+  hdma.write_reg(0xFF54, 0xE5, true);
+  hdma.write_reg(0xFF55, 0x01, true);
+  assert_eq!(hdma.read_reg(0xFF55), 0x01);
+  assert_eq!(hdma.next_block(), Some(HdmaBlock { src: 0xC120, dst: 0x9FE0 }));
+  assert_eq!(hdma.next_block(), Some(HdmaBlock { src: 0xC130, dst: 0x9FF0 }));
+  assert_eq!(hdma.next_block(), None);
+  assert_eq!(hdma.read_reg(0xFF55), 0xFF);
+
+  hdma.write_reg(0xFF55, 0x82, true);
+  assert_eq!(hdma.next_block(), None);
+  hdma.hblank(true);
+  assert_eq!(hdma.next_block(), None);
+  hdma.hblank(false);
+  assert_eq!(hdma.next_block(), Some(HdmaBlock { src: 0xC140, dst: 0x8000 }));
+  assert_eq!(hdma.read_reg(0xFF55), 0x01);
+  hdma.write_reg(0xFF55, 0x00, true);
+  assert!(!hdma.is_active());
+  assert_eq!(hdma.read_reg(0xFF55), 0x81);
+}
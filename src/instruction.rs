@@ -1,4 +1,9 @@
+//! The SM83 instruction set, and decoding bytes into [Instruction]s.
+
 use bitfrob::{u8_get_bit, u8_get_value};
+use core::fmt::{Display, Formatter};
+
+use crate::data_bus::DataBus;
 
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[repr(u8)]
@@ -194,6 +199,25 @@ pub enum IllegalOpByte {
   FC = 0xFC,
   FD = 0xFD,
 }
+impl IllegalOpByte {
+  /// The illegal op code with this value, if it is one.
+  pub const fn new(byte: u8) -> Option<Self> {
+    Some(match byte {
+      0xD3 => Self::D3,
+      0xDB => Self::DB,
+      0xDD => Self::DD,
+      0xE3 => Self::E3,
+      0xE4 => Self::E4,
+      0xEB => Self::EB,
+      0xEC => Self::EC,
+      0xED => Self::ED,
+      0xF4 => Self::F4,
+      0xFC => Self::FC,
+      0xFD => Self::FD,
+      _ => return None,
+    })
+  }
+}
 
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Instruction {
@@ -208,7 +232,7 @@ pub enum Instruction {
   JumpRelative(i8),
   /// `jr cond, <i8>`
   JumpRelativeCond(Cond, i8),
-  /// `add hl, <r8m>`
+  /// `add hl, <r16p>`
   AddHLR16p(R16p),
   /// `ld <r16p>, <u16>`
   LdR16pImm16(R16p, u16),
@@ -265,7 +289,7 @@ pub enum Instruction {
   /// `ldh a, [c]` (true) or `ldh [c], a` (false)
   LdhCToA(bool),
   /// `ld a, [<u16>]` (true) or `ld [<u16>], a` (false)
-  LdImm16ToA(bool),
+  LdImm16ToA(u16, bool),
   /// `jp <u16>`
   JumpImm16(u16),
   /// rot, bit, res, set
@@ -276,16 +300,21 @@ pub enum Instruction {
   EI,
   /// `call <cond>, <u16>`
   CallCond(Cond, u16),
-  Push(R16p),
+  /// `push <r16f>`
+  Push(R16f),
+  /// `call <u16>`
   Call(u16),
   /// `<op> a, <u8>`
   AluImm8(Alu, u8),
   /// `rst <u8>` (but only multiples of 8 can be restarted to).
   Restart(U3),
-  /// A byte that isn't an illegal op code.
+  /// A byte that isn't a legal op code.
   Illegal(IllegalOpByte),
 }
 impl Instruction {
+  /// The instruction for an op code, with any immediate operands set to 0.
+  ///
+  /// Use [decode] to get the real operands.
   pub fn new(op_code: u8) -> Self {
     let x = U2::new_from_byte(6, op_code);
     let y = U3::new_from_byte(3, op_code);
@@ -362,9 +391,9 @@ impl Instruction {
             Instruction::JumpCond(Cond::new(y as u8), 0)
           }
           U3::_4 => Instruction::LdhCToA(false),
-          U3::_5 => Instruction::LdImm16ToA(false),
+          U3::_5 => Instruction::LdImm16ToA(0, false),
           U3::_6 => Instruction::LdhCToA(true),
-          U3::_7 => Instruction::LdImm16ToA(true),
+          U3::_7 => Instruction::LdImm16ToA(0, true),
         },
         U3::_3 => match y {
           U3::_0 => Instruction::JumpImm16(0),
@@ -394,7 +423,7 @@ impl Instruction {
               U2::_3 => Instruction::Illegal(IllegalOpByte::FD),
             }
           } else {
-            Instruction::Push(R16p::new(p))
+            Instruction::Push(R16f::new(p))
           }
         }
         U3::_6 => Instruction::AluImm8(Alu::new(y), 0),
@@ -418,6 +447,19 @@ pub enum PrefixedOp {
   Res(U3, R8m),
   Set(U3, R8m),
 }
+impl PrefixedOp {
+  /// The op for the byte after a `$CB` prefix.
+  pub const fn new(op_code: u8) -> Self {
+    let y = U3::new_from_byte(3, op_code);
+    let r = R8m::new(U3::new_from_byte(0, op_code));
+    match U2::new_from_byte(6, op_code) {
+      U2::_0 => Self::RotR8m(Rot::new(y), r),
+      U2::_1 => Self::Bit(y, r),
+      U2::_2 => Self::Res(y, r),
+      U2::_3 => Self::Set(y, r),
+    }
+  }
+}
 impl Default for PrefixedOp {
   fn default() -> Self {
     PrefixedOp::RotR8m(Rot::default(), R8m::default())
@@ -451,6 +493,10 @@ impl Rot {
   }
 }
 
+/// How many bytes an instruction takes, including the op code.
+///
+/// A `$CB` prefixed instruction is always 2 bytes (the prefix and the second
+/// op code), so that's the length for `$CB`. Illegal op codes are 1 byte.
 pub fn instruction_length(op_code: u8) -> usize {
   let x = U2::new_from_byte(6, op_code);
   let y = U3::new_from_byte(3, op_code);
@@ -481,13 +527,7 @@ pub fn instruction_length(op_code: u8) -> usize {
       U3::_6 => 2,
       U3::_7 => 1,
     },
-    U2::_1 => {
-      if (z as u8 == 6) & (y as u8 == 6) {
-        1
-      } else {
-        1
-      }
-    }
+    U2::_1 => 1,
     U2::_2 => 1,
     U2::_3 => match z {
       U3::_0 => match y {
@@ -497,19 +537,13 @@ pub fn instruction_length(op_code: u8) -> usize {
         U3::_6 => 2,
         U3::_7 => 2,
       },
-      U3::_1 => {
-        if q {
-          1
-        } else {
-          1
-        }
-      }
+      U3::_1 => 1,
       U3::_2 => match y {
         U3::_0 | U3::_1 | U3::_2 | U3::_3 => 3,
         U3::_4 => 1,
-        U3::_5 => 1,
+        U3::_5 => 3,
         U3::_6 => 1,
-        U3::_7 => 1,
+        U3::_7 => 3,
       },
       U3::_3 => match y {
         U3::_0 => 3,
@@ -545,3 +579,762 @@ pub fn instruction_length(op_code: u8) -> usize {
     },
   }
 }
+
+/// Why bytes couldn't be decoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DecodeError {
+  /// There were no bytes.
+  Empty,
+  /// The op code needs `needed` bytes, but there were fewer than that.
+  Truncated { needed: usize },
+}
+impl Display for DecodeError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+    match self {
+      Self::Empty => write!(f, "no bytes to decode"),
+      Self::Truncated { needed } => {
+        write!(f, "the instruction needs {needed} bytes")
+      }
+    }
+  }
+}
+
+/// Decodes the instruction at the start of `bytes`, with its operands.
+///
+/// * **Returns:** The instruction and how many bytes it took.
+pub fn decode(bytes: &[u8]) -> Result<(Instruction, usize), DecodeError> {
+  use Instruction::*;
+  let &op_code = bytes.first().ok_or(DecodeError::Empty)?;
+  let len = instruction_length(op_code);
+  let bytes = bytes.get(..len).ok_or(DecodeError::Truncated { needed: len })?;
+  let imm8 = bytes.get(1).copied().unwrap_or(0);
+  let simm8 = imm8 as i8;
+  let imm16 = u16::from_le_bytes([imm8, bytes.get(2).copied().unwrap_or(0)]);
+  let instruction = match Instruction::new(op_code) {
+    LdImm16SP(_) => LdImm16SP(imm16),
+    JumpRelative(_) => JumpRelative(simm8),
+    JumpRelativeCond(cond, _) => JumpRelativeCond(cond, simm8),
+    LdR16pImm16(r, _) => LdR16pImm16(r, imm16),
+    LdR8mImm8(r, _) => LdR8mImm8(r, imm8),
+    LdhImm8ToA(_, to_a) => LdhImm8ToA(imm8, to_a),
+    AddSPImm8(_) => AddSPImm8(simm8),
+    LdHLSPImm8(_) => LdHLSPImm8(simm8),
+    JumpCond(cond, _) => JumpCond(cond, imm16),
+    LdImm16ToA(_, to_a) => LdImm16ToA(imm16, to_a),
+    JumpImm16(_) => JumpImm16(imm16),
+    Cb(_) => Cb(PrefixedOp::new(imm8)),
+    CallCond(cond, _) => CallCond(cond, imm16),
+    Call(_) => Call(imm16),
+    AluImm8(alu, _) => AluImm8(alu, imm8),
+    other => other,
+  };
+  Ok((instruction, len))
+}
+
+/// Decodes the instruction at `addr`, peeking at the bus so that nothing
+/// notices.
+///
+/// Addresses past `$FFFF` wrap around to `$0000`.
+///
+/// * **Returns:** The instruction and how many bytes it took.
+pub fn decode_at(bus: &mut dyn DataBus, addr: u16) -> (Instruction, usize) {
+  let bytes = [0, 1, 2].map(|i| bus.peek(addr.wrapping_add(i)));
+  // 3 bytes is always enough.
+  decode(&bytes).unwrap()
+}
+
+impl PrefixedOp {
+  /// The byte that goes after the `$CB` prefix.
+  #[must_use]
+  pub const fn op_code(self) -> u8 {
+    match self {
+      Self::RotR8m(rot, r) => ((rot as u8) << 3) | r as u8,
+      Self::Bit(n, r) => 0x40 | ((n as u8) << 3) | r as u8,
+      Self::Res(n, r) => 0x80 | ((n as u8) << 3) | r as u8,
+      Self::Set(n, r) => 0xC0 | ((n as u8) << 3) | r as u8,
+    }
+  }
+}
+
+impl Instruction {
+  /// Encodes the instruction, which is the reverse of [decode].
+  ///
+  /// * **Returns:** The bytes, and how many of them are used.
+  #[must_use]
+  pub fn encode(self) -> ([u8; 3], usize) {
+    use Instruction::*;
+    let op = |op_code: u8| ([op_code, 0, 0], 1);
+    let imm8 = |op_code: u8, n: u8| ([op_code, n, 0], 2);
+    let imm16 = |op_code: u8, n: u16| {
+      let [low, high] = n.to_le_bytes();
+      ([op_code, low, high], 3)
+    };
+    match self {
+      Nop => op(0x00),
+      LdImm16SP(n) => imm16(0x08, n),
+      Stop => op(0x10),
+      JumpRelative(e) => imm8(0x18, e as u8),
+      JumpRelativeCond(cond, e) => imm8(0x20 | ((cond as u8) << 3), e as u8),
+      AddHLR16p(r) => op(0x09 | ((r as u8) << 4)),
+      LdR16pImm16(r, n) => imm16(0x01 | ((r as u8) << 4), n),
+      LdR16idToA(r, to_a) => op(0x02 | ((r as u8) << 4) | ((to_a as u8) << 3)),
+      DecIncR16p(r, dec) => op(0x03 | ((r as u8) << 4) | ((dec as u8) << 3)),
+      DecIncR8m(r, dec) => op(0x04 | ((r as u8) << 3) | dec as u8),
+      LdR8mImm8(r, n) => imm8(0x06 | ((r as u8) << 3), n),
+      Rlca => op(0x07),
+      Rrca => op(0x0F),
+      Rla => op(0x17),
+      Rra => op(0x1F),
+      Daa => op(0x27),
+      Cpl => op(0x2F),
+      Scf => op(0x37),
+      Ccf => op(0x3F),
+      Halt => op(0x76),
+      LdR8mR8m(dst, src) => op(0x40 | ((dst as u8) << 3) | src as u8),
+      AluR8m(alu, r) => op(0x80 | ((alu as u8) << 3) | r as u8),
+      ReturnCond(cond) => op(0xC0 | ((cond as u8) << 3)),
+      LdhImm8ToA(n, to_a) => imm8(if to_a { 0xF0 } else { 0xE0 }, n),
+      AddSPImm8(e) => imm8(0xE8, e as u8),
+      LdHLSPImm8(e) => imm8(0xF8, e as u8),
+      Pop(r) => op(0xC1 | ((r as u8) << 4)),
+      Return => op(0xC9),
+      ReturnIrq => op(0xD9),
+      JumpHL => op(0xE9),
+      LdSPHL => op(0xF9),
+      JumpCond(cond, n) => imm16(0xC2 | ((cond as u8) << 3), n),
+      LdhCToA(to_a) => op(if to_a { 0xF2 } else { 0xE2 }),
+      LdImm16ToA(n, to_a) => imm16(if to_a { 0xFA } else { 0xEA }, n),
+      JumpImm16(n) => imm16(0xC3, n),
+      Cb(prefixed) => imm8(0xCB, prefixed.op_code()),
+      DI => op(0xF3),
+      EI => op(0xFB),
+      CallCond(cond, n) => imm16(0xC4 | ((cond as u8) << 3), n),
+      Push(r) => op(0xC5 | ((r as u8) << 4)),
+      Call(n) => imm16(0xCD, n),
+      AluImm8(alu, n) => imm8(0xC6 | ((alu as u8) << 3), n),
+      Restart(n) => op(0xC7 | ((n as u8) << 3)),
+      Illegal(byte) => op(byte as u8),
+    }
+  }
+}
+
+#[test]
+fn test_decode() {
+  use crate::op_actions::{CpuAction, ACTION_TABLE};
+  // The CPU reads each operand byte with its own action, so the action table
+  // gives the length independently of the decoder.
+  for op_code in 0..=u8::MAX {
+    let fetches = ACTION_TABLE[usize::from(op_code)]
+      .iter()
+      .filter(|a| {
+        matches!(
+          a,
+          CpuAction::ImmLow
+            | CpuAction::ImmLowTo(_)
+            | CpuAction::ImmLowIf(_)
+            | CpuAction::ImmHigh
+            | CpuAction::ImmHighTo(_)
+            | CpuAction::ImmHighIf(_)
+            | CpuAction::AluImm(_)
+            | CpuAction::CbPrefix
+        )
+      })
+      .count();
+    let len = instruction_length(op_code);
+    assert_eq!(len, 1 + fetches, "op code ${op_code:02X}");
+    let bytes = [op_code, 0x34, 0x12];
+    let (instruction, decoded_len) = decode(&bytes).unwrap();
+    assert_eq!(decoded_len, len, "op code ${op_code:02X}");
+    if op_code != 0xCB {
+      let (encoded, encoded_len) = instruction.encode();
+      assert_eq!(
+        &encoded[..encoded_len],
+        &bytes[..len],
+        "op code ${op_code:02X}"
+      );
+    }
+    assert_eq!(
+      decode(&bytes[..len - 1]),
+      if len == 1 {
+        Err(DecodeError::Empty)
+      } else {
+        Err(DecodeError::Truncated { needed: len })
+      }
+    );
+  }
+  for cb in 0..=u8::MAX {
+    let (instruction, len) = decode(&[0xCB, cb]).unwrap();
+    assert_eq!(len, instruction_length(0xCB));
+    assert_eq!(instruction, Instruction::Cb(PrefixedOp::new(cb)));
+    assert_eq!(instruction.encode(), ([0xCB, cb, 0], 2));
+  }
+
+  assert_eq!(
+    decode(&[0x31, 0xFE, 0xFF]),
+    Ok((Instruction::LdR16pImm16(R16p::SP, 0xFFFE), 3))
+  );
+  assert_eq!(
+    decode(&[0x20, 0xFB]),
+    Ok((Instruction::JumpRelativeCond(Cond::NZ, -5), 2))
+  );
+  assert_eq!(
+    decode(&[0xE0, 0x44]),
+    Ok((Instruction::LdhImm8ToA(0x44, false), 2))
+  );
+  assert_eq!(
+    decode(&[0xCB, 0x7E]),
+    Ok((Instruction::Cb(PrefixedOp::Bit(U3::_7, R8m::HLm)), 2))
+  );
+  assert_eq!(
+    decode(&[0xFA, 0x00, 0xC0]),
+    Ok((Instruction::LdImm16ToA(0xC000, true), 3))
+  );
+}
+
+impl Display for R8m {
+  fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+    f.write_str(match self {
+      Self::B => "b",
+      Self::C => "c",
+      Self::D => "d",
+      Self::E => "e",
+      Self::H => "h",
+      Self::L => "l",
+      Self::HLm => "[hl]",
+      Self::A => "a",
+    })
+  }
+}
+impl Display for R16p {
+  fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+    f.write_str(match self {
+      Self::BC => "bc",
+      Self::DE => "de",
+      Self::HL => "hl",
+      Self::SP => "sp",
+    })
+  }
+}
+impl Display for R16f {
+  fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+    f.write_str(match self {
+      Self::BC => "bc",
+      Self::DE => "de",
+      Self::HL => "hl",
+      Self::AF => "af",
+    })
+  }
+}
+impl Display for R16id {
+  fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+    f.write_str(match self {
+      Self::BC => "[bc]",
+      Self::DE => "[de]",
+      Self::HLi => "[hl+]",
+      Self::HLd => "[hl-]",
+    })
+  }
+}
+impl Display for Cond {
+  fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+    f.write_str(match self {
+      Self::NZ => "nz",
+      Self::Z => "z",
+      Self::NC => "nc",
+      Self::C => "c",
+    })
+  }
+}
+impl Display for Alu {
+  fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+    f.write_str(match self {
+      Self::Add => "add",
+      Self::Adc => "adc",
+      Self::Sub => "sub",
+      Self::Sbc => "sbc",
+      Self::And => "and",
+      Self::Xor => "xor",
+      Self::Or => "or",
+      Self::Cp => "cp",
+    })
+  }
+}
+impl Display for Rot {
+  fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+    f.write_str(match self {
+      Self::Rlc => "rlc",
+      Self::Rrc => "rrc",
+      Self::Rl => "rl",
+      Self::RR => "rr",
+      Self::Sla => "sla",
+      Self::Sra => "sra",
+      Self::Swap => "swap",
+      Self::Srl => "srl",
+    })
+  }
+}
+impl Display for PrefixedOp {
+  fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+    match *self {
+      Self::RotR8m(rot, r) => write!(f, "{rot} {r}"),
+      Self::Bit(n, r) => write!(f, "bit {}, {r}", n as u8),
+      Self::Res(n, r) => write!(f, "res {}, {r}", n as u8),
+      Self::Set(n, r) => write!(f, "set {}, {r}", n as u8),
+    }
+  }
+}
+
+/// Shows an instruction in RGBDS syntax.
+///
+/// Relative jumps show their target relative to the start of the instruction,
+/// like `jr nz, @-5`. To show the absolute target instead, use
+/// [at](Instruction::at).
+impl Display for Instruction {
+  fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+    self.fmt_at(f, None)
+  }
+}
+
+/// An [Instruction] at a known address, which shows relative jump targets as
+/// absolute addresses, like `jr nz, $0150`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct InstructionAt {
+  pub instruction: Instruction,
+  pub addr: u16,
+}
+impl Display for InstructionAt {
+  fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+    self.instruction.fmt_at(f, Some(self.addr))
+  }
+}
+
+impl Instruction {
+  /// This instruction at `addr`, for showing relative jumps with absolute
+  /// targets.
+  #[inline]
+  #[must_use]
+  pub const fn at(self, addr: u16) -> InstructionAt {
+    InstructionAt { instruction: self, addr }
+  }
+
+  /// Where this branches to, if it's a `jr`, `jp`, `call`, or `rst` with a
+  /// fixed target (so not `jp hl`, `ret`, or `reti`).
+  ///
+  /// `addr` is where this instruction is, which `jr` is relative to.
+  #[inline]
+  #[must_use]
+  pub const fn jump_target(self, addr: u16) -> Option<u16> {
+    match self {
+      Self::JumpRelative(e) | Self::JumpRelativeCond(_, e) => {
+        Some(addr.wrapping_add(2).wrapping_add(e as u16))
+      }
+      Self::JumpImm16(t) | Self::JumpCond(_, t) => Some(t),
+      Self::Call(t) | Self::CallCond(_, t) => Some(t),
+      Self::Restart(n) => Some((n as u16) * 8),
+      _ => None,
+    }
+  }
+
+  fn fmt_at(
+    &self, f: &mut Formatter<'_>, addr: Option<u16>,
+  ) -> core::fmt::Result {
+    use Instruction::*;
+    let dec_inc = |dec: bool| if dec { "dec" } else { "inc" };
+    match *self {
+      Nop => write!(f, "nop"),
+      LdImm16SP(n) => write!(f, "ld [${n:04X}], sp"),
+      Stop => write!(f, "stop"),
+      JumpRelative(_) | JumpRelativeCond(_, _) => {
+        write!(f, "jr ")?;
+        let e = match *self {
+          JumpRelativeCond(cond, e) => {
+            write!(f, "{cond}, ")?;
+            e
+          }
+          JumpRelative(e) => e,
+          _ => unreachable!(),
+        };
+        match addr.and_then(|addr| self.jump_target(addr)) {
+          Some(target) => write!(f, "${target:04X}"),
+          None => match i16::from(e) + 2 {
+            0 => write!(f, "@"),
+            offset => write!(f, "@{offset:+}"),
+          },
+        }
+      }
+      AddHLR16p(r) => write!(f, "add hl, {r}"),
+      LdR16pImm16(r, n) => write!(f, "ld {r}, ${n:04X}"),
+      LdR16idToA(r, true) => write!(f, "ld a, {r}"),
+      LdR16idToA(r, false) => write!(f, "ld {r}, a"),
+      DecIncR16p(r, dec) => write!(f, "{} {r}", dec_inc(dec)),
+      DecIncR8m(r, dec) => write!(f, "{} {r}", dec_inc(dec)),
+      LdR8mImm8(r, n) => write!(f, "ld {r}, ${n:02X}"),
+      Rlca => write!(f, "rlca"),
+      Rrca => write!(f, "rrca"),
+      Rla => write!(f, "rla"),
+      Rra => write!(f, "rra"),
+      Daa => write!(f, "daa"),
+      Cpl => write!(f, "cpl"),
+      Scf => write!(f, "scf"),
+      Ccf => write!(f, "ccf"),
+      Halt => write!(f, "halt"),
+      LdR8mR8m(dst, src) => write!(f, "ld {dst}, {src}"),
+      AluR8m(op, r) => write!(f, "{op} a, {r}"),
+      ReturnCond(cond) => write!(f, "ret {cond}"),
+      LdhImm8ToA(n, true) => write!(f, "ldh a, [$FF{n:02X}]"),
+      LdhImm8ToA(n, false) => write!(f, "ldh [$FF{n:02X}], a"),
+      AddSPImm8(e) => write!(f, "add sp, {e}"),
+      LdHLSPImm8(e) => write!(f, "ld hl, sp{e:+}"),
+      Pop(r) => write!(f, "pop {r}"),
+      Return => write!(f, "ret"),
+      ReturnIrq => write!(f, "reti"),
+      JumpHL => write!(f, "jp hl"),
+      LdSPHL => write!(f, "ld sp, hl"),
+      JumpCond(cond, n) => write!(f, "jp {cond}, ${n:04X}"),
+      LdhCToA(true) => write!(f, "ldh a, [c]"),
+      LdhCToA(false) => write!(f, "ldh [c], a"),
+      LdImm16ToA(n, true) => write!(f, "ld a, [${n:04X}]"),
+      LdImm16ToA(n, false) => write!(f, "ld [${n:04X}], a"),
+      JumpImm16(n) => write!(f, "jp ${n:04X}"),
+      Cb(op) => write!(f, "{op}"),
+      DI => write!(f, "di"),
+      EI => write!(f, "ei"),
+      CallCond(cond, n) => write!(f, "call {cond}, ${n:04X}"),
+      Push(r) => write!(f, "push {r}"),
+      Call(n) => write!(f, "call ${n:04X}"),
+      AluImm8(op, n) => write!(f, "{op} a, ${n:02X}"),
+      Restart(n) => write!(f, "rst ${:02X}", (n as u8) * 8),
+      // RGBDS has no mnemonic for these, so they're just data.
+      Illegal(byte) => write!(f, "db ${:02X}", byte as u8),
+    }
+  }
+}
+
+/// One operand of an instruction, as written in assembly.
+///
+/// Values are kept as `i32` so that out of range values can be rejected when
+/// the operands are matched to an instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Operand {
+  /// An 8-bit register, or `[hl]`. Also the `c` condition.
+  R8(R8m),
+  /// `bc`, `de`, `hl`, or `sp`.
+  R16(R16p),
+  /// `af`
+  AF,
+  /// `[bc]`, `[de]`, `[hl+]`, or `[hl-]`.
+  R16Mem(R16id),
+  /// `nz`, `z`, or `nc`. The `c` condition is [R8](Operand::R8).
+  Cond(Cond),
+  /// `[c]`, the address `$FF00 + c`.
+  CMem,
+  /// `sp+e` or `sp-e`.
+  SpOffset(i32),
+  /// An address in brackets.
+  Mem(i32),
+  /// A number.
+  Imm(i32),
+  /// An offset from the start of the instruction, written with `@`.
+  Here(i32),
+}
+impl Operand {
+  /// Parses an operand that uses plain numbers, like `$FF`, `%1010`, `&17`
+  /// (octal), or `-5`.
+  #[must_use]
+  pub fn parse(text: &str) -> Option<Self> {
+    let text = text.trim();
+    if let Some(inner) =
+      text.strip_prefix('[').and_then(|t| t.strip_suffix(']'))
+    {
+      let inner = inner.trim();
+      let special = inner.to_ascii_lowercase().replace(' ', "");
+      return Some(match special.as_str() {
+        "hl+" | "hli" => Self::R16Mem(R16id::HLi),
+        "hl-" | "hld" => Self::R16Mem(R16id::HLd),
+        "$ff00+c" => Self::CMem,
+        _ => match Self::parse(inner)? {
+          Self::R8(R8m::C) => Self::CMem,
+          Self::R16(R16p::BC) => Self::R16Mem(R16id::BC),
+          Self::R16(R16p::DE) => Self::R16Mem(R16id::DE),
+          Self::R16(R16p::HL) => Self::R8(R8m::HLm),
+          Self::Imm(n) => Self::Mem(n),
+          _ => return None,
+        },
+      });
+    }
+    let lower = text.to_ascii_lowercase();
+    Some(match lower.as_str() {
+      "a" => Self::R8(R8m::A),
+      "b" => Self::R8(R8m::B),
+      "c" => Self::R8(R8m::C),
+      "d" => Self::R8(R8m::D),
+      "e" => Self::R8(R8m::E),
+      "h" => Self::R8(R8m::H),
+      "l" => Self::R8(R8m::L),
+      "bc" => Self::R16(R16p::BC),
+      "de" => Self::R16(R16p::DE),
+      "hl" => Self::R16(R16p::HL),
+      "sp" => Self::R16(R16p::SP),
+      "af" => Self::AF,
+      "nz" => Self::Cond(Cond::NZ),
+      "z" => Self::Cond(Cond::Z),
+      "nc" => Self::Cond(Cond::NC),
+      "@" => Self::Here(0),
+      _ => {
+        if let Some(rest) = lower.strip_prefix("sp") {
+          Self::SpOffset(parse_number(rest)?)
+        } else if let Some(rest) = lower.strip_prefix('@') {
+          Self::Here(parse_number(rest)?)
+        } else {
+          Self::Imm(parse_number(&lower)?)
+        }
+      }
+    })
+  }
+}
+
+/// Parses a number in RGBDS syntax: decimal, `$` hex, `%` binary, or `&`
+/// octal, with an optional sign.
+#[must_use]
+pub fn parse_number(text: &str) -> Option<i32> {
+  let text = text.trim();
+  let (negative, digits) = match text.as_bytes().first()? {
+    b'-' => (true, text[1..].trim_start()),
+    b'+' => (false, text[1..].trim_start()),
+    _ => (false, text),
+  };
+  let (radix, digits) = match digits.as_bytes().first()? {
+    b'$' => (16, &digits[1..]),
+    b'%' => (2, &digits[1..]),
+    b'&' => (8, &digits[1..]),
+    _ => (10, digits),
+  };
+  let digits = digits.replace('_', "");
+  let value = i64::from_str_radix(&digits, radix).ok()?;
+  i32::try_from(if negative { -value } else { value }).ok()
+}
+
+impl Instruction {
+  /// Parses one instruction in RGBDS syntax, with plain numbers for operands.
+  ///
+  /// Relative jumps have to use `@`, since the instruction's address isn't
+  /// known. See [parse_at](Instruction::parse_at) for that.
+  #[inline]
+  #[must_use]
+  pub fn parse(text: &str) -> Option<Self> {
+    Self::parse_with_addr(text, None)
+  }
+
+  /// Parses one instruction that's at `addr`, so relative jumps can also use
+  /// an absolute target.
+  #[inline]
+  #[must_use]
+  pub fn parse_at(text: &str, addr: u16) -> Option<Self> {
+    Self::parse_with_addr(text, Some(addr))
+  }
+
+  fn parse_with_addr(text: &str, addr: Option<u16>) -> Option<Self> {
+    let text = text.trim();
+    let (mnemonic, rest) =
+      text.split_once(char::is_whitespace).unwrap_or((text, ""));
+    let mut operands = [Operand::Imm(0); 2];
+    let mut count = 0;
+    if !rest.trim().is_empty() {
+      for part in rest.split(',') {
+        *operands.get_mut(count)? = Operand::parse(part)?;
+        count += 1;
+      }
+    }
+    Self::from_operands(
+      &mnemonic.to_ascii_lowercase(),
+      &operands[..count],
+      addr,
+    )
+  }
+
+  /// Picks the instruction for a mnemonic (in lowercase) and its operands.
+  ///
+  /// `addr` is where the instruction goes, if known. It's needed for `@` in
+  /// anything but a relative jump, and for relative jumps to absolute targets.
+  ///
+  /// * **Returns:** `None` if there's no such instruction, or a value is out
+  ///   of range.
+  #[must_use]
+  pub fn from_operands(
+    mnemonic: &str, operands: &[Operand], addr: Option<u16>,
+  ) -> Option<Self> {
+    use Instruction::*;
+    use Operand::{CMem, Imm, Mem, R16Mem, SpOffset, AF, R16, R8};
+    let value = |op: &Operand| match *op {
+      Imm(n) => Some(n),
+      Operand::Here(n) => Some(i32::from(addr?) + n),
+      _ => None,
+    };
+    let imm8 = |op: &Operand| u8_from(value(op)?);
+    let imm16 = |op: &Operand| u16_from(value(op)?);
+    let simm8 = |op: &Operand| i8::try_from(value(op)?).ok();
+    let rel = |op: &Operand| match *op {
+      Operand::Here(n) => i8::try_from(n - 2).ok(),
+      Imm(t) => i8::try_from(t - (i32::from(addr?) + 2)).ok(),
+      _ => None,
+    };
+    let cond = |op: &Operand| match *op {
+      Operand::Cond(cond) => Some(cond),
+      R8(R8m::C) => Some(Cond::C),
+      _ => None,
+    };
+    let r16f = |op: &Operand| match *op {
+      R16(R16p::BC) => Some(R16f::BC),
+      R16(R16p::DE) => Some(R16f::DE),
+      R16(R16p::HL) => Some(R16f::HL),
+      AF => Some(R16f::AF),
+      _ => None,
+    };
+    let high_page = |n: i32| match n {
+      0xFF00..=0xFFFF => Some((n & 0xFF) as u8),
+      0x00..=0xFF => Some(n as u8),
+      _ => None,
+    };
+    let bit = |op: &Operand| match value(op)? {
+      n @ 0..=7 => Some(U3::new_from_byte(0, n as u8)),
+      _ => None,
+    };
+    let alu = match mnemonic {
+      "add" => Some(Alu::Add),
+      "adc" => Some(Alu::Adc),
+      "sub" => Some(Alu::Sub),
+      "sbc" => Some(Alu::Sbc),
+      "and" => Some(Alu::And),
+      "xor" => Some(Alu::Xor),
+      "or" => Some(Alu::Or),
+      "cp" => Some(Alu::Cp),
+      _ => None,
+    };
+    let rot = match mnemonic {
+      "rlc" => Some(Rot::Rlc),
+      "rrc" => Some(Rot::Rrc),
+      "rl" => Some(Rot::Rl),
+      "rr" => Some(Rot::RR),
+      "sla" => Some(Rot::Sla),
+      "sra" => Some(Rot::Sra),
+      "swap" => Some(Rot::Swap),
+      "srl" => Some(Rot::Srl),
+      _ => None,
+    };
+    Some(match (mnemonic, operands) {
+      ("nop", []) => Nop,
+      ("stop", []) => Stop,
+      ("halt", []) => Halt,
+      ("rlca", []) => Rlca,
+      ("rrca", []) => Rrca,
+      ("rla", []) => Rla,
+      ("rra", []) => Rra,
+      ("daa", []) => Daa,
+      ("cpl", []) => Cpl,
+      ("scf", []) => Scf,
+      ("ccf", []) => Ccf,
+      ("di", []) => DI,
+      ("ei", []) => EI,
+      ("ret", []) => Return,
+      ("reti", []) => ReturnIrq,
+      ("ret", [c]) => ReturnCond(cond(c)?),
+      ("jr", [t]) => JumpRelative(rel(t)?),
+      ("jr", [c, t]) => JumpRelativeCond(cond(c)?, rel(t)?),
+      ("jp", [R16(R16p::HL)]) => JumpHL,
+      ("jp", [t]) => JumpImm16(imm16(t)?),
+      ("jp", [c, t]) => JumpCond(cond(c)?, imm16(t)?),
+      ("call", [t]) => Call(imm16(t)?),
+      ("call", [c, t]) => CallCond(cond(c)?, imm16(t)?),
+      ("rst", [t]) => match value(t)? {
+        n @ 0..=0x38 if n % 8 == 0 => {
+          Restart(U3::new_from_byte(0, (n / 8) as u8))
+        }
+        _ => return None,
+      },
+      ("push", [r]) => Push(r16f(r)?),
+      ("pop", [r]) => Pop(r16f(r)?),
+      ("inc" | "dec", [R8(r)]) => DecIncR8m(*r, mnemonic == "dec"),
+      ("inc" | "dec", [R16(r)]) => DecIncR16p(*r, mnemonic == "dec"),
+      ("add", [R16(R16p::HL), R16(r)]) => AddHLR16p(*r),
+      ("add", [R16(R16p::SP), t]) => AddSPImm8(simm8(t)?),
+      (_, [R8(R8m::A), R8(r)] | [R8(r)]) if alu.is_some() => AluR8m(alu?, *r),
+      (_, [R8(R8m::A), t] | [t]) if alu.is_some() => AluImm8(alu?, imm8(t)?),
+      (_, [R8(r)]) if rot.is_some() => Cb(PrefixedOp::RotR8m(rot?, *r)),
+      ("bit", [n, R8(r)]) => Cb(PrefixedOp::Bit(bit(n)?, *r)),
+      ("res", [n, R8(r)]) => Cb(PrefixedOp::Res(bit(n)?, *r)),
+      ("set", [n, R8(r)]) => Cb(PrefixedOp::Set(bit(n)?, *r)),
+      ("ld", [Mem(n), R16(R16p::SP)]) => LdImm16SP(u16_from(*n)?),
+      ("ld", [R16(R16p::SP), R16(R16p::HL)]) => LdSPHL,
+      ("ld", [R16(R16p::HL), SpOffset(e)]) => {
+        LdHLSPImm8(i8::try_from(*e).ok()?)
+      }
+      ("ld", [R16(r), t]) => LdR16pImm16(*r, imm16(t)?),
+      ("ld", [R16Mem(r), R8(R8m::A)]) => LdR16idToA(*r, false),
+      ("ld", [R8(R8m::A), R16Mem(r)]) => LdR16idToA(*r, true),
+      ("ld", [R8(R8m::HLm), R8(R8m::HLm)]) => return None,
+      ("ld", [R8(dst), R8(src)]) => LdR8mR8m(*dst, *src),
+      ("ld" | "ldh", [CMem, R8(R8m::A)]) => LdhCToA(false),
+      ("ld" | "ldh", [R8(R8m::A), CMem]) => LdhCToA(true),
+      ("ld", [Mem(n), R8(R8m::A)]) => LdImm16ToA(u16_from(*n)?, false),
+      ("ld", [R8(R8m::A), Mem(n)]) => LdImm16ToA(u16_from(*n)?, true),
+      ("ld", [R8(r), t]) => LdR8mImm8(*r, imm8(t)?),
+      ("ldh", [Mem(n), R8(R8m::A)]) => LdhImm8ToA(high_page(*n)?, false),
+      ("ldh", [R8(R8m::A), Mem(n)]) => LdhImm8ToA(high_page(*n)?, true),
+      ("db", [t]) => Illegal(IllegalOpByte::new(imm8(t)?)?),
+      _ => return None,
+    })
+  }
+}
+
+/// A byte from a value in `-128..=255`.
+fn u8_from(n: i32) -> Option<u8> {
+  match n {
+    -0x80..=0xFF => Some(n as u8),
+    _ => None,
+  }
+}
+
+/// A word from a value in `-32768..=65535`.
+fn u16_from(n: i32) -> Option<u16> {
+  match n {
+    -0x8000..=0xFFFF => Some(n as u16),
+    _ => None,
+  }
+}
+
+#[test]
+fn test_Instruction_display() {
+  use alloc::{format, string::ToString};
+  let show = |bytes: &[u8]| decode(bytes).unwrap().0.to_string();
+  assert_eq!(show(&[0x31, 0xFE, 0xFF]), "ld sp, $FFFE");
+  assert_eq!(show(&[0x20, 0xF9]), "jr nz, @-5");
+  assert_eq!(show(&[0x18, 0xFE]), "jr @");
+  assert_eq!(show(&[0xE0, 0x44]), "ldh [$FF44], a");
+  assert_eq!(show(&[0xCB, 0x7E]), "bit 7, [hl]");
+  assert_eq!(show(&[0xF8, 0xFB]), "ld hl, sp-5");
+  assert_eq!(show(&[0xF5]), "push af");
+  let (jr, _) = decode(&[0x20, 0xF9]).unwrap();
+  assert_eq!(format!("{}", jr.at(0x0155)), "jr nz, $0150");
+
+  // every op code survives being shown and parsed again, both ways of
+  // showing relative jumps.
+  let mut all = alloc::vec::Vec::new();
+  for op_code in 0..=u8::MAX {
+    for operand in [0x00, 0x7F, 0x80, 0xFF] {
+      all.push([op_code, operand, 0xC3]);
+    }
+    all.push([0xCB, op_code, 0]);
+  }
+  for bytes in all {
+    let (instruction, _) = decode(&bytes).unwrap();
+    let text = instruction.to_string();
+    assert_eq!(Instruction::parse(&text), Some(instruction), "`{text}`");
+    let addr = 0x4000;
+    let text = instruction.at(addr).to_string();
+    assert_eq!(
+      Instruction::parse_at(&text, addr),
+      Some(instruction),
+      "`{text}`"
+    );
+  }
+}
@@ -0,0 +1,9 @@
+//! Interrupt bits, as used in `IF` (`$FF0F`) and `IE` (`$FFFF`).
+//!
+//! * See Also: [Pandocs: Interrupts](https://gbdev.io/pandocs/Interrupts.html)
+
+pub const INT_VBLANK: u8 = 1 << 0;
+pub const INT_STAT: u8 = 1 << 1;
+pub const INT_TIMER: u8 = 1 << 2;
+pub const INT_SERIAL: u8 = 1 << 3;
+pub const INT_JOYPAD: u8 = 1 << 4;
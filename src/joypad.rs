@@ -0,0 +1,130 @@
+//! The joypad, read through `P1` (`$FF00`).
+//!
+//! The eight buttons are wired as a 2x4 matrix. The game selects the d-pad
+//! with bit 4 and/or the action buttons with bit 5 (0 = selected), then reads
+//! the low 4 bits, where 0 means a selected button is held.
+//!
+//! * See Also: [Pandocs: Joypad Input](https://gbdev.io/pandocs/Joypad_Input.html)
+
+use bitfrob::u8_get_bit;
+
+/// A set of held buttons.
+///
+/// The low nibble is the d-pad and the high nibble is the action buttons, in
+/// the same bit order as `P1` reads them.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct Buttons(u8);
+impl Buttons {
+  pub const RIGHT: Self = Self(1 << 0);
+  pub const LEFT: Self = Self(1 << 1);
+  pub const UP: Self = Self(1 << 2);
+  pub const DOWN: Self = Self(1 << 3);
+  pub const A: Self = Self(1 << 4);
+  pub const B: Self = Self(1 << 5);
+  pub const SELECT: Self = Self(1 << 6);
+  pub const START: Self = Self(1 << 7);
+
+  #[inline]
+  #[must_use]
+  pub const fn new(u: u8) -> Self {
+    Self(u)
+  }
+  #[inline]
+  #[must_use]
+  pub const fn get(self) -> u8 {
+    self.0
+  }
+  #[inline]
+  #[must_use]
+  pub const fn contains(self, other: Self) -> bool {
+    self.0 & other.0 == other.0
+  }
+  #[inline]
+  #[must_use]
+  pub const fn with(self, other: Self) -> Self {
+    Self(self.0 | other.0)
+  }
+}
+impl core::ops::BitOr for Buttons {
+  type Output = Self;
+  #[inline]
+  fn bitor(self, rhs: Self) -> Self {
+    self.with(rhs)
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Joypad {
+  /// Bits 4 and 5 of `P1`.
+  select: u8,
+  held: Buttons,
+}
+impl Default for Joypad {
+  #[inline]
+  fn default() -> Self {
+    Self::new()
+  }
+}
+impl Joypad {
+  /// Makes a joypad with nothing selected and nothing held.
+  #[inline]
+  #[must_use]
+  pub const fn new() -> Self {
+    Self { select: 0b11_0000, held: Buttons(0) }
+  }
+
+  #[inline]
+  #[must_use]
+  pub const fn held(&self) -> Buttons {
+    self.held
+  }
+
+  /// The low 4 bits of `P1` (0 = held), for the current selection.
+  #[inline]
+  #[must_use]
+  const fn lines(&self) -> u8 {
+    let mut pressed = 0;
+    if !u8_get_bit(4, self.select) {
+      pressed |= self.held.0 & 0xF;
+    }
+    if !u8_get_bit(5, self.select) {
+      pressed |= self.held.0 >> 4;
+    }
+    !pressed & 0xF
+  }
+
+  #[inline]
+  #[must_use]
+  pub const fn read_p1(&self) -> u8 {
+    0b1100_0000 | self.select | self.lines()
+  }
+  #[inline]
+  pub fn write_p1(&mut self, byte: u8) {
+    self.select = byte & 0b11_0000;
+  }
+
+  /// Changes which buttons are held.
+  ///
+  /// * **Returns:** If a selected line went from high to low, which requests
+  ///   the joypad interrupt (and wakes the CPU from `stop`).
+  pub fn set_held(&mut self, held: Buttons) -> bool {
+    let before = self.lines();
+    self.held = held;
+    before & !self.lines() != 0
+  }
+}
+
+#[test]
+fn test_Joypad_p1() {
+  let mut joypad = Joypad::new();
+  assert_eq!(joypad.read_p1(), 0xFF);
+  assert!(!joypad.set_held(Buttons::START | Buttons::LEFT));
+  joypad.write_p1(0x20);
+  assert_eq!(joypad.read_p1(), 0xED);
+  joypad.write_p1(0x10);
+  assert_eq!(joypad.read_p1(), 0xD7);
+  assert!(joypad.set_held(Buttons::START | Buttons::LEFT | Buttons::A));
+  assert_eq!(joypad.read_p1(), 0xD6);
+  assert!(!joypad.set_held(Buttons::START | Buttons::LEFT | Buttons::DOWN));
+}
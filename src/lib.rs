@@ -1,14 +1,45 @@
-//#![no_std]
+#![no_std]
 #![allow(unused)]
 #![cfg_attr(test, allow(nonstandard_style))]
 
 extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
 
+pub mod apu;
+pub mod assembler;
+pub mod blargg;
+pub mod boot_rom;
+pub mod cart_header;
+pub mod compat;
 pub mod cpu;
 pub mod data_bus;
+pub mod disasm;
+pub mod doctor;
+pub mod gameboy;
+pub mod hdma;
+pub mod instruction;
+pub mod interrupts;
+pub mod joypad;
 pub mod mbc;
+pub mod memory_map;
+pub mod model;
+pub mod mooneye;
 pub mod op_actions;
 pub mod op_disassembly;
+pub mod palette;
+pub mod png;
+pub mod ppu;
+pub mod printer;
 pub mod reg16;
 pub mod reg8;
 pub mod reg_flags;
+pub mod scheduler;
+pub mod screenshot;
+pub mod serial;
+pub mod sgb;
+pub mod speed;
+pub mod timer;
+pub mod trace;
+pub mod vram;
+pub mod wram;
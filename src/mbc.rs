@@ -1,23 +1,364 @@
-use alloc::{boxed::Box, vec::Vec};
+//! Memory bank controllers, which live in the cart and map its ROM and RAM.
+//!
+//! Each cart is a [DataBus] that only sees addresses in `$0000..=$7FFF` (ROM,
+//! and writes there go to the controller's registers) and `$A000..=$BFFF`
+//! (cart RAM).
+//!
+//! * See Also: [Pandocs: MBCs](https://gbdev.io/pandocs/MBCs.html)
 
-use crate::data_bus::DataBus;
+use alloc::{boxed::Box, vec, vec::Vec};
 
+use crate::{cart_header::CartHeader, data_bus::DataBus};
+
+/// Bytes per ROM bank.
+pub const ROM_BANK_SIZE: usize = 0x4000;
+
+/// Bytes per cart RAM bank.
+pub const RAM_BANK_SIZE: usize = 0x2000;
+
+/// Makes the right kind of cart for a ROM, based on the header's cart type.
+///
+/// * **Returns:** `None` if the ROM has no header or uses a controller that
+///   isn't supported.
+#[must_use]
+pub fn cart_from_rom(rom: Vec<u8>) -> Option<Box<dyn DataBus>> {
+  let header = CartHeader::from_rom(&rom)?;
+  let ram_size = header.ram_size_bytes().unwrap_or(0);
+  Some(match header.cart_type {
+    0x00 | 0x08 | 0x09 => Box::new(RomOnly::new(rom, ram_size)),
+    0x01..=0x03 => Box::new(MBC1::new(rom, ram_size)),
+    0x0F..=0x13 => Box::new(MBC3::new(rom, ram_size)),
+    0x19..=0x1E => Box::new(MBC5::new(rom, ram_size)),
+    _ => return None,
+  })
+}
+
+/// Wraps a bank number to the ROM image size.
+#[inline]
+fn rom_bank(rom: &[u8], bank: usize) -> usize {
+  bank % (rom.len() / ROM_BANK_SIZE).max(1)
+}
+
+/// Reads from a ROM image, wrapping the bank number to the image size.
+#[inline]
+fn rom_read(rom: &[u8], bank: usize, addr: u16) -> u8 {
+  let i = rom_bank(rom, bank) * ROM_BANK_SIZE + usize::from(addr & 0x3FFF);
+  rom.get(i).copied().unwrap_or(0xFF)
+}
+
+/// Index into cart RAM, wrapping the bank number to the RAM size.
+#[inline]
+fn ram_index(ram: &[u8], bank: usize, addr: u16) -> Option<usize> {
+  if ram.is_empty() {
+    return None;
+  }
+  let i = bank * RAM_BANK_SIZE + usize::from(addr & 0x1FFF);
+  Some(i % ram.len())
+}
+
+/// A cart with no controller: 32 KiB of ROM and at most 8 KiB of RAM.
+pub struct RomOnly {
+  rom: Vec<u8>,
+  ram: Vec<u8>,
+}
+impl RomOnly {
+  #[must_use]
+  pub fn new(rom: Vec<u8>, ram_size: usize) -> Self {
+    Self { rom, ram: vec![0; ram_size] }
+  }
+}
+impl DataBus for RomOnly {
+  fn read(&mut self, addr: u16) -> u8 {
+    match addr {
+      0x0000..=0x7FFF => rom_read(&self.rom, usize::from(addr >> 14), addr),
+      0xA000..=0xBFFF => match ram_index(&self.ram, 0, addr) {
+        Some(i) => self.ram[i],
+        None => 0xFF,
+      },
+      _ => 0xFF,
+    }
+  }
+  fn write(&mut self, addr: u16, byte: u8) {
+    if let (0xA000..=0xBFFF, Some(i)) = (addr, ram_index(&self.ram, 0, addr)) {
+      self.ram[i] = byte;
+    }
+  }
+  fn rom_bank_at(&self, addr: u16) -> Option<usize> {
+    (addr < 0x8000).then(|| rom_bank(&self.rom, usize::from(addr >> 14)))
+  }
+}
+
+/// MBC1: up to 2 MiB of ROM and 32 KiB of RAM.
+///
+/// The 2 bit `bank2` register gives either the upper ROM bank bits or the RAM
+/// bank, and in mode 1 it also applies to the `$0000..=$3FFF` area.
 pub struct MBC1 {
   rom: Vec<u8>,
+  ram: Vec<u8>,
+  ram_enabled: bool,
+  bank1: u8,
+  bank2: u8,
+  mode: bool,
 }
 impl MBC1 {
+  #[must_use]
+  pub fn new(rom: Vec<u8>, ram_size: usize) -> Self {
+    Self {
+      rom,
+      ram: vec![0; ram_size],
+      ram_enabled: false,
+      bank1: 1,
+      bank2: 0,
+      mode: false,
+    }
+  }
+  /// Makes a boxed MBC1 cart, with the RAM size from the ROM's header.
   pub fn new_boxed(rom: Vec<u8>) -> Box<Self> {
-    Box::new(Self { rom })
+    let ram_size =
+      CartHeader::from_rom(&rom).and_then(|h| h.ram_size_bytes()).unwrap_or(0);
+    Box::new(Self::new(rom, ram_size))
+  }
+  /// The banks mapped at `$0000..=$3FFF` and `$4000..=$7FFF`.
+  #[inline]
+  fn rom_banks(&self) -> [usize; 2] {
+    let high = usize::from(self.bank2) << 5;
+    [if self.mode { high } else { 0 }, high | usize::from(self.bank1)]
+  }
+  #[inline]
+  fn ram_bank(&self) -> usize {
+    if self.mode {
+      usize::from(self.bank2)
+    } else {
+      0
+    }
   }
 }
-
 impl DataBus for MBC1 {
-  fn read(&self, addr: u16) -> u8 {
-    assert!(addr <= 0x3FFF, "illegal read: {addr:04X}");
-    self.rom[addr as usize]
+  fn read(&mut self, addr: u16) -> u8 {
+    match addr {
+      0x0000..=0x7FFF => {
+        rom_read(&self.rom, self.rom_banks()[usize::from(addr >> 14)], addr)
+      }
+      0xA000..=0xBFFF if self.ram_enabled => {
+        match ram_index(&self.ram, self.ram_bank(), addr) {
+          Some(i) => self.ram[i],
+          None => 0xFF,
+        }
+      }
+      _ => 0xFF,
+    }
+  }
+  fn write(&mut self, addr: u16, byte: u8) {
+    match addr {
+      0x0000..=0x1FFF => self.ram_enabled = byte & 0xF == 0xA,
+      0x2000..=0x3FFF => self.bank1 = (byte & 0x1F).max(1),
+      0x4000..=0x5FFF => self.bank2 = byte & 0b11,
+      0x6000..=0x7FFF => self.mode = byte & 1 != 0,
+      0xA000..=0xBFFF if self.ram_enabled => {
+        if let Some(i) = ram_index(&self.ram, self.ram_bank(), addr) {
+          self.ram[i] = byte;
+        }
+      }
+      _ => (),
+    }
+  }
+  fn rom_bank_at(&self, addr: u16) -> Option<usize> {
+    let bank = *self.rom_banks().get(usize::from(addr >> 14))?;
+    Some(rom_bank(&self.rom, bank))
+  }
+}
+
+/// MBC3: up to 2 MiB of ROM and 32 KiB of RAM.
+///
+/// The real-time clock isn't emulated, so selecting an RTC register reads as
+/// `$FF` and ignores writes.
+pub struct MBC3 {
+  rom: Vec<u8>,
+  ram: Vec<u8>,
+  ram_enabled: bool,
+  rom_bank: u8,
+  ram_select: u8,
+}
+impl MBC3 {
+  #[must_use]
+  pub fn new(rom: Vec<u8>, ram_size: usize) -> Self {
+    Self {
+      rom,
+      ram: vec![0; ram_size],
+      ram_enabled: false,
+      rom_bank: 1,
+      ram_select: 0,
+    }
+  }
+}
+impl DataBus for MBC3 {
+  fn read(&mut self, addr: u16) -> u8 {
+    match addr {
+      0x0000..=0x3FFF => rom_read(&self.rom, 0, addr),
+      0x4000..=0x7FFF => rom_read(&self.rom, usize::from(self.rom_bank), addr),
+      0xA000..=0xBFFF if self.ram_enabled && self.ram_select < 4 => {
+        match ram_index(&self.ram, usize::from(self.ram_select), addr) {
+          Some(i) => self.ram[i],
+          None => 0xFF,
+        }
+      }
+      _ => 0xFF,
+    }
+  }
+  fn write(&mut self, addr: u16, byte: u8) {
+    match addr {
+      0x0000..=0x1FFF => self.ram_enabled = byte & 0xF == 0xA,
+      0x2000..=0x3FFF => self.rom_bank = (byte & 0x7F).max(1),
+      0x4000..=0x5FFF => self.ram_select = byte,
+      0xA000..=0xBFFF if self.ram_enabled && self.ram_select < 4 => {
+        let bank = usize::from(self.ram_select);
+        if let Some(i) = ram_index(&self.ram, bank, addr) {
+          self.ram[i] = byte;
+        }
+      }
+      _ => (),
+    }
+  }
+  fn rom_bank_at(&self, addr: u16) -> Option<usize> {
+    match addr {
+      0x0000..=0x3FFF => Some(0),
+      0x4000..=0x7FFF => Some(rom_bank(&self.rom, usize::from(self.rom_bank))),
+      _ => None,
+    }
+  }
+}
+
+/// MBC5: up to 8 MiB of ROM and 128 KiB of RAM.
+///
+/// Unlike the others, ROM bank 0 can be mapped at `$4000..=$7FFF`.
+pub struct MBC5 {
+  rom: Vec<u8>,
+  ram: Vec<u8>,
+  ram_enabled: bool,
+  rom_bank: u16,
+  ram_bank: u8,
+}
+impl MBC5 {
+  #[must_use]
+  pub fn new(rom: Vec<u8>, ram_size: usize) -> Self {
+    Self {
+      rom,
+      ram: vec![0; ram_size],
+      ram_enabled: false,
+      rom_bank: 1,
+      ram_bank: 0,
+    }
+  }
+}
+impl DataBus for MBC5 {
+  fn read(&mut self, addr: u16) -> u8 {
+    match addr {
+      0x0000..=0x3FFF => rom_read(&self.rom, 0, addr),
+      0x4000..=0x7FFF => rom_read(&self.rom, usize::from(self.rom_bank), addr),
+      0xA000..=0xBFFF if self.ram_enabled => {
+        match ram_index(&self.ram, usize::from(self.ram_bank), addr) {
+          Some(i) => self.ram[i],
+          None => 0xFF,
+        }
+      }
+      _ => 0xFF,
+    }
   }
   fn write(&mut self, addr: u16, byte: u8) {
-    println!("Wrote ${byte:02X} to ${addr:04X}")
-    /* TODO */
+    match addr {
+      0x0000..=0x1FFF => self.ram_enabled = byte & 0xF == 0xA,
+      0x2000..=0x2FFF => {
+        self.rom_bank = (self.rom_bank & 0x100) | u16::from(byte);
+      }
+      0x3000..=0x3FFF => {
+        self.rom_bank = (self.rom_bank & 0xFF) | (u16::from(byte & 1) << 8);
+      }
+      0x4000..=0x5FFF => self.ram_bank = byte & 0xF,
+      0xA000..=0xBFFF if self.ram_enabled => {
+        let bank = usize::from(self.ram_bank);
+        if let Some(i) = ram_index(&self.ram, bank, addr) {
+          self.ram[i] = byte;
+        }
+      }
+      _ => (),
+    }
+  }
+  fn rom_bank_at(&self, addr: u16) -> Option<usize> {
+    match addr {
+      0x0000..=0x3FFF => Some(0),
+      0x4000..=0x7FFF => Some(rom_bank(&self.rom, usize::from(self.rom_bank))),
+      _ => None,
+    }
+  }
+}
+
+#[test]
+fn test_MBC1_banking() {
+  let mut rom = vec![0_u8; ROM_BANK_SIZE * 64];
+  for (bank, chunk) in rom.chunks_mut(ROM_BANK_SIZE).enumerate() {
+    chunk[0] = bank as u8;
   }
+  let mut mbc = MBC1::new(rom, RAM_BANK_SIZE * 4);
+  assert_eq!(mbc.read(0x4000), 1);
+  mbc.write(0x2000, 0x00);
+  assert_eq!(mbc.read(0x4000), 1);
+  mbc.write(0x2000, 0x05);
+  mbc.write(0x4000, 0x01);
+  assert_eq!(mbc.read(0x4000), 0x25);
+  assert_eq!(mbc.read(0x0000), 0);
+  mbc.write(0x6000, 0x01);
+  assert_eq!(mbc.read(0x0000), 0x20);
+  assert_eq!(mbc.rom_bank_at(0x0000), Some(0x20));
+  assert_eq!(mbc.rom_bank_at(0x4000), Some(0x25));
+  assert_eq!(mbc.rom_bank_at(0xA000), None);
+
+  assert_eq!(mbc.read(0xA000), 0xFF);
+  mbc.write(0x0000, 0x0A);
+  mbc.write(0xA000, 0x12);
+  mbc.write(0x4000, 0x02);
+  assert_eq!(mbc.read(0xA000), 0x00);
+  mbc.write(0x4000, 0x01);
+  assert_eq!(mbc.read(0xA000), 0x12);
+}
+
+#[test]
+fn test_MBC3_MBC5_banking() {
+  let mut rom = vec![0_u8; ROM_BANK_SIZE * 512];
+  for (bank, chunk) in rom.chunks_mut(ROM_BANK_SIZE).enumerate() {
+    chunk[0] = bank as u8;
+    chunk[1] = (bank >> 8) as u8;
+  }
+
+  let mut mbc3 = MBC3::new(rom[..ROM_BANK_SIZE * 128].to_vec(), RAM_BANK_SIZE);
+  mbc3.write(0x2000, 0x00);
+  assert_eq!(mbc3.read(0x4000), 1);
+  mbc3.write(0x2000, 0x7F);
+  assert_eq!(mbc3.read(0x4000), 0x7F);
+  assert_eq!(mbc3.rom_bank_at(0x0000), Some(0));
+  assert_eq!(mbc3.rom_bank_at(0x4000), Some(0x7F));
+  assert_eq!(mbc3.rom_bank_at(0xA000), None);
+  mbc3.write(0x0000, 0x0A);
+  mbc3.write(0xA000, 0x34);
+  assert_eq!(mbc3.read(0xA000), 0x34);
+  // RTC registers aren't emulated.
+  mbc3.write(0x4000, 0x08);
+  assert_eq!(mbc3.read(0xA000), 0xFF);
+
+  let mut mbc5 = MBC5::new(rom, RAM_BANK_SIZE * 16);
+  mbc5.write(0x2000, 0x00);
+  assert_eq!(mbc5.read(0x4000), 0);
+  assert_eq!(mbc5.rom_bank_at(0x7FFF), Some(0));
+  mbc5.write(0x2000, 0x23);
+  mbc5.write(0x3000, 0x01);
+  assert_eq!([mbc5.read(0x4000), mbc5.read(0x4001)], [0x23, 0x01]);
+  assert_eq!(mbc5.rom_bank_at(0x4000), Some(0x123));
+  assert_eq!(mbc5.rom_bank_at(0xC000), None);
+  assert_eq!(mbc5.read(0x0000), 0);
+  mbc5.write(0x0000, 0x0A);
+  mbc5.write(0x4000, 0x03);
+  mbc5.write(0xA000, 0x56);
+  mbc5.write(0x4000, 0x00);
+  assert_eq!(mbc5.read(0xA000), 0x00);
+  mbc5.write(0x4000, 0x03);
+  assert_eq!(mbc5.read(0xA000), 0x56);
 }
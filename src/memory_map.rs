@@ -0,0 +1,484 @@
+//! The full memory map that the CPU sees, with every component behind it.
+//!
+//! | Range | Goes To |
+//! |:-|:-|
+//! | `$0000..=$7FFF` | Cart ROM (or the boot ROM, while mapped) |
+//! | `$8000..=$9FFF` | VRAM, through the PPU |
+//! | `$A000..=$BFFF` | Cart RAM |
+//! | `$C000..=$FDFF` | WRAM, then echo RAM |
+//! | `$FE00..=$FE9F` | OAM, through the PPU |
+//! | `$FF00..=$FF7F` | IO registers |
+//! | `$FF80..=$FFFE` | HRAM |
+//! | `$FFFF` | `IE` |
+//!
+//! The memory map also owns the work that isn't the CPU's: OAM DMA, VRAM
+//! DMA, and moving interrupt requests from each component into `IF`.
+//!
+//! Components aren't run every dot. Instead the memory map keeps the current
+//! time, and only catches everything up ([sync](MemoryMap::sync)) when the CPU
+//! touches VRAM, OAM, or an IO register, or when the [Scheduler] says that an
+//! event is due.
+//!
+//! * See Also: [Pandocs: Memory Map](https://gbdev.io/pandocs/Memory_Map.html)
+
+use alloc::boxed::Box;
+
+use bitfrob::u16_get_bit;
+
+use crate::{
+  apu::Apu,
+  boot_rom::BootRom,
+  compat::CgbMode,
+  data_bus::DataBus,
+  hdma::{block_m_cycles, Hdma},
+  interrupts::{INT_SERIAL, INT_TIMER, INT_VBLANK},
+  joypad::Joypad,
+  ppu::{Ppu, PpuMode, SCREEN_HEIGHT},
+  scheduler::{Event, Scheduler},
+  serial::SerialPort,
+  sgb::Sgb,
+  speed::{Key1, Speed},
+  timer::Timer,
+  wram::Wram,
+};
+
+/// Bytes copied by one OAM DMA.
+const OAM_DMA_LENGTH: u8 = 160;
+
+/// An OAM DMA in progress, which copies one byte per M-cycle.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+struct OamDma {
+  src: u16,
+  index: u8,
+}
+
+pub struct MemoryMap {
+  pub cart: Box<dyn DataBus>,
+  pub boot_rom: Option<BootRom>,
+  pub ppu: Ppu,
+  pub apu: Apu,
+  pub timer: Timer,
+  pub serial: SerialPort,
+  pub joypad: Joypad,
+  pub wram: Wram,
+  pub hdma: Hdma,
+  /// Only a Super Game Boy has this.
+  pub sgb: Option<Sgb>,
+  hram: [u8; 0x7F],
+  ie: u8,
+  if_: u8,
+  /// The last value written to `$FF46`.
+  dma_reg: u8,
+  oam_dma: Option<OamDma>,
+  /// The current time, in dots since power on.
+  now: u64,
+  /// Components have been run for every dot before this one.
+  synced: u64,
+  scheduler: Scheduler,
+  key1: Key1,
+  cpu_halted: bool,
+  /// Dots since the start of the current M-cycle, for OAM DMA.
+  m_cycle_dots: u32,
+  prev_mode: PpuMode,
+  /// M-cycles that the CPU should be stalled for (VRAM DMA).
+  stall_m_cycles: u16,
+  frame_ready: bool,
+  /// What `LY` reads as, instead of the PPU's real line.
+  ly_stub: Option<u8>,
+}
+impl MemoryMap {
+  /// Makes a memory map around the given parts, with the interrupt
+  /// registers and HRAM cleared.
+  #[must_use]
+  pub fn new(
+    cart: Box<dyn DataBus>, boot_rom: Option<BootRom>, ppu: Ppu, wram: Wram,
+    hdma: Hdma, key1: Key1, sgb: Option<Sgb>,
+  ) -> Self {
+    let prev_mode = ppu.mode();
+    Self {
+      cart,
+      boot_rom,
+      ppu,
+      apu: Apu::new(),
+      timer: Timer::new(),
+      serial: SerialPort::new(),
+      joypad: Joypad::new(),
+      wram,
+      hdma,
+      sgb,
+      hram: [0; 0x7F],
+      ie: 0,
+      if_: 0,
+      dma_reg: 0xFF,
+      oam_dma: None,
+      now: 0,
+      synced: 0,
+      scheduler: Scheduler::new(),
+      key1,
+      cpu_halted: false,
+      m_cycle_dots: 0,
+      prev_mode,
+      stall_m_cycles: 0,
+      frame_ready: false,
+      ly_stub: None,
+    }
+  }
+
+  /// Requests interrupts by setting bits in `IF`.
+  #[inline]
+  pub fn request_interrupts(&mut self, bits: u8) {
+    self.if_ |= bits & 0x1F;
+  }
+
+  /// If OAM DMA is copying, which blocks CPU access to OAM.
+  #[inline]
+  #[must_use]
+  pub const fn oam_dma_active(&self) -> bool {
+    self.oam_dma.is_some()
+  }
+
+  /// Takes the M-cycles that VRAM DMA wants the CPU stalled for.
+  #[inline]
+  pub fn take_stall_m_cycles(&mut self) -> u16 {
+    core::mem::take(&mut self.stall_m_cycles)
+  }
+
+  /// Takes the flag for a VBlank having started.
+  #[inline]
+  pub fn take_frame_ready(&mut self) -> bool {
+    core::mem::take(&mut self.frame_ready)
+  }
+
+  /// Makes `LY` always read as `ly`, or `None` to read the real line again.
+  ///
+  /// This is only for comparing against logs from emulators that do the
+  /// same, see [doctor](crate::doctor). The PPU keeps running normally.
+  #[inline]
+  pub fn stub_ly(&mut self, ly: Option<u8>) {
+    self.ly_stub = ly;
+  }
+
+  /// The current time, in dots since power on.
+  #[inline]
+  #[must_use]
+  pub const fn now(&self) -> u64 {
+    self.now
+  }
+
+  /// When the next scheduled event happens. The memory map should be synced
+  /// once the time reaches this.
+  #[inline]
+  #[must_use]
+  pub const fn next_event_time(&self) -> u64 {
+    self.scheduler.next_time()
+  }
+
+  /// Moves the clock forward, without running anything yet.
+  #[inline]
+  pub fn add_dots(&mut self, dots: u32) {
+    self.now += u64::from(dots);
+  }
+
+  /// The CPU speed, which sets how fast the timer and serial port run
+  /// compared to the system clock.
+  #[inline]
+  #[must_use]
+  pub const fn speed(&self) -> Speed {
+    self.key1.speed()
+  }
+
+  /// Tells the memory map if the CPU is halted, since HBlank VRAM DMA is held
+  /// off while it is.
+  #[inline]
+  pub fn set_cpu_halted(&mut self, halted: bool) {
+    self.cpu_halted = halted;
+  }
+
+  /// Runs every component up to the current time.
+  pub fn sync(&mut self) {
+    while self.synced < self.now {
+      let until = self.scheduler.next_time().max(self.synced + 1);
+      let step = (until.min(self.now) - self.synced).min(u64::from(u32::MAX));
+      self.advance(step as u32);
+    }
+  }
+
+  /// The `DIV` bit that clocks the APU frame sequencer.
+  #[inline]
+  #[must_use]
+  const fn frame_sequencer_bit_index(&self) -> u32 {
+    match self.speed() {
+      Speed::Normal => 12,
+      Speed::Double => 13,
+    }
+  }
+
+  #[inline]
+  #[must_use]
+  fn frame_sequencer_bit(&self) -> bool {
+    u16_get_bit(self.frame_sequencer_bit_index(), self.timer.counter())
+  }
+
+  fn write_div(&mut self) {
+    let before = self.frame_sequencer_bit();
+    self.timer.write_div();
+    if before {
+      self.apu.div_tick();
+    }
+  }
+
+  /// Sets the time of each event from the state of the components.
+  fn reschedule(&mut self) {
+    let per_dot = self.speed().cpu_t_cycles_per_dot();
+    let to_time = |dots: u32| self.synced + u64::from(dots);
+    let from_t_cycles = |t: u32| to_time(t.div_ceil(per_dot));
+    let timer = self.timer.t_cycles_until_irq().map(from_t_cycles);
+    let fs = from_t_cycles(
+      self.timer.t_cycles_until_fall(self.frame_sequencer_bit_index()),
+    );
+    let ppu = self.ppu.dots_until_event().map(to_time);
+    let serial = self.serial.t_cycles_until_irq().map(from_t_cycles);
+    let dma = self.oam_dma.map(|_| {
+      to_time(self.speed().dots_per_m_cycle().saturating_sub(self.m_cycle_dots))
+    });
+    self.scheduler.schedule(Event::TimerIrq, timer);
+    self.scheduler.schedule(Event::FrameSequencer, Some(fs));
+    self.scheduler.schedule(Event::Ppu, ppu);
+    self.scheduler.schedule(Event::Serial, serial);
+    self.scheduler.schedule(Event::OamDma, dma);
+  }
+
+  /// Runs every component for `dots`, which must not go past the next
+  /// scheduled event.
+  fn advance(&mut self, dots: u32) {
+    let end = self.synced + u64::from(dots);
+    let due = |event| self.scheduler.time_of(event) == Some(end);
+    let fs_due = due(Event::FrameSequencer);
+    let dma_due = due(Event::OamDma);
+    let speed = self.speed();
+
+    let t_cycles = dots * speed.cpu_t_cycles_per_dot();
+    if self.timer.advance(t_cycles) {
+      self.if_ |= INT_TIMER;
+    }
+    if self.serial.advance(t_cycles) {
+      self.if_ |= INT_SERIAL;
+    }
+
+    let irq = self.ppu.advance(dots);
+    self.if_ |= irq;
+    if irq & INT_VBLANK != 0 {
+      self.frame_ready = true;
+      if let Some(sgb) = self.sgb.as_mut() {
+        sgb.vblank(self.ppu.shades());
+      }
+    }
+    let mode = self.ppu.mode();
+    if mode != self.prev_mode
+      && mode == PpuMode::HBlank
+      && usize::from(self.ppu.ly()) < SCREEN_HEIGHT
+    {
+      self.hdma.hblank(self.cpu_halted);
+    }
+    self.prev_mode = mode;
+
+    // The frame sequencer steps partway through the last dot, before the APU
+    // runs for that dot.
+    self.apu.advance(dots - 1);
+    if fs_due {
+      self.apu.div_tick();
+    }
+    self.apu.advance(1);
+
+    let dots_per_m_cycle = speed.dots_per_m_cycle();
+    self.m_cycle_dots = (self.m_cycle_dots + dots) % dots_per_m_cycle;
+    if dma_due {
+      self.oam_dma_m_cycle();
+    }
+
+    self.run_hdma();
+
+    self.synced = end;
+    self.reschedule();
+  }
+
+  /// Copies any VRAM DMA blocks that are ready, stalling the CPU for them.
+  fn run_hdma(&mut self) {
+    while let Some(block) = self.hdma.next_block() {
+      for i in 0..16 {
+        let byte = self.read_unsynced(block.src.wrapping_add(i));
+        self.ppu.vram_mut().write(block.dst + i, byte);
+      }
+      self.stall_m_cycles += block_m_cycles(self.speed());
+    }
+  }
+
+  fn oam_dma_m_cycle(&mut self) {
+    let Some(dma) = self.oam_dma.as_mut() else { return };
+    let addr = dma.src.wrapping_add(u16::from(dma.index));
+    let i = usize::from(dma.index);
+    dma.index += 1;
+    if dma.index == OAM_DMA_LENGTH {
+      self.oam_dma = None;
+    }
+    // Sources at `$E000` and up read WRAM, like echo RAM.
+    let byte = match addr {
+      0xE000..=0xFFFF => self.wram.read(addr),
+      _ => self.read_unsynced(addr),
+    };
+    self.ppu.oam_mut()[i] = byte;
+  }
+
+  /// Reads without catching anything up first.
+  fn read_unsynced(&mut self, addr: u16) -> u8 {
+    match addr {
+      0x0000..=0x7FFF => {
+        match self.boot_rom.as_ref().and_then(|b| b.read(addr)) {
+          Some(byte) => byte,
+          None => self.cart.read(addr),
+        }
+      }
+      0x8000..=0x9FFF => self.ppu.cpu_read_vram(addr),
+      0xA000..=0xBFFF => self.cart.read(addr),
+      0xC000..=0xFDFF => self.wram.read(addr),
+      0xFE00..=0xFE9F if self.oam_dma_active() => 0xFF,
+      0xFE00..=0xFE9F => self.ppu.cpu_read_oam(addr),
+      0xFEA0..=0xFEFF => 0xFF,
+      0xFF00..=0xFF7F => self.read_io(addr),
+      0xFF80..=0xFFFE => self.hram[usize::from(addr - 0xFF80)],
+      0xFFFF => self.ie,
+    }
+  }
+
+  fn read_io(&self, addr: u16) -> u8 {
+    match addr {
+      0xFF00 => {
+        let p1 = self.joypad.read_p1();
+        match self.sgb.as_ref().and_then(Sgb::joypad_id) {
+          Some(id) => (p1 & 0xF0) | id,
+          None => p1,
+        }
+      }
+      0xFF01 => self.serial.read_sb(),
+      0xFF02 => self.serial.read_sc(),
+      0xFF04 => self.timer.read_div(),
+      0xFF05 => self.timer.read_tima(),
+      0xFF06 => self.timer.read_tma(),
+      0xFF07 => self.timer.read_tac(),
+      0xFF0F => 0xE0 | self.if_,
+      0xFF10..=0xFF3F => self.apu.read_reg(addr),
+      0xFF46 => self.dma_reg,
+      0xFF44 => self.ly_stub.unwrap_or_else(|| self.ppu.read_reg(addr)),
+      0xFF40..=0xFF4B | 0xFF4F | 0xFF68..=0xFF6C => self.ppu.read_reg(addr),
+      0xFF4C | 0xFF50 => match self.boot_rom.as_ref() {
+        Some(boot_rom) => boot_rom.read_reg(addr),
+        None => 0xFF,
+      },
+      0xFF4D => self.key1.read(),
+      0xFF51..=0xFF55 => self.hdma.read_reg(addr),
+      0xFF70 => self.wram.read_svbk(),
+      _ => 0xFF,
+    }
+  }
+
+  fn write_io(&mut self, addr: u16, byte: u8) {
+    match addr {
+      0xFF00 => {
+        self.joypad.write_p1(byte);
+        if let Some(sgb) = self.sgb.as_mut() {
+          sgb.write_p1(byte);
+        }
+      }
+      0xFF01 => self.serial.write_sb(byte),
+      0xFF02 => self.serial.write_sc(byte),
+      0xFF04 => self.write_div(),
+      0xFF05 => self.timer.write_tima(byte),
+      0xFF06 => self.timer.write_tma(byte),
+      0xFF07 => self.timer.write_tac(byte),
+      0xFF0F => self.if_ = byte & 0x1F,
+      0xFF10..=0xFF3F => self.apu.write_reg(addr, byte),
+      0xFF46 => {
+        self.dma_reg = byte;
+        self.oam_dma = Some(OamDma { src: u16::from(byte) << 8, index: 0 });
+      }
+      0xFF40..=0xFF4B | 0xFF4F | 0xFF68..=0xFF6C => {
+        self.ppu.write_reg(addr, byte)
+      }
+      0xFF4C | 0xFF50 => {
+        let Some(boot_rom) = self.boot_rom.as_mut() else { return };
+        if boot_rom.write_reg(addr, byte)
+          && boot_rom.is_cgb()
+          && boot_rom.key0().mode() == CgbMode::DmgCompat
+        {
+          self.ppu.enter_dmg_compat();
+          self.wram.set_cgb(false);
+          self.hdma.set_cgb(false);
+          self.key1.set_cgb(false);
+        }
+      }
+      0xFF4D => self.key1.write(byte),
+      0xFF51..=0xFF55 => {
+        self.hdma.write_reg(addr, byte, self.ppu.lcd_enabled());
+        self.run_hdma();
+      }
+      0xFF70 => self.wram.write_svbk(byte),
+      _ => (),
+    }
+  }
+}
+impl DataBus for MemoryMap {
+  fn read(&mut self, addr: u16) -> u8 {
+    if matches!(addr, 0x8000..=0x9FFF | 0xFE00..=0xFF7F) {
+      self.sync();
+    }
+    self.read_unsynced(addr)
+  }
+  #[inline]
+  fn peek(&mut self, addr: u16) -> u8 {
+    self.read_unsynced(addr)
+  }
+  fn write(&mut self, addr: u16, byte: u8) {
+    match addr {
+      0x0000..=0x7FFF | 0xA000..=0xBFFF => self.cart.write(addr, byte),
+      0xC000..=0xFDFF => self.wram.write(addr, byte),
+      0xFF80..=0xFFFE => self.hram[usize::from(addr - 0xFF80)] = byte,
+      0xFFFF => self.ie = byte,
+      _ => {
+        self.sync();
+        match addr {
+          0x8000..=0x9FFF => self.ppu.cpu_write_vram(addr, byte),
+          0xFE00..=0xFE9F if self.oam_dma_active() => (),
+          0xFE00..=0xFE9F => self.ppu.cpu_write_oam(addr, byte),
+          0xFF00..=0xFF7F => self.write_io(addr, byte),
+          _ => (),
+        }
+        self.reschedule();
+      }
+    }
+  }
+  fn stop(&mut self) -> Option<Speed> {
+    self.sync();
+    self.write_div();
+    let switched = self.key1.stop();
+    self.reschedule();
+    switched.then(|| self.speed())
+  }
+  #[inline]
+  fn pending_interrupts(&mut self) -> u8 {
+    if self.now >= self.scheduler.next_time() {
+      self.sync();
+    }
+    self.ie & self.if_ & 0x1F
+  }
+  #[inline]
+  fn acknowledge_interrupt(&mut self, bit: u8) {
+    self.if_ &= !bit;
+  }
+  /// While the boot ROM is mapped over the cart, it isn't in any bank.
+  fn rom_bank_at(&self, addr: u16) -> Option<usize> {
+    match self.boot_rom.as_ref().and_then(|b| b.read(addr)) {
+      Some(_) => None,
+      None => self.cart.rom_bank_at(addr),
+    }
+  }
+}
@@ -0,0 +1,214 @@
+//! The different models of Game Boy, and the state that each one's boot ROM
+//! leaves behind.
+//!
+//! Skipping the boot ROM means starting at `$0100` with everything set up the
+//! way the boot ROM would have left it. Most of this is the same on every
+//! model, but the CPU registers, `DIV`, and the PPU's spot in the frame vary.
+//!
+//! * See Also: [Pandocs: Power Up Sequence](https://gbdev.io/pandocs/Power_Up_Sequence.html)
+
+use crate::{cart_header::CartHeader, compat::nintendo_title_checksum};
+
+/// A Game Boy model, in release order.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Model {
+  /// The early DMG, only released in Japan, with a different boot ROM.
+  Dmg0,
+  /// The original Game Boy.
+  #[default]
+  Dmg,
+  /// Game Boy Pocket (and Game Boy Light).
+  Mgb,
+  /// Super Game Boy.
+  Sgb,
+  /// Super Game Boy 2.
+  Sgb2,
+  /// The early CGB, only released in Japan, with a different boot ROM.
+  Cgb0,
+  /// Game Boy Color.
+  Cgb,
+  /// Game Boy Advance (and SP), running GB and GBC games.
+  Agb,
+}
+impl Model {
+  /// If the model has CGB hardware (VRAM and WRAM banks, palette RAM, etc).
+  #[inline]
+  #[must_use]
+  pub const fn is_cgb(self) -> bool {
+    matches!(self, Self::Cgb0 | Self::Cgb | Self::Agb)
+  }
+
+  /// If the model is a Super Game Boy.
+  #[inline]
+  #[must_use]
+  pub const fn is_sgb(self) -> bool {
+    matches!(self, Self::Sgb | Self::Sgb2)
+  }
+
+  /// If a cart runs in CGB mode (rather than DMG compatibility mode) on this
+  /// model.
+  #[inline]
+  #[must_use]
+  pub const fn runs_in_cgb_mode(self, header: &CartHeader) -> bool {
+    self.is_cgb() && header.is_cgb_mode()
+  }
+
+  /// The CPU registers after the boot ROM, as `[a, f, b, c, d, e, h, l]`.
+  ///
+  /// `SP` is always `$FFFE` and `PC` is always `$0100`.
+  #[must_use]
+  pub fn post_boot_registers(self, header: &CartHeader) -> [u8; 8] {
+    // DMG boot ROMs end by comparing the header checksum, so H and C depend on
+    // it.
+    let checksum_flags = if header.header_checksum == 0 { 0x80 } else { 0xB0 };
+    match self {
+      Self::Dmg0 => [0x01, 0x00, 0xFF, 0x13, 0x00, 0xC1, 0x84, 0x03],
+      Self::Dmg => [0x01, checksum_flags, 0x00, 0x13, 0x00, 0xD8, 0x01, 0x4D],
+      Self::Mgb => [0xFF, checksum_flags, 0x00, 0x13, 0x00, 0xD8, 0x01, 0x4D],
+      Self::Sgb => [0x01, 0x00, 0x00, 0x14, 0x00, 0x00, 0xC0, 0x60],
+      Self::Sgb2 => [0xFF, 0x00, 0x00, 0x14, 0x00, 0x00, 0xC0, 0x60],
+      Self::Cgb0 | Self::Cgb | Self::Agb if header.is_cgb_mode() => {
+        if self == Self::Agb {
+          // The AGB boot ROM ends with an extra `inc b`.
+          [0x11, 0x00, 0x01, 0x00, 0xFF, 0x56, 0x00, 0x0D]
+        } else {
+          [0x11, 0x80, 0x00, 0x00, 0xFF, 0x56, 0x00, 0x0D]
+        }
+      }
+      Self::Cgb0 | Self::Cgb | Self::Agb => {
+        // In DMG compatibility mode `B` keeps the title checksum that the
+        // palette lookup used, and `HL` points into the logo tile map only
+        // for the games that get the special logo handling.
+        let mut b = nintendo_title_checksum(header).unwrap_or(0);
+        let mut f = 0x80;
+        let mut special = [0x43, 0x58];
+        if self == Self::Agb {
+          f = if (b & 0xF) == 0xF { 0x20 } else { 0x00 };
+          b = b.wrapping_add(1);
+          if b == 0 {
+            f |= 0x80;
+          }
+          special = [0x44, 0x59];
+        }
+        let [h, l] =
+          if special.contains(&b) { [0x99, 0x1A] } else { [0x00, 0x7C] };
+        [0x11, f, b, 0x00, 0x00, 0x08, h, l]
+      }
+    }
+  }
+
+  /// The timer's full internal counter (`DIV` is the upper 8 bits) at `$0100`.
+  ///
+  /// The CGB boot ROM takes longer when it has to pick a compatibility
+  /// palette, so this depends on the mode as well. mooneye's `boot_div` tests
+  /// check these down to the M-cycle, see `tests/mooneye.rs`.
+  #[inline]
+  #[must_use]
+  pub const fn post_boot_div_counter(self, cgb_mode: bool) -> u16 {
+    match self {
+      Self::Dmg0 => 0x182C,
+      Self::Dmg | Self::Mgb => 0xABCC,
+      Self::Sgb | Self::Sgb2 => 0xD85C,
+      Self::Cgb0 | Self::Cgb | Self::Agb => {
+        if cgb_mode {
+          0x1EA0
+        } else {
+          0x267C
+        }
+      }
+    }
+  }
+
+  /// Where the PPU is at `$0100`, as `(line, dot)`.
+  ///
+  /// Every model but the DMG0 ends part way through line 153, so `LY` already
+  /// reads as 0 even though the PPU is still in VBlank.
+  #[inline]
+  #[must_use]
+  pub const fn post_boot_ppu_position(self) -> (u8, u16) {
+    match self {
+      Self::Dmg0 => (145, 60),
+      _ => (153, 400),
+    }
+  }
+
+  /// What each IO register (`$FF00..=$FF7F`) reads as at `$0100`.
+  ///
+  /// Registers that don't exist on the model (or in the mode) read as `$FF`.
+  /// `IE` (`$FFFF`) is always 0.
+  #[must_use]
+  pub fn post_boot_io(self, header: &CartHeader) -> [u8; 0x80] {
+    let cgb_mode = self.runs_in_cgb_mode(header);
+    let mut io = [0xFF; 0x80];
+    let mut set = |addr: u16, byte: u8| io[usize::from(addr - 0xFF00)] = byte;
+    set(0xFF00, 0xCF);
+    set(0xFF01, 0x00);
+    set(0xFF02, if cgb_mode { 0x7F } else { 0x7E });
+    set(0xFF04, (self.post_boot_div_counter(cgb_mode) >> 8) as u8);
+    set(0xFF05, 0x00);
+    set(0xFF06, 0x00);
+    set(0xFF07, 0xF8);
+    set(0xFF0F, 0xE1);
+    // sound
+    for (addr, byte) in [
+      (0xFF10, 0x80),
+      (0xFF11, 0xBF),
+      (0xFF12, 0xF3),
+      (0xFF14, 0xBF),
+      (0xFF16, 0x3F),
+      (0xFF17, 0x00),
+      (0xFF19, 0xBF),
+      (0xFF1A, 0x7F),
+      (0xFF1C, 0x9F),
+      (0xFF1E, 0xBF),
+      (0xFF21, 0x00),
+      (0xFF22, 0x00),
+      (0xFF23, 0xBF),
+      (0xFF24, 0x77),
+      (0xFF25, 0xF3),
+      (0xFF26, if self.is_sgb() { 0xF0 } else { 0xF1 }),
+    ] {
+      set(addr, byte);
+    }
+    // video
+    let (line, _) = self.post_boot_ppu_position();
+    set(0xFF40, 0x91);
+    set(0xFF41, if line == 153 { 0x85 } else { 0x81 });
+    set(0xFF42, 0x00);
+    set(0xFF43, 0x00);
+    set(0xFF44, if line == 153 { 0x00 } else { line });
+    set(0xFF45, 0x00);
+    set(0xFF46, if self.is_cgb() { 0x00 } else { 0xFF });
+    set(0xFF47, 0xFC);
+    set(0xFF4A, 0x00);
+    set(0xFF4B, 0x00);
+    if cgb_mode {
+      set(0xFF4D, 0x7E);
+      set(0xFF4F, 0xFE);
+      set(0xFF6C, 0xFE);
+      set(0xFF70, 0xF8);
+    }
+    io
+  }
+}
+
+#[test]
+fn test_Model_post_boot_registers() {
+  let mut rom = alloc::vec![0_u8; 0x150];
+  rom[0x134..0x13A].copy_from_slice(b"TETRIS");
+  rom[0x14B] = 0x01;
+  rom[0x14D] = 0x0A;
+  let header = CartHeader::from_rom(&rom).unwrap();
+  assert_eq!(
+    Model::Dmg.post_boot_registers(&header),
+    [0x01, 0xB0, 0x00, 0x13, 0x00, 0xD8, 0x01, 0x4D]
+  );
+  let checksum = b"TETRIS".iter().fold(0_u8, |s, &b| s.wrapping_add(b));
+  assert_eq!(
+    Model::Cgb.post_boot_registers(&header),
+    [0x11, 0x80, checksum, 0x00, 0x00, 0x08, 0x00, 0x7C]
+  );
+  let io = Model::Cgb.post_boot_io(&header);
+  assert_eq!(io[0x4D], 0xFF);
+  assert_eq!(io[0x04], 0x26);
+}
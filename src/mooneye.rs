@@ -0,0 +1,107 @@
+//! Runs mooneye-test-suite ROMs and works out if they passed.
+//!
+//! The tests end by running `ld b, b`, which emulators treat as a software
+//! breakpoint. A pass loads the Fibonacci numbers 3, 5, 8, 13, 21, 34 into
+//! `b`, `c`, `d`, `e`, `h`, `l` first, and a fail loads `$42` into all of
+//! them.
+//!
+//! Each ROM's file name says which models it's for, after the last `-` (such
+//! as `boot_regs-dmgABC.gb` or `di_timing-GS.gb`). Without a suffix, it's for
+//! every model. A suffix with a code that isn't known here (such as `agb0`,
+//! a revision we don't tell apart) is for none of them, so the ROM gets
+//! skipped instead of failing on models it was never meant for.
+//!
+//! * See Also: [mooneye-test-suite](https://github.com/Gekkio/mooneye-test-suite)
+
+use alloc::vec::Vec;
+
+use crate::{gameboy::GameBoy, model::Model};
+
+/// The registers `b`, `c`, `d`, `e`, `h`, `l` when a test passes.
+pub const PASS_REGISTERS: [u8; 6] = [3, 5, 8, 13, 21, 34];
+
+/// The registers `b`, `c`, `d`, `e`, `h`, `l` when a test fails.
+pub const FAIL_REGISTERS: [u8; 6] = [0x42; 6];
+
+/// How a test ROM finished.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum MooneyeResult {
+  Passed,
+  /// The registers `b`, `c`, `d`, `e`, `h`, `l` at the breakpoint. These are
+  /// normally [FAIL_REGISTERS].
+  Failed([u8; 6]),
+  /// The breakpoint never came.
+  Timeout,
+}
+
+/// Runs a test ROM until `ld b, b`, or for `max_frames`.
+pub fn run_mooneye(gb: &mut GameBoy, max_frames: u32) -> MooneyeResult {
+  gb.cpu_mut().ld_b_b_breakpoint = true;
+  for _ in 0..max_frames {
+    if gb.run_frame().breakpoint {
+      let cpu = gb.cpu();
+      let regs = [cpu.b, cpu.c, cpu.d, cpu.e, cpu.h, cpu.l].map(|r| r.get());
+      return if regs == PASS_REGISTERS {
+        MooneyeResult::Passed
+      } else {
+        MooneyeResult::Failed(regs)
+      };
+    }
+  }
+  MooneyeResult::Timeout
+}
+
+/// The models that a test ROM is for, going by its file name.
+///
+/// This is empty when the suffix has a model code that isn't known.
+#[must_use]
+pub fn models_for(file_name: &str) -> Vec<Model> {
+  use Model::*;
+  /// The model codes, longest first so that `sgb2` isn't read as `sgb`.
+  const CODES: &[(&str, &[Model])] = &[
+    ("cgbABCDE", &[Cgb]),
+    ("dmgABC", &[Dmg]),
+    ("dmg0", &[Dmg0]),
+    ("cgb0", &[Cgb0]),
+    ("sgb2", &[Sgb2]),
+    ("mgb", &[Mgb]),
+    ("sgb", &[Sgb]),
+    ("cgb", &[Cgb]),
+    ("agb", &[Agb]),
+    ("ags", &[Agb]),
+    ("G", &[Dmg, Mgb]),
+    ("S", &[Sgb, Sgb2]),
+    ("C", &[Cgb, Agb]),
+    ("A", &[Agb]),
+  ];
+  let all = || alloc::vec![Dmg0, Dmg, Mgb, Sgb, Sgb2, Cgb0, Cgb, Agb];
+  let stem = file_name.strip_suffix(".gb").unwrap_or(file_name);
+  let Some((_, mut suffix)) = stem.rsplit_once('-') else {
+    return all();
+  };
+  let mut models = Vec::new();
+  while !suffix.is_empty() {
+    let Some((code, these)) =
+      CODES.iter().find(|(code, _)| suffix.starts_with(code))
+    else {
+      return Vec::new();
+    };
+    models.extend_from_slice(these);
+    suffix = &suffix[code.len()..];
+  }
+  models.sort();
+  models.dedup();
+  models
+}
+
+#[test]
+fn test_models_for() {
+  use Model::*;
+  assert_eq!(models_for("boot_regs-dmgABC.gb"), [Dmg]);
+  assert_eq!(models_for("boot_div-dmgABCmgb.gb"), [Dmg, Mgb]);
+  assert_eq!(models_for("di_timing-GS.gb"), [Dmg, Mgb, Sgb, Sgb2]);
+  assert_eq!(models_for("boot_regs-sgb2.gb"), [Sgb2]);
+  assert_eq!(models_for("ei_sequence.gb").len(), 8);
+  assert_eq!(models_for("boot_hwio-agb0.gb"), []);
+  assert_eq!(models_for("boot_regs-cgbABC.gb"), []);
+}
@@ -1,4 +1,10 @@
 //! Op code action breakdown info, from <https://izik1.github.io/gbops/>
+//!
+//! Each action is one M-cycle of work. The first action of an op code happens
+//! during the same M-cycle that the op code is fetched.
+//!
+//! `imm` is the CPU's temporary 16-bit register. "Low" and "high" refer to its
+//! two halves.
 
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum CpuAction {
@@ -11,6 +17,83 @@ pub enum CpuAction {
   WriteRegToImm16(ActionRegister),
   WriteRegToHalfAddr(ActionRegister),
   DisableInterrupts,
+  Stop,
+  /// Reads the imm low byte, then ends the op early if the condition fails.
+  ImmLowIf(ActionCond),
+  /// Reads the imm high byte, then ends the op early if the condition fails.
+  ImmHighIf(ActionCond),
+  /// Ends the op early if the condition fails.
+  InternalIf(ActionCond),
+  ReadAddrTo(ActionAddr, ActionRegister),
+  WriteRegToAddr(ActionAddr, ActionRegister),
+  ReadHalfAddrTo(ActionRegister),
+  ReadHalfCTo(ActionRegister),
+  WriteRegToHalfC(ActionRegister),
+  ReadImm16To(ActionRegister),
+  WriteSpLowToImm16,
+  WriteSpHighToImm16,
+  /// Reads `[hl]` into the imm low byte.
+  ReadHlToImm,
+  /// Writes the imm low byte to `[hl]`.
+  WriteImmToHl,
+  WriteIncImmToHl,
+  WriteDecImmToHl,
+  Ld(ActionRegister, ActionRegister),
+  IncR8(ActionRegister),
+  DecR8(ActionRegister),
+  IncR16(ActionRegister),
+  DecR16(ActionRegister),
+  AddHl(ActionRegister),
+  /// `a = a op r`
+  Alu(AluOp, ActionRegister),
+  /// `a = a op [hl]`
+  AluReadHl(AluOp),
+  /// `a = a op u8`, reading the `u8` after the op code.
+  AluImm(AluOp),
+  /// The rotates that only work on `a`, which always clear Z.
+  RotateA(CbOp),
+  Daa,
+  Cpl,
+  Scf,
+  Ccf,
+  /// Adds the imm low byte (as `i8`) to PC.
+  JumpRelative,
+  /// Sets PC to imm.
+  JumpImm,
+  JumpHl,
+  LdSpHl,
+  LdHlSpImm,
+  AddSpImm,
+  /// Decrements SP and writes the register's high byte there.
+  PushHigh(ActionRegister),
+  /// Decrements SP and writes the register's low byte there.
+  PushLow(ActionRegister),
+  /// Pushes PC's low byte and jumps to imm.
+  CallPushLow,
+  /// Pushes PC's low byte and jumps to the given address.
+  Rst(u8),
+  /// Reads the byte at SP into the imm low byte, and increments SP.
+  PopLow,
+  /// Reads the byte at SP into the imm high byte, increments SP, and sets the
+  /// register to imm.
+  PopHighTo(ActionRegister),
+  /// Enables interrupts after the next instruction.
+  EnableInterrupts,
+  /// Enables interrupts immediately (`reti`).
+  EnableInterruptsNow,
+  Halt,
+  /// Fetches the second op code byte and continues with [CB_ACTION_TABLE].
+  CbPrefix,
+  Cb(CbOp, ActionRegister),
+  /// `bit n, [hl]`
+  CbBitHl(u8),
+  /// Writes the result of the op on the imm low byte to `[hl]`.
+  CbWriteHl(CbOp),
+  /// Pushes PC's low byte and jumps to the highest priority pending
+  /// interrupt's handler.
+  InterruptPushLow,
+  /// Op codes that don't exist hang the CPU.
+  Illegal,
 }
 use CpuAction::*;
 
@@ -20,6 +103,16 @@ pub enum ActionRegister {
   A,
   PC,
   SP,
+  B,
+  C,
+  D,
+  E,
+  H,
+  L,
+  BC,
+  DE,
+  HL,
+  AF,
 }
 use ActionRegister::*;
 
@@ -31,279 +124,654 @@ pub enum ActionCond {
   NC,
   C,
 }
-use ActionCond::*;
+use ActionCond as Cond;
+
+/// An address held in a register pair, possibly adjusting `hl` afterwards.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ActionAddr {
+  BC,
+  DE,
+  #[default]
+  HL,
+  HLInc,
+  HLDec,
+}
+use ActionAddr as Addr;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum AluOp {
+  #[default]
+  Add,
+  Adc,
+  Sub,
+  Sbc,
+  And,
+  Xor,
+  Or,
+  Cp,
+}
+use AluOp::*;
 
+/// The rotates, shifts, and bit ops of the `$CB` prefixed op codes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum CbOp {
+  #[default]
+  Rlc,
+  Rrc,
+  Rl,
+  Rr,
+  Sla,
+  Sra,
+  Swap,
+  Srl,
+  Bit(u8),
+  Res(u8),
+  Set(u8),
+}
+use CbOp::*;
+
+/// The actions left in the op code that the CPU is running.
+///
+/// This is just a position within one of the `'static` action tables, so
+/// queueing up an op code is a pointer copy rather than a copy of its actions.
+#[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ActionQueue {
+  actions: &'static [CpuAction],
+  index: usize,
+}
+impl core::fmt::Debug for ActionQueue {
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    core::fmt::Debug::fmt(self.remaining(), f)
+  }
+}
+impl ActionQueue {
+  /// Makes a queue that will run all of `actions`.
+  #[inline]
+  #[must_use]
+  pub const fn new(actions: &'static [CpuAction]) -> Self {
+    Self { actions, index: 0 }
+  }
+
+  /// The actions that haven't run yet.
+  #[inline]
+  #[must_use]
+  pub fn remaining(&self) -> &'static [CpuAction] {
+    &self.actions[self.index..]
+  }
+
+  #[inline]
+  #[must_use]
+  pub const fn is_empty(&self) -> bool {
+    self.index >= self.actions.len()
+  }
+
+  /// Drops any actions that haven't run yet.
+  #[inline]
+  pub fn clear(&mut self) {
+    *self = Self::default();
+  }
+
+  /// Takes the next action.
+  #[inline]
+  pub fn pop_front(&mut self) -> Option<CpuAction> {
+    let action = self.actions.get(self.index).copied()?;
+    self.index += 1;
+    Some(action)
+  }
+}
+
+/// The actions of an interrupt dispatch, which happens in place of an op code.
+pub const INTERRUPT_ACTIONS: &[CpuAction] =
+  &[Internal, Internal, PushHigh(PC), InterruptPushLow, Internal];
+
+#[rustfmt::skip]
 pub const ACTION_TABLE: [&[CpuAction]; 256] = [
   //
   /* 0x00 */ &[Internal],
-  /* 0x01 */ &[],
-  /* 0x02 */ &[],
-  /* 0x03 */ &[],
-  /* 0x04 */ &[],
-  /* 0x05 */ &[],
-  /* 0x06 */ &[],
-  /* 0x07 */ &[],
-  /* 0x08 */ &[],
-  /* 0x09 */ &[],
-  /* 0x0A */ &[],
-  /* 0x0B */ &[],
-  /* 0x0C */ &[],
-  /* 0x0D */ &[],
-  /* 0x0E */ &[],
-  /* 0x0F */ &[],
-  //
-  /* 0x10 */ &[],
-  /* 0x11 */ &[],
-  /* 0x12 */ &[],
-  /* 0x13 */ &[],
-  /* 0x14 */ &[],
-  /* 0x15 */ &[],
-  /* 0x16 */ &[],
-  /* 0x17 */ &[],
-  /* 0x18 */ &[],
-  /* 0x19 */ &[],
-  /* 0x1A */ &[],
-  /* 0x1B */ &[],
-  /* 0x1C */ &[],
-  /* 0x1D */ &[],
-  /* 0x1E */ &[],
-  /* 0x1F */ &[],
-  //
-  /* 0x20 */ &[],
-  /* 0x21 */ &[],
-  /* 0x22 */ &[],
-  /* 0x23 */ &[],
-  /* 0x24 */ &[],
-  /* 0x25 */ &[],
-  /* 0x26 */ &[],
-  /* 0x27 */ &[],
-  /* 0x28 */ &[],
-  /* 0x29 */ &[],
-  /* 0x2A */ &[],
-  /* 0x2B */ &[],
-  /* 0x2C */ &[],
-  /* 0x2D */ &[],
-  /* 0x2E */ &[],
-  /* 0x2F */ &[],
-  //
-  /* 0x30 */ &[],
+  /* 0x01 */ &[Internal, ImmLow, ImmHighTo(BC)],
+  /* 0x02 */ &[Internal, WriteRegToAddr(Addr::BC, A)],
+  /* 0x03 */ &[IncR16(BC), Internal],
+  /* 0x04 */ &[IncR8(B)],
+  /* 0x05 */ &[DecR8(B)],
+  /* 0x06 */ &[Internal, ImmLowTo(B)],
+  /* 0x07 */ &[RotateA(Rlc)],
+  /* 0x08 */ &[Internal, ImmLow, ImmHigh, WriteSpLowToImm16, WriteSpHighToImm16],
+  /* 0x09 */ &[Internal, AddHl(BC)],
+  /* 0x0A */ &[Internal, ReadAddrTo(Addr::BC, A)],
+  /* 0x0B */ &[DecR16(BC), Internal],
+  /* 0x0C */ &[IncR8(C)],
+  /* 0x0D */ &[DecR8(C)],
+  /* 0x0E */ &[Internal, ImmLowTo(C)],
+  /* 0x0F */ &[RotateA(Rrc)],
+  //
+  /* 0x10 */ &[Stop],
+  /* 0x11 */ &[Internal, ImmLow, ImmHighTo(DE)],
+  /* 0x12 */ &[Internal, WriteRegToAddr(Addr::DE, A)],
+  /* 0x13 */ &[IncR16(DE), Internal],
+  /* 0x14 */ &[IncR8(D)],
+  /* 0x15 */ &[DecR8(D)],
+  /* 0x16 */ &[Internal, ImmLowTo(D)],
+  /* 0x17 */ &[RotateA(Rl)],
+  /* 0x18 */ &[Internal, ImmLow, JumpRelative],
+  /* 0x19 */ &[Internal, AddHl(DE)],
+  /* 0x1A */ &[Internal, ReadAddrTo(Addr::DE, A)],
+  /* 0x1B */ &[DecR16(DE), Internal],
+  /* 0x1C */ &[IncR8(E)],
+  /* 0x1D */ &[DecR8(E)],
+  /* 0x1E */ &[Internal, ImmLowTo(E)],
+  /* 0x1F */ &[RotateA(Rr)],
+  //
+  /* 0x20 */ &[Internal, ImmLowIf(Cond::NZ), JumpRelative],
+  /* 0x21 */ &[Internal, ImmLow, ImmHighTo(HL)],
+  /* 0x22 */ &[Internal, WriteRegToAddr(Addr::HLInc, A)],
+  /* 0x23 */ &[IncR16(HL), Internal],
+  /* 0x24 */ &[IncR8(H)],
+  /* 0x25 */ &[DecR8(H)],
+  /* 0x26 */ &[Internal, ImmLowTo(H)],
+  /* 0x27 */ &[Daa],
+  /* 0x28 */ &[Internal, ImmLowIf(Cond::Z), JumpRelative],
+  /* 0x29 */ &[Internal, AddHl(HL)],
+  /* 0x2A */ &[Internal, ReadAddrTo(Addr::HLInc, A)],
+  /* 0x2B */ &[DecR16(HL), Internal],
+  /* 0x2C */ &[IncR8(L)],
+  /* 0x2D */ &[DecR8(L)],
+  /* 0x2E */ &[Internal, ImmLowTo(L)],
+  /* 0x2F */ &[Cpl],
+  //
+  /* 0x30 */ &[Internal, ImmLowIf(Cond::NC), JumpRelative],
   /* 0x31 */ &[Internal, ImmLow, ImmHighTo(SP)],
-  /* 0x32 */ &[],
-  /* 0x33 */ &[],
-  /* 0x34 */ &[],
-  /* 0x35 */ &[],
-  /* 0x36 */ &[],
-  /* 0x37 */ &[],
-  /* 0x38 */ &[],
-  /* 0x39 */ &[],
-  /* 0x3A */ &[],
-  /* 0x3B */ &[],
-  /* 0x3C */ &[],
-  /* 0x3D */ &[],
+  /* 0x32 */ &[Internal, WriteRegToAddr(Addr::HLDec, A)],
+  /* 0x33 */ &[IncR16(SP), Internal],
+  /* 0x34 */ &[Internal, ReadHlToImm, WriteIncImmToHl],
+  /* 0x35 */ &[Internal, ReadHlToImm, WriteDecImmToHl],
+  /* 0x36 */ &[Internal, ImmLow, WriteImmToHl],
+  /* 0x37 */ &[Scf],
+  /* 0x38 */ &[Internal, ImmLowIf(Cond::C), JumpRelative],
+  /* 0x39 */ &[Internal, AddHl(SP)],
+  /* 0x3A */ &[Internal, ReadAddrTo(Addr::HLDec, A)],
+  /* 0x3B */ &[DecR16(SP), Internal],
+  /* 0x3C */ &[IncR8(A)],
+  /* 0x3D */ &[DecR8(A)],
   /* 0x3E */ &[Internal, ImmLowTo(A)],
-  /* 0x3F */ &[],
-  //
-  /* 0x40 */ &[],
-  /* 0x41 */ &[],
-  /* 0x42 */ &[],
-  /* 0x43 */ &[],
-  /* 0x44 */ &[],
-  /* 0x45 */ &[],
-  /* 0x46 */ &[],
-  /* 0x47 */ &[],
-  /* 0x48 */ &[],
-  /* 0x49 */ &[],
-  /* 0x4A */ &[],
-  /* 0x4B */ &[],
-  /* 0x4C */ &[],
-  /* 0x4D */ &[],
-  /* 0x4E */ &[],
-  /* 0x4F */ &[],
-  //
-  /* 0x50 */ &[],
-  /* 0x51 */ &[],
-  /* 0x52 */ &[],
-  /* 0x53 */ &[],
-  /* 0x54 */ &[],
-  /* 0x55 */ &[],
-  /* 0x56 */ &[],
-  /* 0x57 */ &[],
-  /* 0x58 */ &[],
-  /* 0x59 */ &[],
-  /* 0x5A */ &[],
-  /* 0x5B */ &[],
-  /* 0x5C */ &[],
-  /* 0x5D */ &[],
-  /* 0x5E */ &[],
-  /* 0x5F */ &[],
-  //
-  /* 0x60 */ &[],
-  /* 0x61 */ &[],
-  /* 0x62 */ &[],
-  /* 0x63 */ &[],
-  /* 0x64 */ &[],
-  /* 0x65 */ &[],
-  /* 0x66 */ &[],
-  /* 0x67 */ &[],
-  /* 0x68 */ &[],
-  /* 0x69 */ &[],
-  /* 0x6A */ &[],
-  /* 0x6B */ &[],
-  /* 0x6C */ &[],
-  /* 0x6D */ &[],
-  /* 0x6E */ &[],
-  /* 0x6F */ &[],
-  //
-  /* 0x70 */ &[],
-  /* 0x71 */ &[],
-  /* 0x72 */ &[],
-  /* 0x73 */ &[],
-  /* 0x74 */ &[],
-  /* 0x75 */ &[],
-  /* 0x76 */ &[],
-  /* 0x77 */ &[],
-  /* 0x78 */ &[],
-  /* 0x79 */ &[],
-  /* 0x7A */ &[],
-  /* 0x7B */ &[],
-  /* 0x7C */ &[],
-  /* 0x7D */ &[],
-  /* 0x7E */ &[],
-  /* 0x7F */ &[],
-  //
-  /* 0x80 */ &[],
-  /* 0x81 */ &[],
-  /* 0x82 */ &[],
-  /* 0x83 */ &[],
-  /* 0x84 */ &[],
-  /* 0x85 */ &[],
-  /* 0x86 */ &[],
-  /* 0x87 */ &[],
-  /* 0x88 */ &[],
-  /* 0x89 */ &[],
-  /* 0x8A */ &[],
-  /* 0x8B */ &[],
-  /* 0x8C */ &[],
-  /* 0x8D */ &[],
-  /* 0x8E */ &[],
-  /* 0x8F */ &[],
-  //
-  /* 0x90 */ &[],
-  /* 0x91 */ &[],
-  /* 0x92 */ &[],
-  /* 0x93 */ &[],
-  /* 0x94 */ &[],
-  /* 0x95 */ &[],
-  /* 0x96 */ &[],
-  /* 0x97 */ &[],
-  /* 0x98 */ &[],
-  /* 0x99 */ &[],
-  /* 0x9A */ &[],
-  /* 0x9B */ &[],
-  /* 0x9C */ &[],
-  /* 0x9D */ &[],
-  /* 0x9E */ &[],
-  /* 0x9F */ &[],
-  //
-  /* 0xA0 */ &[],
-  /* 0xA1 */ &[],
-  /* 0xA2 */ &[],
-  /* 0xA3 */ &[],
-  /* 0xA4 */ &[],
-  /* 0xA5 */ &[],
-  /* 0xA6 */ &[],
-  /* 0xA7 */ &[],
-  /* 0xA8 */ &[],
-  /* 0xA9 */ &[],
-  /* 0xAA */ &[],
-  /* 0xAB */ &[],
-  /* 0xAC */ &[],
-  /* 0xAD */ &[],
-  /* 0xAE */ &[],
-  /* 0xAF */ &[],
-  //
-  /* 0xB0 */ &[],
-  /* 0xB1 */ &[],
-  /* 0xB2 */ &[],
-  /* 0xB3 */ &[],
-  /* 0xB4 */ &[],
-  /* 0xB5 */ &[],
-  /* 0xB6 */ &[],
-  /* 0xB7 */ &[],
-  /* 0xB8 */ &[],
-  /* 0xB9 */ &[],
-  /* 0xBA */ &[],
-  /* 0xBB */ &[],
-  /* 0xBC */ &[],
-  /* 0xBD */ &[],
-  /* 0xBE */ &[],
-  /* 0xBF */ &[],
-  //
-  /* 0xC0 */ &[],
-  /* 0xC1 */ &[],
-  /* 0xC2 */ &[],
+  /* 0x3F */ &[Ccf],
+  //
+  /* 0x40 */ &[Ld(B, B)],
+  /* 0x41 */ &[Ld(B, C)],
+  /* 0x42 */ &[Ld(B, D)],
+  /* 0x43 */ &[Ld(B, E)],
+  /* 0x44 */ &[Ld(B, H)],
+  /* 0x45 */ &[Ld(B, L)],
+  /* 0x46 */ &[Internal, ReadAddrTo(Addr::HL, B)],
+  /* 0x47 */ &[Ld(B, A)],
+  /* 0x48 */ &[Ld(C, B)],
+  /* 0x49 */ &[Ld(C, C)],
+  /* 0x4A */ &[Ld(C, D)],
+  /* 0x4B */ &[Ld(C, E)],
+  /* 0x4C */ &[Ld(C, H)],
+  /* 0x4D */ &[Ld(C, L)],
+  /* 0x4E */ &[Internal, ReadAddrTo(Addr::HL, C)],
+  /* 0x4F */ &[Ld(C, A)],
+  //
+  /* 0x50 */ &[Ld(D, B)],
+  /* 0x51 */ &[Ld(D, C)],
+  /* 0x52 */ &[Ld(D, D)],
+  /* 0x53 */ &[Ld(D, E)],
+  /* 0x54 */ &[Ld(D, H)],
+  /* 0x55 */ &[Ld(D, L)],
+  /* 0x56 */ &[Internal, ReadAddrTo(Addr::HL, D)],
+  /* 0x57 */ &[Ld(D, A)],
+  /* 0x58 */ &[Ld(E, B)],
+  /* 0x59 */ &[Ld(E, C)],
+  /* 0x5A */ &[Ld(E, D)],
+  /* 0x5B */ &[Ld(E, E)],
+  /* 0x5C */ &[Ld(E, H)],
+  /* 0x5D */ &[Ld(E, L)],
+  /* 0x5E */ &[Internal, ReadAddrTo(Addr::HL, E)],
+  /* 0x5F */ &[Ld(E, A)],
+  //
+  /* 0x60 */ &[Ld(H, B)],
+  /* 0x61 */ &[Ld(H, C)],
+  /* 0x62 */ &[Ld(H, D)],
+  /* 0x63 */ &[Ld(H, E)],
+  /* 0x64 */ &[Ld(H, H)],
+  /* 0x65 */ &[Ld(H, L)],
+  /* 0x66 */ &[Internal, ReadAddrTo(Addr::HL, H)],
+  /* 0x67 */ &[Ld(H, A)],
+  /* 0x68 */ &[Ld(L, B)],
+  /* 0x69 */ &[Ld(L, C)],
+  /* 0x6A */ &[Ld(L, D)],
+  /* 0x6B */ &[Ld(L, E)],
+  /* 0x6C */ &[Ld(L, H)],
+  /* 0x6D */ &[Ld(L, L)],
+  /* 0x6E */ &[Internal, ReadAddrTo(Addr::HL, L)],
+  /* 0x6F */ &[Ld(L, A)],
+  //
+  /* 0x70 */ &[Internal, WriteRegToAddr(Addr::HL, B)],
+  /* 0x71 */ &[Internal, WriteRegToAddr(Addr::HL, C)],
+  /* 0x72 */ &[Internal, WriteRegToAddr(Addr::HL, D)],
+  /* 0x73 */ &[Internal, WriteRegToAddr(Addr::HL, E)],
+  /* 0x74 */ &[Internal, WriteRegToAddr(Addr::HL, H)],
+  /* 0x75 */ &[Internal, WriteRegToAddr(Addr::HL, L)],
+  /* 0x76 */ &[Halt],
+  /* 0x77 */ &[Internal, WriteRegToAddr(Addr::HL, A)],
+  /* 0x78 */ &[Ld(A, B)],
+  /* 0x79 */ &[Ld(A, C)],
+  /* 0x7A */ &[Ld(A, D)],
+  /* 0x7B */ &[Ld(A, E)],
+  /* 0x7C */ &[Ld(A, H)],
+  /* 0x7D */ &[Ld(A, L)],
+  /* 0x7E */ &[Internal, ReadAddrTo(Addr::HL, A)],
+  /* 0x7F */ &[Ld(A, A)],
+  //
+  /* 0x80 */ &[Alu(Add, B)],
+  /* 0x81 */ &[Alu(Add, C)],
+  /* 0x82 */ &[Alu(Add, D)],
+  /* 0x83 */ &[Alu(Add, E)],
+  /* 0x84 */ &[Alu(Add, H)],
+  /* 0x85 */ &[Alu(Add, L)],
+  /* 0x86 */ &[Internal, AluReadHl(Add)],
+  /* 0x87 */ &[Alu(Add, A)],
+  /* 0x88 */ &[Alu(Adc, B)],
+  /* 0x89 */ &[Alu(Adc, C)],
+  /* 0x8A */ &[Alu(Adc, D)],
+  /* 0x8B */ &[Alu(Adc, E)],
+  /* 0x8C */ &[Alu(Adc, H)],
+  /* 0x8D */ &[Alu(Adc, L)],
+  /* 0x8E */ &[Internal, AluReadHl(Adc)],
+  /* 0x8F */ &[Alu(Adc, A)],
+  //
+  /* 0x90 */ &[Alu(Sub, B)],
+  /* 0x91 */ &[Alu(Sub, C)],
+  /* 0x92 */ &[Alu(Sub, D)],
+  /* 0x93 */ &[Alu(Sub, E)],
+  /* 0x94 */ &[Alu(Sub, H)],
+  /* 0x95 */ &[Alu(Sub, L)],
+  /* 0x96 */ &[Internal, AluReadHl(Sub)],
+  /* 0x97 */ &[Alu(Sub, A)],
+  /* 0x98 */ &[Alu(Sbc, B)],
+  /* 0x99 */ &[Alu(Sbc, C)],
+  /* 0x9A */ &[Alu(Sbc, D)],
+  /* 0x9B */ &[Alu(Sbc, E)],
+  /* 0x9C */ &[Alu(Sbc, H)],
+  /* 0x9D */ &[Alu(Sbc, L)],
+  /* 0x9E */ &[Internal, AluReadHl(Sbc)],
+  /* 0x9F */ &[Alu(Sbc, A)],
+  //
+  /* 0xA0 */ &[Alu(And, B)],
+  /* 0xA1 */ &[Alu(And, C)],
+  /* 0xA2 */ &[Alu(And, D)],
+  /* 0xA3 */ &[Alu(And, E)],
+  /* 0xA4 */ &[Alu(And, H)],
+  /* 0xA5 */ &[Alu(And, L)],
+  /* 0xA6 */ &[Internal, AluReadHl(And)],
+  /* 0xA7 */ &[Alu(And, A)],
+  /* 0xA8 */ &[Alu(Xor, B)],
+  /* 0xA9 */ &[Alu(Xor, C)],
+  /* 0xAA */ &[Alu(Xor, D)],
+  /* 0xAB */ &[Alu(Xor, E)],
+  /* 0xAC */ &[Alu(Xor, H)],
+  /* 0xAD */ &[Alu(Xor, L)],
+  /* 0xAE */ &[Internal, AluReadHl(Xor)],
+  /* 0xAF */ &[Alu(Xor, A)],
+  //
+  /* 0xB0 */ &[Alu(Or, B)],
+  /* 0xB1 */ &[Alu(Or, C)],
+  /* 0xB2 */ &[Alu(Or, D)],
+  /* 0xB3 */ &[Alu(Or, E)],
+  /* 0xB4 */ &[Alu(Or, H)],
+  /* 0xB5 */ &[Alu(Or, L)],
+  /* 0xB6 */ &[Internal, AluReadHl(Or)],
+  /* 0xB7 */ &[Alu(Or, A)],
+  /* 0xB8 */ &[Alu(Cp, B)],
+  /* 0xB9 */ &[Alu(Cp, C)],
+  /* 0xBA */ &[Alu(Cp, D)],
+  /* 0xBB */ &[Alu(Cp, E)],
+  /* 0xBC */ &[Alu(Cp, H)],
+  /* 0xBD */ &[Alu(Cp, L)],
+  /* 0xBE */ &[Internal, AluReadHl(Cp)],
+  /* 0xBF */ &[Alu(Cp, A)],
+  //
+  /* 0xC0 */ &[Internal, InternalIf(Cond::NZ), PopLow, PopHighTo(PC), Internal],
+  /* 0xC1 */ &[Internal, PopLow, PopHighTo(BC)],
+  /* 0xC2 */ &[Internal, ImmLow, ImmHighIf(Cond::NZ), JumpImm],
   /* 0xC3 */ &[Internal, ImmLow, ImmHighTo(PC), Internal],
-  /* 0xC4 */ &[],
-  /* 0xC5 */ &[],
-  /* 0xC6 */ &[],
-  /* 0xC7 */ &[],
-  /* 0xC8 */ &[],
-  /* 0xC9 */ &[],
-  /* 0xCA */ &[],
-  /* 0xCB */ &[],
-  /* 0xCC */ &[],
-  /* 0xCD */ &[],
-  /* 0xCE */ &[],
-  /* 0xCF */ &[],
-  //
-  /* 0xD0 */ &[],
-  /* 0xD1 */ &[],
-  /* 0xD2 */ &[],
-  /* 0xD3 */ &[],
-  /* 0xD4 */ &[],
-  /* 0xD5 */ &[],
-  /* 0xD6 */ &[],
-  /* 0xD7 */ &[],
-  /* 0xD8 */ &[],
-  /* 0xD9 */ &[],
-  /* 0xDA */ &[],
-  /* 0xDB */ &[],
-  /* 0xDC */ &[],
-  /* 0xDD */ &[],
-  /* 0xDE */ &[],
-  /* 0xDF */ &[],
+  /* 0xC4 */ &[Internal, ImmLow, ImmHighIf(Cond::NZ), Internal, PushHigh(PC), CallPushLow],
+  /* 0xC5 */ &[Internal, Internal, PushHigh(BC), PushLow(BC)],
+  /* 0xC6 */ &[Internal, AluImm(Add)],
+  /* 0xC7 */ &[Internal, Internal, PushHigh(PC), Rst(0x00)],
+  /* 0xC8 */ &[Internal, InternalIf(Cond::Z), PopLow, PopHighTo(PC), Internal],
+  /* 0xC9 */ &[Internal, PopLow, PopHighTo(PC), Internal],
+  /* 0xCA */ &[Internal, ImmLow, ImmHighIf(Cond::Z), JumpImm],
+  /* 0xCB */ &[Internal, CbPrefix],
+  /* 0xCC */ &[Internal, ImmLow, ImmHighIf(Cond::Z), Internal, PushHigh(PC), CallPushLow],
+  /* 0xCD */ &[Internal, ImmLow, ImmHigh, Internal, PushHigh(PC), CallPushLow],
+  /* 0xCE */ &[Internal, AluImm(Adc)],
+  /* 0xCF */ &[Internal, Internal, PushHigh(PC), Rst(0x08)],
+  //
+  /* 0xD0 */ &[Internal, InternalIf(Cond::NC), PopLow, PopHighTo(PC), Internal],
+  /* 0xD1 */ &[Internal, PopLow, PopHighTo(DE)],
+  /* 0xD2 */ &[Internal, ImmLow, ImmHighIf(Cond::NC), JumpImm],
+  /* 0xD3 */ &[Illegal],
+  /* 0xD4 */ &[Internal, ImmLow, ImmHighIf(Cond::NC), Internal, PushHigh(PC), CallPushLow],
+  /* 0xD5 */ &[Internal, Internal, PushHigh(DE), PushLow(DE)],
+  /* 0xD6 */ &[Internal, AluImm(Sub)],
+  /* 0xD7 */ &[Internal, Internal, PushHigh(PC), Rst(0x10)],
+  /* 0xD8 */ &[Internal, InternalIf(Cond::C), PopLow, PopHighTo(PC), Internal],
+  /* 0xD9 */ &[Internal, PopLow, PopHighTo(PC), EnableInterruptsNow],
+  /* 0xDA */ &[Internal, ImmLow, ImmHighIf(Cond::C), JumpImm],
+  /* 0xDB */ &[Illegal],
+  /* 0xDC */ &[Internal, ImmLow, ImmHighIf(Cond::C), Internal, PushHigh(PC), CallPushLow],
+  /* 0xDD */ &[Illegal],
+  /* 0xDE */ &[Internal, AluImm(Sbc)],
+  /* 0xDF */ &[Internal, Internal, PushHigh(PC), Rst(0x18)],
   //
   /* 0xE0 */ &[Internal, ImmLow, WriteRegToHalfAddr(A)],
-  /* 0xE1 */ &[],
-  /* 0xE2 */ &[],
-  /* 0xE3 */ &[],
-  /* 0xE4 */ &[],
-  /* 0xE5 */ &[],
-  /* 0xE6 */ &[],
-  /* 0xE7 */ &[],
-  /* 0xE8 */ &[],
-  /* 0xE9 */ &[],
+  /* 0xE1 */ &[Internal, PopLow, PopHighTo(HL)],
+  /* 0xE2 */ &[Internal, WriteRegToHalfC(A)],
+  /* 0xE3 */ &[Illegal],
+  /* 0xE4 */ &[Illegal],
+  /* 0xE5 */ &[Internal, Internal, PushHigh(HL), PushLow(HL)],
+  /* 0xE6 */ &[Internal, AluImm(And)],
+  /* 0xE7 */ &[Internal, Internal, PushHigh(PC), Rst(0x20)],
+  /* 0xE8 */ &[Internal, ImmLow, Internal, AddSpImm],
+  /* 0xE9 */ &[JumpHl],
   /* 0xEA */ &[Internal, ImmLow, ImmHigh, WriteRegToImm16(A)],
-  /* 0xEB */ &[],
-  /* 0xEC */ &[],
-  /* 0xED */ &[],
-  /* 0xEE */ &[],
-  /* 0xEF */ &[],
-  //
-  /* 0xF0 */ &[],
-  /* 0xF1 */ &[],
-  /* 0xF2 */ &[],
+  /* 0xEB */ &[Illegal],
+  /* 0xEC */ &[Illegal],
+  /* 0xED */ &[Illegal],
+  /* 0xEE */ &[Internal, AluImm(Xor)],
+  /* 0xEF */ &[Internal, Internal, PushHigh(PC), Rst(0x28)],
+  //
+  /* 0xF0 */ &[Internal, ImmLow, ReadHalfAddrTo(A)],
+  /* 0xF1 */ &[Internal, PopLow, PopHighTo(AF)],
+  /* 0xF2 */ &[Internal, ReadHalfCTo(A)],
   /* 0xF3 */ &[DisableInterrupts],
-  /* 0xF4 */ &[],
-  /* 0xF5 */ &[],
-  /* 0xF6 */ &[],
-  /* 0xF7 */ &[],
-  /* 0xF8 */ &[],
-  /* 0xF9 */ &[],
-  /* 0xFA */ &[],
-  /* 0xFB */ &[],
-  /* 0xFC */ &[],
-  /* 0xFD */ &[],
-  /* 0xFE */ &[],
-  /* 0xFF */ &[],
-];
+  /* 0xF4 */ &[Illegal],
+  /* 0xF5 */ &[Internal, Internal, PushHigh(AF), PushLow(AF)],
+  /* 0xF6 */ &[Internal, AluImm(Or)],
+  /* 0xF7 */ &[Internal, Internal, PushHigh(PC), Rst(0x30)],
+  /* 0xF8 */ &[Internal, ImmLow, LdHlSpImm],
+  /* 0xF9 */ &[Internal, LdSpHl],
+  /* 0xFA */ &[Internal, ImmLow, ImmHigh, ReadImm16To(A)],
+  /* 0xFB */ &[EnableInterrupts],
+  /* 0xFC */ &[Illegal],
+  /* 0xFD */ &[Illegal],
+  /* 0xFE */ &[Internal, AluImm(Cp)],
+  /* 0xFF */ &[Internal, Internal, PushHigh(PC), Rst(0x38)],];
+
+/// Actions for the op code after a `$CB` prefix. The first action happens
+/// during the M-cycle that fetches the second byte.
+#[rustfmt::skip]
+pub const CB_ACTION_TABLE: [&[CpuAction]; 256] = [
+  //
+  /* 0x00 */ &[Cb(Rlc, B)],
+  /* 0x01 */ &[Cb(Rlc, C)],
+  /* 0x02 */ &[Cb(Rlc, D)],
+  /* 0x03 */ &[Cb(Rlc, E)],
+  /* 0x04 */ &[Cb(Rlc, H)],
+  /* 0x05 */ &[Cb(Rlc, L)],
+  /* 0x06 */ &[Internal, ReadHlToImm, CbWriteHl(Rlc)],
+  /* 0x07 */ &[Cb(Rlc, A)],
+  /* 0x08 */ &[Cb(Rrc, B)],
+  /* 0x09 */ &[Cb(Rrc, C)],
+  /* 0x0A */ &[Cb(Rrc, D)],
+  /* 0x0B */ &[Cb(Rrc, E)],
+  /* 0x0C */ &[Cb(Rrc, H)],
+  /* 0x0D */ &[Cb(Rrc, L)],
+  /* 0x0E */ &[Internal, ReadHlToImm, CbWriteHl(Rrc)],
+  /* 0x0F */ &[Cb(Rrc, A)],
+  //
+  /* 0x10 */ &[Cb(Rl, B)],
+  /* 0x11 */ &[Cb(Rl, C)],
+  /* 0x12 */ &[Cb(Rl, D)],
+  /* 0x13 */ &[Cb(Rl, E)],
+  /* 0x14 */ &[Cb(Rl, H)],
+  /* 0x15 */ &[Cb(Rl, L)],
+  /* 0x16 */ &[Internal, ReadHlToImm, CbWriteHl(Rl)],
+  /* 0x17 */ &[Cb(Rl, A)],
+  /* 0x18 */ &[Cb(Rr, B)],
+  /* 0x19 */ &[Cb(Rr, C)],
+  /* 0x1A */ &[Cb(Rr, D)],
+  /* 0x1B */ &[Cb(Rr, E)],
+  /* 0x1C */ &[Cb(Rr, H)],
+  /* 0x1D */ &[Cb(Rr, L)],
+  /* 0x1E */ &[Internal, ReadHlToImm, CbWriteHl(Rr)],
+  /* 0x1F */ &[Cb(Rr, A)],
+  //
+  /* 0x20 */ &[Cb(Sla, B)],
+  /* 0x21 */ &[Cb(Sla, C)],
+  /* 0x22 */ &[Cb(Sla, D)],
+  /* 0x23 */ &[Cb(Sla, E)],
+  /* 0x24 */ &[Cb(Sla, H)],
+  /* 0x25 */ &[Cb(Sla, L)],
+  /* 0x26 */ &[Internal, ReadHlToImm, CbWriteHl(Sla)],
+  /* 0x27 */ &[Cb(Sla, A)],
+  /* 0x28 */ &[Cb(Sra, B)],
+  /* 0x29 */ &[Cb(Sra, C)],
+  /* 0x2A */ &[Cb(Sra, D)],
+  /* 0x2B */ &[Cb(Sra, E)],
+  /* 0x2C */ &[Cb(Sra, H)],
+  /* 0x2D */ &[Cb(Sra, L)],
+  /* 0x2E */ &[Internal, ReadHlToImm, CbWriteHl(Sra)],
+  /* 0x2F */ &[Cb(Sra, A)],
+  //
+  /* 0x30 */ &[Cb(Swap, B)],
+  /* 0x31 */ &[Cb(Swap, C)],
+  /* 0x32 */ &[Cb(Swap, D)],
+  /* 0x33 */ &[Cb(Swap, E)],
+  /* 0x34 */ &[Cb(Swap, H)],
+  /* 0x35 */ &[Cb(Swap, L)],
+  /* 0x36 */ &[Internal, ReadHlToImm, CbWriteHl(Swap)],
+  /* 0x37 */ &[Cb(Swap, A)],
+  /* 0x38 */ &[Cb(Srl, B)],
+  /* 0x39 */ &[Cb(Srl, C)],
+  /* 0x3A */ &[Cb(Srl, D)],
+  /* 0x3B */ &[Cb(Srl, E)],
+  /* 0x3C */ &[Cb(Srl, H)],
+  /* 0x3D */ &[Cb(Srl, L)],
+  /* 0x3E */ &[Internal, ReadHlToImm, CbWriteHl(Srl)],
+  /* 0x3F */ &[Cb(Srl, A)],
+  //
+  /* 0x40 */ &[Cb(Bit(0), B)],
+  /* 0x41 */ &[Cb(Bit(0), C)],
+  /* 0x42 */ &[Cb(Bit(0), D)],
+  /* 0x43 */ &[Cb(Bit(0), E)],
+  /* 0x44 */ &[Cb(Bit(0), H)],
+  /* 0x45 */ &[Cb(Bit(0), L)],
+  /* 0x46 */ &[Internal, CbBitHl(0)],
+  /* 0x47 */ &[Cb(Bit(0), A)],
+  /* 0x48 */ &[Cb(Bit(1), B)],
+  /* 0x49 */ &[Cb(Bit(1), C)],
+  /* 0x4A */ &[Cb(Bit(1), D)],
+  /* 0x4B */ &[Cb(Bit(1), E)],
+  /* 0x4C */ &[Cb(Bit(1), H)],
+  /* 0x4D */ &[Cb(Bit(1), L)],
+  /* 0x4E */ &[Internal, CbBitHl(1)],
+  /* 0x4F */ &[Cb(Bit(1), A)],
+  //
+  /* 0x50 */ &[Cb(Bit(2), B)],
+  /* 0x51 */ &[Cb(Bit(2), C)],
+  /* 0x52 */ &[Cb(Bit(2), D)],
+  /* 0x53 */ &[Cb(Bit(2), E)],
+  /* 0x54 */ &[Cb(Bit(2), H)],
+  /* 0x55 */ &[Cb(Bit(2), L)],
+  /* 0x56 */ &[Internal, CbBitHl(2)],
+  /* 0x57 */ &[Cb(Bit(2), A)],
+  /* 0x58 */ &[Cb(Bit(3), B)],
+  /* 0x59 */ &[Cb(Bit(3), C)],
+  /* 0x5A */ &[Cb(Bit(3), D)],
+  /* 0x5B */ &[Cb(Bit(3), E)],
+  /* 0x5C */ &[Cb(Bit(3), H)],
+  /* 0x5D */ &[Cb(Bit(3), L)],
+  /* 0x5E */ &[Internal, CbBitHl(3)],
+  /* 0x5F */ &[Cb(Bit(3), A)],
+  //
+  /* 0x60 */ &[Cb(Bit(4), B)],
+  /* 0x61 */ &[Cb(Bit(4), C)],
+  /* 0x62 */ &[Cb(Bit(4), D)],
+  /* 0x63 */ &[Cb(Bit(4), E)],
+  /* 0x64 */ &[Cb(Bit(4), H)],
+  /* 0x65 */ &[Cb(Bit(4), L)],
+  /* 0x66 */ &[Internal, CbBitHl(4)],
+  /* 0x67 */ &[Cb(Bit(4), A)],
+  /* 0x68 */ &[Cb(Bit(5), B)],
+  /* 0x69 */ &[Cb(Bit(5), C)],
+  /* 0x6A */ &[Cb(Bit(5), D)],
+  /* 0x6B */ &[Cb(Bit(5), E)],
+  /* 0x6C */ &[Cb(Bit(5), H)],
+  /* 0x6D */ &[Cb(Bit(5), L)],
+  /* 0x6E */ &[Internal, CbBitHl(5)],
+  /* 0x6F */ &[Cb(Bit(5), A)],
+  //
+  /* 0x70 */ &[Cb(Bit(6), B)],
+  /* 0x71 */ &[Cb(Bit(6), C)],
+  /* 0x72 */ &[Cb(Bit(6), D)],
+  /* 0x73 */ &[Cb(Bit(6), E)],
+  /* 0x74 */ &[Cb(Bit(6), H)],
+  /* 0x75 */ &[Cb(Bit(6), L)],
+  /* 0x76 */ &[Internal, CbBitHl(6)],
+  /* 0x77 */ &[Cb(Bit(6), A)],
+  /* 0x78 */ &[Cb(Bit(7), B)],
+  /* 0x79 */ &[Cb(Bit(7), C)],
+  /* 0x7A */ &[Cb(Bit(7), D)],
+  /* 0x7B */ &[Cb(Bit(7), E)],
+  /* 0x7C */ &[Cb(Bit(7), H)],
+  /* 0x7D */ &[Cb(Bit(7), L)],
+  /* 0x7E */ &[Internal, CbBitHl(7)],
+  /* 0x7F */ &[Cb(Bit(7), A)],
+  //
+  /* 0x80 */ &[Cb(Res(0), B)],
+  /* 0x81 */ &[Cb(Res(0), C)],
+  /* 0x82 */ &[Cb(Res(0), D)],
+  /* 0x83 */ &[Cb(Res(0), E)],
+  /* 0x84 */ &[Cb(Res(0), H)],
+  /* 0x85 */ &[Cb(Res(0), L)],
+  /* 0x86 */ &[Internal, ReadHlToImm, CbWriteHl(Res(0))],
+  /* 0x87 */ &[Cb(Res(0), A)],
+  /* 0x88 */ &[Cb(Res(1), B)],
+  /* 0x89 */ &[Cb(Res(1), C)],
+  /* 0x8A */ &[Cb(Res(1), D)],
+  /* 0x8B */ &[Cb(Res(1), E)],
+  /* 0x8C */ &[Cb(Res(1), H)],
+  /* 0x8D */ &[Cb(Res(1), L)],
+  /* 0x8E */ &[Internal, ReadHlToImm, CbWriteHl(Res(1))],
+  /* 0x8F */ &[Cb(Res(1), A)],
+  //
+  /* 0x90 */ &[Cb(Res(2), B)],
+  /* 0x91 */ &[Cb(Res(2), C)],
+  /* 0x92 */ &[Cb(Res(2), D)],
+  /* 0x93 */ &[Cb(Res(2), E)],
+  /* 0x94 */ &[Cb(Res(2), H)],
+  /* 0x95 */ &[Cb(Res(2), L)],
+  /* 0x96 */ &[Internal, ReadHlToImm, CbWriteHl(Res(2))],
+  /* 0x97 */ &[Cb(Res(2), A)],
+  /* 0x98 */ &[Cb(Res(3), B)],
+  /* 0x99 */ &[Cb(Res(3), C)],
+  /* 0x9A */ &[Cb(Res(3), D)],
+  /* 0x9B */ &[Cb(Res(3), E)],
+  /* 0x9C */ &[Cb(Res(3), H)],
+  /* 0x9D */ &[Cb(Res(3), L)],
+  /* 0x9E */ &[Internal, ReadHlToImm, CbWriteHl(Res(3))],
+  /* 0x9F */ &[Cb(Res(3), A)],
+  //
+  /* 0xA0 */ &[Cb(Res(4), B)],
+  /* 0xA1 */ &[Cb(Res(4), C)],
+  /* 0xA2 */ &[Cb(Res(4), D)],
+  /* 0xA3 */ &[Cb(Res(4), E)],
+  /* 0xA4 */ &[Cb(Res(4), H)],
+  /* 0xA5 */ &[Cb(Res(4), L)],
+  /* 0xA6 */ &[Internal, ReadHlToImm, CbWriteHl(Res(4))],
+  /* 0xA7 */ &[Cb(Res(4), A)],
+  /* 0xA8 */ &[Cb(Res(5), B)],
+  /* 0xA9 */ &[Cb(Res(5), C)],
+  /* 0xAA */ &[Cb(Res(5), D)],
+  /* 0xAB */ &[Cb(Res(5), E)],
+  /* 0xAC */ &[Cb(Res(5), H)],
+  /* 0xAD */ &[Cb(Res(5), L)],
+  /* 0xAE */ &[Internal, ReadHlToImm, CbWriteHl(Res(5))],
+  /* 0xAF */ &[Cb(Res(5), A)],
+  //
+  /* 0xB0 */ &[Cb(Res(6), B)],
+  /* 0xB1 */ &[Cb(Res(6), C)],
+  /* 0xB2 */ &[Cb(Res(6), D)],
+  /* 0xB3 */ &[Cb(Res(6), E)],
+  /* 0xB4 */ &[Cb(Res(6), H)],
+  /* 0xB5 */ &[Cb(Res(6), L)],
+  /* 0xB6 */ &[Internal, ReadHlToImm, CbWriteHl(Res(6))],
+  /* 0xB7 */ &[Cb(Res(6), A)],
+  /* 0xB8 */ &[Cb(Res(7), B)],
+  /* 0xB9 */ &[Cb(Res(7), C)],
+  /* 0xBA */ &[Cb(Res(7), D)],
+  /* 0xBB */ &[Cb(Res(7), E)],
+  /* 0xBC */ &[Cb(Res(7), H)],
+  /* 0xBD */ &[Cb(Res(7), L)],
+  /* 0xBE */ &[Internal, ReadHlToImm, CbWriteHl(Res(7))],
+  /* 0xBF */ &[Cb(Res(7), A)],
+  //
+  /* 0xC0 */ &[Cb(Set(0), B)],
+  /* 0xC1 */ &[Cb(Set(0), C)],
+  /* 0xC2 */ &[Cb(Set(0), D)],
+  /* 0xC3 */ &[Cb(Set(0), E)],
+  /* 0xC4 */ &[Cb(Set(0), H)],
+  /* 0xC5 */ &[Cb(Set(0), L)],
+  /* 0xC6 */ &[Internal, ReadHlToImm, CbWriteHl(Set(0))],
+  /* 0xC7 */ &[Cb(Set(0), A)],
+  /* 0xC8 */ &[Cb(Set(1), B)],
+  /* 0xC9 */ &[Cb(Set(1), C)],
+  /* 0xCA */ &[Cb(Set(1), D)],
+  /* 0xCB */ &[Cb(Set(1), E)],
+  /* 0xCC */ &[Cb(Set(1), H)],
+  /* 0xCD */ &[Cb(Set(1), L)],
+  /* 0xCE */ &[Internal, ReadHlToImm, CbWriteHl(Set(1))],
+  /* 0xCF */ &[Cb(Set(1), A)],
+  //
+  /* 0xD0 */ &[Cb(Set(2), B)],
+  /* 0xD1 */ &[Cb(Set(2), C)],
+  /* 0xD2 */ &[Cb(Set(2), D)],
+  /* 0xD3 */ &[Cb(Set(2), E)],
+  /* 0xD4 */ &[Cb(Set(2), H)],
+  /* 0xD5 */ &[Cb(Set(2), L)],
+  /* 0xD6 */ &[Internal, ReadHlToImm, CbWriteHl(Set(2))],
+  /* 0xD7 */ &[Cb(Set(2), A)],
+  /* 0xD8 */ &[Cb(Set(3), B)],
+  /* 0xD9 */ &[Cb(Set(3), C)],
+  /* 0xDA */ &[Cb(Set(3), D)],
+  /* 0xDB */ &[Cb(Set(3), E)],
+  /* 0xDC */ &[Cb(Set(3), H)],
+  /* 0xDD */ &[Cb(Set(3), L)],
+  /* 0xDE */ &[Internal, ReadHlToImm, CbWriteHl(Set(3))],
+  /* 0xDF */ &[Cb(Set(3), A)],
+  //
+  /* 0xE0 */ &[Cb(Set(4), B)],
+  /* 0xE1 */ &[Cb(Set(4), C)],
+  /* 0xE2 */ &[Cb(Set(4), D)],
+  /* 0xE3 */ &[Cb(Set(4), E)],
+  /* 0xE4 */ &[Cb(Set(4), H)],
+  /* 0xE5 */ &[Cb(Set(4), L)],
+  /* 0xE6 */ &[Internal, ReadHlToImm, CbWriteHl(Set(4))],
+  /* 0xE7 */ &[Cb(Set(4), A)],
+  /* 0xE8 */ &[Cb(Set(5), B)],
+  /* 0xE9 */ &[Cb(Set(5), C)],
+  /* 0xEA */ &[Cb(Set(5), D)],
+  /* 0xEB */ &[Cb(Set(5), E)],
+  /* 0xEC */ &[Cb(Set(5), H)],
+  /* 0xED */ &[Cb(Set(5), L)],
+  /* 0xEE */ &[Internal, ReadHlToImm, CbWriteHl(Set(5))],
+  /* 0xEF */ &[Cb(Set(5), A)],
+  //
+  /* 0xF0 */ &[Cb(Set(6), B)],
+  /* 0xF1 */ &[Cb(Set(6), C)],
+  /* 0xF2 */ &[Cb(Set(6), D)],
+  /* 0xF3 */ &[Cb(Set(6), E)],
+  /* 0xF4 */ &[Cb(Set(6), H)],
+  /* 0xF5 */ &[Cb(Set(6), L)],
+  /* 0xF6 */ &[Internal, ReadHlToImm, CbWriteHl(Set(6))],
+  /* 0xF7 */ &[Cb(Set(6), A)],
+  /* 0xF8 */ &[Cb(Set(7), B)],
+  /* 0xF9 */ &[Cb(Set(7), C)],
+  /* 0xFA */ &[Cb(Set(7), D)],
+  /* 0xFB */ &[Cb(Set(7), E)],
+  /* 0xFC */ &[Cb(Set(7), H)],
+  /* 0xFD */ &[Cb(Set(7), L)],
+  /* 0xFE */ &[Internal, ReadHlToImm, CbWriteHl(Set(7))],
+  /* 0xFF */ &[Cb(Set(7), A)],];
@@ -2,274 +2,551 @@
 pub const DISASSEMBLY_TABLE: [&str; 256] = [
   //
   /* 0x00 */ "nop",
-  /* 0x01 */ "$01",
-  /* 0x02 */ "$02",
-  /* 0x03 */ "$03",
-  /* 0x04 */ "$04",
-  /* 0x05 */ "$05",
-  /* 0x06 */ "$06",
-  /* 0x07 */ "$07",
-  /* 0x08 */ "$08",
-  /* 0x09 */ "$09",
-  /* 0x0A */ "$0A",
-  /* 0x0B */ "$0B",
-  /* 0x0C */ "$0C",
-  /* 0x0D */ "$0D",
-  /* 0x0E */ "$0E",
-  /* 0x0F */ "$0F",
-  //
-  /* 0x10 */ "$10",
-  /* 0x11 */ "$11",
-  /* 0x12 */ "$12",
-  /* 0x13 */ "$13",
-  /* 0x14 */ "$14",
-  /* 0x15 */ "$15",
-  /* 0x16 */ "$16",
-  /* 0x17 */ "$17",
-  /* 0x18 */ "$18",
-  /* 0x19 */ "$19",
-  /* 0x1A */ "$1A",
-  /* 0x1B */ "$1B",
-  /* 0x1C */ "$1C",
-  /* 0x1D */ "$1D",
-  /* 0x1E */ "$1E",
-  /* 0x1F */ "$1F",
-  //
-  /* 0x20 */ "$20",
-  /* 0x21 */ "$21",
-  /* 0x22 */ "$22",
-  /* 0x23 */ "$23",
-  /* 0x24 */ "$24",
-  /* 0x25 */ "$25",
-  /* 0x26 */ "$26",
-  /* 0x27 */ "$27",
-  /* 0x28 */ "$28",
-  /* 0x29 */ "$29",
-  /* 0x2A */ "$2A",
-  /* 0x2B */ "$2B",
-  /* 0x2C */ "$2C",
-  /* 0x2D */ "$2D",
-  /* 0x2E */ "$2E",
-  /* 0x2F */ "$2F",
-  //
-  /* 0x30 */ "$30",
+  /* 0x01 */ "ld bc, u16",
+  /* 0x02 */ "ld [bc], a",
+  /* 0x03 */ "inc bc",
+  /* 0x04 */ "inc b",
+  /* 0x05 */ "dec b",
+  /* 0x06 */ "ld b, u8",
+  /* 0x07 */ "rlca",
+  /* 0x08 */ "ld [u16], sp",
+  /* 0x09 */ "add hl, bc",
+  /* 0x0A */ "ld a, [bc]",
+  /* 0x0B */ "dec bc",
+  /* 0x0C */ "inc c",
+  /* 0x0D */ "dec c",
+  /* 0x0E */ "ld c, u8",
+  /* 0x0F */ "rrca",
+  //
+  /* 0x10 */ "stop",
+  /* 0x11 */ "ld de, u16",
+  /* 0x12 */ "ld [de], a",
+  /* 0x13 */ "inc de",
+  /* 0x14 */ "inc d",
+  /* 0x15 */ "dec d",
+  /* 0x16 */ "ld d, u8",
+  /* 0x17 */ "rla",
+  /* 0x18 */ "jr i8",
+  /* 0x19 */ "add hl, de",
+  /* 0x1A */ "ld a, [de]",
+  /* 0x1B */ "dec de",
+  /* 0x1C */ "inc e",
+  /* 0x1D */ "dec e",
+  /* 0x1E */ "ld e, u8",
+  /* 0x1F */ "rra",
+  //
+  /* 0x20 */ "jr nz, i8",
+  /* 0x21 */ "ld hl, u16",
+  /* 0x22 */ "ld [hl+], a",
+  /* 0x23 */ "inc hl",
+  /* 0x24 */ "inc h",
+  /* 0x25 */ "dec h",
+  /* 0x26 */ "ld h, u8",
+  /* 0x27 */ "daa",
+  /* 0x28 */ "jr z, i8",
+  /* 0x29 */ "add hl, hl",
+  /* 0x2A */ "ld a, [hl+]",
+  /* 0x2B */ "dec hl",
+  /* 0x2C */ "inc l",
+  /* 0x2D */ "dec l",
+  /* 0x2E */ "ld l, u8",
+  /* 0x2F */ "cpl",
+  //
+  /* 0x30 */ "jr nc, i8",
   /* 0x31 */ "ld sp, u16",
-  /* 0x32 */ "$32",
-  /* 0x33 */ "$33",
-  /* 0x34 */ "$34",
-  /* 0x35 */ "$35",
-  /* 0x36 */ "$36",
-  /* 0x37 */ "$37",
-  /* 0x38 */ "$38",
-  /* 0x39 */ "$39",
-  /* 0x3A */ "$3A",
-  /* 0x3B */ "$3B",
-  /* 0x3C */ "$3C",
-  /* 0x3D */ "$3D",
+  /* 0x32 */ "ld [hl-], a",
+  /* 0x33 */ "inc sp",
+  /* 0x34 */ "inc [hl]",
+  /* 0x35 */ "dec [hl]",
+  /* 0x36 */ "ld [hl], u8",
+  /* 0x37 */ "scf",
+  /* 0x38 */ "jr c, i8",
+  /* 0x39 */ "add hl, sp",
+  /* 0x3A */ "ld a, [hl-]",
+  /* 0x3B */ "dec sp",
+  /* 0x3C */ "inc a",
+  /* 0x3D */ "dec a",
   /* 0x3E */ "ld a, u8",
-  /* 0x3F */ "$3F",
-  //
-  /* 0x40 */ "$40",
-  /* 0x41 */ "$41",
-  /* 0x42 */ "$42",
-  /* 0x43 */ "$43",
-  /* 0x44 */ "$44",
-  /* 0x45 */ "$45",
-  /* 0x46 */ "$46",
-  /* 0x47 */ "$47",
-  /* 0x48 */ "$48",
-  /* 0x49 */ "$49",
-  /* 0x4A */ "$4A",
-  /* 0x4B */ "$4B",
-  /* 0x4C */ "$4C",
-  /* 0x4D */ "$4D",
-  /* 0x4E */ "$4E",
-  /* 0x4F */ "$4F",
-  //
-  /* 0x50 */ "$50",
-  /* 0x51 */ "$51",
-  /* 0x52 */ "$52",
-  /* 0x53 */ "$53",
-  /* 0x54 */ "$54",
-  /* 0x55 */ "$55",
-  /* 0x56 */ "$56",
-  /* 0x57 */ "$57",
-  /* 0x58 */ "$58",
-  /* 0x59 */ "$59",
-  /* 0x5A */ "$5A",
-  /* 0x5B */ "$5B",
-  /* 0x5C */ "$5C",
-  /* 0x5D */ "$5D",
-  /* 0x5E */ "$5E",
-  /* 0x5F */ "$5F",
-  //
-  /* 0x60 */ "$60",
-  /* 0x61 */ "$61",
-  /* 0x62 */ "$62",
-  /* 0x63 */ "$63",
-  /* 0x64 */ "$64",
-  /* 0x65 */ "$65",
-  /* 0x66 */ "$66",
-  /* 0x67 */ "$67",
-  /* 0x68 */ "$68",
-  /* 0x69 */ "$69",
-  /* 0x6A */ "$6A",
-  /* 0x6B */ "$6B",
-  /* 0x6C */ "$6C",
-  /* 0x6D */ "$6D",
-  /* 0x6E */ "$6E",
-  /* 0x6F */ "$6F",
-  //
-  /* 0x70 */ "$70",
-  /* 0x71 */ "$71",
-  /* 0x72 */ "$72",
-  /* 0x73 */ "$73",
-  /* 0x74 */ "$74",
-  /* 0x75 */ "$75",
-  /* 0x76 */ "$76",
-  /* 0x77 */ "$77",
-  /* 0x78 */ "$78",
-  /* 0x79 */ "$79",
-  /* 0x7A */ "$7A",
-  /* 0x7B */ "$7B",
-  /* 0x7C */ "$7C",
-  /* 0x7D */ "$7D",
-  /* 0x7E */ "$7E",
-  /* 0x7F */ "$7F",
-  //
-  /* 0x80 */ "$80",
-  /* 0x81 */ "$81",
-  /* 0x82 */ "$82",
-  /* 0x83 */ "$83",
-  /* 0x84 */ "$84",
-  /* 0x85 */ "$85",
-  /* 0x86 */ "$86",
-  /* 0x87 */ "$87",
-  /* 0x88 */ "$88",
-  /* 0x89 */ "$89",
-  /* 0x8A */ "$8A",
-  /* 0x8B */ "$8B",
-  /* 0x8C */ "$8C",
-  /* 0x8D */ "$8D",
-  /* 0x8E */ "$8E",
-  /* 0x8F */ "$8F",
-  //
-  /* 0x90 */ "$90",
-  /* 0x91 */ "$91",
-  /* 0x92 */ "$92",
-  /* 0x93 */ "$93",
-  /* 0x94 */ "$94",
-  /* 0x95 */ "$95",
-  /* 0x96 */ "$96",
-  /* 0x97 */ "$97",
-  /* 0x98 */ "$98",
-  /* 0x99 */ "$99",
-  /* 0x9A */ "$9A",
-  /* 0x9B */ "$9B",
-  /* 0x9C */ "$9C",
-  /* 0x9D */ "$9D",
-  /* 0x9E */ "$9E",
-  /* 0x9F */ "$9F",
-  //
-  /* 0xA0 */ "$A0",
-  /* 0xA1 */ "$A1",
-  /* 0xA2 */ "$A2",
-  /* 0xA3 */ "$A3",
-  /* 0xA4 */ "$A4",
-  /* 0xA5 */ "$A5",
-  /* 0xA6 */ "$A6",
-  /* 0xA7 */ "$A7",
-  /* 0xA8 */ "$A8",
-  /* 0xA9 */ "$A9",
-  /* 0xAA */ "$AA",
-  /* 0xAB */ "$AB",
-  /* 0xAC */ "$AC",
-  /* 0xAD */ "$AD",
-  /* 0xAE */ "$AE",
-  /* 0xAF */ "$AF",
-  //
-  /* 0xB0 */ "$B0",
-  /* 0xB1 */ "$B1",
-  /* 0xB2 */ "$B2",
-  /* 0xB3 */ "$B3",
-  /* 0xB4 */ "$B4",
-  /* 0xB5 */ "$B5",
-  /* 0xB6 */ "$B6",
-  /* 0xB7 */ "$B7",
-  /* 0xB8 */ "$B8",
-  /* 0xB9 */ "$B9",
-  /* 0xBA */ "$BA",
-  /* 0xBB */ "$BB",
-  /* 0xBC */ "$BC",
-  /* 0xBD */ "$BD",
-  /* 0xBE */ "$BE",
-  /* 0xBF */ "$BF",
-  //
-  /* 0xC0 */ "$C0",
-  /* 0xC1 */ "$C1",
-  /* 0xC2 */ "$C2",
+  /* 0x3F */ "ccf",
+  //
+  /* 0x40 */ "ld b, b",
+  /* 0x41 */ "ld b, c",
+  /* 0x42 */ "ld b, d",
+  /* 0x43 */ "ld b, e",
+  /* 0x44 */ "ld b, h",
+  /* 0x45 */ "ld b, l",
+  /* 0x46 */ "ld b, [hl]",
+  /* 0x47 */ "ld b, a",
+  /* 0x48 */ "ld c, b",
+  /* 0x49 */ "ld c, c",
+  /* 0x4A */ "ld c, d",
+  /* 0x4B */ "ld c, e",
+  /* 0x4C */ "ld c, h",
+  /* 0x4D */ "ld c, l",
+  /* 0x4E */ "ld c, [hl]",
+  /* 0x4F */ "ld c, a",
+  //
+  /* 0x50 */ "ld d, b",
+  /* 0x51 */ "ld d, c",
+  /* 0x52 */ "ld d, d",
+  /* 0x53 */ "ld d, e",
+  /* 0x54 */ "ld d, h",
+  /* 0x55 */ "ld d, l",
+  /* 0x56 */ "ld d, [hl]",
+  /* 0x57 */ "ld d, a",
+  /* 0x58 */ "ld e, b",
+  /* 0x59 */ "ld e, c",
+  /* 0x5A */ "ld e, d",
+  /* 0x5B */ "ld e, e",
+  /* 0x5C */ "ld e, h",
+  /* 0x5D */ "ld e, l",
+  /* 0x5E */ "ld e, [hl]",
+  /* 0x5F */ "ld e, a",
+  //
+  /* 0x60 */ "ld h, b",
+  /* 0x61 */ "ld h, c",
+  /* 0x62 */ "ld h, d",
+  /* 0x63 */ "ld h, e",
+  /* 0x64 */ "ld h, h",
+  /* 0x65 */ "ld h, l",
+  /* 0x66 */ "ld h, [hl]",
+  /* 0x67 */ "ld h, a",
+  /* 0x68 */ "ld l, b",
+  /* 0x69 */ "ld l, c",
+  /* 0x6A */ "ld l, d",
+  /* 0x6B */ "ld l, e",
+  /* 0x6C */ "ld l, h",
+  /* 0x6D */ "ld l, l",
+  /* 0x6E */ "ld l, [hl]",
+  /* 0x6F */ "ld l, a",
+  //
+  /* 0x70 */ "ld [hl], b",
+  /* 0x71 */ "ld [hl], c",
+  /* 0x72 */ "ld [hl], d",
+  /* 0x73 */ "ld [hl], e",
+  /* 0x74 */ "ld [hl], h",
+  /* 0x75 */ "ld [hl], l",
+  /* 0x76 */ "halt",
+  /* 0x77 */ "ld [hl], a",
+  /* 0x78 */ "ld a, b",
+  /* 0x79 */ "ld a, c",
+  /* 0x7A */ "ld a, d",
+  /* 0x7B */ "ld a, e",
+  /* 0x7C */ "ld a, h",
+  /* 0x7D */ "ld a, l",
+  /* 0x7E */ "ld a, [hl]",
+  /* 0x7F */ "ld a, a",
+  //
+  /* 0x80 */ "add a, b",
+  /* 0x81 */ "add a, c",
+  /* 0x82 */ "add a, d",
+  /* 0x83 */ "add a, e",
+  /* 0x84 */ "add a, h",
+  /* 0x85 */ "add a, l",
+  /* 0x86 */ "add a, [hl]",
+  /* 0x87 */ "add a, a",
+  /* 0x88 */ "adc a, b",
+  /* 0x89 */ "adc a, c",
+  /* 0x8A */ "adc a, d",
+  /* 0x8B */ "adc a, e",
+  /* 0x8C */ "adc a, h",
+  /* 0x8D */ "adc a, l",
+  /* 0x8E */ "adc a, [hl]",
+  /* 0x8F */ "adc a, a",
+  //
+  /* 0x90 */ "sub a, b",
+  /* 0x91 */ "sub a, c",
+  /* 0x92 */ "sub a, d",
+  /* 0x93 */ "sub a, e",
+  /* 0x94 */ "sub a, h",
+  /* 0x95 */ "sub a, l",
+  /* 0x96 */ "sub a, [hl]",
+  /* 0x97 */ "sub a, a",
+  /* 0x98 */ "sbc a, b",
+  /* 0x99 */ "sbc a, c",
+  /* 0x9A */ "sbc a, d",
+  /* 0x9B */ "sbc a, e",
+  /* 0x9C */ "sbc a, h",
+  /* 0x9D */ "sbc a, l",
+  /* 0x9E */ "sbc a, [hl]",
+  /* 0x9F */ "sbc a, a",
+  //
+  /* 0xA0 */ "and a, b",
+  /* 0xA1 */ "and a, c",
+  /* 0xA2 */ "and a, d",
+  /* 0xA3 */ "and a, e",
+  /* 0xA4 */ "and a, h",
+  /* 0xA5 */ "and a, l",
+  /* 0xA6 */ "and a, [hl]",
+  /* 0xA7 */ "and a, a",
+  /* 0xA8 */ "xor a, b",
+  /* 0xA9 */ "xor a, c",
+  /* 0xAA */ "xor a, d",
+  /* 0xAB */ "xor a, e",
+  /* 0xAC */ "xor a, h",
+  /* 0xAD */ "xor a, l",
+  /* 0xAE */ "xor a, [hl]",
+  /* 0xAF */ "xor a, a",
+  //
+  /* 0xB0 */ "or a, b",
+  /* 0xB1 */ "or a, c",
+  /* 0xB2 */ "or a, d",
+  /* 0xB3 */ "or a, e",
+  /* 0xB4 */ "or a, h",
+  /* 0xB5 */ "or a, l",
+  /* 0xB6 */ "or a, [hl]",
+  /* 0xB7 */ "or a, a",
+  /* 0xB8 */ "cp a, b",
+  /* 0xB9 */ "cp a, c",
+  /* 0xBA */ "cp a, d",
+  /* 0xBB */ "cp a, e",
+  /* 0xBC */ "cp a, h",
+  /* 0xBD */ "cp a, l",
+  /* 0xBE */ "cp a, [hl]",
+  /* 0xBF */ "cp a, a",
+  //
+  /* 0xC0 */ "ret nz",
+  /* 0xC1 */ "pop bc",
+  /* 0xC2 */ "jp nz, u16",
   /* 0xC3 */ "jp u16",
-  /* 0xC4 */ "$C4",
-  /* 0xC5 */ "$C5",
-  /* 0xC6 */ "$C6",
-  /* 0xC7 */ "$C7",
-  /* 0xC8 */ "$C8",
-  /* 0xC9 */ "$C9",
-  /* 0xCA */ "$CA",
-  /* 0xCB */ "$CB",
-  /* 0xCC */ "$CC",
-  /* 0xCD */ "$CD",
-  /* 0xCE */ "$CE",
-  /* 0xCF */ "$CF",
-  //
-  /* 0xD0 */ "$D0",
-  /* 0xD1 */ "$D1",
-  /* 0xD2 */ "$D2",
-  /* 0xD3 */ "$D3",
-  /* 0xD4 */ "$D4",
-  /* 0xD5 */ "$D5",
-  /* 0xD6 */ "$D6",
-  /* 0xD7 */ "$D7",
-  /* 0xD8 */ "$D8",
-  /* 0xD9 */ "$D9",
-  /* 0xDA */ "$DA",
-  /* 0xDB */ "$DB",
-  /* 0xDC */ "$DC",
-  /* 0xDD */ "$DD",
-  /* 0xDE */ "$DE",
-  /* 0xDF */ "$DF",
+  /* 0xC4 */ "call nz, u16",
+  /* 0xC5 */ "push bc",
+  /* 0xC6 */ "add a, u8",
+  /* 0xC7 */ "rst $00",
+  /* 0xC8 */ "ret z",
+  /* 0xC9 */ "ret",
+  /* 0xCA */ "jp z, u16",
+  /* 0xCB */ "prefix cb",
+  /* 0xCC */ "call z, u16",
+  /* 0xCD */ "call u16",
+  /* 0xCE */ "adc a, u8",
+  /* 0xCF */ "rst $08",
+  //
+  /* 0xD0 */ "ret nc",
+  /* 0xD1 */ "pop de",
+  /* 0xD2 */ "jp nc, u16",
+  /* 0xD3 */ "illegal $D3",
+  /* 0xD4 */ "call nc, u16",
+  /* 0xD5 */ "push de",
+  /* 0xD6 */ "sub a, u8",
+  /* 0xD7 */ "rst $10",
+  /* 0xD8 */ "ret c",
+  /* 0xD9 */ "reti",
+  /* 0xDA */ "jp c, u16",
+  /* 0xDB */ "illegal $DB",
+  /* 0xDC */ "call c, u16",
+  /* 0xDD */ "illegal $DD",
+  /* 0xDE */ "sbc a, u8",
+  /* 0xDF */ "rst $18",
   //
   /* 0xE0 */ "ldh [u8], a",
-  /* 0xE1 */ "$E1",
-  /* 0xE2 */ "$E2",
-  /* 0xE3 */ "$E3",
-  /* 0xE4 */ "$E4",
-  /* 0xE5 */ "$E5",
-  /* 0xE6 */ "$E6",
-  /* 0xE7 */ "$E7",
-  /* 0xE8 */ "$E8",
-  /* 0xE9 */ "$E9",
+  /* 0xE1 */ "pop hl",
+  /* 0xE2 */ "ld [c], a",
+  /* 0xE3 */ "illegal $E3",
+  /* 0xE4 */ "illegal $E4",
+  /* 0xE5 */ "push hl",
+  /* 0xE6 */ "and a, u8",
+  /* 0xE7 */ "rst $20",
+  /* 0xE8 */ "add sp, i8",
+  /* 0xE9 */ "jp hl",
   /* 0xEA */ "ld [u16], a",
-  /* 0xEB */ "$EB",
-  /* 0xEC */ "$EC",
-  /* 0xED */ "$ED",
-  /* 0xEE */ "$EE",
-  /* 0xEF */ "$EF",
-  //
-  /* 0xF0 */ "$F0",
-  /* 0xF1 */ "$F1",
-  /* 0xF2 */ "$F2",
+  /* 0xEB */ "illegal $EB",
+  /* 0xEC */ "illegal $EC",
+  /* 0xED */ "illegal $ED",
+  /* 0xEE */ "xor a, u8",
+  /* 0xEF */ "rst $28",
+  //
+  /* 0xF0 */ "ldh a, [u8]",
+  /* 0xF1 */ "pop af",
+  /* 0xF2 */ "ld a, [c]",
   /* 0xF3 */ "di",
-  /* 0xF4 */ "$F4",
-  /* 0xF5 */ "$F5",
-  /* 0xF6 */ "$F6",
-  /* 0xF7 */ "$F7",
-  /* 0xF8 */ "$F8",
-  /* 0xF9 */ "$F9",
-  /* 0xFA */ "$FA",
-  /* 0xFB */ "$FB",
-  /* 0xFC */ "$FC",
-  /* 0xFD */ "$FD",
-  /* 0xFE */ "$FE",
-  /* 0xFF */ "$FF",
+  /* 0xF4 */ "illegal $F4",
+  /* 0xF5 */ "push af",
+  /* 0xF6 */ "or a, u8",
+  /* 0xF7 */ "rst $30",
+  /* 0xF8 */ "ld hl, sp+i8",
+  /* 0xF9 */ "ld sp, hl",
+  /* 0xFA */ "ld a, [u16]",
+  /* 0xFB */ "ei",
+  /* 0xFC */ "illegal $FC",
+  /* 0xFD */ "illegal $FD",
+  /* 0xFE */ "cp a, u8",
+  /* 0xFF */ "rst $38",
+];
+
+/// Disassembly for the op code after a `$CB` prefix.
+#[rustfmt::skip]
+pub const CB_DISASSEMBLY_TABLE: [&str; 256] = [
+  //
+  /* 0x00 */ "rlc b",
+  /* 0x01 */ "rlc c",
+  /* 0x02 */ "rlc d",
+  /* 0x03 */ "rlc e",
+  /* 0x04 */ "rlc h",
+  /* 0x05 */ "rlc l",
+  /* 0x06 */ "rlc [hl]",
+  /* 0x07 */ "rlc a",
+  /* 0x08 */ "rrc b",
+  /* 0x09 */ "rrc c",
+  /* 0x0A */ "rrc d",
+  /* 0x0B */ "rrc e",
+  /* 0x0C */ "rrc h",
+  /* 0x0D */ "rrc l",
+  /* 0x0E */ "rrc [hl]",
+  /* 0x0F */ "rrc a",
+  //
+  /* 0x10 */ "rl b",
+  /* 0x11 */ "rl c",
+  /* 0x12 */ "rl d",
+  /* 0x13 */ "rl e",
+  /* 0x14 */ "rl h",
+  /* 0x15 */ "rl l",
+  /* 0x16 */ "rl [hl]",
+  /* 0x17 */ "rl a",
+  /* 0x18 */ "rr b",
+  /* 0x19 */ "rr c",
+  /* 0x1A */ "rr d",
+  /* 0x1B */ "rr e",
+  /* 0x1C */ "rr h",
+  /* 0x1D */ "rr l",
+  /* 0x1E */ "rr [hl]",
+  /* 0x1F */ "rr a",
+  //
+  /* 0x20 */ "sla b",
+  /* 0x21 */ "sla c",
+  /* 0x22 */ "sla d",
+  /* 0x23 */ "sla e",
+  /* 0x24 */ "sla h",
+  /* 0x25 */ "sla l",
+  /* 0x26 */ "sla [hl]",
+  /* 0x27 */ "sla a",
+  /* 0x28 */ "sra b",
+  /* 0x29 */ "sra c",
+  /* 0x2A */ "sra d",
+  /* 0x2B */ "sra e",
+  /* 0x2C */ "sra h",
+  /* 0x2D */ "sra l",
+  /* 0x2E */ "sra [hl]",
+  /* 0x2F */ "sra a",
+  //
+  /* 0x30 */ "swap b",
+  /* 0x31 */ "swap c",
+  /* 0x32 */ "swap d",
+  /* 0x33 */ "swap e",
+  /* 0x34 */ "swap h",
+  /* 0x35 */ "swap l",
+  /* 0x36 */ "swap [hl]",
+  /* 0x37 */ "swap a",
+  /* 0x38 */ "srl b",
+  /* 0x39 */ "srl c",
+  /* 0x3A */ "srl d",
+  /* 0x3B */ "srl e",
+  /* 0x3C */ "srl h",
+  /* 0x3D */ "srl l",
+  /* 0x3E */ "srl [hl]",
+  /* 0x3F */ "srl a",
+  //
+  /* 0x40 */ "bit 0, b",
+  /* 0x41 */ "bit 0, c",
+  /* 0x42 */ "bit 0, d",
+  /* 0x43 */ "bit 0, e",
+  /* 0x44 */ "bit 0, h",
+  /* 0x45 */ "bit 0, l",
+  /* 0x46 */ "bit 0, [hl]",
+  /* 0x47 */ "bit 0, a",
+  /* 0x48 */ "bit 1, b",
+  /* 0x49 */ "bit 1, c",
+  /* 0x4A */ "bit 1, d",
+  /* 0x4B */ "bit 1, e",
+  /* 0x4C */ "bit 1, h",
+  /* 0x4D */ "bit 1, l",
+  /* 0x4E */ "bit 1, [hl]",
+  /* 0x4F */ "bit 1, a",
+  //
+  /* 0x50 */ "bit 2, b",
+  /* 0x51 */ "bit 2, c",
+  /* 0x52 */ "bit 2, d",
+  /* 0x53 */ "bit 2, e",
+  /* 0x54 */ "bit 2, h",
+  /* 0x55 */ "bit 2, l",
+  /* 0x56 */ "bit 2, [hl]",
+  /* 0x57 */ "bit 2, a",
+  /* 0x58 */ "bit 3, b",
+  /* 0x59 */ "bit 3, c",
+  /* 0x5A */ "bit 3, d",
+  /* 0x5B */ "bit 3, e",
+  /* 0x5C */ "bit 3, h",
+  /* 0x5D */ "bit 3, l",
+  /* 0x5E */ "bit 3, [hl]",
+  /* 0x5F */ "bit 3, a",
+  //
+  /* 0x60 */ "bit 4, b",
+  /* 0x61 */ "bit 4, c",
+  /* 0x62 */ "bit 4, d",
+  /* 0x63 */ "bit 4, e",
+  /* 0x64 */ "bit 4, h",
+  /* 0x65 */ "bit 4, l",
+  /* 0x66 */ "bit 4, [hl]",
+  /* 0x67 */ "bit 4, a",
+  /* 0x68 */ "bit 5, b",
+  /* 0x69 */ "bit 5, c",
+  /* 0x6A */ "bit 5, d",
+  /* 0x6B */ "bit 5, e",
+  /* 0x6C */ "bit 5, h",
+  /* 0x6D */ "bit 5, l",
+  /* 0x6E */ "bit 5, [hl]",
+  /* 0x6F */ "bit 5, a",
+  //
+  /* 0x70 */ "bit 6, b",
+  /* 0x71 */ "bit 6, c",
+  /* 0x72 */ "bit 6, d",
+  /* 0x73 */ "bit 6, e",
+  /* 0x74 */ "bit 6, h",
+  /* 0x75 */ "bit 6, l",
+  /* 0x76 */ "bit 6, [hl]",
+  /* 0x77 */ "bit 6, a",
+  /* 0x78 */ "bit 7, b",
+  /* 0x79 */ "bit 7, c",
+  /* 0x7A */ "bit 7, d",
+  /* 0x7B */ "bit 7, e",
+  /* 0x7C */ "bit 7, h",
+  /* 0x7D */ "bit 7, l",
+  /* 0x7E */ "bit 7, [hl]",
+  /* 0x7F */ "bit 7, a",
+  //
+  /* 0x80 */ "res 0, b",
+  /* 0x81 */ "res 0, c",
+  /* 0x82 */ "res 0, d",
+  /* 0x83 */ "res 0, e",
+  /* 0x84 */ "res 0, h",
+  /* 0x85 */ "res 0, l",
+  /* 0x86 */ "res 0, [hl]",
+  /* 0x87 */ "res 0, a",
+  /* 0x88 */ "res 1, b",
+  /* 0x89 */ "res 1, c",
+  /* 0x8A */ "res 1, d",
+  /* 0x8B */ "res 1, e",
+  /* 0x8C */ "res 1, h",
+  /* 0x8D */ "res 1, l",
+  /* 0x8E */ "res 1, [hl]",
+  /* 0x8F */ "res 1, a",
+  //
+  /* 0x90 */ "res 2, b",
+  /* 0x91 */ "res 2, c",
+  /* 0x92 */ "res 2, d",
+  /* 0x93 */ "res 2, e",
+  /* 0x94 */ "res 2, h",
+  /* 0x95 */ "res 2, l",
+  /* 0x96 */ "res 2, [hl]",
+  /* 0x97 */ "res 2, a",
+  /* 0x98 */ "res 3, b",
+  /* 0x99 */ "res 3, c",
+  /* 0x9A */ "res 3, d",
+  /* 0x9B */ "res 3, e",
+  /* 0x9C */ "res 3, h",
+  /* 0x9D */ "res 3, l",
+  /* 0x9E */ "res 3, [hl]",
+  /* 0x9F */ "res 3, a",
+  //
+  /* 0xA0 */ "res 4, b",
+  /* 0xA1 */ "res 4, c",
+  /* 0xA2 */ "res 4, d",
+  /* 0xA3 */ "res 4, e",
+  /* 0xA4 */ "res 4, h",
+  /* 0xA5 */ "res 4, l",
+  /* 0xA6 */ "res 4, [hl]",
+  /* 0xA7 */ "res 4, a",
+  /* 0xA8 */ "res 5, b",
+  /* 0xA9 */ "res 5, c",
+  /* 0xAA */ "res 5, d",
+  /* 0xAB */ "res 5, e",
+  /* 0xAC */ "res 5, h",
+  /* 0xAD */ "res 5, l",
+  /* 0xAE */ "res 5, [hl]",
+  /* 0xAF */ "res 5, a",
+  //
+  /* 0xB0 */ "res 6, b",
+  /* 0xB1 */ "res 6, c",
+  /* 0xB2 */ "res 6, d",
+  /* 0xB3 */ "res 6, e",
+  /* 0xB4 */ "res 6, h",
+  /* 0xB5 */ "res 6, l",
+  /* 0xB6 */ "res 6, [hl]",
+  /* 0xB7 */ "res 6, a",
+  /* 0xB8 */ "res 7, b",
+  /* 0xB9 */ "res 7, c",
+  /* 0xBA */ "res 7, d",
+  /* 0xBB */ "res 7, e",
+  /* 0xBC */ "res 7, h",
+  /* 0xBD */ "res 7, l",
+  /* 0xBE */ "res 7, [hl]",
+  /* 0xBF */ "res 7, a",
+  //
+  /* 0xC0 */ "set 0, b",
+  /* 0xC1 */ "set 0, c",
+  /* 0xC2 */ "set 0, d",
+  /* 0xC3 */ "set 0, e",
+  /* 0xC4 */ "set 0, h",
+  /* 0xC5 */ "set 0, l",
+  /* 0xC6 */ "set 0, [hl]",
+  /* 0xC7 */ "set 0, a",
+  /* 0xC8 */ "set 1, b",
+  /* 0xC9 */ "set 1, c",
+  /* 0xCA */ "set 1, d",
+  /* 0xCB */ "set 1, e",
+  /* 0xCC */ "set 1, h",
+  /* 0xCD */ "set 1, l",
+  /* 0xCE */ "set 1, [hl]",
+  /* 0xCF */ "set 1, a",
+  //
+  /* 0xD0 */ "set 2, b",
+  /* 0xD1 */ "set 2, c",
+  /* 0xD2 */ "set 2, d",
+  /* 0xD3 */ "set 2, e",
+  /* 0xD4 */ "set 2, h",
+  /* 0xD5 */ "set 2, l",
+  /* 0xD6 */ "set 2, [hl]",
+  /* 0xD7 */ "set 2, a",
+  /* 0xD8 */ "set 3, b",
+  /* 0xD9 */ "set 3, c",
+  /* 0xDA */ "set 3, d",
+  /* 0xDB */ "set 3, e",
+  /* 0xDC */ "set 3, h",
+  /* 0xDD */ "set 3, l",
+  /* 0xDE */ "set 3, [hl]",
+  /* 0xDF */ "set 3, a",
+  //
+  /* 0xE0 */ "set 4, b",
+  /* 0xE1 */ "set 4, c",
+  /* 0xE2 */ "set 4, d",
+  /* 0xE3 */ "set 4, e",
+  /* 0xE4 */ "set 4, h",
+  /* 0xE5 */ "set 4, l",
+  /* 0xE6 */ "set 4, [hl]",
+  /* 0xE7 */ "set 4, a",
+  /* 0xE8 */ "set 5, b",
+  /* 0xE9 */ "set 5, c",
+  /* 0xEA */ "set 5, d",
+  /* 0xEB */ "set 5, e",
+  /* 0xEC */ "set 5, h",
+  /* 0xED */ "set 5, l",
+  /* 0xEE */ "set 5, [hl]",
+  /* 0xEF */ "set 5, a",
+  //
+  /* 0xF0 */ "set 6, b",
+  /* 0xF1 */ "set 6, c",
+  /* 0xF2 */ "set 6, d",
+  /* 0xF3 */ "set 6, e",
+  /* 0xF4 */ "set 6, h",
+  /* 0xF5 */ "set 6, l",
+  /* 0xF6 */ "set 6, [hl]",
+  /* 0xF7 */ "set 6, a",
+  /* 0xF8 */ "set 7, b",
+  /* 0xF9 */ "set 7, c",
+  /* 0xFA */ "set 7, d",
+  /* 0xFB */ "set 7, e",
+  /* 0xFC */ "set 7, h",
+  /* 0xFD */ "set 7, l",
+  /* 0xFE */ "set 7, [hl]",
+  /* 0xFF */ "set 7, a",
 ];
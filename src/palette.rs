@@ -0,0 +1,156 @@
+//! CGB palette RAM and color conversion.
+//!
+//! Each of the two palette RAMs (BG and OBJ) holds 8 palettes of 4 colors, and
+//! each color is a little-endian RGB555 value. The CPU reaches them through an
+//! index register (`BCPS`/`OCPS`) and a data register (`BCPD`/`OCPD`).
+//!
+//! * See Also: [Pandocs: LCD Color Palettes (CGB only)](https://gbdev.io/pandocs/Palettes.html#lcd-color-palettes-cgb-only)
+
+use bitfrob::{u16_get_value, u8_get_bit};
+
+/// RGB555 white.
+pub const RGB555_WHITE: u16 = 0x7FFF;
+
+/// The default gray shades used when a DMG picture is given as RGB555.
+pub const DMG_GRAYS: [u16; 4] = [0x7FFF, 0x56B5, 0x294A, 0x0000];
+
+/// One of the palette RAMs, along with its index register.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PaletteRam {
+  data: [u8; 64],
+  spec: u8,
+}
+impl Default for PaletteRam {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+impl PaletteRam {
+  /// All white palettes, with the index at 0.
+  #[inline]
+  #[must_use]
+  pub const fn new() -> Self {
+    Self { data: [0xFF; 64], spec: 0 }
+  }
+
+  /// The raw palette bytes.
+  #[inline]
+  #[must_use]
+  pub const fn data(&self) -> &[u8; 64] {
+    &self.data
+  }
+  /// The raw palette bytes, mutably.
+  #[inline]
+  #[must_use]
+  pub fn data_mut(&mut self) -> &mut [u8; 64] {
+    &mut self.data
+  }
+
+  /// A color as RGB555, with `palette` in `0..8` and `index` in `0..4`.
+  #[inline]
+  #[must_use]
+  pub const fn color(&self, palette: u8, index: u8) -> u16 {
+    let i = (((palette & 0b111) * 8) + ((index & 0b11) * 2)) as usize;
+    u16::from_le_bytes([self.data[i], self.data[i + 1]]) & 0x7FFF
+  }
+  /// Sets a color as RGB555, with `palette` in `0..8` and `index` in `0..4`.
+  #[inline]
+  pub fn set_color(&mut self, palette: u8, index: u8, rgb555: u16) {
+    let i = usize::from(((palette & 0b111) * 8) + ((index & 0b11) * 2));
+    self.data[i..i + 2].copy_from_slice(&rgb555.to_le_bytes());
+  }
+
+  /// Reads `BCPS`/`OCPS`. Bit 6 is unused and reads as 1.
+  #[inline]
+  #[must_use]
+  pub const fn read_spec(&self) -> u8 {
+    self.spec | 0b0100_0000
+  }
+  #[inline]
+  pub fn write_spec(&mut self, byte: u8) {
+    self.spec = byte & 0b1011_1111;
+  }
+
+  /// Reads `BCPD`/`OCPD`.
+  ///
+  /// While the PPU is drawing (mode 3) the CPU can't see palette RAM, so
+  /// `blocked` reads give `$FF`. Reads never advance the index.
+  #[inline]
+  #[must_use]
+  pub const fn read_data(&self, blocked: bool) -> u8 {
+    if blocked {
+      0xFF
+    } else {
+      self.data[(self.spec & 0x3F) as usize]
+    }
+  }
+  /// Writes `BCPD`/`OCPD`.
+  ///
+  /// While the PPU is drawing (mode 3) a `blocked` write doesn't change the
+  /// palette, but auto-increment still advances the index.
+  #[inline]
+  pub fn write_data(&mut self, byte: u8, blocked: bool) {
+    if !blocked {
+      self.data[usize::from(self.spec & 0x3F)] = byte;
+    }
+    if u8_get_bit(7, self.spec) {
+      self.spec = 0x80 | ((self.spec + 1) & 0x3F);
+    }
+  }
+}
+
+/// How to turn RGB555 into RGB888 for display.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ColorCorrection {
+  /// Scale each channel linearly. Colors will look much more saturated than
+  /// they did on the real screen.
+  #[default]
+  None,
+  /// Mix channels the way the CGB's LCD does, giving the washed out look of
+  /// the real screen. This is Gambatte's curve. higan uses the same weights,
+  /// but clamps so that white is 240 instead of 248.
+  Lcd,
+}
+
+/// Converts an RGB555 color into an `[r, g, b]` RGB888 color.
+#[inline]
+#[must_use]
+pub const fn rgb555_to_rgb888(
+  color: u16, correction: ColorCorrection,
+) -> [u8; 3] {
+  let r = u16_get_value(0, 4, color) as u32;
+  let g = u16_get_value(5, 9, color) as u32;
+  let b = u16_get_value(10, 14, color) as u32;
+  match correction {
+    ColorCorrection::None => [
+      ((r << 3) | (r >> 2)) as u8,
+      ((g << 3) | (g >> 2)) as u8,
+      ((b << 3) | (b >> 2)) as u8,
+    ],
+    // Each row of weights adds up to 16, so white comes out as 31 * 8.
+    ColorCorrection::Lcd => [
+      ((r * 13 + g * 2 + b) >> 1) as u8,
+      ((g * 3 + b) << 1) as u8,
+      ((r * 3 + g * 2 + b * 11) >> 1) as u8,
+    ],
+  }
+}
+
+#[test]
+fn test_PaletteRam_auto_increment() {
+  let mut ram = PaletteRam::new();
+  ram.write_spec(0x80 | 0x3E);
+  assert_eq!(ram.read_spec(), 0xFE);
+  ram.write_data(0x1F, false);
+  ram.write_data(0x00, false);
+  assert_eq!(ram.read_spec(), 0xC0);
+  assert_eq!(ram.color(7, 3), 0x001F);
+  ram.write_data(0x12, true);
+  assert_eq!(ram.read_spec(), 0xC1);
+  assert_eq!(ram.read_data(true), 0xFF);
+  assert_eq!(ram.read_data(false), 0xFF);
+  assert_eq!(rgb555_to_rgb888(0x7FFF, ColorCorrection::None), [255, 255, 255]);
+  assert_eq!(rgb555_to_rgb888(0x7FFF, ColorCorrection::Lcd), [248, 248, 248]);
+  // pure red bleeds into the other channels.
+  assert_eq!(rgb555_to_rgb888(0x001F, ColorCorrection::Lcd), [201, 0, 46]);
+}
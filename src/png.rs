@@ -0,0 +1,144 @@
+//! A tiny PNG encoder.
+//!
+//! This only writes "stored" (uncompressed) deflate blocks, so the files are
+//! bigger than they strictly need to be, but it keeps us free of any
+//! compression dependency and every image viewer can still open them.
+//!
+//! * See Also: [PNG Specification](https://www.w3.org/TR/png/)
+
+use alloc::vec::Vec;
+
+/// The pixel layout of the data given to [encode_png].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ColorType {
+  /// One byte per pixel.
+  #[default]
+  Grayscale,
+  /// Three bytes per pixel, in R, G, B order.
+  Rgb,
+  /// Four bytes per pixel, in R, G, B, A order.
+  Rgba,
+}
+impl ColorType {
+  /// Bytes per pixel.
+  #[inline]
+  #[must_use]
+  pub const fn bytes_per_pixel(self) -> usize {
+    match self {
+      Self::Grayscale => 1,
+      Self::Rgb => 3,
+      Self::Rgba => 4,
+    }
+  }
+  const fn png_code(self) -> u8 {
+    match self {
+      Self::Grayscale => 0,
+      Self::Rgb => 2,
+      Self::Rgba => 6,
+    }
+  }
+}
+
+const CRC_TABLE: [u32; 256] = {
+  let mut table = [0_u32; 256];
+  let mut n = 0;
+  while n < 256 {
+    let mut c = n as u32;
+    let mut k = 0;
+    while k < 8 {
+      c = if c & 1 != 0 { 0xEDB88320 ^ (c >> 1) } else { c >> 1 };
+      k += 1;
+    }
+    table[n] = c;
+    n += 1;
+  }
+  table
+};
+
+fn crc32(bytes: &[u8]) -> u32 {
+  let mut c = u32::MAX;
+  for &b in bytes {
+    c = CRC_TABLE[usize::from((c as u8) ^ b)] ^ (c >> 8);
+  }
+  c ^ u32::MAX
+}
+
+fn adler32(bytes: &[u8]) -> u32 {
+  const MOD: u32 = 65521;
+  let mut a = 1_u32;
+  let mut b = 0_u32;
+  for &byte in bytes {
+    a = (a + u32::from(byte)) % MOD;
+    b = (b + a) % MOD;
+  }
+  (b << 16) | a
+}
+
+fn push_chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+  out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+  let crc_start = out.len();
+  out.extend_from_slice(kind);
+  out.extend_from_slice(data);
+  let crc = crc32(&out[crc_start..]);
+  out.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// Encodes an image as a PNG file.
+///
+/// The `pixels` are given row by row, top to bottom, with no padding between
+/// rows.
+///
+/// ## Panics
+/// * If `pixels` isn't exactly `width * height` pixels of the color type given.
+#[must_use]
+pub fn encode_png(
+  width: u32, height: u32, color: ColorType, pixels: &[u8],
+) -> Vec<u8> {
+  let row_len = width as usize * color.bytes_per_pixel();
+  assert_eq!(pixels.len(), row_len * height as usize, "wrong pixel data size");
+
+  // Each row gets a leading filter-type byte, and we always use filter 0.
+  let mut raw = Vec::with_capacity((row_len + 1) * height as usize);
+  for row in pixels.chunks_exact(row_len.max(1)).take(height as usize) {
+    raw.push(0);
+    raw.extend_from_slice(row);
+  }
+
+  let mut zlib = Vec::with_capacity(raw.len() + raw.len() / 0xFFFF * 5 + 11);
+  zlib.extend_from_slice(&[0x78, 0x01]);
+  let mut blocks = raw.chunks(0xFFFF).peekable();
+  if blocks.peek().is_none() {
+    zlib.extend_from_slice(&[1, 0x00, 0x00, 0xFF, 0xFF]);
+  }
+  while let Some(block) = blocks.next() {
+    let is_final = blocks.peek().is_none();
+    let len = block.len() as u16;
+    zlib.push(u8::from(is_final));
+    zlib.extend_from_slice(&len.to_le_bytes());
+    zlib.extend_from_slice(&(!len).to_le_bytes());
+    zlib.extend_from_slice(block);
+  }
+  zlib.extend_from_slice(&adler32(&raw).to_be_bytes());
+
+  let mut ihdr = [0_u8; 13];
+  ihdr[0..4].copy_from_slice(&width.to_be_bytes());
+  ihdr[4..8].copy_from_slice(&height.to_be_bytes());
+  ihdr[8] = 8;
+  ihdr[9] = color.png_code();
+
+  let mut out = Vec::with_capacity(zlib.len() + 57);
+  out.extend_from_slice(&[0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n']);
+  push_chunk(&mut out, b"IHDR", &ihdr);
+  push_chunk(&mut out, b"IDAT", &zlib);
+  push_chunk(&mut out, b"IEND", &[]);
+  out
+}
+
+#[test]
+fn test_encode_png() {
+  assert_eq!(crc32(b"IEND"), 0xAE426082);
+  assert_eq!(adler32(b"Wikipedia"), 0x11E60398);
+  let png = encode_png(2, 1, ColorType::Grayscale, &[0x00, 0xFF]);
+  assert_eq!(&png[..8], b"\x89PNG\r\n\x1A\n");
+  assert_eq!(&png[png.len() - 12..], b"\0\0\0\0IEND\xAE\x42\x60\x82");
+}
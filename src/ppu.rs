@@ -0,0 +1,873 @@
+//! The Picture Processing Unit.
+//!
+//! This is a scanline renderer: each line is drawn all at once as mode 3
+//! begins, so register writes in the middle of mode 3 don't show up until the
+//! next line. The length of mode 3 is still estimated from the scroll, window,
+//! and objects on the line so that HBlank timing is about right.
+//!
+//! The PPU always runs on the system clock (one "dot" per call to
+//! [Ppu::t_cycle]), even when the CPU is in double speed mode.
+//!
+//! * See Also: [Pandocs: Rendering](https://gbdev.io/pandocs/Rendering.html)
+
+use alloc::{boxed::Box, vec, vec::Vec};
+
+use bitfrob::{u8_get_bit, u8_get_value};
+
+use crate::{
+  interrupts::{INT_STAT, INT_VBLANK},
+  palette::{
+    rgb555_to_rgb888, ColorCorrection, PaletteRam, DMG_GRAYS, RGB555_WHITE,
+  },
+  vram::Vram,
+};
+
+pub const SCREEN_WIDTH: usize = 160;
+pub const SCREEN_HEIGHT: usize = 144;
+pub const DOTS_PER_LINE: u16 = 456;
+pub const LINES_PER_FRAME: u8 = 154;
+pub const DOTS_PER_FRAME: u32 = DOTS_PER_LINE as u32 * LINES_PER_FRAME as u32;
+
+const OAM_SCAN_DOTS: u16 = 80;
+const MIN_DRAWING_DOTS: u16 = 172;
+const MAX_DRAWING_DOTS: u16 = 289;
+const OBJS_PER_LINE: usize = 10;
+/// Dots into line 153 before `LY` reads as 0.
+const LINE_153_LY_DOTS: u16 = 4;
+
+/// The PPU mode, as shown in the low bits of `STAT`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(u8)]
+pub enum PpuMode {
+  #[default]
+  HBlank = 0,
+  VBlank = 1,
+  OamScan = 2,
+  Drawing = 3,
+}
+
+/// A BG map attribute byte (CGB only).
+///
+/// These live in VRAM bank 1, at the same address as the tile index they go
+/// with in bank 0.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct BgAttributes(pub u8);
+impl BgAttributes {
+  #[inline]
+  #[must_use]
+  pub const fn palette(self) -> u8 {
+    u8_get_value(0, 2, self.0)
+  }
+  #[inline]
+  #[must_use]
+  pub const fn bank(self) -> u8 {
+    u8_get_bit(3, self.0) as u8
+  }
+  #[inline]
+  #[must_use]
+  pub const fn x_flip(self) -> bool {
+    u8_get_bit(5, self.0)
+  }
+  #[inline]
+  #[must_use]
+  pub const fn y_flip(self) -> bool {
+    u8_get_bit(6, self.0)
+  }
+  /// If the BG pixel (when not color 0) is drawn over all objects.
+  #[inline]
+  #[must_use]
+  pub const fn priority(self) -> bool {
+    u8_get_bit(7, self.0)
+  }
+}
+
+/// The flags byte of an OAM entry.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct ObjAttributes(pub u8);
+impl ObjAttributes {
+  /// CGB mode palette.
+  #[inline]
+  #[must_use]
+  pub const fn cgb_palette(self) -> u8 {
+    u8_get_value(0, 2, self.0)
+  }
+  /// CGB mode VRAM bank.
+  #[inline]
+  #[must_use]
+  pub const fn bank(self) -> u8 {
+    u8_get_bit(3, self.0) as u8
+  }
+  /// DMG mode palette: `false` for `OBP0`, `true` for `OBP1`.
+  #[inline]
+  #[must_use]
+  pub const fn dmg_palette(self) -> bool {
+    u8_get_bit(4, self.0)
+  }
+  #[inline]
+  #[must_use]
+  pub const fn x_flip(self) -> bool {
+    u8_get_bit(5, self.0)
+  }
+  #[inline]
+  #[must_use]
+  pub const fn y_flip(self) -> bool {
+    u8_get_bit(6, self.0)
+  }
+  /// If BG colors 1-3 are drawn over this object.
+  #[inline]
+  #[must_use]
+  pub const fn behind_bg(self) -> bool {
+    u8_get_bit(7, self.0)
+  }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+struct ObjEntry {
+  y: u8,
+  x: u8,
+  tile: u8,
+  attrs: ObjAttributes,
+}
+
+/// Applies a DMG palette register (`BGP`, `OBP0`, `OBP1`) to a color index.
+#[inline]
+#[must_use]
+pub const fn dmg_shade(palette: u8, index: u8) -> u8 {
+  (palette >> ((index & 0b11) * 2)) & 0b11
+}
+
+/// The PPU, along with the memory that it owns (VRAM, OAM, palette RAM).
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Ppu {
+  vram: Vram,
+  oam: [u8; 160],
+  bg_palettes: PaletteRam,
+  obj_palettes: PaletteRam,
+  lcdc: u8,
+  stat: u8,
+  scy: u8,
+  scx: u8,
+  ly: u8,
+  lyc: u8,
+  bgp: u8,
+  obp0: u8,
+  obp1: u8,
+  wy: u8,
+  wx: u8,
+  mode: PpuMode,
+  dot: u16,
+  drawing_dots: u16,
+  window_line: u8,
+  window_triggered: bool,
+  stat_line: bool,
+  frame_count: u32,
+  frame: Box<[u16]>,
+  shades: Box<[u8]>,
+  dmg_compat: bool,
+  opri: u8,
+  /// RGB555 colors for the four DMG shades, used when not in CGB mode.
+  pub dmg_colors: [u16; 4],
+}
+impl Ppu {
+  /// Makes a PPU with the LCD off.
+  ///
+  /// In `cgb` mode the PPU uses VRAM bank 1, BG attributes, and palette RAM.
+  #[must_use]
+  pub fn new(cgb: bool) -> Self {
+    Self {
+      vram: Vram::new(cgb),
+      oam: [0; 160],
+      bg_palettes: PaletteRam::new(),
+      obj_palettes: PaletteRam::new(),
+      lcdc: 0,
+      stat: 0,
+      scy: 0,
+      scx: 0,
+      ly: 0,
+      lyc: 0,
+      bgp: 0,
+      obp0: 0,
+      obp1: 0,
+      wy: 0,
+      wx: 0,
+      mode: PpuMode::HBlank,
+      dot: 0,
+      drawing_dots: MIN_DRAWING_DOTS,
+      window_line: 0,
+      window_triggered: false,
+      stat_line: false,
+      frame_count: 0,
+      frame: vec![RGB555_WHITE; SCREEN_WIDTH * SCREEN_HEIGHT]
+        .into_boxed_slice(),
+      shades: vec![0; SCREEN_WIDTH * SCREEN_HEIGHT].into_boxed_slice(),
+      dmg_compat: false,
+      opri: 0,
+      dmg_colors: DMG_GRAYS,
+    }
+  }
+
+  /// Switches a CGB mode PPU into DMG compatibility mode.
+  ///
+  /// The PPU then draws like a DMG (no VRAM bank 1, no BG attributes, and
+  /// objects sorted by X), but turns the `BGP`, `OBP0`, and `OBP1` shades into
+  /// colors using BG palette 0 and OBJ palettes 0 and 1. The boot ROM fills
+  /// those palettes in first, see [CompatPalette](crate::compat::CompatPalette).
+  pub fn enter_dmg_compat(&mut self) {
+    self.vram.set_cgb(false);
+    self.dmg_compat = true;
+    self.opri = 1;
+  }
+
+  /// If this is a CGB PPU in DMG compatibility mode.
+  #[inline]
+  #[must_use]
+  pub const fn is_dmg_compat(&self) -> bool {
+    self.dmg_compat
+  }
+
+  #[inline]
+  #[must_use]
+  pub const fn is_cgb(&self) -> bool {
+    self.vram.is_cgb()
+  }
+  #[inline]
+  #[must_use]
+  pub const fn lcd_enabled(&self) -> bool {
+    u8_get_bit(7, self.lcdc)
+  }
+  #[inline]
+  #[must_use]
+  pub const fn mode(&self) -> PpuMode {
+    self.mode
+  }
+  #[inline]
+  #[must_use]
+  pub const fn ly(&self) -> u8 {
+    self.ly
+  }
+  /// The dot within the current line, `0..456`.
+  #[inline]
+  #[must_use]
+  pub const fn dot(&self) -> u16 {
+    self.dot
+  }
+  /// Counts up each time VBlank begins.
+  #[inline]
+  #[must_use]
+  pub const fn frame_count(&self) -> u32 {
+    self.frame_count
+  }
+
+  #[inline]
+  #[must_use]
+  pub const fn vram(&self) -> &Vram {
+    &self.vram
+  }
+  #[inline]
+  #[must_use]
+  pub fn vram_mut(&mut self) -> &mut Vram {
+    &mut self.vram
+  }
+  #[inline]
+  #[must_use]
+  pub const fn oam(&self) -> &[u8; 160] {
+    &self.oam
+  }
+  #[inline]
+  #[must_use]
+  pub fn oam_mut(&mut self) -> &mut [u8; 160] {
+    &mut self.oam
+  }
+  #[inline]
+  #[must_use]
+  pub const fn bg_palettes(&self) -> &PaletteRam {
+    &self.bg_palettes
+  }
+  #[inline]
+  #[must_use]
+  pub fn bg_palettes_mut(&mut self) -> &mut PaletteRam {
+    &mut self.bg_palettes
+  }
+  #[inline]
+  #[must_use]
+  pub const fn obj_palettes(&self) -> &PaletteRam {
+    &self.obj_palettes
+  }
+  #[inline]
+  #[must_use]
+  pub fn obj_palettes_mut(&mut self) -> &mut PaletteRam {
+    &mut self.obj_palettes
+  }
+
+  /// The last drawn picture, as RGB555, row by row.
+  #[inline]
+  #[must_use]
+  pub fn frame(&self) -> &[u16] {
+    &self.frame
+  }
+  /// The last drawn picture as DMG shades (0 is white, 3 is black).
+  ///
+  /// This is only kept up to date when not in CGB mode.
+  #[inline]
+  #[must_use]
+  pub fn shades(&self) -> &[u8] {
+    &self.shades
+  }
+  /// The last drawn picture as RGB888 bytes, row by row.
+  #[must_use]
+  pub fn frame_rgb888(&self, correction: ColorCorrection) -> Vec<u8> {
+    self.frame.iter().flat_map(|&c| rgb555_to_rgb888(c, correction)).collect()
+  }
+
+  /// CPU read of `$8000..=$9FFF`. VRAM can't be read during mode 3.
+  #[inline]
+  #[must_use]
+  pub fn cpu_read_vram(&self, addr: u16) -> u8 {
+    if self.mode == PpuMode::Drawing {
+      0xFF
+    } else {
+      self.vram.read(addr)
+    }
+  }
+  /// CPU write of `$8000..=$9FFF`. VRAM can't be written during mode 3.
+  #[inline]
+  pub fn cpu_write_vram(&mut self, addr: u16, byte: u8) {
+    if self.mode != PpuMode::Drawing {
+      self.vram.write(addr, byte)
+    }
+  }
+  /// CPU read of `$FE00..=$FE9F`. OAM can't be read during modes 2 and 3.
+  #[inline]
+  #[must_use]
+  pub fn cpu_read_oam(&self, addr: u16) -> u8 {
+    match self.mode {
+      PpuMode::OamScan | PpuMode::Drawing => 0xFF,
+      _ => self.oam.get(usize::from(addr & 0xFF)).copied().unwrap_or(0xFF),
+    }
+  }
+  /// CPU write of `$FE00..=$FE9F`. OAM can't be written during modes 2 and 3.
+  #[inline]
+  pub fn cpu_write_oam(&mut self, addr: u16, byte: u8) {
+    if matches!(self.mode, PpuMode::HBlank | PpuMode::VBlank) {
+      if let Some(b) = self.oam.get_mut(usize::from(addr & 0xFF)) {
+        *b = byte;
+      }
+    }
+  }
+
+  /// Reads one of the PPU's IO registers.
+  #[must_use]
+  pub fn read_reg(&self, addr: u16) -> u8 {
+    let cgb = self.is_cgb();
+    let drawing = self.mode == PpuMode::Drawing;
+    match addr {
+      0xFF40 => self.lcdc,
+      0xFF41 => {
+        let lyc_flag =
+          (self.lcd_enabled() && self.visible_ly() == self.lyc) as u8;
+        0x80 | self.stat | (lyc_flag << 2) | (self.mode as u8)
+      }
+      0xFF42 => self.scy,
+      0xFF43 => self.scx,
+      0xFF44 => self.visible_ly(),
+      0xFF45 => self.lyc,
+      0xFF47 => self.bgp,
+      0xFF48 => self.obp0,
+      0xFF49 => self.obp1,
+      0xFF4A => self.wy,
+      0xFF4B => self.wx,
+      0xFF4F => self.vram.read_vbk(),
+      0xFF68 if cgb => self.bg_palettes.read_spec(),
+      0xFF69 if cgb => self.bg_palettes.read_data(drawing),
+      0xFF6A if cgb => self.obj_palettes.read_spec(),
+      0xFF6B if cgb => self.obj_palettes.read_data(drawing),
+      0xFF6C if cgb => 0xFE | self.opri,
+      _ => 0xFF,
+    }
+  }
+
+  /// Writes one of the PPU's IO registers.
+  pub fn write_reg(&mut self, addr: u16, byte: u8) {
+    let cgb = self.is_cgb();
+    let drawing = self.mode == PpuMode::Drawing;
+    match addr {
+      0xFF40 => {
+        let was_enabled = self.lcd_enabled();
+        self.lcdc = byte;
+        match (was_enabled, self.lcd_enabled()) {
+          (true, false) => self.turn_off(),
+          (false, true) => self.turn_on(),
+          _ => (),
+        }
+      }
+      0xFF41 => self.stat = byte & 0b0111_1000,
+      0xFF42 => self.scy = byte,
+      0xFF43 => self.scx = byte,
+      0xFF45 => self.lyc = byte,
+      0xFF47 => self.bgp = byte,
+      0xFF48 => self.obp0 = byte,
+      0xFF49 => self.obp1 = byte,
+      0xFF4A => self.wy = byte,
+      0xFF4B => self.wx = byte,
+      0xFF4F => self.vram.write_vbk(byte),
+      0xFF68 if cgb => self.bg_palettes.write_spec(byte),
+      0xFF69 if cgb => self.bg_palettes.write_data(byte, drawing),
+      0xFF6A if cgb => self.obj_palettes.write_spec(byte),
+      0xFF6B if cgb => self.obj_palettes.write_data(byte, drawing),
+      0xFF6C if cgb => self.opri = byte & 1,
+      _ => (),
+    }
+  }
+
+  /// Moves the (enabled) LCD to a spot in the frame, as the boot ROM would have
+  /// left it.
+  pub fn set_position(&mut self, ly: u8, dot: u16) {
+    self.ly = ly % LINES_PER_FRAME;
+    self.dot = dot % DOTS_PER_LINE;
+    self.mode = if usize::from(self.ly) >= SCREEN_HEIGHT {
+      PpuMode::VBlank
+    } else if self.dot < OAM_SCAN_DOTS {
+      PpuMode::OamScan
+    } else if self.dot < OAM_SCAN_DOTS + self.drawing_dots {
+      PpuMode::Drawing
+    } else {
+      PpuMode::HBlank
+    };
+    self.stat_line = false;
+  }
+
+  fn turn_off(&mut self) {
+    self.ly = 0;
+    self.dot = 0;
+    self.mode = PpuMode::HBlank;
+    self.stat_line = false;
+    self.frame.fill(RGB555_WHITE);
+    self.shades.fill(0);
+  }
+
+  fn turn_on(&mut self) {
+    self.ly = 0;
+    self.dot = 0;
+    self.window_line = 0;
+    self.window_triggered = false;
+  }
+
+  /// Grants a T-cycle (one dot) worth of time to the PPU.
+  ///
+  /// * **Returns:** The interrupt bits (`IF` style) that should be requested.
+  pub fn t_cycle(&mut self) -> u8 {
+    if !self.lcd_enabled() {
+      return 0;
+    }
+    let mut irq = 0;
+    if usize::from(self.ly) < SCREEN_HEIGHT {
+      if self.dot == 0 {
+        self.mode = PpuMode::OamScan;
+        if self.ly == self.wy {
+          self.window_triggered = true;
+        }
+      } else if self.dot == OAM_SCAN_DOTS {
+        self.mode = PpuMode::Drawing;
+        self.render_line();
+      } else if self.dot == OAM_SCAN_DOTS + self.drawing_dots {
+        self.mode = PpuMode::HBlank;
+      }
+    } else if usize::from(self.ly) == SCREEN_HEIGHT && self.dot == 0 {
+      self.mode = PpuMode::VBlank;
+      self.frame_count = self.frame_count.wrapping_add(1);
+      irq |= INT_VBLANK;
+    }
+    if self.update_stat_line() {
+      irq |= INT_STAT;
+    }
+    self.dot += 1;
+    if self.dot == DOTS_PER_LINE {
+      self.dot = 0;
+      self.ly += 1;
+      if self.ly == LINES_PER_FRAME {
+        self.ly = 0;
+        self.window_line = 0;
+        self.window_triggered = false;
+      }
+    }
+    irq
+  }
+
+  /// Grants many dots worth of time to the PPU at once.
+  ///
+  /// This gives the same result as calling [t_cycle](Ppu::t_cycle) that many
+  /// times, but skips straight over the dots between mode changes.
+  ///
+  /// * **Returns:** The interrupt bits (`IF` style) that should be requested.
+  pub fn advance(&mut self, mut dots: u32) -> u8 {
+    let mut irq = 0;
+    while dots > 0 {
+      // The first dot always runs in full, since a register write just
+      // before it can change the STAT line.
+      irq |= self.t_cycle();
+      dots -= 1;
+      let Some(until) = self.dots_until_event() else { break };
+      let skip = (until - 1).min(dots);
+      self.dot += skip as u16;
+      dots -= skip;
+    }
+    irq
+  }
+
+  /// Dots until the PPU next changes mode, line, or `LY`, counting the dot
+  /// where it happens. Interrupts can only be requested on those dots.
+  ///
+  /// * **Returns:** `None` if the LCD is off.
+  #[must_use]
+  pub fn dots_until_event(&self) -> Option<u32> {
+    if !self.lcd_enabled() {
+      return None;
+    }
+    // The last dot of each line is included so that skipping ahead never has
+    // to wrap to the next line.
+    let last = DOTS_PER_LINE - 1;
+    let boundaries: &[u16] = if usize::from(self.ly) < SCREEN_HEIGHT {
+      &[0, OAM_SCAN_DOTS, OAM_SCAN_DOTS + self.drawing_dots, last]
+    } else if self.ly == LINES_PER_FRAME - 1 {
+      &[0, LINE_153_LY_DOTS, last]
+    } else {
+      &[0, last]
+    };
+    let next = boundaries.iter().copied().find(|&b| b >= self.dot)?;
+    Some(u32::from(next - self.dot) + 1)
+  }
+
+  /// `LY` as the CPU sees it. Only a few dots into line 153, `LY` already
+  /// reads as 0.
+  #[inline]
+  const fn visible_ly(&self) -> u8 {
+    if self.ly == LINES_PER_FRAME - 1 && self.dot >= LINE_153_LY_DOTS {
+      0
+    } else {
+      self.ly
+    }
+  }
+
+  /// Updates the internal STAT interrupt line.
+  ///
+  /// * **Returns:** If the line went from low to high, which is the only time
+  ///   that the STAT interrupt is requested.
+  fn update_stat_line(&mut self) -> bool {
+    let line = (u8_get_bit(6, self.stat) && self.visible_ly() == self.lyc)
+      || match self.mode {
+        PpuMode::HBlank => u8_get_bit(3, self.stat),
+        PpuMode::VBlank => u8_get_bit(4, self.stat),
+        PpuMode::OamScan => u8_get_bit(5, self.stat),
+        PpuMode::Drawing => false,
+      };
+    let rising = line && !self.stat_line;
+    self.stat_line = line;
+    rising
+  }
+
+  /// Offset within a VRAM bank of a BG/window tile, using the `LCDC` bit 4
+  /// addressing mode.
+  #[inline]
+  #[must_use]
+  fn bg_tile_offset(&self, tile: u8) -> usize {
+    if u8_get_bit(4, self.lcdc) {
+      usize::from(tile) * 16
+    } else {
+      (0x1000 + i32::from(tile as i8) * 16) as usize
+    }
+  }
+
+  /// The color index of one pixel of tile data. The `row` can go past 7 to
+  /// reach into the following tile, which is how tall objects work.
+  #[inline]
+  #[must_use]
+  fn tile_pixel(&self, bank: u8, offset: usize, row: u8, col: u8) -> u8 {
+    let data = self.vram.bank(bank);
+    let i = offset + usize::from(row) * 2;
+    let bit = u32::from(7 - col);
+    ((u8_get_bit(bit, data[i + 1]) as u8) << 1)
+      | (u8_get_bit(bit, data[i]) as u8)
+  }
+
+  fn render_line(&mut self) {
+    let cgb = self.is_cgb();
+    let ly = self.ly;
+    // In CGB mode LCDC bit 0 only takes away BG priority, on a DMG it turns
+    // the BG and window off entirely.
+    let bg_priority = u8_get_bit(0, self.lcdc);
+    let mut drawing_dots = MIN_DRAWING_DOTS + u16::from(self.scx & 7);
+
+    // BG and window
+    let mut bg_index = [0_u8; SCREEN_WIDTH];
+    let mut bg_attrs = [BgAttributes::default(); SCREEN_WIDTH];
+    if cgb || bg_priority {
+      let bg_map = if u8_get_bit(3, self.lcdc) { 0x1C00 } else { 0x1800 };
+      let win_map = if u8_get_bit(6, self.lcdc) { 0x1C00 } else { 0x1800 };
+      let win_x = i16::from(self.wx) - 7;
+      let window_on_line = u8_get_bit(5, self.lcdc)
+        && self.window_triggered
+        && win_x < SCREEN_WIDTH as i16;
+      for x in 0..SCREEN_WIDTH {
+        let (map, tx, ty) = if window_on_line && (x as i16) >= win_x {
+          (win_map, (x as i16 - win_x) as u8, self.window_line)
+        } else {
+          (bg_map, self.scx.wrapping_add(x as u8), self.scy.wrapping_add(ly))
+        };
+        let map_offset = map + usize::from(ty / 8) * 32 + usize::from(tx / 8);
+        let tile = self.vram.bank(0)[map_offset];
+        let attrs = if cgb {
+          BgAttributes(self.vram.bank(1)[map_offset])
+        } else {
+          BgAttributes::default()
+        };
+        let row = if attrs.y_flip() { 7 - ty % 8 } else { ty % 8 };
+        let col = if attrs.x_flip() { 7 - tx % 8 } else { tx % 8 };
+        bg_index[x] =
+          self.tile_pixel(attrs.bank(), self.bg_tile_offset(tile), row, col);
+        bg_attrs[x] = attrs;
+      }
+      if window_on_line {
+        self.window_line += 1;
+        drawing_dots += 6;
+      }
+    }
+
+    // Objects
+    let mut obj_pixels = [None::<(u8, ObjAttributes)>; SCREEN_WIDTH];
+    if u8_get_bit(1, self.lcdc) {
+      let height = if u8_get_bit(2, self.lcdc) { 16 } else { 8 };
+      let mut objs = [ObjEntry::default(); OBJS_PER_LINE];
+      let mut count = 0;
+      for entry in self.oam.chunks_exact(4) {
+        let top = i16::from(entry[0]) - 16;
+        if (top..top + height).contains(&i16::from(ly)) {
+          objs[count] = ObjEntry {
+            y: entry[0],
+            x: entry[1],
+            tile: entry[2],
+            attrs: ObjAttributes(entry[3]),
+          };
+          count += 1;
+          if count == OBJS_PER_LINE {
+            break;
+          }
+        }
+      }
+      let objs = &mut objs[..count];
+      if !cgb || self.opri != 0 {
+        // DMG priority goes to the lowest X, with OAM order breaking ties.
+        // A CGB uses this too when `OPRI` (`$FF6C`) asks for it.
+        objs.sort_by_key(|obj| obj.x);
+      }
+      drawing_dots += 6 * count as u16;
+      for obj in objs.iter() {
+        let mut row = (i16::from(ly) - (i16::from(obj.y) - 16)) as u8;
+        if obj.attrs.y_flip() {
+          row = height as u8 - 1 - row;
+        }
+        let tile = if height == 16 { obj.tile & 0xFE } else { obj.tile };
+        let bank = if cgb { obj.attrs.bank() } else { 0 };
+        for px in 0..8_u8 {
+          let sx = i16::from(obj.x) - 8 + i16::from(px);
+          if !(0..SCREEN_WIDTH as i16).contains(&sx) {
+            continue;
+          }
+          let slot = &mut obj_pixels[sx as usize];
+          if slot.is_some() {
+            continue;
+          }
+          let col = if obj.attrs.x_flip() { 7 - px } else { px };
+          let color = self.tile_pixel(bank, usize::from(tile) * 16, row, col);
+          if color != 0 {
+            *slot = Some((color, obj.attrs));
+          }
+        }
+      }
+    }
+
+    // Mixing
+    let line_start = usize::from(ly) * SCREEN_WIDTH;
+    for x in 0..SCREEN_WIDTH {
+      let bi = bg_index[x];
+      let out = line_start + x;
+      if cgb {
+        let attrs = bg_attrs[x];
+        let mut color = self.bg_palettes.color(attrs.palette(), bi);
+        if let Some((oi, obj_attrs)) = obj_pixels[x] {
+          let bg_wins = bg_priority
+            && bi != 0
+            && (attrs.priority() || obj_attrs.behind_bg());
+          if !bg_wins {
+            color = self.obj_palettes.color(obj_attrs.cgb_palette(), oi);
+          }
+        }
+        self.frame[out] = color;
+      } else {
+        let mut shade = dmg_shade(self.bgp, bi);
+        let mut obj_palette = None;
+        if let Some((oi, obj_attrs)) = obj_pixels[x] {
+          if !(obj_attrs.behind_bg() && bi != 0) {
+            let obp =
+              if obj_attrs.dmg_palette() { self.obp1 } else { self.obp0 };
+            shade = dmg_shade(obp, oi);
+            obj_palette = Some(obj_attrs.dmg_palette() as u8);
+          }
+        }
+        if !bg_priority && obj_pixels[x].is_none() {
+          shade = 0;
+        }
+        self.shades[out] = shade;
+        self.frame[out] = match obj_palette {
+          _ if !self.dmg_compat => self.dmg_colors[usize::from(shade)],
+          None => self.bg_palettes.color(0, shade),
+          Some(p) => self.obj_palettes.color(p, shade),
+        };
+      }
+    }
+
+    self.drawing_dots = drawing_dots.min(MAX_DRAWING_DOTS);
+  }
+}
+
+#[test]
+fn test_Ppu_cgb_bg_attributes() {
+  let mut ppu = Ppu::new(true);
+  // tile 1 of bank 1 has just its top-left pixel set to color 1
+  ppu.vram_mut().bank_mut(1)[16] = 0b1000_0000;
+  // the first map entry uses that tile with palette 2 and an X flip
+  ppu.vram_mut().bank_mut(0)[0x1800] = 1;
+  ppu.vram_mut().bank_mut(1)[0x1800] = 0b0010_1010;
+  ppu.write_reg(0xFF68, 0x80 | (2 * 8 + 2));
+  ppu.write_reg(0xFF69, 0x1F);
+  ppu.write_reg(0xFF69, 0x00);
+  assert_eq!(ppu.read_reg(0xFF68), 0xC0 | (2 * 8 + 4));
+  ppu.write_reg(0xFF40, 0x91);
+  for _ in 0..=OAM_SCAN_DOTS {
+    ppu.t_cycle();
+  }
+  assert_eq!(ppu.mode(), PpuMode::Drawing);
+  assert_eq!(ppu.read_reg(0xFF69), 0xFF);
+  for _ in OAM_SCAN_DOTS + 1..DOTS_PER_LINE {
+    ppu.t_cycle();
+  }
+  assert_eq!(ppu.ly(), 1);
+  assert_eq!(ppu.frame()[7], 0x001F);
+  assert_eq!(ppu.frame()[0], RGB555_WHITE);
+}
+
+/// Sets every pixel of a tile (addressed from `$8000`) to one color index.
+#[cfg(test)]
+fn fill_tile(ppu: &mut Ppu, bank: u8, tile: usize, color: u8) {
+  let lo = if u8_get_bit(0, color) { 0xFF } else { 0x00 };
+  let hi = if u8_get_bit(1, color) { 0xFF } else { 0x00 };
+  for row in ppu.vram_mut().bank_mut(bank)[tile * 16..][..16].chunks_mut(2) {
+    row.copy_from_slice(&[lo, hi]);
+  }
+}
+
+#[test]
+fn test_Ppu_dmg_objects() {
+  let mut ppu = Ppu::new(false);
+  fill_tile(&mut ppu, 0, 1, 3);
+  fill_tile(&mut ppu, 0, 2, 1);
+  // the first BG map entry is color 1, the rest are color 0.
+  ppu.vram_mut().bank_mut(0)[0x1800] = 2;
+  ppu.write_reg(0xFF47, 0b11_10_01_00);
+  ppu.write_reg(0xFF48, 0b11_10_01_00);
+  // behind the BG, at X 4, overlapping both BG tiles.
+  ppu.oam_mut()[0..4].copy_from_slice(&[16, 8 + 4, 1, 0x80]);
+  // 10 more objects on the line, but the last one is past the limit.
+  for i in 1..11 {
+    ppu.oam_mut()[i * 4..][..4].copy_from_slice(&[16, 8 + 10 * i as u8, 1, 0]);
+  }
+  ppu.write_reg(0xFF40, 0x93);
+  ppu.advance(u32::from(DOTS_PER_LINE));
+  let line = &ppu.shades()[..SCREEN_WIDTH];
+  assert_eq!(line[0..12], [1, 1, 1, 1, 1, 1, 1, 1, 3, 3, 3, 3]);
+  for i in 2..10 {
+    assert_eq!(line[10 * i], 3, "object {i}");
+  }
+  assert_eq!(line[100], 0);
+
+  // with LCDC bit 0 off a DMG draws no BG, so nothing hides the object.
+  ppu.write_reg(0xFF40, 0x92);
+  ppu.advance(DOTS_PER_FRAME);
+  let line = &ppu.shades()[..SCREEN_WIDTH];
+  assert_eq!(line[0..6], [0, 0, 0, 0, 3, 3]);
+}
+
+#[test]
+fn test_Ppu_window() {
+  let mut ppu = Ppu::new(false);
+  fill_tile(&mut ppu, 0, 1, 1);
+  fill_tile(&mut ppu, 0, 2, 2);
+  // window tile rows 0 and 1 are color 1 and color 2, the rest are color 0.
+  ppu.vram_mut().bank_mut(0)[0x1C00..0x1C20].fill(1);
+  ppu.vram_mut().bank_mut(0)[0x1C20..0x1C40].fill(2);
+  ppu.write_reg(0xFF47, 0b11_10_01_00);
+  ppu.write_reg(0xFF4A, 4);
+  ppu.write_reg(0xFF4B, 7 + 80);
+  ppu.write_reg(0xFF40, 0xF1);
+  let line = |ppu: &Ppu, ly: usize| {
+    let start = ly * SCREEN_WIDTH;
+    (ppu.shades()[start + 79], ppu.shades()[start + 80])
+  };
+  ppu.advance(u32::from(DOTS_PER_LINE) * 12);
+  assert_eq!(line(&ppu, 3), (0, 0));
+  assert_eq!(line(&ppu, 4), (0, 1));
+  assert_eq!(line(&ppu, 11), (0, 1));
+  // turning the window off pauses its line counter, so when it comes back on
+  // it picks up with window line 8, not `LY - WY`.
+  ppu.write_reg(0xFF40, 0xD1);
+  ppu.advance(u32::from(DOTS_PER_LINE) * 8);
+  assert_eq!(line(&ppu, 19), (0, 0));
+  ppu.write_reg(0xFF40, 0xF1);
+  ppu.advance(u32::from(DOTS_PER_LINE) * 9);
+  assert_eq!(line(&ppu, 20), (0, 2));
+  assert_eq!(line(&ppu, 27), (0, 2));
+  assert_eq!(line(&ppu, 28), (0, 0));
+}
+
+#[test]
+fn test_Ppu_cgb_obj_priority() {
+  const RED: u16 = 0x001F;
+  const GREEN: u16 = 0x03E0;
+  const BLUE: u16 = 0x7C00;
+  let mut ppu = Ppu::new(true);
+  fill_tile(&mut ppu, 0, 1, 3);
+  fill_tile(&mut ppu, 0, 2, 1);
+  // the first two BG map entries are color 1, and the first has the priority
+  // attribute.
+  ppu.vram_mut().bank_mut(0)[0x1800..0x1802].fill(2);
+  ppu.vram_mut().bank_mut(1)[0x1800] = 0x80;
+  let colors = [(0x80 | 2, GREEN), (0x80 | 6, RED), (0x80 | 14, BLUE)];
+  for (i, &(spec, color)) in colors.iter().enumerate() {
+    let [lo, hi] = color.to_le_bytes();
+    let (spec_reg, data_reg) =
+      if i == 0 { (0xFF68, 0xFF69) } else { (0xFF6A, 0xFF6B) };
+    ppu.write_reg(spec_reg, spec);
+    ppu.write_reg(data_reg, lo);
+    ppu.write_reg(data_reg, hi);
+  }
+  // a red object at X 4 in front of both BG tiles, and a blue object at X 2
+  // that comes later in OAM.
+  ppu.oam_mut()[0..4].copy_from_slice(&[16, 8 + 4, 1, 0]);
+  ppu.oam_mut()[4..8].copy_from_slice(&[16, 8 + 2, 1, 1]);
+  let line = |ppu: &mut Ppu, lcdc: u8, opri: u8| {
+    ppu.write_reg(0xFF6C, opri);
+    ppu.write_reg(0xFF40, 0);
+    ppu.write_reg(0xFF40, lcdc);
+    ppu.advance(u32::from(DOTS_PER_LINE));
+    [2, 4, 10].map(|x| ppu.frame()[x])
+  };
+  // the BG attribute wins over both objects, until LCDC bit 0 takes away
+  // the BG's priority.
+  assert_eq!(line(&mut ppu, 0x93, 0), [GREEN, GREEN, RED]);
+  assert_eq!(line(&mut ppu, 0x92, 0), [BLUE, RED, RED]);
+  // `OPRI` bit 0 sorts by X like a DMG, so the blue object goes on top.
+  assert_eq!(ppu.read_reg(0xFF6C), 0xFE);
+  assert_eq!(line(&mut ppu, 0x92, 1), [BLUE, BLUE, RED]);
+  assert_eq!(ppu.read_reg(0xFF6C), 0xFF);
+}
@@ -0,0 +1,458 @@
+//! Emulates the Game Boy Printer, which is attached through the serial port.
+//!
+//! The game sends the printer packets of the following form, one byte per
+//! serial transfer:
+//!
+//! | Bytes | Meaning |
+//! |:-|:-|
+//! | `$88 $33` | Magic bytes |
+//! | 1 | Command |
+//! | 1 | Compression flag |
+//! | 2 | Data length (little-endian) |
+//! | N | Data |
+//! | 2 | Checksum (little-endian sum of the command through the data) |
+//! | `$00` | Printer replies `$81` (the device ID) |
+//! | `$00` | Printer replies with the status byte |
+//!
+//! * See Also: [Pandocs: Game Boy Printer](https://gbdev.io/pandocs/Gameboy_Printer.html)
+
+use alloc::{boxed::Box, vec::Vec};
+use core::fmt::{Debug, Write};
+
+use bitfrob::{u8_get_bit, u8_with_bit};
+
+use crate::{
+  png::{encode_png, ColorType},
+  serial::SerialDevice,
+};
+
+/// Initialize: clears the image buffer.
+pub const PRINTER_INIT: u8 = 0x01;
+/// Print: prints the image buffer, with settings given in the data.
+pub const PRINTER_PRINT: u8 = 0x02;
+/// Data: adds image data to the buffer. Empty data marks the end of the image.
+pub const PRINTER_DATA: u8 = 0x04;
+/// Status: does nothing, but the game still gets the status byte.
+pub const PRINTER_STATUS: u8 = 0x0F;
+
+/// The printer holds this many bytes of image data (9 bands of 2 tile rows).
+pub const PRINTER_BUFFER_SIZE: usize = 0x1680;
+
+/// Bytes of 2bpp tile data in one tile row of printer image data.
+const TILE_ROW_BYTES: usize = 20 * 16;
+
+/// How many status replies after a print the printer claims to still be busy.
+const BUSY_STATUS_REPLIES: u8 = 4;
+
+/// The status byte that the printer sends back at the end of each packet.
+#[derive(Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct PrinterStatus(u8);
+impl PrinterStatus {
+  #[inline]
+  #[must_use]
+  pub const fn new(u: u8) -> Self {
+    Self(u)
+  }
+  #[inline]
+  #[must_use]
+  pub const fn get(self) -> u8 {
+    self.0
+  }
+  #[inline]
+  #[must_use]
+  pub const fn checksum_error(self) -> bool {
+    u8_get_bit(0, self.0)
+  }
+  #[inline]
+  pub fn set_checksum_error(&mut self, val: bool) {
+    self.0 = u8_with_bit(0, self.0, val);
+  }
+  #[inline]
+  #[must_use]
+  pub const fn printing(self) -> bool {
+    u8_get_bit(1, self.0)
+  }
+  #[inline]
+  pub fn set_printing(&mut self, val: bool) {
+    self.0 = u8_with_bit(1, self.0, val);
+  }
+  #[inline]
+  #[must_use]
+  pub const fn image_data_full(self) -> bool {
+    u8_get_bit(2, self.0)
+  }
+  #[inline]
+  pub fn set_image_data_full(&mut self, val: bool) {
+    self.0 = u8_with_bit(2, self.0, val);
+  }
+  #[inline]
+  #[must_use]
+  pub const fn unprocessed_data(self) -> bool {
+    u8_get_bit(3, self.0)
+  }
+  #[inline]
+  pub fn set_unprocessed_data(&mut self, val: bool) {
+    self.0 = u8_with_bit(3, self.0, val);
+  }
+  #[inline]
+  #[must_use]
+  pub const fn packet_error(self) -> bool {
+    u8_get_bit(4, self.0)
+  }
+  #[inline]
+  pub fn set_packet_error(&mut self, val: bool) {
+    self.0 = u8_with_bit(4, self.0, val);
+  }
+}
+impl Debug for PrinterStatus {
+  fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+    for (bit, c) in [(4, 'P'), (3, 'U'), (2, 'F'), (1, 'B'), (0, 'S')] {
+      if u8_get_bit(bit, self.0) {
+        f.write_char(c)?;
+      } else {
+        f.write_char('_')?;
+      }
+    }
+    Ok(())
+  }
+}
+
+/// One print job, as it would come out of the printer.
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PrintedImage {
+  /// Always 160.
+  pub width: usize,
+  /// A multiple of 8.
+  pub height: usize,
+  /// One byte per pixel, row by row, with `$00` as black and `$FF` as white.
+  pub pixels: Vec<u8>,
+  /// Copies requested. Zero means the game only wanted a line feed.
+  pub sheets: u8,
+  /// Blank lines to feed before the image.
+  pub margin_before: u8,
+  /// Blank lines to feed after the image.
+  pub margin_after: u8,
+  /// The palette that mapped color indexes to shades, `$E4` is the identity.
+  pub palette: u8,
+  /// Print darkness from `$00` to `$7F`, with `$40` being normal.
+  pub exposure: u8,
+}
+impl PrintedImage {
+  /// Encodes the image as a grayscale PNG file.
+  #[must_use]
+  pub fn to_png(&self) -> Vec<u8> {
+    encode_png(
+      self.width as u32,
+      self.height as u32,
+      ColorType::Grayscale,
+      &self.pixels,
+    )
+  }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+enum PacketState {
+  #[default]
+  Magic0,
+  Magic1,
+  Command,
+  Compression,
+  LengthLow,
+  LengthHigh,
+  Data,
+  ChecksumLow,
+  ChecksumHigh,
+  DeviceId,
+  Status,
+}
+
+/// A Game Boy Printer.
+///
+/// Plug this into a [SerialPort](crate::serial::SerialPort). Each time the
+/// game prints, the callback gets the finished image.
+pub struct GbPrinter {
+  state: PacketState,
+  command: u8,
+  compressed: bool,
+  length: u16,
+  data: Vec<u8>,
+  checksum: u16,
+  expected_checksum: u16,
+  buffer: Vec<u8>,
+  status: PrinterStatus,
+  busy_replies: u8,
+  on_print: Box<dyn FnMut(&PrintedImage)>,
+}
+impl GbPrinter {
+  /// Makes a new printer which passes each printed image to `on_print`.
+  pub fn new(on_print: impl FnMut(&PrintedImage) + 'static) -> Self {
+    Self {
+      state: PacketState::default(),
+      command: 0,
+      compressed: false,
+      length: 0,
+      data: Vec::new(),
+      checksum: 0,
+      expected_checksum: 0,
+      buffer: Vec::with_capacity(PRINTER_BUFFER_SIZE),
+      status: PrinterStatus::default(),
+      busy_replies: 0,
+      on_print: Box::new(on_print),
+    }
+  }
+
+  #[inline]
+  #[must_use]
+  pub const fn status(&self) -> PrinterStatus {
+    self.status
+  }
+
+  /// The image data received since the last print, as 2bpp tile data.
+  #[inline]
+  #[must_use]
+  pub fn buffer(&self) -> &[u8] {
+    &self.buffer
+  }
+
+  fn process_packet(&mut self) {
+    if self.checksum != self.expected_checksum {
+      self.status.set_checksum_error(true);
+      return;
+    }
+    self.status.set_checksum_error(false);
+    match self.command {
+      PRINTER_INIT => {
+        self.buffer.clear();
+        self.status = PrinterStatus::default();
+        self.busy_replies = 0;
+      }
+      PRINTER_DATA => {
+        if self.data.is_empty() {
+          self.status.set_image_data_full(true);
+        } else {
+          if self.compressed {
+            rle_decompress_into(&self.data, &mut self.buffer);
+          } else {
+            self.buffer.extend_from_slice(&self.data);
+          }
+          if self.buffer.len() >= PRINTER_BUFFER_SIZE {
+            self.buffer.truncate(PRINTER_BUFFER_SIZE);
+            self.status.set_image_data_full(true);
+          }
+          self.status.set_unprocessed_data(true);
+        }
+      }
+      PRINTER_PRINT => {
+        if self.data.len() != 4 {
+          self.status.set_packet_error(true);
+          return;
+        }
+        let image =
+          self.render(self.data[0], self.data[1], self.data[2], self.data[3]);
+        (self.on_print)(&image);
+        self.buffer.clear();
+        self.status.set_unprocessed_data(false);
+        self.status.set_printing(true);
+        self.busy_replies = BUSY_STATUS_REPLIES;
+      }
+      PRINTER_STATUS => {
+        if self.busy_replies > 0 {
+          self.busy_replies -= 1;
+          if self.busy_replies == 0 {
+            self.status.set_printing(false);
+            self.status.set_image_data_full(false);
+          }
+        }
+      }
+      _ => self.status.set_packet_error(true),
+    }
+  }
+
+  fn render(
+    &self, sheets: u8, margins: u8, palette: u8, exposure: u8,
+  ) -> PrintedImage {
+    const SHADES: [u8; 4] = [0xFF, 0xAA, 0x55, 0x00];
+    let width = 160;
+    let tile_rows = self.buffer.len() / TILE_ROW_BYTES;
+    let height = tile_rows * 8;
+    let mut pixels = Vec::with_capacity(width * height);
+    for tile_row in self.buffer.chunks_exact(TILE_ROW_BYTES) {
+      for line in 0..8 {
+        for tile in tile_row.chunks_exact(16) {
+          let low = tile[line * 2];
+          let high = tile[line * 2 + 1];
+          for bit in (0..8).rev() {
+            let index = (u8::from(u8_get_bit(bit, high)) << 1)
+              | u8::from(u8_get_bit(bit, low));
+            let shade = (palette >> (index * 2)) & 0b11;
+            pixels.push(SHADES[usize::from(shade)]);
+          }
+        }
+      }
+    }
+    PrintedImage {
+      width,
+      height,
+      pixels,
+      sheets,
+      margin_before: margins >> 4,
+      margin_after: margins & 0xF,
+      palette,
+      exposure: exposure & 0x7F,
+    }
+  }
+}
+impl SerialDevice for GbPrinter {
+  fn exchange(&mut self, byte: u8) -> u8 {
+    use PacketState::*;
+    let mut reply = 0x00;
+    self.state = match self.state {
+      Magic0 => {
+        if byte == 0x88 {
+          Magic1
+        } else {
+          Magic0
+        }
+      }
+      Magic1 => match byte {
+        0x33 => Command,
+        0x88 => Magic1,
+        _ => Magic0,
+      },
+      Command => {
+        self.command = byte;
+        self.checksum = u16::from(byte);
+        Compression
+      }
+      Compression => {
+        self.compressed = u8_get_bit(0, byte);
+        self.checksum = self.checksum.wrapping_add(u16::from(byte));
+        LengthLow
+      }
+      LengthLow => {
+        self.length = u16::from(byte);
+        self.checksum = self.checksum.wrapping_add(u16::from(byte));
+        LengthHigh
+      }
+      LengthHigh => {
+        self.length |= u16::from(byte) << 8;
+        self.checksum = self.checksum.wrapping_add(u16::from(byte));
+        self.data.clear();
+        if self.length == 0 {
+          ChecksumLow
+        } else {
+          Data
+        }
+      }
+      Data => {
+        self.data.push(byte);
+        self.checksum = self.checksum.wrapping_add(u16::from(byte));
+        if self.data.len() < usize::from(self.length) {
+          Data
+        } else {
+          ChecksumLow
+        }
+      }
+      ChecksumLow => {
+        self.expected_checksum = u16::from(byte);
+        ChecksumHigh
+      }
+      ChecksumHigh => {
+        self.expected_checksum |= u16::from(byte) << 8;
+        self.process_packet();
+        DeviceId
+      }
+      DeviceId => {
+        reply = 0x81;
+        Status
+      }
+      Status => {
+        reply = self.status.get();
+        Magic0
+      }
+    };
+    reply
+  }
+}
+
+/// Expands the printer's run-length encoding.
+///
+/// Each control byte with bit 7 set repeats the following byte
+/// `(control & 0x7F) + 2` times. Otherwise the next `control + 1` bytes are
+/// copied as is.
+fn rle_decompress_into(mut src: &[u8], dest: &mut Vec<u8>) {
+  while let [control, rest @ ..] = src {
+    if u8_get_bit(7, *control) {
+      let Some((&byte, rest)) = rest.split_first() else { break };
+      let count = usize::from(control & 0x7F) + 2;
+      dest.extend(core::iter::repeat_n(byte, count));
+      src = rest;
+    } else {
+      let count = usize::from(*control) + 1;
+      let (literal, rest) = rest.split_at(count.min(rest.len()));
+      dest.extend_from_slice(literal);
+      src = rest;
+    }
+  }
+}
+
+#[test]
+fn test_GbPrinter_exchange() {
+  use alloc::rc::Rc;
+  use core::cell::RefCell;
+
+  fn send(
+    printer: &mut GbPrinter, command: u8, compressed: bool, data: &[u8],
+  ) -> [u8; 2] {
+    let mut packet = alloc::vec![0x88, 0x33, command, u8::from(compressed)];
+    packet.extend_from_slice(&(data.len() as u16).to_le_bytes());
+    packet.extend_from_slice(data);
+    let sum =
+      packet[2..].iter().fold(0_u16, |s, &b| s.wrapping_add(u16::from(b)));
+    packet.extend_from_slice(&sum.to_le_bytes());
+    packet.extend_from_slice(&[0, 0]);
+    let replies: Vec<u8> =
+      packet.iter().map(|&b| printer.exchange(b)).collect();
+    [replies[replies.len() - 2], replies[replies.len() - 1]]
+  }
+
+  let printed = Rc::new(RefCell::new(Vec::new()));
+  let sink = printed.clone();
+  let mut printer =
+    GbPrinter::new(move |image| sink.borrow_mut().push(image.clone()));
+
+  assert_eq!(send(&mut printer, PRINTER_INIT, false, &[]), [0x81, 0x00]);
+  // one tile row where every pixel is color 3, sent compressed.
+  assert_eq!(
+    send(
+      &mut printer,
+      PRINTER_DATA,
+      true,
+      &[0xFF, 0xFF, 0xFF, 0xFF, 0xBC, 0xFF]
+    ),
+    [0x81, 0x08]
+  );
+  assert_eq!(printer.buffer().len(), TILE_ROW_BYTES);
+  assert_eq!(send(&mut printer, PRINTER_DATA, false, &[]), [0x81, 0x0C]);
+  assert_eq!(
+    send(&mut printer, PRINTER_PRINT, false, &[1, 0x13, 0xE4, 0x40]),
+    [0x81, 0x06]
+  );
+  assert_eq!(printed.borrow().len(), 1);
+  let image = &printed.borrow()[0];
+  assert_eq!((image.width, image.height), (160, 8));
+  assert!(image.pixels.iter().all(|&p| p == 0x00));
+  assert_eq!((image.margin_before, image.margin_after), (1, 3));
+  for _ in 0..BUSY_STATUS_REPLIES {
+    send(&mut printer, PRINTER_STATUS, false, &[]);
+  }
+  assert_eq!(send(&mut printer, PRINTER_STATUS, false, &[]), [0x81, 0x00]);
+
+  // a bad checksum is reported and the packet is ignored
+  let replies: Vec<u8> = [0x88, 0x33, PRINTER_INIT, 0, 0, 0, 0xAA, 0xAA, 0, 0]
+    .iter()
+    .map(|&b| printer.exchange(b))
+    .collect();
+  assert_eq!(replies[8..], [0x81, 0x01]);
+}
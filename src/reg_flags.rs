@@ -7,6 +7,17 @@ use bitfrob::{u8_get_bit, u8_with_bit};
 #[repr(transparent)]
 pub struct RegFlags(u8);
 impl RegFlags {
+  /// Makes the flags from a byte. The low 4 bits are always zero.
+  #[inline]
+  #[must_use]
+  pub const fn new(u: u8) -> Self {
+    Self(u & 0xF0)
+  }
+  #[inline]
+  #[must_use]
+  pub const fn get(self) -> u8 {
+    self.0
+  }
   #[inline]
   #[must_use]
   pub const fn z(self) -> bool {
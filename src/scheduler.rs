@@ -0,0 +1,110 @@
+//! Timestamps of upcoming events, so that components only need to be caught
+//! up when something actually happens.
+//!
+//! Times are in system T-cycles (dots) since power on. There's only ever one
+//! pending event of each kind, and only a handful of kinds, so the scheduler is
+//! just a table with one timestamp per kind. Finding the next event is a scan
+//! of that table, which beats a heap at this size and makes rescheduling an
+//! event a single store.
+
+/// A kind of event that something outside the component cares about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(u8)]
+pub enum Event {
+  /// The timer's `TMA` reload, which requests the timer interrupt.
+  TimerIrq,
+  /// A `DIV` bit falling, which steps the APU's frame sequencer.
+  FrameSequencer,
+  /// The PPU changing mode, line, or `LY`.
+  Ppu,
+  /// A serial transfer finishing.
+  Serial,
+  /// OAM DMA copying its next byte.
+  OamDma,
+}
+impl Event {
+  /// Every event kind.
+  pub const ALL: [Self; EVENT_COUNT] = [
+    Self::TimerIrq,
+    Self::FrameSequencer,
+    Self::Ppu,
+    Self::Serial,
+    Self::OamDma,
+  ];
+}
+
+/// How many kinds of [Event] there are.
+pub const EVENT_COUNT: usize = 5;
+
+/// The timestamp used for an event that isn't scheduled.
+const NEVER: u64 = u64::MAX;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Scheduler {
+  times: [u64; EVENT_COUNT],
+  next: u64,
+}
+impl Default for Scheduler {
+  #[inline]
+  fn default() -> Self {
+    Self::new()
+  }
+}
+impl Scheduler {
+  /// Makes a scheduler with nothing scheduled.
+  #[inline]
+  #[must_use]
+  pub const fn new() -> Self {
+    Self { times: [NEVER; EVENT_COUNT], next: NEVER }
+  }
+
+  /// Sets when an event happens, replacing any previous time for it. A time
+  /// of `None` cancels the event.
+  #[inline]
+  pub fn schedule(&mut self, event: Event, time: Option<u64>) {
+    self.times[event as usize] = time.unwrap_or(NEVER);
+    self.next = self.times.iter().copied().min().unwrap_or(NEVER);
+  }
+
+  /// When an event is scheduled for.
+  #[inline]
+  #[must_use]
+  pub const fn time_of(&self, event: Event) -> Option<u64> {
+    match self.times[event as usize] {
+      NEVER => None,
+      t => Some(t),
+    }
+  }
+
+  /// The time of the soonest event, or `u64::MAX` if nothing is scheduled.
+  #[inline]
+  #[must_use]
+  pub const fn next_time(&self) -> u64 {
+    self.next
+  }
+
+  /// The soonest event and its time.
+  #[must_use]
+  pub fn next(&self) -> Option<(u64, Event)> {
+    Event::ALL
+      .iter()
+      .map(|&e| (self.times[e as usize], e))
+      .filter(|&(t, _)| t != NEVER)
+      .min()
+  }
+}
+
+#[test]
+fn test_Scheduler_next() {
+  let mut scheduler = Scheduler::new();
+  assert_eq!(scheduler.next(), None);
+  scheduler.schedule(Event::Ppu, Some(80));
+  scheduler.schedule(Event::Serial, Some(40));
+  scheduler.schedule(Event::TimerIrq, Some(40));
+  assert_eq!(scheduler.next(), Some((40, Event::TimerIrq)));
+  scheduler.schedule(Event::TimerIrq, None);
+  assert_eq!(scheduler.next(), Some((40, Event::Serial)));
+  scheduler.schedule(Event::Serial, Some(100));
+  assert_eq!(scheduler.next_time(), 80);
+  assert_eq!(scheduler.time_of(Event::OamDma), None);
+}
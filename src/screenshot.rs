@@ -0,0 +1,113 @@
+//! Screenshots of the PPU's picture, for test ROMs that are judged by how the
+//! screen looks (like dmg-acid2, cgb-acid2, and mealybug-tearoom).
+//!
+//! Screenshots are RGB888 bytes, row by row, which is what
+//! [encode_png](crate::png::encode_png) takes. DMG shades are the evenly
+//! spaced grays `$FF`, `$AA`, `$55`, `$00`, and CGB colors are scaled with
+//! no color correction, which is how the reference images of those test ROMs
+//! are made. A CGB running a DMG cart shows the colors of its compatibility
+//! palette, like the real thing.
+
+use alloc::{boxed::Box, string::String, vec::Vec};
+
+use crate::{
+  gameboy::GameBoy, palette::ColorCorrection, serial::SerialCapture,
+};
+
+/// When a test ROM is done drawing.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Trigger {
+  /// The ROM runs `ld b, b`.
+  Breakpoint,
+  /// A number of frames have passed.
+  Frames(u32),
+  /// The ROM has sent this text over the serial port.
+  Serial(String),
+}
+
+/// Runs until the trigger, or for `max_frames`.
+///
+/// * **Returns:** If the trigger happened.
+pub fn run_until(gb: &mut GameBoy, trigger: &Trigger, max_frames: u32) -> bool {
+  match trigger {
+    Trigger::Breakpoint => {
+      gb.cpu_mut().ld_b_b_breakpoint = true;
+      (0..max_frames).any(|_| gb.run_frame().breakpoint)
+    }
+    Trigger::Frames(frames) => {
+      let frames_to_run = (*frames).min(max_frames);
+      for _ in 0..frames_to_run {
+        gb.run_frame();
+      }
+      frames_to_run == *frames
+    }
+    Trigger::Serial(text) => {
+      let serial = SerialCapture::new();
+      gb.serial_mut().attach(Box::new(serial.clone()));
+      let found = (0..max_frames).any(|_| {
+        gb.run_frame();
+        serial.contains(text.as_bytes())
+      });
+      gb.serial_mut().detach();
+      found
+    }
+  }
+}
+
+/// The last drawn picture, as RGB888.
+#[must_use]
+pub fn screenshot(gb: &GameBoy) -> Vec<u8> {
+  let ppu = gb.ppu();
+  if ppu.is_cgb() || ppu.is_dmg_compat() {
+    ppu.frame_rgb888(ColorCorrection::None)
+  } else {
+    const GRAYS: [u8; 4] = [0xFF, 0xAA, 0x55, 0x00];
+    ppu.shades().iter().flat_map(|&s| [GRAYS[usize::from(s & 3)]; 3]).collect()
+  }
+}
+
+/// How two screenshots differ.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ScreenDiff {
+  /// How many pixels are different.
+  pub pixels: usize,
+  /// An RGB888 picture of the differences: the reference faded out, with
+  /// each different pixel in red.
+  pub image: Vec<u8>,
+}
+
+/// Compares two RGB888 pictures of the same size.
+///
+/// * **Returns:** `None` if they're the same.
+///
+/// ## Panics
+/// * If the pictures are different sizes.
+#[must_use]
+pub fn diff_screens(ours: &[u8], reference: &[u8]) -> Option<ScreenDiff> {
+  assert_eq!(ours.len(), reference.len(), "different sized pictures");
+  let mut pixels = 0;
+  let image = ours
+    .chunks_exact(3)
+    .zip(reference.chunks_exact(3))
+    .flat_map(|(a, b)| {
+      if a == b {
+        let gray = (b.iter().map(|&c| u16::from(c)).sum::<u16>() / 3) as u8;
+        [0xC0 | (gray >> 2); 3]
+      } else {
+        pixels += 1;
+        [0xFF, 0x00, 0x00]
+      }
+    })
+    .collect();
+  (pixels > 0).then_some(ScreenDiff { pixels, image })
+}
+
+#[test]
+fn test_diff_screens() {
+  let a = [0, 0, 0, 255, 255, 255];
+  let b = [0, 0, 0, 255, 255, 0];
+  assert_eq!(diff_screens(&a, &a), None);
+  let diff = diff_screens(&a, &b).unwrap();
+  assert_eq!(diff.pixels, 1);
+  assert_eq!(diff.image, [0xC0, 0xC0, 0xC0, 0xFF, 0x00, 0x00]);
+}
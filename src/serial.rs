@@ -0,0 +1,164 @@
+//! The serial port, and the devices that can be plugged into it.
+//!
+//! * See Also: [Pandocs: Serial Data Transfer](https://gbdev.io/pandocs/Serial_Data_Transfer_(Link_Cable).html)
+
+use alloc::{boxed::Box, rc::Rc, vec::Vec};
+use core::cell::RefCell;
+
+use bitfrob::u8_get_bit;
+
+/// Something on the other end of the link cable.
+pub trait SerialDevice {
+  /// Exchanges one byte with the device.
+  ///
+  /// The Game Boy shifts `byte` out while the device shifts its reply in, so
+  /// exactly one byte comes back for each byte sent.
+  fn exchange(&mut self, byte: u8) -> u8;
+}
+
+/// A device that keeps every byte the Game Boy sends, and replies `$FF` like
+/// nothing is plugged in.
+///
+/// Clones share the same bytes, so keep a clone to look at what was sent.
+#[derive(Debug, Clone, Default)]
+pub struct SerialCapture(pub Rc<RefCell<Vec<u8>>>);
+impl SerialCapture {
+  #[inline]
+  #[must_use]
+  pub fn new() -> Self {
+    Self::default()
+  }
+  /// If the bytes so far contain `needle`.
+  #[must_use]
+  pub fn contains(&self, needle: &[u8]) -> bool {
+    self.0.borrow().windows(needle.len().max(1)).any(|w| w == needle)
+  }
+}
+impl SerialDevice for SerialCapture {
+  fn exchange(&mut self, byte: u8) -> u8 {
+    self.0.borrow_mut().push(byte);
+    0xFF
+  }
+}
+
+/// CPU T-cycles per bit when the Game Boy drives the serial clock (8192 Hz).
+const T_CYCLES_PER_BIT: u32 = 512;
+
+/// The serial port registers, `SB` (`$FF01`) and `SC` (`$FF02`).
+///
+/// Transfers are modeled a byte at a time: once all 8 bits worth of time have
+/// passed, the byte in `SB` is exchanged with the attached device. With no
+/// device attached the line floats high, so `$FF` is shifted in.
+#[derive(Default)]
+pub struct SerialPort {
+  sb: u8,
+  sc: u8,
+  transfer_t_cycles: u32,
+  device: Option<Box<dyn SerialDevice>>,
+}
+impl SerialPort {
+  #[inline]
+  #[must_use]
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Plugs a device into the port, returning any previous device.
+  pub fn attach(
+    &mut self, device: Box<dyn SerialDevice>,
+  ) -> Option<Box<dyn SerialDevice>> {
+    self.device.replace(device)
+  }
+
+  /// Unplugs the current device, if any.
+  pub fn detach(&mut self) -> Option<Box<dyn SerialDevice>> {
+    self.device.take()
+  }
+
+  #[inline]
+  #[must_use]
+  pub const fn read_sb(&self) -> u8 {
+    self.sb
+  }
+  #[inline]
+  pub fn write_sb(&mut self, byte: u8) {
+    self.sb = byte;
+  }
+
+  #[inline]
+  #[must_use]
+  pub const fn read_sc(&self) -> u8 {
+    self.sc | 0b0111_1110
+  }
+  pub fn write_sc(&mut self, byte: u8) {
+    self.sc = byte & 0b1000_0001;
+    if self.transfer_requested() && self.internal_clock() {
+      self.transfer_t_cycles = 8 * T_CYCLES_PER_BIT;
+    } else {
+      self.transfer_t_cycles = 0;
+    }
+  }
+
+  #[inline]
+  #[must_use]
+  pub const fn transfer_requested(&self) -> bool {
+    u8_get_bit(7, self.sc)
+  }
+  #[inline]
+  #[must_use]
+  pub const fn internal_clock(&self) -> bool {
+    u8_get_bit(0, self.sc)
+  }
+
+  /// Grants many CPU T-cycles worth of time to the serial port at once.
+  ///
+  /// * **Returns:** If a transfer finished.
+  pub fn advance(&mut self, t_cycles: u32) -> bool {
+    if self.transfer_t_cycles == 0 {
+      return false;
+    }
+    if t_cycles < self.transfer_t_cycles {
+      self.transfer_t_cycles -= t_cycles;
+      return false;
+    }
+    self.transfer_t_cycles = 1;
+    self.t_cycle()
+  }
+
+  /// CPU T-cycles until the current transfer finishes, counting the T-cycle
+  /// where it happens.
+  ///
+  /// * **Returns:** `None` if no transfer is running.
+  #[inline]
+  #[must_use]
+  pub const fn t_cycles_until_irq(&self) -> Option<u32> {
+    if self.transfer_t_cycles == 0 {
+      None
+    } else {
+      Some(self.transfer_t_cycles)
+    }
+  }
+
+  /// Grants a CPU T-cycle worth of time to the serial port.
+  ///
+  /// Only transfers using the internal clock make progress. An external clock
+  /// transfer waits for a remote Game Boy, which we don't emulate.
+  ///
+  /// * **Returns:** If a transfer finished, which requests the serial
+  ///   interrupt.
+  pub fn t_cycle(&mut self) -> bool {
+    if self.transfer_t_cycles == 0 {
+      return false;
+    }
+    self.transfer_t_cycles -= 1;
+    if self.transfer_t_cycles != 0 {
+      return false;
+    }
+    self.sb = match self.device.as_mut() {
+      Some(device) => device.exchange(self.sb),
+      None => 0xFF,
+    };
+    self.sc &= 0b0111_1111;
+    true
+  }
+}
@@ -0,0 +1,681 @@
+//! Super Game Boy commands, which a game sends through the joypad register.
+//!
+//! A cart with `sgb_flag == 0x03` (see [CartHeader::supports_sgb]) can talk to
+//! the SNES by writing bits 4 and 5 of `P1` (`$FF00`):
+//!
+//! | P15 | P14 | Meaning |
+//! |:-:|:-:|:-|
+//! | 0 | 0 | Reset, starts a packet |
+//! | 1 | 0 | A 0 bit |
+//! | 0 | 1 | A 1 bit |
+//! | 1 | 1 | Release, between pulses |
+//!
+//! Each packet is 16 bytes sent LSB first, followed by a 0 stop bit. The
+//! first byte of a command's first packet is `command << 3 | packet_count`.
+//! Some commands also move 4 KiB of data by putting it on screen as tiles
+//! during the following frame.
+//!
+//! The SGB colors the DMG picture by giving each 8x8 cell of the screen one of
+//! four palettes (the "attribute map"), and mapping the cell's four DMG shades
+//! to that palette's colors. Color 0 is shared by all four palettes.
+//!
+//! The SGB also draws a 256x224 border around the game, from SNES tiles that
+//! the game sends with `CHR_TRN` and a tile map sent with `PCT_TRN`.
+//!
+//! * See Also: [Pandocs: SGB Functions](https://gbdev.io/pandocs/SGB_Functions.html)
+//!
+//! [CartHeader::supports_sgb]: crate::cart_header::CartHeader::supports_sgb
+
+use alloc::{boxed::Box, vec};
+use core::cmp::Ordering;
+
+use bitfrob::u8_get_bit;
+
+use crate::{
+  palette::DMG_GRAYS,
+  ppu::{SCREEN_HEIGHT, SCREEN_WIDTH},
+};
+
+/// Sets colors 0-3 of palettes 0 and 1.
+pub const SGB_PAL01: u8 = 0x00;
+/// Sets colors 0-3 of palettes 2 and 3.
+pub const SGB_PAL23: u8 = 0x01;
+/// Sets colors 0-3 of palettes 0 and 3.
+pub const SGB_PAL03: u8 = 0x02;
+/// Sets colors 0-3 of palettes 1 and 2.
+pub const SGB_PAL12: u8 = 0x03;
+/// Sets the attribute map inside, on the edge of, and outside of rectangles.
+pub const SGB_ATTR_BLK: u8 = 0x04;
+/// Sets the attribute map for whole rows or columns.
+pub const SGB_ATTR_LIN: u8 = 0x05;
+/// Splits the attribute map in two along a row or column.
+pub const SGB_ATTR_DIV: u8 = 0x06;
+/// Sets the attribute map cell by cell.
+pub const SGB_ATTR_CHR: u8 = 0x07;
+/// Sets palettes 0-3 from the system palettes, and maybe an attribute file.
+pub const SGB_PAL_SET: u8 = 0x0A;
+/// Transfers the 512 system palettes.
+pub const SGB_PAL_TRN: u8 = 0x0B;
+/// Requests multiplayer joypads.
+pub const SGB_MLT_REQ: u8 = 0x11;
+/// Transfers the 45 attribute files.
+pub const SGB_ATTR_TRN: u8 = 0x15;
+/// Sets the attribute map from an attribute file.
+pub const SGB_ATTR_SET: u8 = 0x16;
+/// Transfers 128 border tiles.
+pub const SGB_CHR_TRN: u8 = 0x13;
+/// Transfers the border's tile map and palettes.
+pub const SGB_PCT_TRN: u8 = 0x14;
+/// Masks the screen while the game updates it.
+pub const SGB_MASK_EN: u8 = 0x17;
+
+/// Bytes moved by a `_TRN` command.
+pub const SGB_TRANSFER_SIZE: usize = 0x1000;
+
+/// Width of the full SGB picture, with the border.
+pub const SGB_SCREEN_WIDTH: usize = 256;
+/// Height of the full SGB picture, with the border.
+pub const SGB_SCREEN_HEIGHT: usize = 224;
+/// Where the game's picture goes within the full SGB picture.
+const GAME_X: usize = (SGB_SCREEN_WIDTH - SCREEN_WIDTH) / 2;
+const GAME_Y: usize = (SGB_SCREEN_HEIGHT - SCREEN_HEIGHT) / 2;
+
+/// Attribute map width, in 8x8 cells.
+pub const ATTR_MAP_WIDTH: usize = SCREEN_WIDTH / 8;
+/// Attribute map height, in 8x8 cells.
+pub const ATTR_MAP_HEIGHT: usize = SCREEN_HEIGHT / 8;
+
+const PACKET_BYTES: usize = 16;
+const PACKET_BITS: u8 = PACKET_BYTES as u8 * 8;
+const MAX_PACKETS: usize = 7;
+const SYSTEM_PALETTES: usize = 512;
+const ATTR_FILES: usize = 45;
+const ATTR_FILE_BYTES: usize = ATTR_MAP_WIDTH * ATTR_MAP_HEIGHT / 4;
+/// Border tiles are SNES 4bpp tiles.
+const BORDER_TILE_BYTES: usize = 32;
+const BORDER_TILES: usize = 256;
+/// The border's 32x32 tile map, followed by palettes 4 through 7.
+const BORDER_MAP_BYTES: usize = 32 * 32 * 2;
+const BORDER_PALETTES: usize = 4;
+
+/// How `MASK_EN` is hiding the screen.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SgbMask {
+  /// The screen updates normally.
+  #[default]
+  None,
+  /// The last picture stays up.
+  Freeze,
+  /// The screen is all black.
+  Black,
+  /// The screen is all color 0.
+  Color0,
+}
+
+/// The data that the next frame's picture gets read as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+enum Transfer {
+  Palettes,
+  AttrFiles,
+  /// Tiles `$00..=$7F`, or `$80..=$FF` when `upper`.
+  BorderTiles {
+    upper: bool,
+  },
+  BorderMap,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Sgb {
+  /// The last written `P1` bits 4 and 5.
+  p1: u8,
+  /// Bits of the current packet so far, or `None` outside of a packet.
+  packet_bit: Option<u8>,
+  /// Pulses need a release (both lines high) between them.
+  awaiting_release: bool,
+  packet: [u8; PACKET_BYTES],
+  command: [u8; PACKET_BYTES * MAX_PACKETS],
+  packets: usize,
+  palettes: [[u16; 4]; 4],
+  system_palettes: Box<[[u16; 4]]>,
+  attr_map: [u8; ATTR_MAP_WIDTH * ATTR_MAP_HEIGHT],
+  attr_files: Box<[u8]>,
+  mask: SgbMask,
+  players: u8,
+  player: u8,
+  transfer: Option<Transfer>,
+  frame: Box<[u16]>,
+  border_tiles: Box<[u8]>,
+  border_map: Box<[u8]>,
+  border_palettes: [[u16; 16]; BORDER_PALETTES],
+  border_frame: Box<[u16]>,
+}
+impl Default for Sgb {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+impl Sgb {
+  /// Gray palettes, with every cell using palette 0.
+  #[must_use]
+  pub fn new() -> Self {
+    Self {
+      p1: 0x30,
+      packet_bit: None,
+      awaiting_release: false,
+      packet: [0; PACKET_BYTES],
+      command: [0; PACKET_BYTES * MAX_PACKETS],
+      packets: 0,
+      palettes: [DMG_GRAYS; 4],
+      system_palettes: vec![DMG_GRAYS; SYSTEM_PALETTES].into_boxed_slice(),
+      attr_map: [0; ATTR_MAP_WIDTH * ATTR_MAP_HEIGHT],
+      attr_files: vec![0; ATTR_FILES * ATTR_FILE_BYTES].into_boxed_slice(),
+      mask: SgbMask::None,
+      players: 1,
+      player: 0,
+      transfer: None,
+      frame: vec![DMG_GRAYS[0]; SCREEN_WIDTH * SCREEN_HEIGHT]
+        .into_boxed_slice(),
+      border_tiles: vec![0; BORDER_TILES * BORDER_TILE_BYTES]
+        .into_boxed_slice(),
+      border_map: vec![0; BORDER_MAP_BYTES].into_boxed_slice(),
+      border_palettes: [[0; 16]; BORDER_PALETTES],
+      border_frame: vec![DMG_GRAYS[0]; SGB_SCREEN_WIDTH * SGB_SCREEN_HEIGHT]
+        .into_boxed_slice(),
+    }
+  }
+
+  /// The four palettes in use, as RGB555.
+  #[inline]
+  #[must_use]
+  pub const fn palettes(&self) -> &[[u16; 4]; 4] {
+    &self.palettes
+  }
+  /// The palette (0-3) of each 8x8 cell, row by row.
+  #[inline]
+  #[must_use]
+  pub const fn attr_map(&self) -> &[u8; ATTR_MAP_WIDTH * ATTR_MAP_HEIGHT] {
+    &self.attr_map
+  }
+  #[inline]
+  #[must_use]
+  pub const fn mask(&self) -> SgbMask {
+    self.mask
+  }
+  /// Joypads being polled, 1, 2, or 4.
+  #[inline]
+  #[must_use]
+  pub const fn players(&self) -> u8 {
+    self.players
+  }
+  /// The joypad (counting from 0) whose buttons `P1` should show.
+  #[inline]
+  #[must_use]
+  pub const fn current_player(&self) -> u8 {
+    self.player
+  }
+  /// The colored picture, as RGB555, row by row.
+  #[inline]
+  #[must_use]
+  pub fn frame(&self) -> &[u16] {
+    &self.frame
+  }
+
+  /// The colored picture inside of the border, as RGB555, row by row.
+  ///
+  /// This is [SGB_SCREEN_WIDTH] by [SGB_SCREEN_HEIGHT], with the game's
+  /// picture in the middle. Until the game sends a border, it's all color 0
+  /// around the game's picture.
+  #[inline]
+  #[must_use]
+  pub fn border_frame(&self) -> &[u16] {
+    &self.border_frame
+  }
+
+  /// Watches a write to `P1`.
+  pub fn write_p1(&mut self, byte: u8) {
+    let lines = byte & 0x30;
+    let prev = core::mem::replace(&mut self.p1, lines);
+    match lines {
+      0x00 => {
+        self.packet_bit = Some(0);
+        self.packet = [0; PACKET_BYTES];
+        self.awaiting_release = true;
+      }
+      0x30 => {
+        self.awaiting_release = false;
+        // With multiplayer on, P15 going high moves to the next joypad.
+        if self.packet_bit.is_none() && !u8_get_bit(5, prev) {
+          self.player = (self.player + 1) % self.players;
+        }
+      }
+      _ => {
+        let Some(bit) = self.packet_bit else { return };
+        if self.awaiting_release {
+          return;
+        }
+        self.awaiting_release = true;
+        let one = lines == 0x10;
+        if bit < PACKET_BITS {
+          if one {
+            self.packet[usize::from(bit / 8)] |= 1 << (bit % 8);
+          }
+          self.packet_bit = Some(bit + 1);
+        } else {
+          self.packet_bit = None;
+          if !one {
+            self.finish_packet();
+          }
+        }
+      }
+    }
+  }
+
+  /// The joypad ID, which the low nibble of `P1` shows instead of buttons
+  /// when multiplayer is on and neither button group is selected.
+  #[inline]
+  #[must_use]
+  pub const fn joypad_id(&self) -> Option<u8> {
+    if self.players > 1 && self.p1 == 0x30 {
+      Some(0xF - self.player)
+    } else {
+      None
+    }
+  }
+
+  /// Colors a finished DMG picture, given as shades (see [Ppu::shades]).
+  ///
+  /// This should be called once per frame, at VBlank. If a `_TRN` command is
+  /// waiting for its data, that data is read from this picture.
+  ///
+  /// [Ppu::shades]: crate::ppu::Ppu::shades
+  pub fn vblank(&mut self, shades: &[u8]) {
+    if let Some(transfer) = self.transfer.take() {
+      let data = transfer_data(shades);
+      match transfer {
+        Transfer::Palettes => {
+          for (palette, bytes) in
+            self.system_palettes.iter_mut().zip(data.chunks_exact(8))
+          {
+            for (color, c) in palette.iter_mut().zip(bytes.chunks_exact(2)) {
+              *color = u16::from_le_bytes([c[0], c[1]]) & 0x7FFF;
+            }
+          }
+        }
+        Transfer::AttrFiles => {
+          let len = self.attr_files.len();
+          self.attr_files.copy_from_slice(&data[..len]);
+        }
+        Transfer::BorderTiles { upper } => {
+          let start = usize::from(upper) * SGB_TRANSFER_SIZE;
+          self.border_tiles[start..start + SGB_TRANSFER_SIZE]
+            .copy_from_slice(&data);
+        }
+        Transfer::BorderMap => {
+          self.border_map.copy_from_slice(&data[..BORDER_MAP_BYTES]);
+          let colors = data[BORDER_MAP_BYTES..].chunks_exact(2);
+          for (color, c) in
+            self.border_palettes.iter_mut().flatten().zip(colors)
+          {
+            *color = u16::from_le_bytes([c[0], c[1]]) & 0x7FFF;
+          }
+        }
+      }
+    }
+    match self.mask {
+      SgbMask::None => {
+        for (i, (out, &shade)) in self.frame.iter_mut().zip(shades).enumerate()
+        {
+          let (x, y) = (i % SCREEN_WIDTH, i / SCREEN_WIDTH);
+          let palette = self.attr_map[(y / 8) * ATTR_MAP_WIDTH + (x / 8)];
+          *out = self.palettes[usize::from(palette)][usize::from(shade & 3)];
+        }
+      }
+      SgbMask::Freeze => (),
+      SgbMask::Black => self.frame.fill(0),
+      SgbMask::Color0 => self.frame.fill(self.palettes[0][0]),
+    }
+    self.draw_border_frame();
+  }
+
+  /// Puts the game's picture in the middle, with the border drawn over it.
+  ///
+  /// Color 0 of a border tile is see-through, showing color 0 of the SGB
+  /// palettes (or the game's picture, in the middle).
+  fn draw_border_frame(&mut self) {
+    self.border_frame.fill(self.palettes[0][0]);
+    for (y, row) in self.frame.chunks_exact(SCREEN_WIDTH).enumerate() {
+      let start = (GAME_Y + y) * SGB_SCREEN_WIDTH + GAME_X;
+      self.border_frame[start..start + SCREEN_WIDTH].copy_from_slice(row);
+    }
+    for ty in 0..SGB_SCREEN_HEIGHT / 8 {
+      for tx in 0..SGB_SCREEN_WIDTH / 8 {
+        let i = (ty * 32 + tx) * 2;
+        let entry =
+          u16::from_le_bytes([self.border_map[i], self.border_map[i + 1]]);
+        let tile = usize::from(entry & 0xFF) * BORDER_TILE_BYTES;
+        let tile = &self.border_tiles[tile..tile + BORDER_TILE_BYTES];
+        // Palettes 4 to 7 are the border's palettes.
+        let palette = &self.border_palettes[usize::from((entry >> 10) & 3)];
+        let x_flip = (entry & (1 << 14)) != 0;
+        let y_flip = (entry & (1 << 15)) != 0;
+        for row in 0..8 {
+          let r = if y_flip { 7 - row } else { row };
+          let planes = [
+            tile[r * 2],
+            tile[r * 2 + 1],
+            tile[16 + r * 2],
+            tile[16 + r * 2 + 1],
+          ];
+          for col in 0..8 {
+            let bit = if x_flip { col } else { 7 - col };
+            let index = planes
+              .iter()
+              .enumerate()
+              .fold(0, |acc, (p, b)| acc | (((b >> bit) & 1) << p));
+            if index != 0 {
+              let out = (ty * 8 + row) * SGB_SCREEN_WIDTH + tx * 8 + col;
+              self.border_frame[out] = palette[usize::from(index)];
+            }
+          }
+        }
+      }
+    }
+  }
+
+  fn finish_packet(&mut self) {
+    let start = self.packets * PACKET_BYTES;
+    self.command[start..start + PACKET_BYTES].copy_from_slice(&self.packet);
+    self.packets += 1;
+    let expected = usize::from(self.command[0] & 0b111).max(1);
+    if self.packets >= expected {
+      self.packets = 0;
+      self.run_command();
+    }
+  }
+
+  fn run_command(&mut self) {
+    let c = self.command;
+    let color = |i: usize| u16::from_le_bytes([c[i], c[i + 1]]) & 0x7FFF;
+    match c[0] >> 3 {
+      cmd @ (SGB_PAL01 | SGB_PAL23 | SGB_PAL03 | SGB_PAL12) => {
+        let [a, b] = match cmd {
+          SGB_PAL01 => [0, 1],
+          SGB_PAL23 => [2, 3],
+          SGB_PAL03 => [0, 3],
+          _ => [1, 2],
+        };
+        for palette in self.palettes.iter_mut() {
+          palette[0] = color(1);
+        }
+        for i in 0..3 {
+          self.palettes[a][1 + i] = color(3 + i * 2);
+          self.palettes[b][1 + i] = color(9 + i * 2);
+        }
+      }
+      SGB_ATTR_BLK => {
+        let sets = usize::from(c[1] & 0x1F).min(18);
+        for d in c[2..].chunks_exact(6).take(sets) {
+          self.attr_block(d);
+        }
+      }
+      SGB_ATTR_LIN => {
+        let lines = usize::from(c[1]).min(c.len() - 2);
+        for &b in &c[2..2 + lines] {
+          let line = usize::from(b & 0x1F);
+          let palette = (b >> 5) & 3;
+          if u8_get_bit(7, b) {
+            if line < ATTR_MAP_HEIGHT {
+              let row = line * ATTR_MAP_WIDTH;
+              self.attr_map[row..row + ATTR_MAP_WIDTH].fill(palette);
+            }
+          } else if line < ATTR_MAP_WIDTH {
+            for row in self.attr_map.chunks_exact_mut(ATTR_MAP_WIDTH) {
+              row[line] = palette;
+            }
+          }
+        }
+      }
+      SGB_ATTR_DIV => {
+        let after = c[1] & 3;
+        let before = (c[1] >> 2) & 3;
+        let on = (c[1] >> 4) & 3;
+        let by_row = u8_get_bit(6, c[1]);
+        let split = usize::from(c[2] & 0x1F);
+        for (i, cell) in self.attr_map.iter_mut().enumerate() {
+          let (x, y) = (i % ATTR_MAP_WIDTH, i / ATTR_MAP_WIDTH);
+          let v = if by_row { y } else { x };
+          *cell = match v.cmp(&split) {
+            Ordering::Less => before,
+            Ordering::Equal => on,
+            Ordering::Greater => after,
+          };
+        }
+      }
+      SGB_ATTR_CHR => {
+        let mut x = usize::from(c[1] & 0x1F);
+        let mut y = usize::from(c[2] & 0x1F);
+        let count = usize::from(u16::from_le_bytes([c[3], c[4]]))
+          .min(ATTR_MAP_WIDTH * ATTR_MAP_HEIGHT);
+        let by_column = u8_get_bit(0, c[5]);
+        for i in 0..count {
+          let Some(&b) = c.get(6 + i / 4) else { break };
+          if x < ATTR_MAP_WIDTH && y < ATTR_MAP_HEIGHT {
+            self.attr_map[y * ATTR_MAP_WIDTH + x] =
+              (b >> (6 - 2 * (i % 4))) & 3;
+          }
+          if by_column {
+            y += 1;
+            if y == ATTR_MAP_HEIGHT {
+              y = 0;
+              x += 1;
+            }
+          } else {
+            x += 1;
+            if x == ATTR_MAP_WIDTH {
+              x = 0;
+              y += 1;
+            }
+          }
+        }
+      }
+      SGB_PAL_SET => {
+        for i in 0..4 {
+          let n = usize::from(color(1 + i * 2) & 0x1FF);
+          self.palettes[i] = self.system_palettes[n];
+        }
+        let color0 = self.palettes[0][0];
+        for palette in self.palettes.iter_mut() {
+          palette[0] = color0;
+        }
+        if u8_get_bit(7, c[9]) {
+          self.apply_attr_file(c[9] & 0x3F);
+        }
+        if u8_get_bit(6, c[9]) {
+          self.mask = SgbMask::None;
+        }
+      }
+      SGB_PAL_TRN => self.transfer = Some(Transfer::Palettes),
+      SGB_MLT_REQ => {
+        self.players = match c[1] & 3 {
+          1 => 2,
+          3 => 4,
+          _ => 1,
+        };
+        self.player = 0;
+      }
+      SGB_ATTR_TRN => self.transfer = Some(Transfer::AttrFiles),
+      SGB_ATTR_SET => {
+        self.apply_attr_file(c[1] & 0x3F);
+        if u8_get_bit(6, c[1]) {
+          self.mask = SgbMask::None;
+        }
+      }
+      SGB_CHR_TRN => {
+        let upper = u8_get_bit(0, c[1]);
+        self.transfer = Some(Transfer::BorderTiles { upper });
+      }
+      SGB_PCT_TRN => self.transfer = Some(Transfer::BorderMap),
+      SGB_MASK_EN => {
+        self.mask = match c[1] & 3 {
+          0 => SgbMask::None,
+          1 => SgbMask::Freeze,
+          2 => SgbMask::Black,
+          _ => SgbMask::Color0,
+        };
+      }
+      _ => (),
+    }
+  }
+
+  /// Applies one `ATTR_BLK` data set.
+  fn attr_block(&mut self, d: &[u8]) {
+    let control = d[0] & 0b111;
+    let inside = (u8_get_bit(0, control), d[1] & 3);
+    let mut border = (u8_get_bit(1, control), (d[1] >> 2) & 3);
+    let outside = (u8_get_bit(2, control), (d[1] >> 4) & 3);
+    // With only the inside or only the outside set, the border goes with it.
+    match control {
+      0b001 => border = (true, inside.1),
+      0b100 => border = (true, outside.1),
+      _ => (),
+    }
+    let [x1, y1, x2, y2] =
+      [d[2], d[3], d[4], d[5]].map(|v| usize::from(v & 0x1F));
+    for (i, cell) in self.attr_map.iter_mut().enumerate() {
+      let (x, y) = (i % ATTR_MAP_WIDTH, i / ATTR_MAP_WIDTH);
+      let (set, palette) = if x > x1 && x < x2 && y > y1 && y < y2 {
+        inside
+      } else if (x1..=x2).contains(&x) && (y1..=y2).contains(&y) {
+        border
+      } else {
+        outside
+      };
+      if set {
+        *cell = palette;
+      }
+    }
+  }
+
+  fn apply_attr_file(&mut self, file: u8) {
+    let file = usize::from(file);
+    if file >= ATTR_FILES {
+      return;
+    }
+    let start = file * ATTR_FILE_BYTES;
+    let bytes = &self.attr_files[start..start + ATTR_FILE_BYTES];
+    for (i, cell) in self.attr_map.iter_mut().enumerate() {
+      *cell = (bytes[i / 4] >> (6 - 2 * (i % 4))) & 3;
+    }
+  }
+}
+
+/// Reads 4 KiB of transfer data out of a picture.
+///
+/// The data is sent as 256 tiles of 2bpp data, filling the screen left to
+/// right and top to bottom. Games set `BGP` to `$E4` while doing this, so the
+/// shades are the same as the color indexes.
+fn transfer_data(shades: &[u8]) -> Box<[u8]> {
+  let mut data = vec![0; SGB_TRANSFER_SIZE].into_boxed_slice();
+  for (tile, bytes) in data.chunks_exact_mut(16).enumerate() {
+    let (tx, ty) = (tile % ATTR_MAP_WIDTH, tile / ATTR_MAP_WIDTH);
+    for (row, pair) in bytes.chunks_exact_mut(2).enumerate() {
+      let line = (ty * 8 + row) * SCREEN_WIDTH + tx * 8;
+      for (col, &shade) in shades[line..line + 8].iter().enumerate() {
+        pair[0] |= (shade & 1) << (7 - col);
+        pair[1] |= ((shade >> 1) & 1) << (7 - col);
+      }
+    }
+  }
+  data
+}
+
+#[test]
+fn test_Sgb_packets() {
+  fn send(sgb: &mut Sgb, packet: [u8; PACKET_BYTES]) {
+    sgb.write_p1(0x00);
+    sgb.write_p1(0x30);
+    for i in 0..PACKET_BITS {
+      let one = u8_get_bit(u32::from(i % 8), packet[usize::from(i / 8)]);
+      sgb.write_p1(if one { 0x10 } else { 0x20 });
+      sgb.write_p1(0x30);
+    }
+    sgb.write_p1(0x20);
+    sgb.write_p1(0x30);
+  }
+  let mut sgb = Sgb::new();
+  // PAL01: color 0 white, palette 0 all green, palette 1 all red
+  let mut pal01 = [0; PACKET_BYTES];
+  pal01[0] = (SGB_PAL01 << 3) | 1;
+  pal01[1..3].copy_from_slice(&0x7FFF_u16.to_le_bytes());
+  for i in 0..3 {
+    pal01[3 + i * 2..5 + i * 2].copy_from_slice(&0x03E0_u16.to_le_bytes());
+    pal01[9 + i * 2..11 + i * 2].copy_from_slice(&0x001F_u16.to_le_bytes());
+  }
+  send(&mut sgb, pal01);
+  assert_eq!(sgb.palettes()[1], [0x7FFF, 0x001F, 0x001F, 0x001F]);
+  // ATTR_BLK: a 2x2 cell block in the corner uses palette 1
+  let mut attr_blk = [0; PACKET_BYTES];
+  attr_blk[0] = (SGB_ATTR_BLK << 3) | 1;
+  attr_blk[1] = 1;
+  attr_blk[2..8].copy_from_slice(&[0b001, 0b01, 0, 0, 1, 1]);
+  send(&mut sgb, attr_blk);
+  assert_eq!(sgb.attr_map()[..3], [1, 1, 0]);
+  assert_eq!(sgb.attr_map()[ATTR_MAP_WIDTH + 1], 1);
+
+  sgb.vblank(&[1; SCREEN_WIDTH * SCREEN_HEIGHT]);
+  assert_eq!(sgb.frame()[0], 0x001F);
+  assert_eq!(sgb.frame()[16], 0x03E0);
+
+  // MLT_REQ: two players, switched by P15 going high
+  let mut mlt_req = [0; PACKET_BYTES];
+  mlt_req[0] = (SGB_MLT_REQ << 3) | 1;
+  mlt_req[1] = 1;
+  send(&mut sgb, mlt_req);
+  assert_eq!(sgb.joypad_id(), Some(0xF));
+  sgb.write_p1(0x10);
+  sgb.write_p1(0x30);
+  assert_eq!(sgb.joypad_id(), Some(0xE));
+
+  // The border: tile 0 has its top-left pixel set to color 1, and every map
+  // entry uses tile 0 with palette 4, whose color 1 is blue.
+  fn shades_for(data: &[u8]) -> alloc::vec::Vec<u8> {
+    let mut shades = alloc::vec![0; SCREEN_WIDTH * SCREEN_HEIGHT];
+    for (tile, bytes) in data.chunks_exact(16).enumerate() {
+      let (tx, ty) = (tile % ATTR_MAP_WIDTH, tile / ATTR_MAP_WIDTH);
+      for (row, pair) in bytes.chunks_exact(2).enumerate() {
+        for col in 0..8 {
+          let i = (ty * 8 + row) * SCREEN_WIDTH + tx * 8 + col;
+          shades[i] =
+            ((pair[0] >> (7 - col)) & 1) | (((pair[1] >> (7 - col)) & 1) << 1);
+        }
+      }
+    }
+    shades
+  }
+  let mut chr_trn = [0; PACKET_BYTES];
+  chr_trn[0] = (SGB_CHR_TRN << 3) | 1;
+  send(&mut sgb, chr_trn);
+  let mut data = [0; SGB_TRANSFER_SIZE];
+  data[0] = 0x80;
+  sgb.vblank(&shades_for(&data));
+  let mut pct_trn = [0; PACKET_BYTES];
+  pct_trn[0] = (SGB_PCT_TRN << 3) | 1;
+  send(&mut sgb, pct_trn);
+  let mut data = [0; SGB_TRANSFER_SIZE];
+  for entry in data[..BORDER_MAP_BYTES].chunks_exact_mut(2) {
+    entry.copy_from_slice(&0x1000_u16.to_le_bytes());
+  }
+  data[BORDER_MAP_BYTES + 2..BORDER_MAP_BYTES + 4]
+    .copy_from_slice(&0x7C00_u16.to_le_bytes());
+  let mut frame = shades_for(&data);
+  sgb.vblank(&frame);
+  assert_eq!(sgb.border_frame()[0], 0x7C00);
+  assert_eq!(sgb.border_frame()[1], 0x7FFF);
+  // the game's picture shows through in the middle, except where the border
+  // tiles are drawn over it
+  frame.fill(3);
+  sgb.vblank(&frame);
+  let middle = (GAME_Y + 1) * SGB_SCREEN_WIDTH + GAME_X + 1;
+  assert_eq!(sgb.border_frame()[middle], sgb.frame()[SCREEN_WIDTH + 1]);
+  assert_eq!(sgb.border_frame()[GAME_Y * SGB_SCREEN_WIDTH + GAME_X], 0x7C00);
+}
@@ -0,0 +1,145 @@
+//! CGB double speed mode, and the `KEY1` register that controls it.
+//!
+//! The system clock (what the PPU and APU run on) always ticks at the same
+//! rate. What changes in double speed mode is how many of those ticks the CPU
+//! takes per M-cycle. Anything clocked by the CPU, such as the timer (`DIV`
+//! and `TIMA`) and the serial port, speeds up along with it.
+//!
+//! * See Also: [Pandocs: KEY1](https://gbdev.io/pandocs/CGB_Registers.html#ff4d--key1-cgb-mode-only-prepare-speed-switch)
+
+use bitfrob::u8_get_bit;
+
+/// M-cycles that the CPU sits idle after `stop` switches the speed.
+pub const SPEED_SWITCH_M_CYCLES: u16 = 2050;
+
+/// The CPU's clock speed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Speed {
+  /// The normal ~1 MiHz M-cycle rate, which is the only speed a DMG has.
+  #[default]
+  Normal,
+  /// The CGB's ~2 MiHz M-cycle rate.
+  Double,
+}
+impl Speed {
+  /// System T-cycles (PPU "dots") per CPU M-cycle.
+  #[inline]
+  #[must_use]
+  pub const fn dots_per_m_cycle(self) -> u32 {
+    match self {
+      Self::Normal => 4,
+      Self::Double => 2,
+    }
+  }
+  /// CPU-clocked T-cycles (timer, serial) per system T-cycle.
+  #[inline]
+  #[must_use]
+  pub const fn cpu_t_cycles_per_dot(self) -> u32 {
+    match self {
+      Self::Normal => 1,
+      Self::Double => 2,
+    }
+  }
+}
+
+/// The `KEY1` register (`$FF4D`), which prepares a speed switch.
+///
+/// On a DMG (or a CGB running a DMG game) the register doesn't exist, so it
+/// reads as `$FF` and ignores writes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Key1 {
+  cgb: bool,
+  prepare: bool,
+  speed: Speed,
+}
+impl Key1 {
+  #[inline]
+  #[must_use]
+  pub const fn new(cgb: bool) -> Self {
+    Self { cgb, prepare: false, speed: Speed::Normal }
+  }
+  #[inline]
+  #[must_use]
+  pub const fn is_cgb(self) -> bool {
+    self.cgb
+  }
+  /// Changes mode, such as when the boot ROM picks DMG compatibility mode.
+  #[inline]
+  pub fn set_cgb(&mut self, cgb: bool) {
+    self.cgb = cgb;
+  }
+  #[inline]
+  #[must_use]
+  pub const fn speed(self) -> Speed {
+    self.speed
+  }
+  /// If a speed switch will happen on the next `stop`.
+  #[inline]
+  #[must_use]
+  pub const fn prepare(self) -> bool {
+    self.prepare
+  }
+  #[inline]
+  #[must_use]
+  pub const fn read(self) -> u8 {
+    if !self.cgb {
+      return 0xFF;
+    }
+    let current = match self.speed {
+      Speed::Normal => 0,
+      Speed::Double => 1 << 7,
+    };
+    0b0111_1110 | current | (self.prepare as u8)
+  }
+  #[inline]
+  pub fn write(&mut self, byte: u8) {
+    if self.cgb {
+      self.prepare = u8_get_bit(0, byte);
+    }
+  }
+  /// Performs the prepared speed switch, if any, as part of `stop`.
+  ///
+  /// * **Returns:** If the speed changed.
+  pub fn stop(&mut self) -> bool {
+    if !self.prepare {
+      return false;
+    }
+    self.prepare = false;
+    self.speed = match self.speed {
+      Speed::Normal => Speed::Double,
+      Speed::Double => Speed::Normal,
+    };
+    true
+  }
+}
+
+#[test]
+fn test_Key1() {
+  let mut dmg = Key1::new(false);
+  assert_eq!(dmg.read(), 0xFF);
+  dmg.write(0x01);
+  assert!(!dmg.prepare());
+  assert!(!dmg.stop());
+  assert_eq!(dmg.speed(), Speed::Normal);
+
+  let mut key1 = Key1::new(true);
+  assert_eq!(key1.read(), 0x7E);
+  assert!(!key1.stop());
+  key1.write(0xFF);
+  assert_eq!(key1.read(), 0x7F);
+  assert!(key1.stop());
+  assert_eq!(key1.speed(), Speed::Double);
+  assert_eq!(key1.read(), 0xFE);
+  assert!(!key1.stop());
+  key1.write(0x01);
+  assert!(key1.stop());
+  assert_eq!(key1.speed(), Speed::Normal);
+  // a CGB that picked DMG compatibility mode loses the register.
+  key1.write(0x01);
+  key1.set_cgb(false);
+  assert_eq!(key1.read(), 0xFF);
+
+  assert_eq!(Speed::Normal.dots_per_m_cycle(), 4);
+  assert_eq!(Speed::Double.dots_per_m_cycle(), 2);
+  assert_eq!(Speed::Double.cpu_t_cycles_per_dot(), 2);
+}
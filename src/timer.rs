@@ -0,0 +1,243 @@
+//! The timer: `DIV` (`$FF04`), `TIMA` (`$FF05`), `TMA` (`$FF06`), and `TAC`
+//! (`$FF07`).
+//!
+//! Everything here is driven by a 16-bit counter that ticks once per CPU
+//! T-cycle, so in CGB double speed mode the timer also runs twice as fast
+//! compared to the PPU.
+//!
+//! * See Also: [Pandocs: Timer and Divider Registers](https://gbdev.io/pandocs/Timer_and_Divider_Registers.html)
+//! * See Also: [Pandocs: Timer obscure behaviour](https://gbdev.io/pandocs/Timer_Obscure_Behaviour.html)
+
+use bitfrob::{u16_get_bit, u8_get_bit};
+
+/// T-cycles between `TIMA` overflowing and the `TMA` reload.
+const RELOAD_DELAY: u8 = 4;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Timer {
+  counter: u16,
+  tima: u8,
+  tma: u8,
+  tac: u8,
+  reload_delay: u8,
+}
+impl Timer {
+  #[inline]
+  #[must_use]
+  pub const fn new() -> Self {
+    Self { counter: 0, tima: 0, tma: 0, tac: 0, reload_delay: 0 }
+  }
+
+  /// The full internal counter. `DIV` is the upper 8 bits of this.
+  #[inline]
+  #[must_use]
+  pub const fn counter(&self) -> u16 {
+    self.counter
+  }
+  /// Sets the internal counter, as the boot ROM would have left it.
+  #[inline]
+  pub fn set_counter(&mut self, counter: u16) {
+    self.counter = counter;
+  }
+
+  #[inline]
+  #[must_use]
+  pub const fn read_div(&self) -> u8 {
+    (self.counter >> 8) as u8
+  }
+  #[inline]
+  #[must_use]
+  pub const fn read_tima(&self) -> u8 {
+    self.tima
+  }
+  #[inline]
+  #[must_use]
+  pub const fn read_tma(&self) -> u8 {
+    self.tma
+  }
+  #[inline]
+  #[must_use]
+  pub const fn read_tac(&self) -> u8 {
+    self.tac | 0b1111_1000
+  }
+
+  /// Any write to `DIV` resets the whole counter. This is also what `stop`
+  /// does.
+  pub fn write_div(&mut self) {
+    let before = self.timer_bit();
+    self.counter = 0;
+    self.falling_edge_check(before);
+  }
+  pub fn write_tima(&mut self, byte: u8) {
+    // writing during the delay cancels the reload (and the interrupt).
+    self.reload_delay = 0;
+    self.tima = byte;
+  }
+  pub fn write_tma(&mut self, byte: u8) {
+    self.tma = byte;
+  }
+  pub fn write_tac(&mut self, byte: u8) {
+    let before = self.timer_bit();
+    self.tac = byte & 0b111;
+    self.falling_edge_check(before);
+  }
+
+  /// Grants a CPU T-cycle worth of time to the timer.
+  ///
+  /// * **Returns:** If the timer interrupt should be requested.
+  pub fn t_cycle(&mut self) -> bool {
+    let mut irq = false;
+    if self.reload_delay > 0 {
+      self.reload_delay -= 1;
+      if self.reload_delay == 0 {
+        self.tima = self.tma;
+        irq = true;
+      }
+    }
+    let before = self.timer_bit();
+    self.counter = self.counter.wrapping_add(1);
+    self.falling_edge_check(before);
+    irq
+  }
+
+  /// Grants many CPU T-cycles worth of time to the timer at once.
+  ///
+  /// This gives the same result as calling [t_cycle](Timer::t_cycle) that
+  /// many times, but skips straight over the T-cycles where nothing happens.
+  ///
+  /// * **Returns:** If the timer interrupt should be requested.
+  pub fn advance(&mut self, mut t_cycles: u32) -> bool {
+    let mut irq = false;
+    while t_cycles > 0 {
+      let mut step = t_cycles;
+      if self.reload_delay > 0 {
+        step = step.min(u32::from(self.reload_delay));
+      }
+      if u8_get_bit(2, self.tac) {
+        step = step.min(self.t_cycles_until_fall(self.timer_bit_index()));
+      }
+      // Every T-cycle before the last one of the step only counts up.
+      let plain = step - 1;
+      self.counter = self.counter.wrapping_add(plain as u16);
+      self.reload_delay = self.reload_delay.saturating_sub(plain as u8);
+      irq |= self.t_cycle();
+      t_cycles -= step;
+    }
+    irq
+  }
+
+  /// CPU T-cycles until the counter's `bit` next goes from 1 to 0, counting
+  /// the T-cycle where it happens.
+  #[inline]
+  #[must_use]
+  pub const fn t_cycles_until_fall(&self, bit: u32) -> u32 {
+    let period = 1_u32 << (bit + 1);
+    period - (self.counter as u32 % period)
+  }
+
+  /// CPU T-cycles until the timer next requests an interrupt, counting the
+  /// T-cycle where it happens.
+  ///
+  /// * **Returns:** `None` if the timer is stopped.
+  #[must_use]
+  pub const fn t_cycles_until_irq(&self) -> Option<u32> {
+    if self.reload_delay > 0 {
+      Some(self.reload_delay as u32)
+    } else if u8_get_bit(2, self.tac) {
+      let bit = self.timer_bit_index();
+      let first = self.t_cycles_until_fall(bit);
+      let period = 1_u32 << (bit + 1);
+      Some(first + (0xFF - self.tima as u32) * period + RELOAD_DELAY as u32)
+    } else {
+      None
+    }
+  }
+
+  /// The counter bit that `TIMA` watches, based on `TAC`.
+  #[inline]
+  const fn timer_bit_index(&self) -> u32 {
+    match self.tac & 0b11 {
+      0b00 => 9,
+      0b01 => 3,
+      0b10 => 5,
+      _ => 7,
+    }
+  }
+
+  /// The counter bit that `TIMA` watches, AND the timer enable bit.
+  #[inline]
+  const fn timer_bit(&self) -> bool {
+    u8_get_bit(2, self.tac) && u16_get_bit(self.timer_bit_index(), self.counter)
+  }
+
+  fn falling_edge_check(&mut self, before: bool) {
+    if before && !self.timer_bit() {
+      let (tima, overflow) = self.tima.overflowing_add(1);
+      self.tima = tima;
+      if overflow {
+        self.reload_delay = RELOAD_DELAY;
+      }
+    }
+  }
+}
+
+#[test]
+fn test_Timer() {
+  // `TIMA` every 16 T-cycles, overflowing straight away.
+  let mut timer = Timer::new();
+  timer.write_tac(0b101);
+  timer.write_tma(0x42);
+  timer.write_tima(0xFF);
+  assert_eq!(timer.read_tac(), 0xFD);
+  assert_eq!(timer.t_cycles_until_irq(), Some(20));
+  for i in 1..20 {
+    assert!(!timer.t_cycle());
+    if i >= 16 {
+      // `TIMA` reads 0 until the reload.
+      assert_eq!(timer.read_tima(), 0);
+    }
+  }
+  let mut cancelled = timer;
+  assert!(timer.t_cycle());
+  assert_eq!(timer.read_tima(), 0x42);
+  // writing `TIMA` during the delay cancels the reload and the interrupt.
+  cancelled.write_tima(0x10);
+  assert!(!cancelled.advance(12));
+  assert_eq!(cancelled.read_tima(), 0x10);
+  cancelled.advance(1);
+  assert_eq!(cancelled.read_tima(), 0x11);
+
+  // resetting `DIV` or changing `TAC` while the watched bit is set makes a
+  // falling edge.
+  let mut timer = Timer::new();
+  timer.write_tac(0b101);
+  timer.set_counter(0x0007);
+  timer.write_div();
+  assert_eq!(timer.read_tima(), 0);
+  timer.set_counter(0xAB08);
+  assert_eq!(timer.read_div(), 0xAB);
+  timer.write_div();
+  assert_eq!((timer.read_div(), timer.read_tima()), (0, 1));
+  timer.set_counter(0x0008);
+  timer.write_tac(0b100);
+  assert_eq!(timer.read_tima(), 2);
+  timer.set_counter(0x0200);
+  timer.write_tac(0b000);
+  assert_eq!(timer.read_tima(), 3);
+  timer.write_tac(0b000);
+  assert_eq!(timer.read_tima(), 3);
+
+  // `advance` matches stepping one T-cycle at a time.
+  let mut timer = Timer::new();
+  timer.set_counter(0x1234);
+  timer.write_tac(0b100);
+  timer.write_tima(0xFE);
+  let mut stepped = timer;
+  let irq = (0..5000).fold(false, |irq, _| stepped.t_cycle() | irq);
+  assert_eq!(timer.advance(5000), irq);
+  assert_eq!(timer, stepped);
+  assert!(irq);
+  let until = timer.t_cycles_until_irq().unwrap();
+  assert!(!timer.advance(until - 1));
+  assert!(timer.advance(1));
+}
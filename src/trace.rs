@@ -0,0 +1,157 @@
+//! Diagnostic output, sent to hooks that the embedder controls.
+//!
+//! The emulator never prints anything itself. Instead, anything that's useful
+//! to see while debugging goes through the [trace!](crate::trace!) macro,
+//! which calls the current [TraceHook], if any. With no hook set the message
+//! isn't even formatted, so leaving tracing in the hot paths costs one atomic
+//! load.
+//!
+//! For structured tracing of the CPU there's the [Tracer] trait instead, which
+//! gets a record of each instruction, and optionally each M-cycle action and
+//! bus access.
+
+use core::{
+  fmt::Arguments,
+  ptr::null_mut,
+  sync::atomic::{AtomicPtr, Ordering},
+};
+
+use crate::{
+  instruction::Instruction, op_actions::CpuAction, reg_flags::RegFlags,
+};
+
+/// Receives each trace message.
+pub type TraceHook = fn(Arguments<'_>);
+
+static HOOK: AtomicPtr<()> = AtomicPtr::new(null_mut());
+
+/// Sets where trace messages go. `None` turns tracing off, which is the
+/// default.
+#[inline]
+pub fn set_trace_hook(hook: Option<TraceHook>) {
+  let p = hook.map_or(null_mut(), |hook| hook as *mut ());
+  HOOK.store(p, Ordering::Relaxed);
+}
+
+/// The current trace hook.
+#[inline]
+#[must_use]
+pub fn trace_hook() -> Option<TraceHook> {
+  let p = HOOK.load(Ordering::Relaxed);
+  if p.is_null() {
+    None
+  } else {
+    // Safety: the only non-null values ever stored are `TraceHook` pointers.
+    Some(unsafe { core::mem::transmute::<*mut (), TraceHook>(p) })
+  }
+}
+
+/// Sends a message to the trace hook, if there is one.
+#[inline]
+pub fn trace_args(args: Arguments<'_>) {
+  if let Some(hook) = trace_hook() {
+    hook(args)
+  }
+}
+
+/// A trace hook that prints each message to stderr.
+#[cfg(feature = "std")]
+pub fn trace_to_stderr(args: Arguments<'_>) {
+  std::eprintln!("{args}");
+}
+
+/// Formats a trace message, like `println!`, and sends it to the trace hook.
+#[macro_export]
+macro_rules! trace {
+  ($($arg:tt)*) => {
+    $crate::trace::trace_args(::core::format_args!($($arg)*))
+  };
+}
+
+/// The CPU state as an instruction starts.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct InstructionRecord {
+  /// Where the op code is.
+  pub pc: u16,
+  /// The 4 bytes starting at `pc`: the op code and anything after it.
+  pub pc_mem: [u8; 4],
+  /// The instruction about to run, or `None` for an interrupt dispatch.
+  pub instruction: Option<Instruction>,
+  pub a: u8,
+  pub flags: RegFlags,
+  pub b: u8,
+  pub c: u8,
+  pub d: u8,
+  pub e: u8,
+  pub h: u8,
+  pub l: u8,
+  pub sp: u16,
+  /// The CPU's T-cycle count, see [Cpu::t_cycles](crate::cpu::Cpu::t_cycles).
+  pub t_cycles: u32,
+}
+
+/// One read or write that the CPU made.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum BusAccess {
+  Read { addr: u16, byte: u8 },
+  Write { addr: u16, byte: u8 },
+}
+
+/// Gets told what the CPU is doing.
+///
+/// Every method does nothing by default, so a tracer only needs to write the
+/// ones it cares about.
+pub trait Tracer {
+  /// If this tracer wants to be called at all. When it doesn't, the CPU
+  /// doesn't bother building [InstructionRecord]s.
+  #[inline]
+  fn enabled(&self) -> bool {
+    true
+  }
+
+  /// Called as each instruction (or interrupt dispatch) starts, before any of
+  /// its bus accesses.
+  #[inline]
+  fn instruction(&mut self, record: &InstructionRecord) {}
+
+  /// Called for each M-cycle action, before it runs.
+  #[inline]
+  fn action(&mut self, action: CpuAction) {}
+
+  /// Called for each read and write the CPU makes.
+  #[inline]
+  fn bus_access(&mut self, access: BusAccess) {}
+}
+
+/// The tracer used when there isn't one, which compiles away to nothing.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NoTracer;
+impl Tracer for NoTracer {
+  #[inline]
+  fn enabled(&self) -> bool {
+    false
+  }
+}
+
+#[test]
+fn test_trace_hook() {
+  use core::sync::atomic::AtomicUsize;
+  // Other tests run in parallel and may trace while the hook is set, so only
+  // this test's messages are counted.
+  static SENT: AtomicUsize = AtomicUsize::new(0);
+  static NOT_SENT: AtomicUsize = AtomicUsize::new(0);
+  fn count(args: Arguments<'_>) {
+    match alloc::format!("{args}").as_str() {
+      "test_trace_hook: sent" => SENT.fetch_add(1, Ordering::Relaxed),
+      "test_trace_hook: not sent" => NOT_SENT.fetch_add(1, Ordering::Relaxed),
+      _ => 0,
+    };
+  }
+  crate::trace!("test_trace_hook: {}", "not sent");
+  set_trace_hook(Some(count));
+  crate::trace!("test_trace_hook: {}", "sent");
+  set_trace_hook(None);
+  crate::trace!("test_trace_hook: {}", "not sent");
+  assert_eq!(SENT.load(Ordering::Relaxed), 1);
+  assert_eq!(NOT_SENT.load(Ordering::Relaxed), 0);
+}
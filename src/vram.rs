@@ -0,0 +1,132 @@
+//! Video RAM (`$8000..=$9FFF`), with the CGB's second bank selected by `VBK`
+//! (`$FF4F`).
+//!
+//! * See Also: [Pandocs: VBK](https://gbdev.io/pandocs/CGB_Registers.html#ff4f--vbk-cgb-mode-only-vram-bank)
+
+use alloc::{boxed::Box, vec};
+
+/// Bytes per VRAM bank.
+pub const VRAM_BANK_SIZE: usize = 0x2000;
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Vram {
+  bytes: Box<[u8]>,
+  bank: u8,
+  cgb: bool,
+}
+impl Vram {
+  /// Makes a zeroed VRAM.
+  ///
+  /// Bank 1 is only reachable when `cgb` mode is on, which a CGB picks based on
+  /// the cart's header (see
+  /// [CartHeader::is_cgb_mode](crate::cart_header::CartHeader::is_cgb_mode)).
+  #[must_use]
+  pub fn new(cgb: bool) -> Self {
+    Self { bytes: vec![0; VRAM_BANK_SIZE * 2].into_boxed_slice(), bank: 0, cgb }
+  }
+
+  #[inline]
+  #[must_use]
+  pub const fn is_cgb(&self) -> bool {
+    self.cgb
+  }
+  /// Changes mode, such as when the boot ROM picks DMG compatibility mode.
+  ///
+  /// Leaving `cgb` mode goes back to bank 0.
+  #[inline]
+  pub fn set_cgb(&mut self, cgb: bool) {
+    self.cgb = cgb;
+    if !cgb {
+      self.bank = 0;
+    }
+  }
+
+  /// The bank that the CPU currently sees.
+  #[inline]
+  #[must_use]
+  pub const fn current_bank(&self) -> u8 {
+    self.bank
+  }
+
+  /// Direct access to a bank (0 or 1), regardless of `VBK`.
+  ///
+  /// This is how the PPU sees VRAM.
+  #[inline]
+  #[must_use]
+  pub fn bank(&self, bank: u8) -> &[u8] {
+    let start = usize::from(bank & 1) * VRAM_BANK_SIZE;
+    &self.bytes[start..start + VRAM_BANK_SIZE]
+  }
+  /// Direct mutable access to a bank (0 or 1), regardless of `VBK`.
+  #[inline]
+  #[must_use]
+  pub fn bank_mut(&mut self, bank: u8) -> &mut [u8] {
+    let start = usize::from(bank & 1) * VRAM_BANK_SIZE;
+    &mut self.bytes[start..start + VRAM_BANK_SIZE]
+  }
+
+  #[inline]
+  #[must_use]
+  fn index(&self, addr: u16) -> usize {
+    usize::from(self.bank) * VRAM_BANK_SIZE + usize::from(addr & 0x1FFF)
+  }
+  /// Reads from the currently selected bank. Only the low 13 bits of `addr`
+  /// are used.
+  #[inline]
+  #[must_use]
+  pub fn read(&self, addr: u16) -> u8 {
+    self.bytes[self.index(addr)]
+  }
+  /// Writes to the currently selected bank. Only the low 13 bits of `addr`
+  /// are used.
+  #[inline]
+  pub fn write(&mut self, addr: u16, byte: u8) {
+    let i = self.index(addr);
+    self.bytes[i] = byte;
+  }
+
+  #[inline]
+  #[must_use]
+  pub const fn read_vbk(&self) -> u8 {
+    if self.cgb {
+      0b1111_1110 | self.bank
+    } else {
+      0xFF
+    }
+  }
+  #[inline]
+  pub fn write_vbk(&mut self, byte: u8) {
+    if self.cgb {
+      self.bank = byte & 1;
+    }
+  }
+}
+
+#[test]
+fn test_Vram_banks() {
+  let mut vram = Vram::new(true);
+  assert_eq!(vram.read_vbk(), 0xFE);
+  vram.write(0x8000, 0x12);
+  vram.write_vbk(0xFF);
+  assert_eq!(vram.read_vbk(), 0xFF);
+  assert_eq!(vram.current_bank(), 1);
+  assert_eq!(vram.read(0x8000), 0);
+  vram.write(0x9FFF, 0x34);
+  assert_eq!(vram.bank(1)[0x1FFF], 0x34);
+  assert_eq!(vram.bank(0)[0x1FFF], 0);
+  vram.write_vbk(0x02);
+  assert_eq!(vram.read_vbk(), 0xFE);
+  assert_eq!(vram.read(0x8000), 0x12);
+  // leaving CGB mode goes back to bank 0 for good.
+  vram.write_vbk(0x01);
+  vram.set_cgb(false);
+  assert_eq!(vram.current_bank(), 0);
+
+  let mut dmg = Vram::new(false);
+  assert_eq!(dmg.read_vbk(), 0xFF);
+  dmg.write_vbk(0x01);
+  assert_eq!(dmg.current_bank(), 0);
+  dmg.write(0x8000, 0x56);
+  assert_eq!(dmg.bank(0)[0], 0x56);
+  assert_eq!(dmg.bank(1)[0], 0);
+}
@@ -0,0 +1,118 @@
+//! Work RAM (`$C000..=$DFFF`, echoed at `$E000..=$FDFF`), with the CGB's
+//! switchable upper bank selected by `SVBK` (`$FF70`).
+//!
+//! * See Also: [Pandocs: SVBK](https://gbdev.io/pandocs/CGB_Registers.html#ff70--svbk-cgb-mode-only-wram-bank)
+
+use alloc::{boxed::Box, vec};
+
+/// Bytes per WRAM bank.
+pub const WRAM_BANK_SIZE: usize = 0x1000;
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Wram {
+  bytes: Box<[u8]>,
+  svbk: u8,
+  cgb: bool,
+}
+impl Wram {
+  /// Makes a zeroed WRAM.
+  ///
+  /// Banks 2 through 7 are only reachable when `cgb` mode is on, which a CGB
+  /// picks based on the cart's header (see
+  /// [CartHeader::is_cgb_mode](crate::cart_header::CartHeader::is_cgb_mode)).
+  #[must_use]
+  pub fn new(cgb: bool) -> Self {
+    Self { bytes: vec![0; WRAM_BANK_SIZE * 8].into_boxed_slice(), svbk: 0, cgb }
+  }
+
+  #[inline]
+  #[must_use]
+  pub const fn is_cgb(&self) -> bool {
+    self.cgb
+  }
+
+  /// Changes mode, such as when the boot ROM picks DMG compatibility mode.
+  /// Leaving CGB mode puts bank 1 back at `$D000`.
+  #[inline]
+  pub fn set_cgb(&mut self, cgb: bool) {
+    self.cgb = cgb;
+    if !cgb {
+      self.svbk = 0;
+    }
+  }
+
+  /// The bank mapped at `$D000..=$DFFF`.
+  ///
+  /// Selecting bank 0 with `SVBK` actually selects bank 1.
+  #[inline]
+  #[must_use]
+  pub const fn upper_bank(&self) -> u8 {
+    match self.svbk & 0b111 {
+      0 => 1,
+      n => n,
+    }
+  }
+
+  #[inline]
+  #[must_use]
+  fn index(&self, addr: u16) -> usize {
+    let offset = usize::from(addr & 0x0FFF);
+    if addr & 0x1000 == 0 {
+      offset
+    } else {
+      usize::from(self.upper_bank()) * WRAM_BANK_SIZE + offset
+    }
+  }
+  /// Reads WRAM. Only the low 13 bits of `addr` are used, which also handles
+  /// echo RAM.
+  #[inline]
+  #[must_use]
+  pub fn read(&self, addr: u16) -> u8 {
+    self.bytes[self.index(addr)]
+  }
+  /// Writes WRAM. Only the low 13 bits of `addr` are used, which also handles
+  /// echo RAM.
+  #[inline]
+  pub fn write(&mut self, addr: u16, byte: u8) {
+    let i = self.index(addr);
+    self.bytes[i] = byte;
+  }
+
+  #[inline]
+  #[must_use]
+  pub const fn read_svbk(&self) -> u8 {
+    if self.cgb {
+      0b1111_1000 | self.svbk
+    } else {
+      0xFF
+    }
+  }
+  #[inline]
+  pub fn write_svbk(&mut self, byte: u8) {
+    if self.cgb {
+      self.svbk = byte & 0b111;
+    }
+  }
+}
+
+#[test]
+fn test_Wram_svbk() {
+  let mut wram = Wram::new(true);
+  wram.write(0xC000, 0xAA);
+  wram.write(0xD000, 0x11);
+  wram.write_svbk(0xFA);
+  assert_eq!(wram.read_svbk(), 0xFA);
+  assert_eq!(wram.upper_bank(), 2);
+  assert_eq!(wram.read(0xD000), 0x00);
+  wram.write(0xF000, 0x22);
+  assert_eq!(wram.read(0xD000), 0x22);
+  wram.write_svbk(0);
+  assert_eq!(wram.read_svbk(), 0xF8);
+  assert_eq!(wram.read(0xD000), 0x11);
+  assert_eq!(wram.read(0xE000), 0xAA);
+
+  let mut dmg = Wram::new(false);
+  dmg.write_svbk(3);
+  assert_eq!(dmg.read_svbk(), 0xFF);
+  assert_eq!(dmg.upper_bank(), 1);
+}
@@ -0,0 +1,99 @@
+//! Runs blargg's test ROMs, which check themselves and report over serial.
+//!
+//! Small ROMs built from source here check the cart RAM reports too.
+
+use std::rc::Rc;
+
+use kpasim::{
+  assembler::assemble,
+  blargg::{run_blargg, BlarggStatus},
+  gameboy::GameBoy,
+  model::Model,
+  serial::SerialCapture,
+};
+
+/// A ROM that reports `code` and a line of `text` in cart RAM, like the newer
+/// tests.
+fn ram_report_rom(code: u8, text: &str) -> Vec<u8> {
+  let mut rom = assemble(&format!(
+    r#"
+SECTION "Entry", ROM0[$0100]
+    nop
+    jp Main
+SECTION "Cart", ROM0[$0147]
+    db $03, $00, $02 ; MBC1 with 8 KiB of RAM
+SECTION "Main", ROM0[$0150]
+Main:
+    ld a, $0A
+    ld [$0000], a
+    ld hl, $A001
+    ld a, $DE
+    ld [hl+], a
+    ld a, $B0
+    ld [hl+], a
+    ld a, $61
+    ld [hl+], a
+    ld de, Text
+.copy:
+    ld a, [de]
+    ld [hl+], a
+    inc de
+    and a
+    jr nz, .copy
+    ld a, {code}
+    ld [$A000], a
+.spin:
+    jr .spin
+Text:
+    db "{text}", 10, 0
+"#
+  ))
+  .unwrap();
+  rom.resize(0x8000, 0);
+  rom
+}
+
+#[test]
+fn blargg_ram_reports() {
+  for (code, status) in [
+    (0x00, BlarggStatus::Passed),
+    (0x81, BlarggStatus::ResetRequired),
+    (0x01, BlarggStatus::Failed(vec!["halt_bug".to_string()])),
+  ] {
+    let rom = ram_report_rom(code, "halt_bug");
+    let mut gb = GameBoy::new(Model::Dmg, rom).unwrap();
+    let report = run_blargg(&mut gb, 10);
+    assert_eq!(report.status, status);
+    assert_eq!(report.output, "halt_bug\n");
+    // the capture device doesn't stay plugged in.
+    assert!(gb.serial_mut().detach().is_none());
+  }
+
+  // a device that was already plugged in gets put back.
+  let ours = SerialCapture::new();
+  let mut gb = GameBoy::new(Model::Dmg, ram_report_rom(0, "ok")).unwrap();
+  gb.serial_mut().attach(Box::new(ours.clone()));
+  run_blargg(&mut gb, 10);
+  assert_eq!(Rc::strong_count(&ours.0), 2);
+  gb.serial_mut().detach();
+  assert_eq!(Rc::strong_count(&ours.0), 1);
+}
+
+/// Runs a ROM from `tests/` on each model, and checks that it passes.
+fn check(rom: &str, max_frames: u32) {
+  let path = format!("{}/tests/{rom}", env!("CARGO_MANIFEST_DIR"));
+  for model in [Model::Dmg, Model::Cgb] {
+    let mut gb = GameBoy::from_file(model, &path).unwrap().unwrap();
+    let report = run_blargg(&mut gb, max_frames);
+    println!(
+      "== {rom} on {model:?}, {} frames\n{}",
+      report.frames, report.output
+    );
+    assert_eq!(report.status, BlarggStatus::Passed, "{rom} on {model:?}");
+  }
+}
+
+#[test]
+fn blargg_cpu_instrs() {
+  check("blargg_cpu_instrs.gb", 4000);
+}
@@ -0,0 +1,150 @@
+//! Runs mooneye-test-suite ROMs on every model they're for, and prints a
+//! pass/fail table.
+//!
+//! ROMs go anywhere under `tests/mooneye/`. Two small ROMs built from source
+//! here check that passes and fails are told apart.
+//!
+//! The `boot_regs` and `boot_div` tests are required, since they're what
+//! checks the post-boot state in [Model]. `tests/mooneye/fetch.sh` builds the
+//! suite and copies them in.
+
+use std::path::{Path, PathBuf};
+
+use kpasim::{
+  assembler::assemble,
+  gameboy::GameBoy,
+  model::Model,
+  mooneye::{models_for, run_mooneye, MooneyeResult},
+};
+
+/// Mooneye's tests are all done well within this.
+const MAX_FRAMES: u32 = 60 * 30;
+
+const MODELS: [Model; 8] = [
+  Model::Dmg0,
+  Model::Dmg,
+  Model::Mgb,
+  Model::Sgb,
+  Model::Sgb2,
+  Model::Cgb0,
+  Model::Cgb,
+  Model::Agb,
+];
+
+/// The tests of each model's post-boot registers and `DIV`, by their path
+/// under `tests/mooneye/`.
+const BOOT_ROMS: [&str; 14] = [
+  "acceptance/boot_div-dmg0.gb",
+  "acceptance/boot_div-dmgABCmgb.gb",
+  "acceptance/boot_div-S.gb",
+  "acceptance/boot_div2-S.gb",
+  "acceptance/boot_regs-dmg0.gb",
+  "acceptance/boot_regs-dmgABC.gb",
+  "acceptance/boot_regs-mgb.gb",
+  "acceptance/boot_regs-sgb.gb",
+  "acceptance/boot_regs-sgb2.gb",
+  "misc/boot_div-A.gb",
+  "misc/boot_div-cgb0.gb",
+  "misc/boot_div-cgbABCDE.gb",
+  "misc/boot_regs-A.gb",
+  "misc/boot_regs-cgb.gb",
+];
+
+/// A ROM that ends like a mooneye test, with the given registers.
+fn signature_rom(regs: [u8; 6]) -> Vec<u8> {
+  let [b, c, d, e, h, l] = regs;
+  let mut rom = assemble(&format!(
+    r#"
+SECTION "Entry", ROM0[$0100]
+    nop
+    jp Main
+SECTION "Main", ROM0[$0150]
+Main:
+    ld b, {b}
+    ld c, {c}
+    ld d, {d}
+    ld e, {e}
+    ld h, {h}
+    ld l, {l}
+    ld b, b
+.spin:
+    jr .spin
+"#
+  ))
+  .unwrap();
+  rom.resize(0x8000, 0);
+  rom
+}
+
+fn find_roms(dir: &Path, roms: &mut Vec<PathBuf>) {
+  let Ok(entries) = std::fs::read_dir(dir) else { return };
+  for path in entries.map(|entry| entry.unwrap().path()) {
+    if path.is_dir() {
+      find_roms(&path, roms);
+    } else if path.extension().is_some_and(|ext| ext == "gb") {
+      roms.push(path);
+    }
+  }
+}
+
+#[test]
+fn mooneye() {
+  let pass = signature_rom([3, 5, 8, 13, 21, 34]);
+  let fail = signature_rom([0x42; 6]);
+  for model in MODELS {
+    let mut gb = GameBoy::new(model, pass.clone()).unwrap();
+    assert_eq!(run_mooneye(&mut gb, 10), MooneyeResult::Passed, "{model:?}");
+    let mut gb = GameBoy::new(model, fail.clone()).unwrap();
+    assert_eq!(
+      run_mooneye(&mut gb, 10),
+      MooneyeResult::Failed([0x42; 6]),
+      "{model:?}"
+    );
+  }
+
+  let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/mooneye");
+  let missing: Vec<_> =
+    BOOT_ROMS.into_iter().filter(|rom| !dir.join(rom).exists()).collect();
+  assert!(
+    missing.is_empty(),
+    "missing from `{}` (run fetch.sh there):\n{}",
+    dir.display(),
+    missing.join("\n")
+  );
+  let mut roms = Vec::new();
+  find_roms(&dir, &mut roms);
+  roms.sort();
+  println!(
+    "{:<40} {}",
+    "ROM",
+    MODELS.map(|m| format!("{:<5}", format!("{m:?}"))).join(" ")
+  );
+  let mut failures = Vec::new();
+  for path in &roms {
+    let name = path.strip_prefix(&dir).unwrap().display().to_string();
+    let rom = std::fs::read(path).unwrap();
+    let models = models_for(&path.file_name().unwrap().to_string_lossy());
+    let cells = MODELS.map(|model| {
+      if !models.contains(&model) {
+        return "-";
+      }
+      let Some(mut gb) = GameBoy::new(model, rom.clone()) else {
+        failures.push(format!("{name} on {model:?}: unsupported cart"));
+        return "cart";
+      };
+      match run_mooneye(&mut gb, MAX_FRAMES) {
+        MooneyeResult::Passed => "pass",
+        MooneyeResult::Failed(regs) => {
+          failures.push(format!("{name} on {model:?}: {regs:02X?}"));
+          "FAIL"
+        }
+        MooneyeResult::Timeout => {
+          failures.push(format!("{name} on {model:?}: timeout"));
+          "TIME"
+        }
+      }
+    });
+    println!("{name:<40} {}", cells.map(|c| format!("{c:<5}")).join(" "));
+  }
+  assert!(failures.is_empty(), "failed:\n{}", failures.join("\n"));
+}
@@ -0,0 +1,23 @@
+#!/bin/sh
+# Builds mooneye-test-suite and copies its boot state tests next to this
+# script, where `tests/mooneye.rs` runs them. Building needs git, make, and
+# RGBDS. The ROMs are MIT licensed, see the suite's `LICENSE`.
+#
+# Pass a directory that already has a build in it (with `acceptance/` and
+# `misc/` inside) to copy from that instead.
+set -eu
+cd "$(dirname "$0")"
+if [ $# -gt 0 ]; then
+  build=$1
+else
+  src=$(mktemp -d)
+  git clone --depth 1 https://github.com/Gekkio/mooneye-test-suite "$src"
+  make -C "$src"
+  build=$src/build
+fi
+for rom in "$build"/acceptance/boot_*.gb "$build"/misc/boot_*.gb; do
+  dir=$(basename "$(dirname "$rom")")
+  mkdir -p "$dir"
+  cp "$rom" "$dir/"
+  echo "got $dir/$(basename "$rom")"
+done
@@ -0,0 +1,157 @@
+//! Compares screenshots of test ROMs against reference images.
+//!
+//! A test is a ROM (`X.gb`, or `X.asm` to be assembled) anywhere under
+//! `tests/screenshots/`, with `X.dmg.png` and/or `X.cgb.png` next to it. Each
+//! reference runs the ROM on that model until `ld b, b` and compares the
+//! screen. dmg-acid2, cgb-acid2, and the mealybug-tearoom ROMs can be copied
+//! in along with their reference images.
+//!
+//! When a screen doesn't match, our picture and a diff of the two are written
+//! to `screenshots/` in the target directory. A test with no references
+//! fails. Setting `KPASIM_BLESS` writes our pictures over the references
+//! instead, and makes both references for an `.asm` test that has none.
+//!
+//! Blessed references only catch changes, not mistakes. A reference with a
+//! script of the same name next to it (`tiles.dmg.py` for `tiles.dmg.png`) was
+//! drawn by that script without kpasim, so it checks that the picture is
+//! right, and blessing leaves it alone.
+
+use std::path::{Path, PathBuf};
+
+use kpasim::{
+  assembler::assemble,
+  gameboy::GameBoy,
+  model::Model,
+  png::{encode_png, ColorType},
+  screenshot::{diff_screens, run_until, screenshot, Trigger},
+};
+
+/// The test ROMs are all done well within this.
+const MAX_FRAMES: u32 = 60 * 10;
+
+const WIDTH: u32 = 160;
+const HEIGHT: u32 = 144;
+
+const MODELS: [(&str, Model); 2] = [("dmg", Model::Dmg), ("cgb", Model::Cgb)];
+
+fn find_roms(dir: &Path, roms: &mut Vec<PathBuf>) {
+  let Ok(entries) = std::fs::read_dir(dir) else { return };
+  for path in entries.map(|entry| entry.unwrap().path()) {
+    if path.is_dir() {
+      find_roms(&path, roms);
+    } else if path.extension().is_some_and(|ext| ext == "gb" || ext == "asm") {
+      roms.push(path);
+    }
+  }
+}
+
+fn load_rom(path: &Path) -> Vec<u8> {
+  if path.extension().is_some_and(|ext| ext == "asm") {
+    let source = std::fs::read_to_string(path).unwrap();
+    let mut rom =
+      assemble(&source).unwrap_or_else(|e| panic!("{}: {e}", path.display()));
+    rom.resize(rom.len().max(0x8000), 0);
+    rom
+  } else {
+    std::fs::read(path).unwrap()
+  }
+}
+
+/// Reads a PNG as RGB888.
+fn read_png(path: &Path) -> Vec<u8> {
+  let mut decoder = png::Decoder::new(std::fs::File::open(path).unwrap());
+  decoder.set_transformations(
+    png::Transformations::EXPAND | png::Transformations::STRIP_16,
+  );
+  let mut reader = decoder.read_info().unwrap();
+  let mut bytes = vec![0; reader.output_buffer_size()];
+  let info = reader.next_frame(&mut bytes).unwrap();
+  assert_eq!((info.width, info.height), (WIDTH, HEIGHT), "{}", path.display());
+  bytes.truncate(info.buffer_size());
+  match info.color_type {
+    png::ColorType::Grayscale => bytes.iter().flat_map(|&g| [g; 3]).collect(),
+    png::ColorType::GrayscaleAlpha => {
+      bytes.chunks_exact(2).flat_map(|ga| [ga[0]; 3]).collect()
+    }
+    png::ColorType::Rgb => bytes,
+    png::ColorType::Rgba => {
+      bytes.chunks_exact(4).flat_map(|p| [p[0], p[1], p[2]]).collect()
+    }
+    png::ColorType::Indexed => unreachable!("expanded by the decoder"),
+  }
+}
+
+/// Writes an RGB888 picture, as grayscale when it has no color.
+fn write_png(path: &Path, pixels: &[u8]) {
+  let gray = pixels.chunks_exact(3).all(|p| p[0] == p[1] && p[1] == p[2]);
+  let png = if gray {
+    let pixels: Vec<u8> = pixels.iter().step_by(3).copied().collect();
+    encode_png(WIDTH, HEIGHT, ColorType::Grayscale, &pixels)
+  } else {
+    encode_png(WIDTH, HEIGHT, ColorType::Rgb, pixels)
+  };
+  std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+  std::fs::write(path, png).unwrap();
+}
+
+#[test]
+fn screenshots() {
+  let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/screenshots");
+  let out_dir = std::env::var_os("CARGO_TARGET_DIR")
+    .map_or_else(|| PathBuf::from("target"), PathBuf::from)
+    .join("screenshots");
+  let bless = std::env::var_os("KPASIM_BLESS").is_some();
+  let mut roms = Vec::new();
+  find_roms(&dir, &mut roms);
+  roms.sort();
+  let (mut total, mut failures) = (0, Vec::new());
+  for path in &roms {
+    let name = path.strip_prefix(&dir).unwrap().with_extension("");
+    let is_asm = path.extension().is_some_and(|ext| ext == "asm");
+    let references =
+      MODELS.map(|(tag, _)| path.with_extension(format!("{tag}.png")));
+    let make_all = bless && is_asm && !references.iter().any(|r| r.exists());
+    if !(make_all || references.iter().any(|r| r.exists())) {
+      let hint = if is_asm { ", set KPASIM_BLESS to make them" } else { "" };
+      failures.push(format!("{}: no reference images{hint}", name.display()));
+      continue;
+    }
+    let rom = load_rom(path);
+    for ((tag, model), reference) in MODELS.into_iter().zip(&references) {
+      if !(reference.exists() || make_all) {
+        continue;
+      }
+      total += 1;
+      let label = format!("{} on {model:?}", name.display());
+      let independent = reference.with_extension("py").exists();
+      let Some(mut gb) = GameBoy::new(model, rom.clone()) else {
+        failures.push(format!("{label}: unsupported cart"));
+        continue;
+      };
+      if !run_until(&mut gb, &Trigger::Breakpoint, MAX_FRAMES) {
+        failures.push(format!("{label}: no breakpoint"));
+        continue;
+      }
+      let ours = screenshot(&gb);
+      if bless && !independent {
+        write_png(reference, &ours);
+        println!("{label}: wrote {}", reference.display());
+        continue;
+      }
+      if let Some(diff) = diff_screens(&ours, &read_png(reference)) {
+        let base = out_dir.join(&name);
+        let ours_path = base.with_extension(format!("{tag}.png"));
+        let diff_path = base.with_extension(format!("{tag}.diff.png"));
+        write_png(&ours_path, &ours);
+        write_png(&diff_path, &diff.image);
+        failures.push(format!(
+          "{label}: {} pixels differ, see {}",
+          diff.pixels,
+          diff_path.display()
+        ));
+      }
+    }
+  }
+  assert!(failures.is_empty(), "failed:\n{}", failures.join("\n"));
+  assert!(total > 0, "no screenshot tests in `{}`", dir.display());
+}
@@ -0,0 +1,107 @@
+; Draws a scrolled checkerboard of two tiles, with one flipped sprite on top,
+; then runs `ld b, b` once a couple of whole frames have been drawn.
+
+DEF rLCDC EQU $FF40
+DEF rSCX EQU $FF43
+DEF rSCY EQU $FF42
+DEF rLY EQU $FF44
+DEF rBGP EQU $FF47
+DEF rOBP0 EQU $FF48
+
+SECTION "Entry", ROM0[$0100]
+    nop
+    jp Main
+
+SECTION "Main", ROM0[$0150]
+Main:
+    di
+.waitVBlank:
+    ldh a, [rLY]
+    cp 144
+    jr c, .waitVBlank
+    xor a
+    ldh [rLCDC], a
+
+    ld hl, $8000
+    ld de, Tiles
+    ld b, TilesEnd - Tiles
+.copyTiles:
+    ld a, [de]
+    ld [hl+], a
+    inc de
+    dec b
+    jr nz, .copyTiles
+
+    ; tile (row ^ column) & 1 everywhere
+    ld hl, $9800
+    ld c, 0
+.row:
+    ld b, 0
+.column:
+    ld a, b
+    xor c
+    and 1
+    ld [hl+], a
+    inc b
+    ld a, b
+    cp 32
+    jr nz, .column
+    inc c
+    ld a, c
+    cp 32
+    jr nz, .row
+
+    ld hl, $FE00
+    ld b, 160
+    xor a
+.clearOam:
+    ld [hl+], a
+    dec b
+    jr nz, .clearOam
+    ld hl, $FE00
+    ld a, 16 + 60
+    ld [hl+], a
+    ld a, 8 + 76
+    ld [hl+], a
+    ld a, 2
+    ld [hl+], a
+    ld a, %0010_0000 ; x flip
+    ld [hl+], a
+
+    ld a, %11_10_01_00
+    ldh [rBGP], a
+    ld a, %00_01_10_11
+    ldh [rOBP0], a
+    ld a, 3
+    ldh [rSCX], a
+    ld a, 5
+    ldh [rSCY], a
+    ld a, %1001_0011 ; LCD, tiles at $8000, sprites, BG
+    ldh [rLCDC], a
+
+    ld d, 3
+.frame:
+    ldh a, [rLY]
+    cp 144
+    jr z, .frame
+.waitVBlank2:
+    ldh a, [rLY]
+    cp 144
+    jr nz, .waitVBlank2
+    dec d
+    jr nz, .frame
+    ld b, b
+.spin:
+    jr .spin
+
+Tiles:
+    ; an outlined square
+    db $FF, $00, $81, $00, $81, $00, $81, $00
+    db $81, $00, $81, $00, $81, $00, $FF, $00
+    ; a checkerboard of colors 1 and 2
+    db $AA, $55, $55, $AA, $AA, $55, $55, $AA
+    db $AA, $55, $55, $AA, $AA, $55, $55, $AA
+    ; a corner, for the sprite
+    db $F0, $F0, $F8, $F8, $FC, $FC, $FE, $FE
+    db $0F, $00, $07, $00, $03, $00, $01, $00
+TilesEnd:
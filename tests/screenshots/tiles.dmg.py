@@ -0,0 +1,68 @@
+"""Draws tiles.dmg.png by hand, without kpasim, as an independent reference.
+
+This follows what tiles.asm sets up, using the rendering rules from the Pan
+Docs (https://gbdev.io/pandocs/Rendering.html): BG tile (row ^ column) & 1
+everywhere, scrolled by SCX 3 and SCY 5, with one X flipped object at (76,
+60) in front of it. DMG shades 0-3 are the grays $FF, $AA, $55, $00.
+
+Run it from anywhere with `python3 tests/screenshots/tiles.dmg.py`.
+"""
+
+import os
+import struct
+import zlib
+
+WIDTH, HEIGHT = 160, 144
+GRAYS = [0xFF, 0xAA, 0x55, 0x00]
+
+# The tile data from tiles.asm, as (low, high) bit plane pairs for each row.
+SQUARE = [(0xFF, 0x00)] + [(0x81, 0x00)] * 6 + [(0xFF, 0x00)]
+CHECKER = [(0xAA, 0x55), (0x55, 0xAA)] * 4
+CORNER = [(0xF0, 0xF0), (0xF8, 0xF8), (0xFC, 0xFC), (0xFE, 0xFE),
+          (0x0F, 0x00), (0x07, 0x00), (0x03, 0x00), (0x01, 0x00)]
+TILES = [SQUARE, CHECKER, CORNER]
+
+SCX, SCY = 3, 5
+BGP = 0b11_10_01_00
+OBP0 = 0b00_01_10_11
+OBJ_X, OBJ_Y, OBJ_TILE = 76, 60, 2
+
+
+def color(tile, row, col):
+    low, high = TILES[tile][row]
+    bit = 7 - col
+    return ((high >> bit) & 1) << 1 | ((low >> bit) & 1)
+
+
+def shade(palette, index):
+    return (palette >> (index * 2)) & 3
+
+
+def pixel(x, y):
+    bx, by = (x + SCX) & 0xFF, (y + SCY) & 0xFF
+    tile = ((by // 8) ^ (bx // 8)) & 1
+    result = shade(BGP, color(tile, by % 8, bx % 8))
+    if OBJ_X <= x < OBJ_X + 8 and OBJ_Y <= y < OBJ_Y + 8:
+        index = color(OBJ_TILE, y - OBJ_Y, 7 - (x - OBJ_X))
+        if index != 0:
+            result = shade(OBP0, index)
+    return GRAYS[result]
+
+
+def chunk(kind, data):
+    body = kind + data
+    return struct.pack(">I", len(data)) + body + struct.pack(">I", zlib.crc32(body))
+
+
+rows = b"".join(
+    b"\0" + bytes(pixel(x, y) for x in range(WIDTH)) for y in range(HEIGHT)
+)
+png = (
+    b"\x89PNG\r\n\x1a\n"
+    + chunk(b"IHDR", struct.pack(">IIBBBBB", WIDTH, HEIGHT, 8, 0, 0, 0, 0))
+    + chunk(b"IDAT", zlib.compress(rows, 9))
+    + chunk(b"IEND", b"")
+)
+out = os.path.join(os.path.dirname(os.path.abspath(__file__)), "tiles.dmg.png")
+with open(out, "wb") as f:
+    f.write(png)
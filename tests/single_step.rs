@@ -0,0 +1,203 @@
+//! Runs the SingleStepTests SM83 JSON tests against [Cpu].
+//!
+//! Every `.json` file in `tests/sm83/` is an array of cases in the format of
+//! <https://github.com/SingleStepTests/sm83>. Each case gives the registers
+//! and RAM before and after one instruction, and the bus activity of every
+//! M-cycle: `[addr, byte, "r-m"]` for a read, `[addr, byte, "-wm"]` for a
+//! write, and `null` (or a string without `r` or `w`) for an internal cycle.
+//!
+//! The conformance check is the upstream files (`00.json`, `cb 7e.json`,
+//! ...), one for every legal op code, which `tests/sm83/fetch.sh` downloads.
+//! The test fails if any of them are missing. `sample.json` is a smoke test
+//! of the harness itself: a handful of cases worked out by hand from the Pan
+//! Docs' timings.
+
+use kpasim::{cpu::Cpu, data_bus::DataBus, reg16::Reg16};
+use serde_json::Value;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Access {
+  Read(u16, u8),
+  Write(u16, u8),
+}
+
+/// A flat 64 KiB of RAM that records what the CPU does each M-cycle.
+struct FlatRam {
+  ram: Vec<u8>,
+  cycles: Vec<Vec<Access>>,
+}
+impl DataBus for FlatRam {
+  fn read(&mut self, addr: u16) -> u8 {
+    let byte = self.ram[usize::from(addr)];
+    self.cycles.last_mut().unwrap().push(Access::Read(addr, byte));
+    byte
+  }
+  fn write(&mut self, addr: u16, byte: u8) {
+    self.ram[usize::from(addr)] = byte;
+    self.cycles.last_mut().unwrap().push(Access::Write(addr, byte));
+  }
+  fn peek(&mut self, addr: u16) -> u8 {
+    self.ram[usize::from(addr)]
+  }
+  // The CPU checking for interrupts isn't bus activity.
+  fn pending_interrupts(&mut self) -> u8 {
+    self.ram[0xFFFF] & self.ram[0xFF0F] & 0x1F
+  }
+  fn acknowledge_interrupt(&mut self, bit: u8) {
+    self.ram[0xFF0F] &= !bit;
+  }
+}
+
+/// The CPU registers and RAM in a test case.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct State {
+  regs: [u16; 6],
+  ime: bool,
+  ram: Vec<(u16, u8)>,
+}
+impl State {
+  fn from_json(v: &Value) -> Self {
+    let n = |key: &str| v[key].as_u64().unwrap() as u16;
+    Self {
+      regs: [
+        n("a") << 8 | n("f"),
+        n("b") << 8 | n("c"),
+        n("d") << 8 | n("e"),
+        n("h") << 8 | n("l"),
+        n("sp"),
+        n("pc"),
+      ],
+      ime: n("ime") != 0,
+      ram: v["ram"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|pair| {
+          let n = |i: usize| pair[i].as_u64().unwrap();
+          (n(0) as u16, n(1) as u8)
+        })
+        .collect(),
+    }
+  }
+
+  fn from_cpu(cpu: &Cpu, ram: &FlatRam, expected: &Self) -> Self {
+    Self {
+      regs: [cpu.af, cpu.bc, cpu.de, cpu.hl, cpu.sp, cpu.pc].map(Reg16::get),
+      // `ei` turns on IME after the next instruction, but the tests count it
+      // as already on.
+      ime: cpu.ime || cpu.ei_pending,
+      ram: expected
+        .ram
+        .iter()
+        .map(|&(addr, _)| (addr, ram.ram[usize::from(addr)]))
+        .collect(),
+    }
+  }
+}
+
+/// Runs one case, describing how it went wrong if it did.
+fn run_case(case: &Value) -> Result<(), String> {
+  let initial = State::from_json(&case["initial"]);
+  let expected = State::from_json(&case["final"]);
+  let expected_cycles: Vec<Vec<Access>> = case["cycles"]
+    .as_array()
+    .unwrap()
+    .iter()
+    .map(|cycle| {
+      let Some(cycle) = cycle.as_array() else { return vec![] };
+      let addr = cycle[0].as_u64().unwrap_or(0) as u16;
+      let byte = cycle[1].as_u64().unwrap_or(0) as u8;
+      match cycle[2].as_str().unwrap_or("") {
+        pins if pins.contains('r') => vec![Access::Read(addr, byte)],
+        pins if pins.contains('w') => vec![Access::Write(addr, byte)],
+        _ => vec![],
+      }
+    })
+    .collect();
+
+  let mut bus = FlatRam { ram: vec![0; 0x10000], cycles: Vec::new() };
+  for &(addr, byte) in &initial.ram {
+    bus.ram[usize::from(addr)] = byte;
+  }
+  bus.ram[0xFFFF] = case["initial"]["ie"].as_u64().unwrap_or(0) as u8;
+  let mut cpu = Cpu::new();
+  let [af, bc, de, hl, sp, pc] = initial.regs;
+  cpu.af.set(af);
+  cpu.bc.set(bc);
+  cpu.de.set(de);
+  cpu.hl.set(hl);
+  cpu.sp.set(sp);
+  cpu.pc.set(pc);
+  cpu.ime = initial.ime;
+
+  for _ in 0..expected_cycles.len() {
+    bus.cycles.push(Vec::new());
+    cpu.m_cycle(&mut bus);
+  }
+  let mut problems = Vec::new();
+  if !cpu.is_between_instructions() {
+    problems.push(format!("still running: {:?}", cpu.action_queue));
+  }
+  let ours = State::from_cpu(&cpu, &bus, &expected);
+  if ours != expected {
+    problems
+      .push(format!("state:\n    ours: {ours:?}\n    test: {expected:?}"));
+  }
+  if bus.cycles != expected_cycles {
+    problems.push(format!(
+      "cycles:\n    ours: {:?}\n    test: {expected_cycles:?}",
+      bus.cycles
+    ));
+  }
+  if problems.is_empty() {
+    Ok(())
+  } else {
+    Err(problems.join("\n  "))
+  }
+}
+
+/// The upstream file names: every op code but the illegal ones and the `$CB`
+/// prefix, then every `$CB` op code.
+fn upstream_files() -> impl Iterator<Item = String> {
+  const ILLEGAL: [u8; 12] =
+    [0xCB, 0xD3, 0xDB, 0xDD, 0xE3, 0xE4, 0xEB, 0xEC, 0xED, 0xF4, 0xFC, 0xFD];
+  let plain = (0..=u8::MAX).filter(|op| !ILLEGAL.contains(op));
+  let plain = plain.map(|op| format!("{op:02x}.json"));
+  plain.chain((0..=u8::MAX).map(|op| format!("cb {op:02x}.json")))
+}
+
+#[test]
+fn single_step_tests() {
+  let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/sm83");
+  let mut paths: Vec<_> = std::fs::read_dir(dir)
+    .unwrap()
+    .map(|entry| entry.unwrap().path())
+    .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+    .collect();
+  paths.sort();
+  let (mut total, mut failures) = (0, Vec::new());
+  for path in &paths {
+    let text = std::fs::read_to_string(path).unwrap();
+    let cases: Value = serde_json::from_str(&text).unwrap();
+    for case in cases.as_array().unwrap() {
+      total += 1;
+      if let Err(why) = run_case(case) {
+        failures.push(format!("{}: {why}", case["name"]));
+      }
+    }
+  }
+  for failure in failures.iter().take(20) {
+    println!("{failure}");
+  }
+  let missing: Vec<_> = upstream_files()
+    .filter(|name| !paths.iter().any(|path| path.ends_with(name)))
+    .collect();
+  assert!(
+    missing.is_empty(),
+    "{} upstream files are missing from `{dir}` (run fetch.sh there), such \
+     as `{}`",
+    missing.len(),
+    missing[0]
+  );
+  assert!(failures.is_empty(), "{} of {total} cases failed", failures.len());
+}
@@ -0,0 +1,28 @@
+#!/bin/sh
+# Downloads the SingleStepTests SM83 vectors next to this script, where
+# `tests/single_step.rs` runs them: one file per op code, including the `$CB`
+# ones, but not the illegal ones. Only the first $CASES cases (20 unless set)
+# of each file are kept, so the whole set is small enough to commit. Set REF
+# to an upstream commit to pin it.
+#
+# Pass op codes (like `40` or `"cb 11"`) to get just those.
+set -eu
+cd "$(dirname "$0")"
+BASE=https://raw.githubusercontent.com/SingleStepTests/sm83/${REF:-main}/v1
+CASES=${CASES:-20}
+if [ $# -eq 0 ]; then
+  for i in $(seq 0 255); do
+    op=$(printf %02x "$i")
+    case $op in
+      cb | d3 | db | dd | e3 | e4 | eb | ec | ed | f4 | fc | fd) ;;
+      *) set -- "$@" "$op" ;;
+    esac
+    set -- "$@" "cb $op"
+  done
+fi
+for op in "$@"; do
+  curl -fsSL "$BASE/$(printf %s "$op" | sed 's/ /%20/g').json" |
+    python3 -c "import json, sys; json.dump(json.load(sys.stdin)[:$CASES], sys.stdout)" \
+      >"$op.json"
+  echo "got $op.json"
+done